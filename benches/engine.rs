@@ -0,0 +1,69 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use expert_kb::engine::Budget;
+use expert_kb::generator::generate_kb;
+use expert_kb::parser::{parse_db_from_file, DB};
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+
+    for size in SIZES {
+        let source = generate_kb(size, 42);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &source, |b, source| {
+            b.iter(|| parse_db_from_file(black_box(source)))
+        });
+    }
+
+    group.finish();
+}
+
+fn query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query");
+
+    for size in SIZES {
+        let Ok(db) = parse_db_from_file(&generate_kb(size, 42)) else {
+            panic!("generated knowledge base failed to parse");
+        };
+        let target = String::from("диагноз");
+        let answers = sample_answers(&db);
+        let budget = Budget::default();
+
+        group.bench_with_input(BenchmarkId::new("find_value", size), &db, |b, db| {
+            b.iter(|| {
+                db.find_value(
+                    Some(&target),
+                    answers.iter().map(|(x, y)| (x, y)).collect(),
+                    &budget,
+                )
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("find_all_values", size), &db, |b, db| {
+            b.iter(|| {
+                db.find_all_values(
+                    Some(&target),
+                    answers.iter().map(|(x, y)| (x, y)).collect(),
+                    &budget,
+                )
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// Answers taken from the middle entry, so the query has to skip half of the base.
+fn sample_answers(db: &DB) -> Vec<(String, String)> {
+    db.entries[db.entries.len() / 2]
+        .conditions
+        .iter()
+        .map(|condition| (condition.category.clone(), condition.value.clone()))
+        .collect()
+}
+
+criterion_group!(benches, parse, query);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Extensions of the knowledge base files that can be embedded.
+const EMBEDDED_EXTENSIONS: [&str; 8] = ["kb", "esdb", "txt", "json", "yaml", "yml", "csv", "clp"];
+
+fn main() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=GIT_HASH={}", hash);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    embed_bases();
+}
+
+/// Writes the registry of the knowledge bases found in the directory named
+/// by `EXPERT_KB_EMBED`, which the application offers as built-in bases.
+fn embed_bases() {
+    println!("cargo:rerun-if-env-changed=EXPERT_KB_EMBED");
+
+    let mut files = Vec::new();
+    if let Some(dir) = std::env::var_os("EXPERT_KB_EMBED").map(PathBuf::from) {
+        println!("cargo:rerun-if-changed={}", dir.display());
+        let entries = std::fs::read_dir(&dir)
+            .unwrap_or_else(|err| panic!("cannot read {}: {}", dir.display(), err));
+        files = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| is_knowledge_base(path))
+            .collect();
+        files.sort();
+    }
+
+    let mut registry = String::from("&[\n");
+    for path in files {
+        let path = path.canonicalize().unwrap_or(path);
+        let (Some(file), Some(name), Some(full)) = (
+            path.file_name().and_then(|x| x.to_str()),
+            path.file_stem().and_then(|x| x.to_str()),
+            path.to_str(),
+        ) else {
+            continue;
+        };
+        println!("cargo:rerun-if-changed={}", full);
+        registry.push_str(&format!(
+            "    EmbeddedBase {{ name: {:?}, file: {:?}, contents: include_bytes!({:?}) }},\n",
+            name, file, full
+        ));
+    }
+    registry.push(']');
+
+    let out = PathBuf::from(std::env::var_os("OUT_DIR").unwrap()).join("embedded.rs");
+    std::fs::write(out, registry).expect("cannot write the registry of embedded bases");
+}
+
+fn is_knowledge_base(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|x| x.to_str())
+            .is_some_and(|x| EMBEDDED_EXTENSIONS.contains(&x.to_lowercase().as_str()))
+}
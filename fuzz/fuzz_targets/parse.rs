@@ -0,0 +1,8 @@
+#![no_main]
+
+use expert_kb::parser::parse_db_from_file;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_db_from_file(&String::from_utf8_lossy(data));
+});
@@ -0,0 +1,281 @@
+//! Keyboard and touch accessibility. iced does not expose an accessibility
+//! tree yet, so interactive widgets are named by their widget ids, which
+//! also let the keyboard move focus between them.
+
+use std::borrow::Cow;
+
+use iced::{
+    advanced::{
+        layout::{self, Layout},
+        overlay, renderer,
+        widget::{self, operation, tree, Tree, Widget},
+        Clipboard, Shell,
+    },
+    event,
+    keyboard::{self, key::Named, Key},
+    mouse, theme,
+    widget::{text_input, TextInput},
+    Border, Color, Element, Event, Length, Rectangle, Renderer, Size, Theme, Vector,
+};
+
+use crate::main_window::Message;
+
+/// Scale of the interface with large controls turned on.
+pub const LARGE_SCALE: f64 = 1.3;
+
+/// Outline of the focused widget.
+const FOCUS_COLOR: Color = Color::from_rgb(1.0, 0.8, 0.2);
+
+/// Text input named `id` that shows a clear outline while focused.
+pub fn input<'a>(
+    id: impl Into<Cow<'static, str>>,
+    placeholder: &str,
+    value: &str,
+) -> TextInput<'a, Message> {
+    text_input(placeholder, value)
+        .id(text_input::Id::new(id))
+        .style(theme::TextInput::Custom(Box::new(FocusOutline)))
+}
+
+/// Default text input look with a thick, high-contrast border on focus.
+struct FocusOutline;
+
+impl text_input::StyleSheet for FocusOutline {
+    type Style = Theme;
+
+    fn active(&self, style: &Theme) -> text_input::Appearance {
+        style.active(&theme::TextInput::Default)
+    }
+
+    fn focused(&self, style: &Theme) -> text_input::Appearance {
+        let appearance = style.focused(&theme::TextInput::Default);
+        text_input::Appearance {
+            border: Border {
+                width: 3.0,
+                color: FOCUS_COLOR,
+                ..appearance.border
+            },
+            ..appearance
+        }
+    }
+
+    fn placeholder_color(&self, style: &Theme) -> Color {
+        style.placeholder_color(&theme::TextInput::Default)
+    }
+
+    fn value_color(&self, style: &Theme) -> Color {
+        style.value_color(&theme::TextInput::Default)
+    }
+
+    fn disabled_color(&self, style: &Theme) -> Color {
+        style.disabled_color(&theme::TextInput::Default)
+    }
+
+    fn selection_color(&self, style: &Theme) -> Color {
+        style.selection_color(&theme::TextInput::Default)
+    }
+
+    fn hovered(&self, style: &Theme) -> text_input::Appearance {
+        style.hovered(&theme::TextInput::Default)
+    }
+
+    fn disabled(&self, style: &Theme) -> text_input::Appearance {
+        style.disabled(&theme::TextInput::Default)
+    }
+}
+
+/// Names buttons, pick lists and checkboxes for the keyboard: Tab focuses
+/// them, Enter or Space presses them as a click would.
+pub trait Accessible<'a>: Into<Element<'a, Message>> {
+    fn named(self, id: impl Into<Cow<'static, str>>) -> Element<'a, Message> {
+        Element::new(Focus {
+            id: widget::Id::new(id),
+            content: self.into(),
+        })
+    }
+}
+
+impl<'a, T: Into<Element<'a, Message>>> Accessible<'a> for T {}
+
+struct Focus<'a> {
+    id: widget::Id,
+    content: Element<'a, Message>,
+}
+
+#[derive(Default)]
+struct Focused(bool);
+
+impl operation::Focusable for Focused {
+    fn is_focused(&self) -> bool {
+        self.0
+    }
+
+    fn focus(&mut self) {
+        self.0 = true;
+    }
+
+    fn unfocus(&mut self) {
+        self.0 = false;
+    }
+}
+
+impl<'a> Widget<Message, Theme, Renderer> for Focus<'a> {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<Focused>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(Focused::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[&self.content]);
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.content
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            viewport,
+        );
+
+        if tree.state.downcast_ref::<Focused>().0 {
+            <Renderer as renderer::Renderer>::fill_quad(
+                renderer,
+                renderer::Quad {
+                    bounds: layout.bounds().expand(2.0),
+                    border: Border {
+                        color: FOCUS_COLOR,
+                        width: 3.0,
+                        radius: 4.0.into(),
+                    },
+                    ..renderer::Quad::default()
+                },
+                Color::TRANSPARENT,
+            );
+        }
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn widget::Operation<Message>,
+    ) {
+        operation.focusable(tree.state.downcast_mut::<Focused>(), Some(&self.id));
+        self.content
+            .as_widget()
+            .operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let focused = &mut tree.state.downcast_mut::<Focused>().0;
+        match &event {
+            // Focus follows the keyboard only, so clicks leave no outline.
+            Event::Mouse(mouse::Event::ButtonPressed(_)) => *focused = false,
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: Key::Named(Named::Enter | Named::Space),
+                ..
+            }) if *focused => {
+                let center = mouse::Cursor::Available(layout.bounds().center());
+                for click in [mouse::Event::ButtonPressed, mouse::Event::ButtonReleased] {
+                    let _ = self.content.as_widget_mut().on_event(
+                        &mut tree.children[0],
+                        Event::Mouse(click(mouse::Button::Left)),
+                        layout,
+                        center,
+                        renderer,
+                        clipboard,
+                        shell,
+                        viewport,
+                    );
+                }
+                return event::Status::Captured;
+            }
+            _ => {}
+        }
+
+        self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        self.content
+            .as_widget_mut()
+            .overlay(&mut tree.children[0], layout, renderer, translation)
+    }
+}
@@ -0,0 +1,135 @@
+//! Version of the application and a reference of the knowledge base syntax.
+
+use iced::{
+    theme,
+    widget::{button, column, container, horizontal_space, row, scrollable, text},
+    Element, Font, Length,
+};
+
+use crate::{a11y::Accessible, main_window::Message, parser::DB};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const GIT_HASH: &str = env!("GIT_HASH");
+
+/// Declarations of the language, each with a description and an example.
+const SYNTAX: [(&str, &str); 15] = [
+    (
+        "Правило: условия через «и», вывод после «то».",
+        "1 если стебель - древесный\nи положение - прямостоящее\nто тип - деревья",
+    ),
+    (
+        "Вес и вероятность условия, безразличная категория.",
+        "2 если цвет - красный (2.5) [0.8] и размер - *\nто плод - яблоко",
+    ),
+    (
+        "Происхождение правила, примечание к нему и его отключение.",
+        "3 если стебель - зеленый\nто тип - травянистые\nавтор Иванов\nисточник Справочник ботаника\nдата 2024-01-31\nпримечание Стебель без одревеснения\nотключено",
+    ),
+    (
+        "Вопрос: порядок, группа, тип ответа и текст.",
+        "вопрос стебель #1 [Внешний вид] :выбор какой стебель у растения?",
+    ),
+    (
+        "Перевод категории для вывода результата.",
+        "перевод тип Тип растения",
+    ),
+    (
+        "Подсказка к вопросу, допускается Markdown.",
+        "подсказка стебель Стебель **древесный**, если он одревесневший",
+    ),
+    (
+        "Подсказка к значению; {категория} заменяется ответом.",
+        "подсказка семейство = сосновые\nХвоя {форма_листа}, поливать умеренно",
+    ),
+    (
+        "Текст на другом языке, строкой после основного.",
+        "перевод тип Тип растения\nen: Plant type",
+    ),
+    ("Подтип значения.", "подтип ель - хвойные"),
+    ("Синоним значения.", "синоним лиственница = листвянка"),
+    (
+        "Допустимые значения категории.",
+        "значения стебель - древесный, зеленый",
+    ),
+    ("Категория с несколькими значениями.", "многозначный цвет"),
+    ("Цель поиска по умолчанию.", "цель семейство"),
+    (
+        "Пароль редактора: SHA-256 от пароля «secret».",
+        "защита 2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b",
+    ),
+    (
+        "Априорная вероятность и встроенный тест с выбранной стратегией.",
+        "вероятность тип - деревья = 0.3\nтест: байес если стебель - зеленый то тип - травянистые",
+    ),
+];
+
+/// Summary of the loaded base.
+pub struct Loaded<'a> {
+    pub name: &'a str,
+    pub db: &'a DB,
+    /// Whether examples can be inserted into the editor.
+    pub editable: bool,
+}
+
+pub fn view<'a>(loaded: Loaded<'a>) -> Element<'a, Message> {
+    let db = loaded.db;
+
+    let build = column![
+        text("Экспертная система").size(18),
+        text(format!("Версия {} ({})", VERSION, GIT_HASH)).size(14),
+        button(text("Сочетания клавиш (?)").size(14))
+            .on_press(Message::ShortcutsToggled)
+            .style(theme::Button::Secondary)
+            .named("shortcuts"),
+    ]
+    .spacing(4);
+
+    let base = column![
+        text("База знаний").size(16),
+        text(format!("Источник: {}", loaded.name)).size(14),
+        text(format!(
+            "Правил: {}, категорий: {}, вопросов: {}, тестов: {}",
+            db.entries.len(),
+            db.categories.len(),
+            db.questions.len(),
+            db.tests.len()
+        ))
+        .size(14),
+    ]
+    .spacing(4);
+
+    let syntax = SYNTAX.iter().enumerate().fold(
+        column![text("Синтаксис базы знаний").size(16)].spacing(10),
+        |col, (i, &(description, example))| {
+            col.push(
+                container(
+                    column![
+                        row![
+                            text(description).size(14),
+                            horizontal_space(),
+                            button(text("Вставить в редактор").size(12))
+                                .on_press(Message::InsertExample(example))
+                                .style(theme::Button::Secondary)
+                                .named(format!("insert-example-{}", i)),
+                        ]
+                        .spacing(10)
+                        .align_items(iced::Alignment::Center),
+                        text(example).size(14).font(Font::MONOSPACE),
+                    ]
+                    .spacing(6)
+                    .padding(8),
+                )
+                .width(Length::Fill)
+                .style(theme::Container::Box),
+            )
+        },
+    );
+
+    scrollable(
+        column![build, base]
+            .push_maybe(loaded.editable.then_some(syntax))
+            .spacing(20)
+            .padding(10),
+    )
+    .into()
+}
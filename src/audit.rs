@@ -0,0 +1,384 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use chrono::{DateTime, Local};
+use indexmap::IndexMap;
+use serde_json::json;
+
+use crate::{
+    engine::Interrupt,
+    parser::{Entry, DB},
+    strategy::QueryResult,
+    template,
+};
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub time: DateTime<Local>,
+    pub kind: AuditKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum AuditKind {
+    /// An answer was selected, `None` when it was cleared.
+    Answer(String, Option<String>),
+    Query {
+        target: Option<String>,
+        answers: Vec<(String, String)>,
+    },
+    Result(Vec<String>),
+    /// The query failed, with the reason.
+    Error(String),
+}
+
+/// Trail of everything that happened during one consultation.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    events: Vec<AuditEvent>,
+}
+
+impl AuditLog {
+    /// Records an answer. Consecutive edits of the same answer, such as
+    /// typing a number, are kept as a single event.
+    pub fn answer(&mut self, category: &str, answer: Option<String>) {
+        if let Some(AuditEvent {
+            time,
+            kind: AuditKind::Answer(last, last_answer),
+        }) = self.events.last_mut()
+        {
+            if last == category && last_answer.is_some() && answer.is_some() {
+                *time = Local::now();
+                *last_answer = answer;
+                return;
+            }
+        }
+
+        self.push(AuditKind::Answer(category.to_string(), answer));
+    }
+
+    pub fn push(&mut self, kind: AuditKind) {
+        self.events.push(AuditEvent {
+            time: Local::now(),
+            kind,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn events(&self) -> &[AuditEvent] {
+        &self.events
+    }
+}
+
+pub struct Consultation<'a> {
+    pub db: &'a DB,
+    pub source: &'a str,
+    pub answers: Vec<(&'a String, &'a String, DateTime<Local>)>,
+    pub result: Option<&'a QueryResult>,
+    pub audit: &'a AuditLog,
+}
+
+/// Renders the consultation as a Markdown report.
+pub fn report(consultation: &Consultation) -> String {
+    let db = consultation.db;
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# Отчёт о консультации\n");
+    let _ = writeln!(out, "База знаний: {}", consultation.source);
+    let _ = writeln!(out, "Сформирован: {}\n", Local::now().format(TIME_FORMAT));
+
+    let _ = writeln!(out, "## Ответы\n");
+    for (category, answer, time) in consultation.answers.iter() {
+        let question = db
+            .question_text(category)
+            .map_or(category.as_str(), String::as_str);
+        let _ = writeln!(
+            out,
+            "- {}: {} ({})",
+            question,
+            answer,
+            time.format(TIME_FORMAT)
+        );
+    }
+
+    let _ = writeln!(out, "\n## Результат\n");
+    if let Some(interrupt) = consultation.result.and_then(|result| result.interrupted) {
+        let _ = writeln!(out, "{}\n", interrupt_message(interrupt));
+    }
+    match consultation.result.map(QueryResult::values).as_deref() {
+        Some([value, alternatives @ ..]) => {
+            let _ = writeln!(out, "{}", value);
+            if !alternatives.is_empty() {
+                let _ = writeln!(out, "\nТакже подходят: {}", alternatives.join(", "));
+            }
+        }
+        _ => {
+            let _ = match last_failure(consultation.audit) {
+                Some(reason) => writeln!(out, "Поиск не удался: {}", reason),
+                None => writeln!(out, "Не найдено"),
+            };
+        }
+    }
+    if let Some(confidence) = consultation.result.and_then(|result| result.confidence) {
+        let _ = writeln!(out, "\nУверенность: {:.1}%", confidence * 100.0);
+    }
+    if let Some(score) = consultation.result.and_then(|result| result.score.as_ref()) {
+        let _ = writeln!(out, "\nСчёт: {} из {}", score.score, score.total);
+    }
+    if let Some(result) = consultation
+        .result
+        .filter(|result| !result.posteriors.is_empty())
+    {
+        let _ = writeln!(out, "\nВероятности:\n");
+        for posterior in result.posteriors.iter() {
+            let _ = writeln!(
+                out,
+                "- {} - {}: {:.1}%",
+                posterior.category,
+                posterior.value,
+                posterior.probability * 100.0
+            );
+        }
+    }
+
+    let recommendations = recommendations(consultation);
+    if !recommendations.is_empty() {
+        let _ = writeln!(out, "\n## Рекомендации\n");
+        for recommendation in recommendations {
+            let _ = writeln!(out, "{}", recommendation.text);
+        }
+    }
+
+    let _ = writeln!(out, "\n## Журнал\n");
+    for event in consultation.audit.events() {
+        let _ = write!(out, "- {} ", event.time.format(TIME_FORMAT));
+        let _ = match &event.kind {
+            AuditKind::Answer(category, Some(answer)) => {
+                writeln!(out, "ответ: {} - {}", category, answer)
+            }
+            AuditKind::Answer(category, None) => writeln!(out, "ответ сброшен: {}", category),
+            AuditKind::Query { target, answers } => writeln!(
+                out,
+                "поиск: цель {}, ответы: {}",
+                target.as_deref().unwrap_or("любая"),
+                answers
+                    .iter()
+                    .map(|(category, answer)| format!("{} - {}", category, answer))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            AuditKind::Result(values) if values.is_empty() => writeln!(out, "ничего не найдено"),
+            AuditKind::Result(values) => writeln!(out, "результат: {}", values.join(", ")),
+            AuditKind::Error(reason) => writeln!(out, "поиск не удался: {}", reason),
+        };
+    }
+
+    out
+}
+
+/// Reason the last query failed, unless it returned a result.
+fn last_failure(audit: &AuditLog) -> Option<&str> {
+    audit
+        .events()
+        .iter()
+        .rev()
+        .find_map(|event| match &event.kind {
+            AuditKind::Result(_) => Some(None),
+            AuditKind::Error(reason) => Some(Some(reason.as_str())),
+            _ => None,
+        })
+        .flatten()
+}
+
+pub fn interrupt_message(interrupt: Interrupt) -> &'static str {
+    match interrupt {
+        Interrupt::Cancelled => "Поиск отменён, результат может быть неполным",
+        Interrupt::TimedOut => "Время поиска истекло, результат может быть неполным",
+    }
+}
+
+/// Conclusion of the consultation as plain text, to paste into reports or
+/// tickets.
+pub fn summary(consultation: &Consultation) -> String {
+    let result = consultation.result;
+    let values = result.map(QueryResult::values).unwrap_or_default();
+    let confidence = result.and_then(|result| result.confidence);
+    let entry = matched_entry(consultation);
+    let mut out = String::new();
+
+    let _ = writeln!(
+        out,
+        "Результат: {}",
+        if values.is_empty() {
+            "не найдено".to_string()
+        } else {
+            values.join(", ")
+        }
+    );
+    if let Some(confidence) = confidence {
+        let _ = writeln!(out, "Уверенность: {:.1}%", confidence * 100.0);
+    }
+    if let Some((id, entry)) = entry {
+        let _ = writeln!(out, "Правило {}: {}", id + 1, entry_text(entry));
+    }
+    let _ = writeln!(out, "Ответы:");
+    for (category, answer, _) in consultation.answers.iter() {
+        let _ = writeln!(out, "- {} - {}", category, answer);
+    }
+
+    out
+}
+
+/// Conclusion of the consultation as JSON, for other programs.
+pub fn summary_json(consultation: &Consultation) -> String {
+    let result = consultation.result;
+    let values = result.map(QueryResult::values).unwrap_or_default();
+    let entry = matched_entry(consultation);
+    let data = json!({
+        "values": values,
+        "confidence": result.and_then(|result| result.confidence),
+        "entry": entry.map(|(id, entry)| json!({
+            "number": id + 1,
+            "category": entry.category,
+            "value": entry.value,
+            "conditions": entry
+                .conditions
+                .iter()
+                .map(|condition| json!({
+                    "category": condition.category,
+                    "value": condition.value,
+                }))
+                .collect::<Vec<_>>(),
+        })),
+        "answers": consultation
+            .answers
+            .iter()
+            .map(|&(category, answer, _)| (category, answer))
+            .collect::<IndexMap<_, _>>(),
+    });
+
+    serde_json::to_string_pretty(&data).unwrap_or_default()
+}
+
+/// Translation or tip shown with the result of a consultation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recommendation {
+    /// Category and value the text is written for.
+    pub category: String,
+    pub value: String,
+    pub text: String,
+}
+
+/// Translations and tips of the concluded value, then of the answers to
+/// the conditions of the entry it follows from, with placeholders filled
+/// and each text once.
+pub fn recommendations(consultation: &Consultation) -> Vec<Recommendation> {
+    let db = consultation.db;
+    let Some((_, entry)) = matched_entry(consultation) else {
+        return Vec::new();
+    };
+
+    let answer = |category: &String| {
+        consultation
+            .answers
+            .iter()
+            .find(|(answered, ..)| *answered == category)
+            .map(|&(_, answer, _)| db.canonical(answer))
+    };
+    let sources = [(&entry.category, &entry.value)].into_iter().chain(
+        entry
+            .conditions
+            .iter()
+            .filter_map(|condition| Some((&condition.category, answer(&condition.category)?))),
+    );
+
+    let mut recommendations: Vec<Recommendation> = Vec::new();
+    for (category, value) in sources {
+        let texts = [db.change_of(category, value), db.tip_of(category, value)];
+        for text in texts.into_iter().flatten() {
+            let text = fill_placeholders(text, consultation);
+            if !recommendations.iter().any(|x| x.text == text) {
+                recommendations.push(Recommendation {
+                    category: category.clone(),
+                    value: value.clone(),
+                    text,
+                });
+            }
+        }
+    }
+    recommendations
+}
+
+/// Recommendations of the consultation as a Markdown checklist, the ones in
+/// `done` ticked.
+pub fn checklist(consultation: &Consultation, done: &HashSet<String>) -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# Рекомендации\n");
+    let _ = writeln!(out, "База знаний: {}", consultation.source);
+    if let Some(values) = consultation.result.map(QueryResult::values) {
+        if !values.is_empty() {
+            let _ = writeln!(out, "Вывод: {}", values.join(", "));
+        }
+    }
+    let _ = writeln!(out, "Сформирован: {}\n", Local::now().format(TIME_FORMAT));
+
+    for recommendation in recommendations(consultation) {
+        let mark = if done.contains(&recommendation.text) {
+            'x'
+        } else {
+            ' '
+        };
+        let text = recommendation.text.trim().replace('\n', "\n  ");
+        let _ = writeln!(out, "- [{}] {}", mark, text);
+    }
+
+    out
+}
+
+/// Fills the placeholders of a translation or tip with the answers of the
+/// consultation and the value it concluded.
+pub fn fill_placeholders(text: &str, consultation: &Consultation) -> String {
+    let db = consultation.db;
+    let conclusion = matched_entry(consultation).map(|(_, entry)| entry);
+    template::render(text, |name| {
+        let name = db.spelling(name);
+        consultation
+            .answers
+            .iter()
+            .find(|(category, ..)| category.as_str() == name)
+            .map(|(_, answer, _)| answer.as_str())
+            .or_else(|| {
+                conclusion
+                    .filter(|entry| entry.category == name)
+                    .map(|entry| entry.value.as_str())
+            })
+    })
+}
+
+/// Entry the engine drew the first conclusion by.
+fn matched_entry<'a>(consultation: &Consultation<'a>) -> Option<(usize, &'a Entry)> {
+    let db = consultation.db;
+    let result = consultation.result?;
+    let conclusion = result.conclusions.first()?;
+
+    result
+        .matched
+        .iter()
+        .filter_map(|&id| Some((id, db.entries.get(id)?)))
+        .find(|(_, entry)| entry.category == conclusion.category && entry.value == conclusion.value)
+}
+
+fn entry_text(entry: &Entry) -> String {
+    let conditions = entry
+        .conditions
+        .iter()
+        .map(|condition| format!("{} - {}", condition.category, condition.value))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{} → {} - {}", conditions, entry.category, entry.value)
+}
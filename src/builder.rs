@@ -0,0 +1,166 @@
+//! Knowledge bases put together in code instead of written in the DSL, and
+//! checks of what queries against them conclude, for tests of the engine and
+//! of code built on it.
+
+use indexmap::IndexMap;
+
+use crate::cases::TestCase;
+use crate::parser::{Block, Condition, Entry, ParsedBlock, Question, DB};
+use crate::strategy::MatchStrategy;
+
+/// Builds a [`DB`] from the same declarations the DSL has, read as the
+/// parser would read them, so aliases apply to every entry whatever order
+/// they are added in.
+///
+/// ```
+/// use expert_kb::builder::{assert_concludes, KbBuilder};
+/// use expert_kb::strategy::Exact;
+///
+/// let db = KbBuilder::new()
+///     .entry(&[("стебель", "зеленый")], ("тип", "травянистые"))
+///     .question("стебель", "Какой стебель?")
+///     .build();
+/// assert_concludes(&db, &Exact, Some("тип"), &[("стебель", "зеленый")], &["травянистые"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KbBuilder {
+    blocks: Vec<ParsedBlock>,
+    entries: u64,
+}
+
+impl KbBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry concluding `conclusion` when every condition, a
+    /// `(category, value)` pair of weight 1, holds.
+    pub fn entry(self, conditions: &[(&str, &str)], conclusion: (&str, &str)) -> Self {
+        let (category, value) = conclusion;
+        self.with_entry(Entry {
+            value: value.to_string(),
+            category: category.to_string(),
+            conditions: conditions
+                .iter()
+                .map(|&(category, value)| Condition::new(category, value))
+                .collect(),
+            ..Entry::default()
+        })
+    }
+
+    /// Adds `entry` as it is, for weights, ignored categories and the rest
+    /// [`entry`](Self::entry) leaves out. Entries are numbered in the order
+    /// they are added.
+    pub fn with_entry(mut self, entry: Entry) -> Self {
+        self.entries += 1;
+        let number = self.entries;
+        self.block(Block::Entry(Some(number), entry))
+    }
+
+    pub fn question(self, category: &str, text: &str) -> Self {
+        self.block(Block::Advice(category.to_string(), Question::new(text)))
+    }
+
+    pub fn change(self, category: &str, text: &str) -> Self {
+        self.block(Block::Change(category.to_string(), None, text.to_string()))
+    }
+
+    pub fn tip(self, category: &str, text: &str) -> Self {
+        self.block(Block::Tip(category.to_string(), None, text.to_string()))
+    }
+
+    pub fn subtype(self, value: &str, parent: &str) -> Self {
+        self.block(Block::Subtype(value.to_string(), parent.to_string()))
+    }
+
+    pub fn alias(self, alias: &str, value: &str) -> Self {
+        self.block(Block::Alias(alias.to_string(), value.to_string()))
+    }
+
+    pub fn target(self, category: &str) -> Self {
+        self.block(Block::Target(category.to_string()))
+    }
+
+    pub fn prior(self, category: &str, value: &str, probability: f64) -> Self {
+        self.block(Block::Prior(
+            category.to_string(),
+            value.to_string(),
+            probability,
+        ))
+    }
+
+    /// Adds an example consultation, like `тест` does, looking for every
+    /// value in `expected` when there are several.
+    pub fn test(self, answers: &[(&str, &str)], target: &str, expected: &[&str]) -> Self {
+        self.block(Block::Test(case(Some(target), answers, expected)))
+    }
+
+    fn block(mut self, block: Block) -> Self {
+        self.blocks.push(ParsedBlock {
+            span: 0..0,
+            block,
+            pairs: Vec::new(),
+            variants: IndexMap::new(),
+        });
+        self
+    }
+
+    pub fn build(&self) -> DB {
+        DB::from_blocks(&self.blocks)
+    }
+}
+
+/// Asks `db` about `target` with `answers` and panics unless the values
+/// found are `expected`, in any order. Every matching value is looked for
+/// when several are expected, otherwise only the best one.
+#[track_caller]
+pub fn assert_concludes(
+    db: &DB,
+    strategy: &dyn MatchStrategy,
+    target: Option<&str>,
+    answers: &[(&str, &str)],
+    expected: &[&str],
+) {
+    let outcome = case(target, answers, expected).run(db, strategy);
+    assert!(
+        outcome.passed(),
+        "answers {:?} should conclude {:?}, found {:?}",
+        answers,
+        outcome.expected,
+        outcome.actual
+    );
+}
+
+/// Runs the tests of `db` and panics listing the ones that fail.
+#[track_caller]
+pub fn assert_tests_pass(db: &DB, strategy: &dyn MatchStrategy) {
+    let failed = db
+        .tests
+        .iter()
+        .map(|case| (case, case.run(db, strategy)))
+        .filter(|(_, outcome)| !outcome.passed())
+        .map(|(case, outcome)| {
+            format!(
+                "answers {:?} should conclude {:?}, found {:?}",
+                case.answers, outcome.expected, outcome.actual
+            )
+        })
+        .collect::<Vec<_>>();
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
+
+fn case(target: Option<&str>, answers: &[(&str, &str)], expected: &[&str]) -> TestCase {
+    TestCase {
+        name: String::new(),
+        target: target.map(str::to_string),
+        answers: answers
+            .iter()
+            .map(|&(category, value)| (category.to_string(), value.to_string()))
+            .collect::<IndexMap<_, _>>(),
+        degrees: IndexMap::new(),
+        find_all: expected.len() > 1,
+        strategy: None,
+        threshold: None,
+        expected: expected.iter().map(|x| x.to_string()).collect(),
+    }
+}
@@ -0,0 +1,178 @@
+//! Recorded consultations used as regression tests of a knowledge base: the
+//! answers given and the conclusion they are expected to lead to.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::Budget;
+use crate::parser::{merge_list, name_key, Block, ParseCache, DB};
+use crate::schema::{line_col, Warning};
+use crate::strategy::{self, MatchStrategy, Query};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestCase {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub answers: IndexMap<String, String>,
+    /// Degrees of answers that do not fully hold, for fuzzy matching.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub degrees: IndexMap<String, f64>,
+    #[serde(default)]
+    pub find_all: bool,
+    /// Name of the strategy the case was recorded with; the one the case
+    /// is run with otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    /// Threshold of scored matching the case was recorded with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
+    /// Values the query should find, in any order.
+    pub expected: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CaseOutcome {
+    pub name: String,
+    pub expected: Vec<String>,
+    pub actual: Vec<String>,
+}
+
+impl CaseOutcome {
+    pub fn passed(&self) -> bool {
+        let mut expected = self.expected.iter().collect::<Vec<_>>();
+        let mut actual = self.actual.iter().collect::<Vec<_>>();
+        expected.sort_unstable();
+        actual.sort_unstable();
+        expected == actual
+    }
+}
+
+/// File the cases of the knowledge base at `path` are kept in.
+pub fn cases_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".cases.json");
+    path.with_file_name(name)
+}
+
+pub fn parse_cases(contents: &str) -> serde_json::Result<Vec<TestCase>> {
+    serde_json::from_str(contents)
+}
+
+pub fn format_cases(cases: &[TestCase]) -> String {
+    serde_json::to_string_pretty(cases).unwrap_or_default()
+}
+
+/// Runs the tests embedded in the base, each with the strategy it names or
+/// `strategy`, reporting each failing one at the position of its block and
+/// every category answered twice at the repeated answer.
+pub fn validate(cache: &ParseCache, db: &DB, strategy: &dyn MatchStrategy) -> Vec<Warning> {
+    let source = cache.source();
+    let mut warnings = Vec::new();
+    for parsed in cache.blocks() {
+        let Block::Test(case) = &parsed.block else {
+            continue;
+        };
+
+        let mut answered = HashSet::new();
+        for span in parsed.pairs.iter() {
+            let category = source[span.clone()]
+                .split('-')
+                .next()
+                .unwrap_or_default()
+                .trim();
+            if !answered.insert(name_key(category)) {
+                warnings.push(Warning {
+                    message: format!(
+                        "test answers `{}` more than once, only the last answer is checked",
+                        category
+                    ),
+                    position: line_col(source, span.start),
+                });
+            }
+        }
+
+        let outcome = embedded(db, case).run(db, strategy);
+        if outcome.passed() {
+            continue;
+        }
+        let found = if outcome.actual.is_empty() {
+            "nothing".to_string()
+        } else {
+            format!("`{}`", outcome.actual.join(", "))
+        };
+        warnings.push(Warning {
+            message: format!(
+                "test expected `{}`, found {}",
+                outcome.expected.join(", "),
+                found
+            ),
+            position: line_col(source, parsed.span.start),
+        });
+    }
+    warnings
+}
+
+/// Test of a block as the base keeps it, its expected values spelled the
+/// way the base spells them and aliases replaced with their values.
+fn embedded(db: &DB, case: &TestCase) -> TestCase {
+    let expected = case
+        .expected
+        .iter()
+        .map(|value| db.canonical(&db.spelling(value).to_string()).clone())
+        .collect();
+    TestCase {
+        expected,
+        ..case.clone()
+    }
+}
+
+impl TestCase {
+    /// Replaces value `from` of `category` with `into` in the answers and
+    /// the expected values, returning whether anything changed.
+    pub fn merge_value(&mut self, category: &str, from: &str, into: &str) -> bool {
+        let mut changed = false;
+        if let Some(answer) = self.answers.get_mut(category).filter(|x| *x == from) {
+            *answer = into.to_string();
+            changed = true;
+        }
+        if self.target.as_deref() == Some(category) && self.expected.iter().any(|x| x == from) {
+            merge_list(&mut self.expected, from, into);
+            changed = true;
+        }
+        changed
+    }
+
+    /// Runs the case with the strategy it was recorded with, or `strategy`
+    /// when it names none.
+    pub fn run(&self, db: &DB, strategy: &dyn MatchStrategy) -> CaseOutcome {
+        let strategy = match self.strategy.as_deref().and_then(strategy::by_name) {
+            Some(recorded) => recorded,
+            None => strategy,
+        };
+        let budget = Budget::default();
+        let query = Query {
+            target: self.target.as_ref(),
+            answers: self
+                .answers
+                .iter()
+                .map(|(category, value)| {
+                    let degree = self.degrees.get(category).copied().unwrap_or(1.0);
+                    (category, value, degree)
+                })
+                .collect(),
+            find_all: self.find_all,
+            threshold: self.threshold.unwrap_or(0.0),
+            budget: &budget,
+        };
+
+        CaseOutcome {
+            name: self.name.clone(),
+            expected: self.expected.clone(),
+            actual: strategy.run(db, &query).values(),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! `--check`: parses and lints a knowledge base without opening the window,
+//! printing the diagnostics as JSON on one line for other tools. Failing to
+//! read the arguments or the file is reported the same way.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::{
+    encoding::{self, FileEncoding},
+    kb_format::KbFormat,
+    lint::{self, Check},
+    parser::{parse_with_cache, ParserError},
+};
+
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    message: String,
+    severity: Severity,
+    line: usize,
+    column: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+impl Diagnostic {
+    /// Failure not tied to a place in the base.
+    fn failure(message: String) -> Self {
+        Self {
+            message,
+            severity: Severity::Error,
+            line: 0,
+            column: 0,
+        }
+    }
+}
+
+/// Whether the command line asks for a check instead of the window.
+pub fn requested(args: &[OsString]) -> bool {
+    args.iter().any(|arg| arg == "--check")
+}
+
+/// Checks the base named in `args` with every lint, failing when the
+/// arguments do not name exactly one base or it cannot be read or parsed.
+pub fn run(args: &[OsString]) -> ExitCode {
+    let diagnostics = match base_path(args) {
+        Ok(path) => check(&path),
+        Err(message) => vec![Diagnostic::failure(message)],
+    };
+
+    println!(
+        "{}",
+        serde_json::to_string(&diagnostics).unwrap_or_default()
+    );
+
+    let failed = diagnostics
+        .iter()
+        .any(|x| matches!(x.severity, Severity::Error));
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn base_path(args: &[OsString]) -> Result<PathBuf, String> {
+    let mut paths = Vec::new();
+    for arg in args.iter().filter(|arg| *arg != "--check") {
+        match arg.to_str() {
+            Some(flag) if flag.starts_with("--") => {
+                return Err(format!("unknown option {} with --check", flag));
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    match paths.as_slice() {
+        [path] => Ok(path.clone()),
+        [] => Err("no knowledge base to check, usage: --check <path>".to_string()),
+        _ => Err("more than one knowledge base to check, usage: --check <path>".to_string()),
+    }
+}
+
+/// Parses and lints the base at `path`. Files in other formats than the DSL
+/// are only parsed.
+fn check(path: &Path) -> Vec<Diagnostic> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            return vec![Diagnostic::failure(format!(
+                "failed to read {}: {}",
+                path.display(),
+                err
+            ))]
+        }
+    };
+    let (contents, _) = encoding::decode(&bytes, FileEncoding::Auto);
+
+    match KbFormat::Auto.resolve(path) {
+        KbFormat::Dsl => match parse_with_cache(&contents) {
+            Ok((cache, db)) => lint::lint(&cache, &db, &Check::ALL)
+                .into_iter()
+                .map(|finding| Diagnostic {
+                    message: finding.warning.message,
+                    severity: Severity::Warning,
+                    line: finding.warning.position.0,
+                    column: finding.warning.position.1,
+                })
+                .collect(),
+            Err(err) => errors(err),
+        },
+        format => format
+            .source()
+            .parse(&contents)
+            .err()
+            .map_or_else(Vec::new, errors),
+    }
+}
+
+fn errors(err: ParserError) -> Vec<Diagnostic> {
+    let errors = match err {
+        ParserError::Parse(message, position) => vec![(message, position)],
+        ParserError::Recovered(errors, _) => errors,
+    };
+
+    errors
+        .into_iter()
+        .map(|(message, (line, column))| Diagnostic {
+            // Pest draws the offending line above the reason; only the
+            // reason is kept.
+            message: message
+                .lines()
+                .map(str::trim)
+                .rfind(|line| !line.is_empty())
+                .unwrap_or_default()
+                .trim_start_matches("= ")
+                .to_string(),
+            severity: Severity::Error,
+            line,
+            column,
+        })
+        .collect()
+}
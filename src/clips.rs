@@ -0,0 +1,222 @@
+//! Front-end for a subset of CLIPS: `defrule` constructs whose left-hand
+//! side is a list of `(category value)` patterns and whose right-hand side
+//! asserts a single `(category value)` fact.
+//!
+//! ```text
+//! (defrule tiger "optional comment"
+//!    (class mammal)
+//!    (diet meat)
+//!    =>
+//!    (assert (animal tiger)))
+//! ```
+
+use std::sync::Arc;
+
+use crate::kb_format::{identifier, KnowledgeSource};
+use crate::parser::{Condition, Entry, ParserError, Provenance, DB};
+
+pub struct Clips;
+
+impl KnowledgeSource for Clips {
+    fn parse(&self, contents: &str) -> Result<DB, ParserError> {
+        let mut db = DB::new();
+
+        for form in read_forms(contents)? {
+            let (entry, position) = parse_rule(form)?;
+            if entry.conditions.is_empty() {
+                return Err(error("rule has no patterns", position));
+            }
+            db.push_entry(entry);
+        }
+
+        Ok(db)
+    }
+}
+
+#[derive(Debug)]
+enum Expr {
+    Atom(String, (usize, usize)),
+    List(Vec<Expr>, (usize, usize)),
+}
+
+impl Expr {
+    fn position(&self) -> (usize, usize) {
+        match self {
+            Expr::Atom(_, position) | Expr::List(_, position) => *position,
+        }
+    }
+}
+
+fn parse_rule(form: Expr) -> Result<(Entry, (usize, usize)), ParserError> {
+    let Expr::List(items, position) = form else {
+        return Err(error("expected a construct", form.position()));
+    };
+
+    let mut items = items.into_iter();
+    match items.next() {
+        Some(Expr::Atom(keyword, _)) if keyword == "defrule" => {}
+        Some(Expr::Atom(keyword, position)) => {
+            return Err(error(
+                &format!("unsupported construct `{}`", keyword),
+                position,
+            ))
+        }
+        _ => return Err(error("expected `defrule`", position)),
+    }
+    if !matches!(items.next(), Some(Expr::Atom(..))) {
+        return Err(error("expected a rule name", position));
+    }
+
+    let mut conditions = Vec::new();
+    let mut conclusion = None;
+    let mut note = None;
+    let mut after_arrow = false;
+    for item in items {
+        match item {
+            Expr::Atom(atom, _) if atom.starts_with('"') && conditions.is_empty() => {
+                let comment = atom.trim_matches('"').split_whitespace();
+                note = Some(comment.collect::<Vec<_>>().join(" ")).filter(|x| !x.is_empty());
+            }
+            Expr::Atom(atom, _) if atom == "=>" && !after_arrow => after_arrow = true,
+            Expr::List(pattern, position) if !after_arrow => {
+                let (category, value) = pair(pattern, position)?;
+                conditions.push(Condition::new(category, value));
+            }
+            Expr::List(action, position) if conclusion.is_none() => {
+                conclusion = Some(assertion(action, position)?);
+            }
+            item => return Err(error("unexpected element of the rule", item.position())),
+        }
+    }
+
+    let Some((category, value)) = conclusion else {
+        return Err(error("rule does not assert a fact", position));
+    };
+    Ok((
+        Entry {
+            value,
+            category,
+            conditions,
+            ignored: Vec::new(),
+            provenance: Provenance::default(),
+            disabled: false,
+            note,
+        },
+        position,
+    ))
+}
+
+fn assertion(action: Vec<Expr>, position: (usize, usize)) -> Result<(String, String), ParserError> {
+    let mut action = action.into_iter();
+    match (action.next(), action.next(), action.next()) {
+        (Some(Expr::Atom(keyword, _)), Some(Expr::List(fact, position)), None)
+            if keyword == "assert" =>
+        {
+            pair(fact, position)
+        }
+        _ => Err(error("expected `(assert (category value))`", position)),
+    }
+}
+
+fn pair(items: Vec<Expr>, position: (usize, usize)) -> Result<(String, String), ParserError> {
+    match <[Expr; 2]>::try_from(items) {
+        Ok([Expr::Atom(category, _), Expr::Atom(value, _)]) => {
+            let located = |err: ParserError| match err {
+                ParserError::Parse(message, _) => ParserError::Parse(message, position),
+                err => err,
+            };
+            Ok((
+                identifier(category).map_err(located)?,
+                identifier(value).map_err(located)?,
+            ))
+        }
+        _ => Err(error("expected `(category value)`", position)),
+    }
+}
+
+fn read_forms(contents: &str) -> Result<Vec<Expr>, ParserError> {
+    let mut stack: Vec<(Vec<Expr>, (usize, usize))> = Vec::new();
+    let mut forms = Vec::new();
+    let mut chars = contents.chars().peekable();
+    let (mut line, mut column) = (1, 1);
+
+    while let Some(&x) = chars.peek() {
+        let position = (line, column);
+        let mut advance = |x: char| {
+            if x == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        };
+
+        match x {
+            ';' => {
+                while let Some(x) = chars.next_if(|x| *x != '\n') {
+                    advance(x);
+                }
+                continue;
+            }
+            '(' => {
+                chars.next();
+                advance(x);
+                stack.push((Vec::new(), position));
+                continue;
+            }
+            ')' => {
+                chars.next();
+                advance(x);
+                let Some((items, start)) = stack.pop() else {
+                    return Err(error("unbalanced `)`", position));
+                };
+                let list = Expr::List(items, start);
+                match stack.last_mut() {
+                    Some((parent, _)) => parent.push(list),
+                    None => forms.push(list),
+                }
+                continue;
+            }
+            x if x.is_whitespace() => {
+                chars.next();
+                advance(x);
+                continue;
+            }
+            _ => {}
+        }
+
+        let mut atom = String::new();
+        let quoted = x == '"';
+        while let Some(x) = chars.next_if(|x| {
+            if quoted {
+                true
+            } else {
+                !x.is_whitespace() && !matches!(x, '(' | ')' | ';')
+            }
+        }) {
+            advance(x);
+            atom.push(x);
+            if quoted && x == '"' && atom.len() > 1 {
+                break;
+            }
+        }
+        if quoted && (atom.len() < 2 || !atom.ends_with('"')) {
+            return Err(error("unterminated string", position));
+        }
+
+        match stack.last_mut() {
+            Some((parent, _)) => parent.push(Expr::Atom(atom, position)),
+            None => return Err(error("expected `(`", position)),
+        }
+    }
+
+    if let Some((_, position)) = stack.pop() {
+        return Err(error("unbalanced `(`", position));
+    }
+
+    Ok(forms)
+}
+
+fn error(message: &str, position: (usize, usize)) -> ParserError {
+    ParserError::Parse(Arc::new(message.to_string()), position)
+}
@@ -0,0 +1,246 @@
+//! Which questions lead to the conclusions of a target category: questions,
+//! the values answered to them, and the entries those values take part in.
+
+use iced::{
+    mouse,
+    widget::{
+        canvas::{self, Canvas, Frame, Geometry, Path, Stroke},
+        column, pick_list, row, scrollable, text,
+    },
+    Alignment, Color, Element, Length, Pixels, Point, Rectangle, Renderer, Theme,
+};
+use indexmap::IndexSet;
+
+use crate::{
+    a11y::Accessible,
+    generation::{Change, Subscriber},
+    main_window::Message,
+    parser::DB,
+};
+
+const ROW_HEIGHT: f32 = 24.0;
+const TEXT_SIZE: f32 = 14.0;
+/// Longest label drawn in full; longer ones are cut to fit their column.
+const LABEL_CHARS: usize = 32;
+
+#[derive(Debug, Default)]
+pub struct Dependencies {
+    pub target: Option<String>,
+}
+
+impl Dependencies {
+    pub fn view<'a>(&'a self, db: &'a DB) -> Element<'a, Message> {
+        let target = self.target.as_ref().or(db.target.as_ref());
+        let controls = row![
+            text("Целевая категория"),
+            pick_list(
+                db.conclusion_categories()
+                    .into_iter()
+                    .cloned()
+                    .collect::<Vec<_>>(),
+                target.cloned(),
+                Message::DependencyTargetSelected,
+            )
+            .placeholder("категория")
+            .named("dependency-target"),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let Some(graph) = target.map(|target| Graph::new(db, target)) else {
+            return controls.into();
+        };
+        if graph.entries.is_empty() {
+            return column![controls, text("Ни одно правило не выводит эту категорию")]
+                .spacing(10)
+                .into();
+        }
+
+        let rows = graph
+            .questions
+            .len()
+            .max(graph.values.len())
+            .max(graph.entries.len());
+        let canvas = Canvas::new(graph)
+            .width(Length::Fill)
+            .height(ROW_HEIGHT * (rows as f32 + 1.0));
+
+        column![
+            controls,
+            row![
+                text("Вопросы").width(Length::Fill),
+                text("Ответы").width(Length::Fill),
+                text("Правила").width(Length::Fill),
+            ],
+            scrollable(canvas),
+        ]
+        .spacing(10)
+        .into()
+    }
+}
+
+impl Subscriber for Dependencies {
+    /// A new base starts from its own target, and a removed category is no
+    /// longer shown.
+    fn refresh(&mut self, db: &DB, change: Change) {
+        let removed = self
+            .target
+            .as_ref()
+            .is_some_and(|target| !db.categories.contains_key(target));
+        if change == Change::Replaced || removed {
+            self.target = None;
+        }
+    }
+}
+
+/// Bipartite layers of the dependencies of one target category.
+struct Graph {
+    /// Categories in conditions, with whether the user is asked about them
+    /// rather than them being concluded by other entries.
+    questions: IndexSet<(String, bool)>,
+    values: IndexSet<(String, String)>,
+    /// Entries with their labels.
+    entries: Vec<(usize, String)>,
+    /// Question to value, by position in the layers.
+    answers: Vec<(usize, usize)>,
+    /// Value to entry, by position in the layers.
+    uses: Vec<(usize, usize)>,
+}
+
+impl Graph {
+    /// Collects the enabled entries concluding `target`, and those
+    /// concluding the categories their conditions depend on in turn.
+    fn new(db: &DB, target: &str) -> Self {
+        let mut categories = IndexSet::from([target.to_string()]);
+        let mut ids = Vec::new();
+        let mut next = 0;
+        while let Some(category) = categories.get_index(next).cloned() {
+            next += 1;
+            for (id, entry) in db.entries.iter().enumerate() {
+                if entry.disabled || entry.category != category {
+                    continue;
+                }
+                ids.push(id);
+                for condition in entry.conditions.iter() {
+                    if db.entries.iter().any(|x| x.category == condition.category) {
+                        categories.insert(condition.category.clone());
+                    }
+                }
+            }
+        }
+        ids.sort_unstable();
+
+        let mut graph = Graph {
+            questions: IndexSet::new(),
+            values: IndexSet::new(),
+            entries: Vec::new(),
+            answers: Vec::new(),
+            uses: Vec::new(),
+        };
+        for id in ids {
+            let entry = &db.entries[id];
+            let position = graph.entries.len();
+            graph.entries.push((
+                id,
+                format!("{}: {} - {}", id + 1, entry.category, entry.value),
+            ));
+
+            for condition in entry.conditions.iter() {
+                let asked = !categories.contains(&condition.category);
+                let (question, _) = graph
+                    .questions
+                    .insert_full((condition.category.clone(), asked));
+                let (value, added) = graph
+                    .values
+                    .insert_full((condition.category.clone(), condition.value.clone()));
+                if added {
+                    graph.answers.push((question, value));
+                }
+                graph.uses.push((value, position));
+            }
+        }
+
+        graph
+    }
+}
+
+impl canvas::Program<Message> for Graph {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.extended_palette();
+        let column = bounds.width / 3.0;
+        let y = |row: usize| ROW_HEIGHT * (row as f32 + 0.5);
+        let edge = Stroke::default()
+            .with_color(Color {
+                a: 0.5,
+                ..palette.primary.base.color
+            })
+            .with_width(1.0);
+
+        for &(from, to) in self.answers.iter() {
+            frame.stroke(
+                &Path::line(Point::new(column - 8.0, y(from)), Point::new(column, y(to))),
+                edge.clone(),
+            );
+        }
+        for &(from, to) in self.uses.iter() {
+            frame.stroke(
+                &Path::line(
+                    Point::new(2.0 * column - 8.0, y(from)),
+                    Point::new(2.0 * column, y(to)),
+                ),
+                edge.clone(),
+            );
+        }
+
+        let mut label = |layer: usize, row: usize, content: String, color: Color| {
+            frame.fill_text(canvas::Text {
+                content: shorten(content),
+                position: Point::new(layer as f32 * column + 4.0, y(row)),
+                color,
+                size: Pixels(TEXT_SIZE),
+                vertical_alignment: iced::alignment::Vertical::Center,
+                ..canvas::Text::default()
+            });
+        };
+        let text_color = palette.background.base.text;
+        // Categories concluded by other entries are told apart from the
+        // questions the user answers.
+        let derived = palette.background.strong.color;
+        for (row, (category, asked)) in self.questions.iter().enumerate() {
+            label(
+                0,
+                row,
+                category.clone(),
+                if *asked { text_color } else { derived },
+            );
+        }
+        for (row, (_, value)) in self.values.iter().enumerate() {
+            label(1, row, value.clone(), text_color);
+        }
+        for (row, (_, entry)) in self.entries.iter().enumerate() {
+            label(2, row, entry.clone(), text_color);
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+fn shorten(label: String) -> String {
+    if label.chars().count() <= LABEL_CHARS {
+        return label;
+    }
+
+    let mut short = label.chars().take(LABEL_CHARS - 1).collect::<String>();
+    short.push('…');
+    short
+}
@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use iced::futures::{channel::mpsc::Sender, SinkExt, StreamExt};
+use reqwest::{header, StatusCode};
+use sha2::{Digest, Sha256};
+
+use crate::encoding::{self, FileEncoding};
+use crate::store::Error;
+use crate::store::StoreEvent;
+
+/// Downloads the base at `url`, falling back to the copy cached by the last
+/// download when offline.
+pub async fn fetch(
+    url: &str,
+    encoding: FileEncoding,
+    sender: &mut Sender<StoreEvent>,
+) -> Result<Arc<String>, Error> {
+    let (cache_file, etag_file) = cache_paths(url);
+    let cached_etag = tokio::fs::read_to_string(&etag_file).await.ok();
+
+    let mut request = reqwest::Client::new().get(url);
+    if let Some(etag) = cached_etag.as_ref() {
+        request = request.header(header::IF_NONE_MATCH, etag);
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(err) => {
+            return read_cache(&cache_file)
+                .await
+                .ok_or_else(|| network_error(err));
+        }
+    };
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some(contents) = read_cache(&cache_file).await {
+            return Ok(contents);
+        }
+    }
+
+    let response = response.error_for_status().map_err(network_error)?;
+
+    let total = response.content_length();
+    let etag = response
+        .headers()
+        .get(header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_string);
+
+    let mut received = 0;
+    let mut bytes = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(network_error)?;
+        received += chunk.len() as u64;
+        bytes.extend_from_slice(&chunk);
+
+        let _ = sender.send(StoreEvent::Progress(received, total)).await;
+    }
+
+    let (contents, _) = encoding::decode(&bytes, encoding);
+
+    if let Some(parent) = cache_file.parent() {
+        if tokio::fs::create_dir_all(parent).await.is_ok() {
+            let _ = tokio::fs::write(&cache_file, &contents).await;
+            match etag {
+                Some(etag) => {
+                    let _ = tokio::fs::write(&etag_file, etag).await;
+                }
+                None => {
+                    let _ = tokio::fs::remove_file(&etag_file).await;
+                }
+            }
+        }
+    }
+
+    Ok(Arc::new(contents))
+}
+
+async fn read_cache(path: &Path) -> Option<Arc<String>> {
+    tokio::fs::read_to_string(path).await.ok().map(Arc::new)
+}
+
+fn cache_paths(url: &str) -> (PathBuf, PathBuf) {
+    // Stable across builds, unlike the hasher of the standard library.
+    let key = format!("{:x}", Sha256::digest(url.as_bytes()));
+
+    let dir = dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("expert_kb")
+        .join("downloads");
+
+    (
+        dir.join(format!("{key}.kb")),
+        dir.join(format!("{key}.etag")),
+    )
+}
+
+fn network_error(err: reqwest::Error) -> Error {
+    Error::Network(Arc::new(err.to_string()))
+}
@@ -1,45 +1,692 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
 use iced::{
-    widget::{column, horizontal_space, row, text, text_editor},
-    Element, Length,
+    advanced::{
+        layout::{self, Layout},
+        renderer,
+        text::{LineHeight, Paragraph, Renderer as _, Shaping, Text},
+        widget::{self, tree, Tree, Widget},
+        Clipboard, Shell,
+    },
+    alignment, event, mouse, theme,
+    widget::{
+        button, column, container, horizontal_space, pick_list, row, text, text_editor, tooltip,
+        Column,
+    },
+    window, Border, Color, Element, Event, Length, Point, Rectangle, Renderer, Size, Theme, Vector,
 };
 
+use crate::a11y::Accessible;
+use crate::encoding::{FileEncoding, LineEnding, TextFormat};
 use crate::main_window::Message;
+use crate::schema::Conflict;
+use crate::store::Error;
+
+const WARNING_COLOR: Color = Color::from_rgb(0.9, 0.6, 0.1);
+
+/// Pause in typing after which the editor contents are checked in the background.
+pub const CHECK_DELAY: Duration = Duration::from_millis(500);
+
+/// Space iced leaves around the text of an editor.
+const EDITOR_PADDING: f32 = 5.0;
+
+/// Height the editor is laid out up to, far more than any base needs.
+const MAX_HEIGHT: f32 = 1.0e6;
+
+/// Lines scrolled by a notch of the mouse wheel.
+const WHEEL_LINES: f32 = 3.0;
 
 #[derive(Debug)]
 pub struct TextEditor {
     content: text_editor::Content,
+    /// Format the text is saved in, detected when the file was opened.
+    pub format: TextFormat,
+
+    /// Bumped on every change of the text.
+    revision: u64,
+    /// Revision the current base was parsed from, if any.
+    applied: Option<u64>,
+    /// Revision last read from or written to the file, if any.
+    saved: Option<u64>,
+    diagnostics: Diagnostics,
+    /// Duplicate and contradicting entries found by the last check.
+    conflicts: Vec<Conflict>,
+    /// Revision the conflicts were found at.
+    checked: u64,
+}
+
+#[derive(Debug, Default)]
+enum Diagnostics {
+    #[default]
+    Unchecked,
+    Valid,
+    /// First error and the number of further errors.
+    Invalid(Arc<String>, (usize, usize), usize),
 }
 
 impl Default for TextEditor {
     fn default() -> Self {
         Self {
             content: text_editor::Content::new(),
+            format: TextFormat::default(),
+            revision: 0,
+            applied: Some(0),
+            saved: Some(0),
+            diagnostics: Diagnostics::default(),
+            conflicts: Vec::new(),
+            checked: 0,
         }
     }
 }
 
 impl TextEditor {
-    pub fn view(&self) -> Element<Message> {
+    /// `lockable` offers to lock the editor again, for bases with a
+    /// passphrase.
+    pub fn view(&self, lockable: bool) -> Element<'_, Message> {
+        let diagnostics = match &self.diagnostics {
+            Diagnostics::Unchecked => text(""),
+            Diagnostics::Valid => text("Ошибок нет"),
+            Diagnostics::Invalid(msg, (line, column), more) => text(format!(
+                "Ошибка {}:{}: {}{}",
+                line,
+                column,
+                msg.lines().last().unwrap_or_default().trim(),
+                if *more > 0 {
+                    format!(" (и ещё {})", more)
+                } else {
+                    String::new()
+                }
+            ))
+            .style(theme::Text::Color(iced::Color::from_rgb(0.9, 0.4, 0.4))),
+        };
+
+        // Conflicts found before the last edits would mark the wrong text.
+        let spans = if self.checked == self.revision {
+            self.conflicts
+                .iter()
+                .map(|conflict| (conflict.span.clone(), conflict.message.as_str()))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
         column![
-            text_editor(&self.content)
-                .height(Length::Fill)
-                .on_action(Message::EditorActionPerformed),
+            Element::new(Underlined {
+                editor: text_editor(&self.content)
+                    .on_action(Message::EditorActionPerformed)
+                    .into(),
+                content: &self.content,
+                revision: self.revision,
+                spans,
+            }),
             row![
-                horizontal_space(),
-                text({
-                    let (line, column) = self.content.cursor_position();
-                    format!("{}:{}", line + 1, column + 1)
-                })
+                button("Применить")
+                    .on_press(Message::ApplyEditor)
+                    .style(theme::Button::Primary)
+                    .named("editor-apply"),
+                button("Перенумеровать")
+                    .on_press(Message::Renumber)
+                    .style(theme::Button::Secondary)
+                    .named("editor-renumber"),
+                button("Сохранить")
+                    .on_press(Message::SaveEditor)
+                    .style(theme::Button::Secondary)
+                    .named("editor-save"),
+                button("Сохранить как...")
+                    .on_press(Message::SaveEditorAs)
+                    .style(theme::Button::Secondary)
+                    .named("editor-save-as"),
             ]
+            .push_maybe(lockable.then(|| {
+                button("Заблокировать")
+                    .on_press(Message::Lock)
+                    .style(theme::Button::Secondary)
+                    .named("editor-lock")
+            }))
+            .push(diagnostics)
+            .push_maybe(self.view_conflicts())
+            .push(horizontal_space())
+            .push(self.view_format())
+            .push(text({
+                let (line, column) = self.content.cursor_position();
+                format!("{}:{}", line + 1, column + 1)
+            }))
+            .spacing(10)
+            .align_items(iced::Alignment::Center)
         ]
         .into()
     }
 
+    /// Number of conflicts, listing them when hovered. The editor itself
+    /// only underlines the conflicting entries.
+    fn view_conflicts(&self) -> Option<Element<'_, Message>> {
+        if self.conflicts.is_empty() {
+            return None;
+        }
+
+        let list = self
+            .conflicts
+            .iter()
+            .fold(Column::new().spacing(3), |col, conflict| {
+                col.push(
+                    text(format!(
+                        "Строка {}: {}",
+                        conflict.lines.start + 1,
+                        conflict.message
+                    ))
+                    .size(14),
+                )
+            });
+
+        Some(
+            tooltip(
+                text(format!("Конфликтов: {}", self.conflicts.len()))
+                    .style(theme::Text::Color(WARNING_COLOR)),
+                container(list).padding(8),
+                tooltip::Position::Top,
+            )
+            .style(theme::Container::Box)
+            .into(),
+        )
+    }
+
+    /// Encoding and line endings of the file, which can be changed to
+    /// convert it on the next save.
+    fn view_format(&self) -> Element<'_, Message> {
+        let format = self.format;
+        let encoding = pick_list(
+            &FileEncoding::ALL[1..],
+            format.file_encoding(),
+            move |encoding| Message::TextFormatChanged(format.with_encoding(encoding)),
+        )
+        .placeholder(format.encoding.name())
+        .named("editor-encoding");
+        let line_ending = pick_list(
+            &LineEnding::ALL[..],
+            Some(format.line_ending),
+            move |line_ending| Message::TextFormatChanged(format.with_line_ending(line_ending)),
+        )
+        .named("editor-line-ending");
+
+        row![text("Формат файла:"), encoding, line_ending]
+            .spacing(5)
+            .align_items(iced::Alignment::Center)
+            .into()
+    }
+
+    /// Replaces the text with the source of the base about to be shown.
     pub fn set_content(&mut self, cont: &str) {
         self.content = text_editor::Content::with_text(cont);
+        self.revision += 1;
+        self.applied = Some(self.revision);
+        self.diagnostics = Diagnostics::Unchecked;
+        self.conflicts.clear();
+    }
+
+    /// Inserts `snippet` as separate lines at the cursor.
+    pub fn insert(&mut self, snippet: &str) {
+        let (_, column) = self.content.cursor_position();
+        let snippet = if column == 0 {
+            format!("{}\n", snippet)
+        } else {
+            format!("\n{}\n", snippet)
+        };
+
+        self.perform_action(text_editor::Action::Edit(text_editor::Edit::Paste(
+            Arc::new(snippet),
+        )));
+    }
+
+    pub fn text(&self) -> String {
+        self.content.text()
+    }
+
+    /// Moves the cursor to `line` and `column`, both counted from 1.
+    pub fn go_to(&mut self, line: usize, column: usize) {
+        use text_editor::{Action, Motion};
+
+        self.content.perform(Action::Move(Motion::DocumentStart));
+        for _ in 1..line {
+            self.content.perform(Action::Move(Motion::Down));
+        }
+        for _ in 1..column {
+            self.content.perform(Action::Move(Motion::Right));
+        }
     }
 
     pub fn perform_action(&mut self, action: text_editor::Action) {
+        if action.is_edit() {
+            self.revision += 1;
+        }
+
         self.content.perform(action);
     }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Records that the base was parsed from the text at `revision`, or
+    /// from none of it.
+    pub fn mark_applied(&mut self, revision: Option<u64>) {
+        self.applied = revision;
+    }
+
+    /// Whether the base was parsed from the text as it is now.
+    pub fn is_applied(&self) -> bool {
+        self.applied == Some(self.revision)
+    }
+
+    /// Records that the file holds the text at `revision`, or that the base
+    /// was changed in a way the file does not hold.
+    pub fn mark_saved(&mut self, revision: Option<u64>) {
+        self.saved = revision;
+    }
+
+    /// Whether the file holds the text as it is now.
+    pub fn is_saved(&self) -> bool {
+        self.saved == Some(self.revision)
+    }
+
+    pub fn set_diagnostics(&mut self, result: Result<Vec<Conflict>, Error>) {
+        self.conflicts.clear();
+        self.checked = self.revision;
+        self.diagnostics = match result {
+            Ok(conflicts) => {
+                self.conflicts = conflicts;
+                Diagnostics::Valid
+            }
+            Err(Error::Parse(msg, pos)) => Diagnostics::Invalid(msg, pos, 0),
+            Err(Error::ParseErrors(errors, _)) => match errors.first() {
+                Some((msg, pos)) => Diagnostics::Invalid(msg.clone(), *pos, errors.len() - 1),
+                None => Diagnostics::Unchecked,
+            },
+            Err(_) => Diagnostics::Unchecked,
+        };
+    }
+}
+
+/// Text editor underlining the blocks of conflicting entries, with the
+/// conflict shown over the hovered one. iced's editor scrolls itself without
+/// telling where to, so it is laid out at its full height and scrolled here.
+struct Underlined<'a> {
+    editor: Element<'a, Message>,
+    content: &'a text_editor::Content,
+    revision: u64,
+    /// Bytes of the text and the conflict found there.
+    spans: Vec<(Range<usize>, &'a str)>,
+}
+
+#[derive(Default)]
+struct Scroll {
+    offset: f32,
+    /// Cursor position the editor was last scrolled to.
+    cursor: (usize, usize),
+    /// Revision and width the text was measured at.
+    measured: Option<(u64, f32)>,
+    /// Top of every line and the bottom of the last one.
+    tops: Vec<f32>,
+    /// Text of the spans, line by line, with the span it belongs to.
+    underlined: Vec<(Rectangle, usize)>,
+    /// Width of each line when not wrapped.
+    widths: HashMap<String, f32>,
+}
+
+impl<'a> Underlined<'a> {
+    /// Finds where each line and span is drawn, relative to the editor.
+    fn measure(&self, state: &mut Scroll, renderer: &Renderer, width: f32) {
+        let size = renderer.default_size();
+        let line_height = f32::from(LineHeight::default().to_absolute(size));
+        let measure = |content: &str, width: f32| {
+            <Renderer as iced::advanced::text::Renderer>::Paragraph::with_text(Text {
+                content,
+                bounds: Size::new(width, f32::INFINITY),
+                size,
+                line_height: LineHeight::default(),
+                font: renderer.default_font(),
+                horizontal_alignment: alignment::Horizontal::Left,
+                vertical_alignment: alignment::Vertical::Top,
+                shaping: Shaping::Advanced,
+            })
+            .min_bounds()
+        };
+        let wrap = width - 2.0 * EDITOR_PADDING;
+
+        let lines = self
+            .content
+            .lines()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>();
+        let mut widths = HashMap::new();
+        let mut tops = Vec::with_capacity(lines.len() + 1);
+        let mut top = EDITOR_PADDING;
+        for line in &lines {
+            let full = state
+                .widths
+                .remove(line)
+                .unwrap_or_else(|| measure(line, f32::INFINITY).width);
+            tops.push(top);
+            top += if full <= wrap {
+                line_height
+            } else {
+                measure(line, wrap).height
+            };
+            widths.insert(line.clone(), full);
+        }
+        tops.push(top);
+
+        let mut underlined = Vec::new();
+        let mut start = 0;
+        for (number, line) in lines.iter().enumerate() {
+            let end = start + line.len();
+            for (index, (span, _)) in self.spans.iter().enumerate() {
+                if span.start >= end.max(start + 1) || span.end <= start {
+                    continue;
+                }
+                let to = span.end.min(end) - start;
+                let from = span.start.max(start) - start;
+                let from = to - line[from..to].trim_start().len();
+                if from == to {
+                    continue;
+                }
+
+                let (top, bottom) = (tops[number], tops[number + 1]);
+                // Wrapped lines are marked whole, as the rows they break
+                // into are not known.
+                let bounds = if widths[line] <= wrap {
+                    let left = EDITOR_PADDING + measure(&line[..from], f32::INFINITY).width;
+                    let right = EDITOR_PADDING + measure(&line[..to], f32::INFINITY).width;
+                    Rectangle::new(Point::new(left, top), Size::new(right - left, line_height))
+                } else {
+                    Rectangle::new(
+                        Point::new(EDITOR_PADDING, top),
+                        Size::new(wrap, bottom - top),
+                    )
+                };
+                underlined.push((bounds, index));
+            }
+            start = end + 1;
+        }
+
+        state.tops = tops;
+        state.underlined = underlined;
+        state.widths = widths;
+        state.measured = Some((self.revision, width));
+    }
+
+    /// Largest offset, with the last line at the bottom.
+    fn max_offset(layout: Layout<'_>) -> f32 {
+        let editor = layout.children().next().map_or(0.0, |x| x.bounds().height);
+        (editor - layout.bounds().height).max(0.0)
+    }
+}
+
+/// Cursor as the editor sees it, scrolled by `offset`.
+fn scrolled(cursor: mouse::Cursor, bounds: Rectangle, offset: f32) -> mouse::Cursor {
+    match cursor.position_over(bounds) {
+        Some(position) => mouse::Cursor::Available(position + Vector::new(0.0, offset)),
+        None => mouse::Cursor::Unavailable,
+    }
+}
+
+impl<'a> Widget<Message, Theme, Renderer> for Underlined<'a> {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<Scroll>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(Scroll::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.editor)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[&self.editor]);
+    }
+
+    fn size(&self) -> Size<Length> {
+        Size::new(Length::Fill, Length::Fill)
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let size = limits.max();
+        let editor = self.editor.as_widget().layout(
+            &mut tree.children[0],
+            renderer,
+            &layout::Limits::new(Size::ZERO, Size::new(size.width, MAX_HEIGHT)),
+        );
+
+        let state = tree.state.downcast_mut::<Scroll>();
+        if state.measured != Some((self.revision, size.width)) {
+            self.measure(state, renderer, size.width);
+        }
+        let max = (editor.size().height - size.height).max(0.0);
+        state.offset = state.offset.min(max);
+
+        layout::Node::with_children(size, vec![editor])
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let state = tree.state.downcast_ref::<Scroll>();
+        let Some(editor) = layout.children().next() else {
+            return;
+        };
+        let origin = Vector::new(editor.bounds().x, editor.bounds().y);
+        let inner = scrolled(cursor, bounds, state.offset);
+
+        <Renderer as renderer::Renderer>::with_layer(renderer, bounds, |renderer| {
+            <Renderer as renderer::Renderer>::with_translation(
+                renderer,
+                Vector::new(0.0, -state.offset),
+                |renderer| {
+                    self.editor.as_widget().draw(
+                        &tree.children[0],
+                        renderer,
+                        theme,
+                        style,
+                        editor,
+                        inner,
+                        &Rectangle {
+                            y: bounds.y + state.offset,
+                            ..bounds
+                        },
+                    );
+
+                    let line_height =
+                        f32::from(LineHeight::default().to_absolute(renderer.default_size()));
+                    for (text, _) in &state.underlined {
+                        let text = *text + origin;
+                        let rows = (text.height / line_height).round().max(1.0) as usize;
+                        for row in 1..=rows {
+                            let y = text.y + row as f32 * line_height - 2.0;
+                            <Renderer as renderer::Renderer>::fill_quad(
+                                renderer,
+                                renderer::Quad {
+                                    bounds: Rectangle::new(
+                                        Point::new(text.x, y),
+                                        Size::new(text.width, 1.5),
+                                    ),
+                                    ..renderer::Quad::default()
+                                },
+                                WARNING_COLOR,
+                            );
+                        }
+                    }
+                },
+            );
+        });
+
+        let hovered = inner.position().and_then(|position| {
+            state
+                .underlined
+                .iter()
+                .find(|(text, _)| (*text + origin).contains(position))
+        });
+        let (Some((_, index)), Some(position)) = (hovered, cursor.position()) else {
+            return;
+        };
+
+        let message = Text {
+            content: self.spans[*index].1,
+            bounds: Size::new(400.0, f32::INFINITY),
+            size: renderer.default_size(),
+            line_height: LineHeight::default(),
+            font: renderer.default_font(),
+            horizontal_alignment: alignment::Horizontal::Left,
+            vertical_alignment: alignment::Vertical::Top,
+            shaping: Shaping::Advanced,
+        };
+        let size = <Renderer as iced::advanced::text::Renderer>::Paragraph::with_text(message)
+            .min_bounds();
+        let size = Size::new(size.width + 12.0, size.height + 8.0);
+        let corner = Point::new(
+            (position.x + 12.0).min(viewport.x + viewport.width - size.width),
+            (position.y + 16.0).min(viewport.y + viewport.height - size.height),
+        );
+        let palette = theme.extended_palette();
+
+        <Renderer as renderer::Renderer>::with_layer(renderer, *viewport, |renderer| {
+            <Renderer as renderer::Renderer>::fill_quad(
+                renderer,
+                renderer::Quad {
+                    bounds: Rectangle::new(corner, size),
+                    border: Border {
+                        color: WARNING_COLOR,
+                        width: 1.0,
+                        radius: 4.0.into(),
+                    },
+                    ..renderer::Quad::default()
+                },
+                palette.background.base.color,
+            );
+            renderer.fill_text(
+                Text {
+                    bounds: size,
+                    ..message
+                },
+                corner + Vector::new(6.0, 4.0),
+                palette.background.base.text,
+                Rectangle::new(corner, size),
+            );
+        });
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn widget::Operation<Message>,
+    ) {
+        if let Some(editor) = layout.children().next() {
+            self.editor
+                .as_widget()
+                .operate(&mut tree.children[0], editor, renderer, operation);
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let bounds = layout.bounds();
+        let max = Self::max_offset(layout);
+        let state = tree.state.downcast_mut::<Scroll>();
+        let line_height = f32::from(LineHeight::default().to_absolute(renderer.default_size()));
+
+        match &event {
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) if cursor.is_over(bounds) => {
+                let scrolled = match *delta {
+                    mouse::ScrollDelta::Lines { y, .. } => y * WHEEL_LINES * line_height,
+                    mouse::ScrollDelta::Pixels { y, .. } => y,
+                };
+                state.offset = (state.offset - scrolled).clamp(0.0, max);
+                return event::Status::Captured;
+            }
+            // The line with the cursor is kept in view, as the editor would.
+            Event::Window(_, window::Event::RedrawRequested(_)) => {
+                let position = self.content.cursor_position();
+                if position != state.cursor {
+                    state.cursor = position;
+                    if let (Some(&top), Some(&bottom)) =
+                        (state.tops.get(position.0), state.tops.get(position.0 + 1))
+                    {
+                        let top = top - EDITOR_PADDING;
+                        let bottom = bottom + EDITOR_PADDING;
+                        if top < state.offset {
+                            state.offset = top;
+                        } else if bottom > state.offset + bounds.height {
+                            state.offset = (bottom - bounds.height).min(max);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let Some(editor) = layout.children().next() else {
+            return event::Status::Ignored;
+        };
+        let offset = state.offset;
+        self.editor.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            editor,
+            scrolled(cursor, bounds, offset),
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        let offset = tree.state.downcast_ref::<Scroll>().offset;
+        layout
+            .children()
+            .next()
+            .map_or(mouse::Interaction::default(), |editor| {
+                self.editor.as_widget().mouse_interaction(
+                    &tree.children[0],
+                    editor,
+                    scrolled(cursor, layout.bounds(), offset),
+                    viewport,
+                    renderer,
+                )
+            })
+    }
 }
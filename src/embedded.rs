@@ -0,0 +1,40 @@
+//! Knowledge bases compiled into the executable, so it can be shipped as a
+//! single file preloaded with its domain knowledge. They are picked up at
+//! build time from the directory named by `EXPERT_KB_EMBED`.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::encoding::{self, FileEncoding};
+use crate::formatter::format_db;
+use crate::kb_format::KbFormat;
+use crate::parser::ParserError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddedBase {
+    pub name: &'static str,
+    /// Name of the embedded file, whose extension tells its format.
+    pub file: &'static str,
+    /// Bytes of the file, in whatever encoding it was written.
+    pub contents: &'static [u8],
+}
+
+pub const BASES: &[EmbeddedBase] = include!(concat!(env!("OUT_DIR"), "/embedded.rs"));
+
+impl EmbeddedBase {
+    /// DSL source of the base, decoded from `encoding` and converted from
+    /// the format of its file.
+    pub fn source(&self, encoding: FileEncoding) -> Result<String, ParserError> {
+        let (contents, _) = encoding::decode(self.contents, encoding);
+        match KbFormat::Auto.resolve(Path::new(self.file)) {
+            KbFormat::Dsl => Ok(contents),
+            format => format.source().parse(&contents).map(|db| format_db(&db)),
+        }
+    }
+}
+
+impl fmt::Display for EmbeddedBase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
@@ -0,0 +1,225 @@
+use std::{borrow::Cow, fmt};
+
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{Encoding, KOI8_R, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileEncoding {
+    #[default]
+    Auto,
+    Utf8,
+    Windows1251,
+    Koi8R,
+    Utf16Le,
+}
+
+impl FileEncoding {
+    pub const ALL: [FileEncoding; 5] = [
+        FileEncoding::Auto,
+        FileEncoding::Utf8,
+        FileEncoding::Windows1251,
+        FileEncoding::Koi8R,
+        FileEncoding::Utf16Le,
+    ];
+
+    fn encoding(self) -> Option<&'static Encoding> {
+        match self {
+            FileEncoding::Auto => None,
+            FileEncoding::Utf8 => Some(UTF_8),
+            FileEncoding::Windows1251 => Some(WINDOWS_1251),
+            FileEncoding::Koi8R => Some(KOI8_R),
+            FileEncoding::Utf16Le => Some(UTF_16LE),
+        }
+    }
+}
+
+impl fmt::Display for FileEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.encoding() {
+            Some(encoding) => f.write_str(encoding.name()),
+            None => f.write_str("Авто"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub const ALL: [LineEnding; 2] = [LineEnding::Lf, LineEnding::CrLf];
+
+    /// Line ending most lines of `text` end with.
+    fn detect(text: &str) -> Self {
+        let lines = text.matches('\n').count();
+        let crlf = text.matches("\r\n").count();
+        if crlf * 2 > lines {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    fn apply(self, text: &str) -> Cow<'_, str> {
+        let text = if text.contains('\r') {
+            Cow::Owned(text.replace("\r\n", "\n"))
+        } else {
+            Cow::Borrowed(text)
+        };
+        match self {
+            LineEnding::Lf => text,
+            LineEnding::CrLf => Cow::Owned(text.replace('\n', "\r\n")),
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+        })
+    }
+}
+
+/// Encoding and line endings a text file was read with, so it can be
+/// written back the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextFormat {
+    pub encoding: &'static Encoding,
+    /// Whether the file starts with a byte order mark.
+    pub bom: bool,
+    pub line_ending: LineEnding,
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            bom: false,
+            line_ending: LineEnding::default(),
+        }
+    }
+}
+
+impl TextFormat {
+    /// Encoding among the ones that can be picked, `None` for other
+    /// detected ones.
+    pub fn file_encoding(&self) -> Option<FileEncoding> {
+        FileEncoding::ALL
+            .into_iter()
+            .find(|x| x.encoding() == Some(self.encoding))
+    }
+
+    /// The format converted to `encoding`. UTF-16 is always written with a
+    /// byte order mark, UTF-8 keeps it only if it had one.
+    pub fn with_encoding(self, encoding: FileEncoding) -> Self {
+        let Some(encoding) = encoding.encoding() else {
+            return self;
+        };
+        let bom = encoding == UTF_16LE || (self.bom && encoding == UTF_8);
+
+        Self {
+            encoding,
+            bom,
+            ..self
+        }
+    }
+
+    pub fn with_line_ending(self, line_ending: LineEnding) -> Self {
+        Self {
+            line_ending,
+            ..self
+        }
+    }
+
+    /// Encodes `text`, failing when some character has no representation
+    /// in the encoding.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let text = self.line_ending.apply(text);
+
+        let mut bytes = Vec::new();
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            let big_endian = self.encoding == UTF_16BE;
+            let units = self.bom.then_some('\u{feff}' as u16).into_iter();
+            for unit in units.chain(text.encode_utf16()) {
+                bytes.extend(if big_endian {
+                    unit.to_be_bytes()
+                } else {
+                    unit.to_le_bytes()
+                });
+            }
+            return Ok(bytes);
+        }
+
+        if self.bom && self.encoding == UTF_8 {
+            bytes.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+        let (encoded, _, unmappable) = self.encoding.encode(&text);
+        if unmappable {
+            let character = text
+                .chars()
+                .find(|x| {
+                    let mut buffer = [0; 4];
+                    self.encoding.encode(x.encode_utf8(&mut buffer)).2
+                })
+                .unwrap_or_default();
+            return Err(format!(
+                "character `{}` cannot be written in {}",
+                character,
+                self.encoding.name()
+            ));
+        }
+        bytes.extend_from_slice(&encoded);
+
+        Ok(bytes)
+    }
+}
+
+impl fmt::Display for TextFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.encoding.name())?;
+        if self.bom {
+            f.write_str(" BOM")?;
+        }
+        write!(f, ", {}", self.line_ending)
+    }
+}
+
+/// Decodes file contents, stripping any byte order mark, and tells the
+/// format they were written in. With [`FileEncoding::Auto`] a BOM wins,
+/// then valid UTF-8, then the guess of `chardetng`, which recognises the
+/// Cyrillic single-byte encodings.
+pub fn decode(bytes: &[u8], encoding: FileEncoding) -> (String, TextFormat) {
+    let (contents, used) = match encoding.encoding() {
+        Some(encoding) => {
+            let (contents, _) = encoding.decode_with_bom_removal(bytes);
+            (contents.into_owned(), encoding)
+        }
+        None => {
+            let (contents, used, _) = detect(bytes).decode(bytes);
+            (contents.into_owned(), used)
+        }
+    };
+    let bom = Encoding::for_bom(bytes).is_some_and(|(encoding, _)| encoding == used);
+    let format = TextFormat {
+        encoding: used,
+        bom,
+        line_ending: LineEnding::detect(&contents),
+    };
+
+    (contents, format)
+}
+
+fn detect(bytes: &[u8]) -> &'static Encoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+    detector.feed(bytes, true);
+    detector.guess(None, Utf8Detection::Deny)
+}
@@ -0,0 +1,916 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use indexmap::IndexMap;
+use rayon::prelude::*;
+
+use crate::parser::{Entry, DB};
+
+/// How well an entry is supported by the user's answers in scored matching.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+    pub entry: usize,
+    /// Sum of the weights of satisfied conditions.
+    pub score: f64,
+    /// Sum of the weights of all conditions.
+    pub total: f64,
+    /// Whether each condition of the entry, in order, is satisfied.
+    pub satisfied: Vec<bool>,
+}
+
+impl Score {
+    /// Share of the weight of the conditions that is satisfied, none for an
+    /// entry without weighted conditions.
+    pub fn share(&self) -> f64 {
+        if self.total == 0.0 {
+            return 0.0;
+        }
+        self.score / self.total
+    }
+}
+
+/// Limits how long a query may run. Once it is cancelled or past its
+/// deadline the engine stops early and returns what it has found so far.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    /// Why work was first left undone, shared by the clones.
+    stopped: Arc<OnceLock<Interrupt>>,
+}
+
+/// Why a query stopped before finishing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    Cancelled,
+    TimedOut,
+}
+
+impl Budget {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            stopped: Arc::default(),
+        }
+    }
+
+    /// Cancels the query from another thread; clones share the token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, atomic::Ordering::Relaxed);
+    }
+
+    pub fn interrupt(&self) -> Option<Interrupt> {
+        if self.cancelled.load(atomic::Ordering::Relaxed) {
+            Some(Interrupt::Cancelled)
+        } else if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            Some(Interrupt::TimedOut)
+        } else {
+            None
+        }
+    }
+
+    /// Whether work about to be done has to be left out, which is then
+    /// remembered as [`stopped`](Self::stopped). Checked only when there is
+    /// work left.
+    pub fn is_exhausted(&self) -> bool {
+        let interrupt = self.interrupt();
+        if let Some(interrupt) = interrupt {
+            let _ = self.stopped.set(interrupt);
+        }
+        interrupt.is_some()
+    }
+
+    /// Why some work was left out, `None` when everything checked was done
+    /// even if the budget ran out afterwards.
+    pub fn stopped(&self) -> Option<Interrupt> {
+        self.stopped.get().copied()
+    }
+}
+
+/// Probability of a conclusion given the user's answers in Bayesian matching.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Posterior {
+    pub category: String,
+    pub value: String,
+    pub probability: f64,
+}
+
+/// Answer contradicting an intermediate entry whose conditions the rest of
+/// the facts satisfy. The answer takes precedence, so the entry's conclusion
+/// is not derived.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub entry: usize,
+    pub answer: String,
+}
+
+/// Probability of a condition holding given the conclusion of its entry when
+/// the base does not declare one.
+const DEFAULT_LIKELIHOOD: f64 = 0.9;
+
+impl DB {
+    pub fn find_value(
+        &self,
+        target_category: Option<&String>,
+        query: Vec<(&String, &String)>,
+        budget: &Budget,
+    ) -> Option<String> {
+        self.matched_entries(target_category, query, false, budget)
+            .first()
+            .map(|&id| self.entries[id].value.clone())
+    }
+
+    pub fn find_all_values(
+        &self,
+        target_category: Option<&String>,
+        query: Vec<(&String, &String)>,
+        budget: &Budget,
+    ) -> Vec<String> {
+        let matched = self
+            .matched_entries(target_category, query, true, budget)
+            .into_iter()
+            .map(|id| self.entries[id].value.clone())
+            .collect::<Vec<_>>();
+
+        unique(matched)
+    }
+
+    /// Entries exact matching concludes by: every matching entry with
+    /// `find_all`, otherwise the ones concluding the first value found.
+    pub fn matched_entries(
+        &self,
+        target_category: Option<&String>,
+        query: Vec<(&String, &String)>,
+        find_all: bool,
+        budget: &Budget,
+    ) -> Vec<usize> {
+        let answered = query.iter().map(|&(cat, _)| cat).collect::<Vec<_>>();
+        let facts = self.known_facts(query, budget);
+        let mut ids = self
+            .matching_entries(target_category, &facts, &answered, budget)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        if let Some(&first) = ids.first().filter(|_| !find_all) {
+            let first = &self.entries[first];
+            ids.retain(|&id| {
+                let entry = &self.entries[id];
+                entry.category == first.category && entry.value == first.value
+            });
+        }
+        ids
+    }
+
+    /// Scores every entry concluding `target_category` (or, when no target is
+    /// given, every entry concluding a category that is not already known) by
+    /// the weights of the conditions the answers satisfy, each scaled by the
+    /// confidence in the answer satisfying it. Entries with no satisfied
+    /// condition are dropped; the rest are ordered by score, then by the
+    /// satisfied share, then by file order.
+    pub fn score_entries(
+        &self,
+        target_category: Option<&String>,
+        answers: &[(&String, &String, f64)],
+        budget: &Budget,
+    ) -> Vec<Score> {
+        let confidence = answers
+            .iter()
+            .map(|&(cat, _, confidence)| (cat, confidence.clamp(0.0, 1.0)))
+            .collect::<HashMap<_, _>>();
+        let query = answers.iter().map(|&(cat, val, _)| (cat, val)).collect();
+        let query = self.known_facts(query, budget);
+        let mut scores = self
+            .entries
+            .par_iter()
+            .enumerate()
+            .filter(|_| !budget.is_exhausted())
+            .filter(|(_, entry)| !entry.disabled)
+            .filter(|(_, entry)| match target_category {
+                Some(target) => &entry.category == target,
+                None => !query.iter().any(|&(cat, _)| cat == &entry.category),
+            })
+            .map(|(id, entry)| score_entry(id, entry, &query, &confidence))
+            .filter(|score| score.score > 0.0)
+            .collect::<Vec<_>>();
+
+        scores.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| {
+                    (b.score / b.total)
+                        .partial_cmp(&(a.score / a.total))
+                        .unwrap_or(Ordering::Equal)
+                })
+                .then(a.entry.cmp(&b.entry))
+        });
+        scores
+    }
+
+    /// Unanswered categories that narrow down the entries still matching the
+    /// answers, best first. A category is ranked by the number of entries
+    /// expected to remain after answering it, with every value taken as
+    /// likely as the share of remaining entries that require it.
+    pub fn next_questions(
+        &self,
+        target_category: Option<&String>,
+        query: Vec<(&String, &String)>,
+        budget: &Budget,
+    ) -> Vec<String> {
+        let answered = query.iter().map(|&(cat, _)| cat).collect::<Vec<_>>();
+        let facts = self.known_facts(query, budget);
+
+        let mut candidates = self
+            .matching_entries(target_category, &facts, &answered, budget)
+            .concat();
+        if candidates.is_empty() && target_category.is_none() {
+            candidates = (0..self.entries.len())
+                .filter(|&id| {
+                    let entry = &self.entries[id];
+                    !entry.disabled && !facts.iter().any(|&(cat, _)| cat == &entry.category)
+                })
+                .collect();
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let mut ranked: Vec<(&String, f64)> = Vec::new();
+        for (category, values) in self.categories.iter() {
+            if budget.is_exhausted() {
+                break;
+            }
+            if target_category == Some(category) || facts.iter().any(|&(cat, _)| cat == category) {
+                continue;
+            }
+
+            let counts = values
+                .iter()
+                .map(|value| intersect(&candidates, self.entries_with(category, value)).len())
+                .collect::<Vec<_>>();
+            let mentioned = counts.iter().sum::<usize>();
+            if mentioned == 0 {
+                continue;
+            }
+
+            let ignoring = intersect(&candidates, self.entries_ignoring(category)).len();
+            let expected = counts
+                .iter()
+                .map(|&count| count as f64 / mentioned as f64 * (count + ignoring) as f64)
+                .sum::<f64>();
+            if expected < candidates.len() as f64 {
+                ranked.push((category, expected));
+            }
+        }
+
+        ranked.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        ranked
+            .into_iter()
+            .map(|(category, _)| category.clone())
+            .collect()
+    }
+
+    /// Fewest answers found that lead exact matching to conclude `value` of
+    /// `category`: the conditions of the entry concluding it that needs the
+    /// fewest, with intermediate categories without a question answered
+    /// through the entries concluding them, and an answer ruling out every
+    /// entry concluding another value that would match as well. `None` when
+    /// no entry can be reached this way.
+    pub fn example_answers(
+        &self,
+        category: &String,
+        value: &String,
+        budget: &Budget,
+    ) -> Option<IndexMap<String, String>> {
+        let concludes = |answers: &IndexMap<String, String>| {
+            self.find_value(Some(category), answers.iter().collect(), budget)
+                .is_some_and(|found| &found == value)
+        };
+
+        let mut best: Option<IndexMap<String, String>> = None;
+        for &id in self.conclusions_of(category) {
+            if budget.is_exhausted() {
+                break;
+            }
+            let entry = &self.entries[id];
+            if entry.disabled || &entry.value != value {
+                continue;
+            }
+
+            let mut answers = IndexMap::new();
+            if !self.answer_conditions(id, &mut answers, &mut vec![category])
+                || !self.rule_out_competitors(id, &mut answers, budget)
+                || !concludes(&answers)
+            {
+                continue;
+            }
+
+            if best.as_ref().is_none_or(|best| answers.len() < best.len()) {
+                best = Some(answers);
+            }
+        }
+
+        best
+    }
+
+    /// Adds answers satisfying the conditions of entry `id`, returning
+    /// `false` when they contradict the answers already given.
+    fn answer_conditions<'a>(
+        &'a self,
+        id: usize,
+        answers: &mut IndexMap<String, String>,
+        visiting: &mut Vec<&'a String>,
+    ) -> bool {
+        for condition in self.entries[id].conditions.iter() {
+            let category = &condition.category;
+            if let Some(answer) = answers.get(category) {
+                if !condition.matches(category, answer) {
+                    return false;
+                }
+                continue;
+            }
+
+            let derivable = !self.questions.contains_key(category) && !visiting.contains(&category);
+            visiting.push(category);
+            let derived = derivable
+                && self.conclusions_of(category).iter().any(|&other| {
+                    let entry = &self.entries[other];
+                    if entry.disabled || entry.value != condition.value {
+                        return false;
+                    }
+                    // Answers are only ever added, so dropping the ones past
+                    // `mark` undoes a failed attempt.
+                    let mark = answers.len();
+                    let answered = self.answer_conditions(other, answers, visiting);
+                    if !answered {
+                        answers.truncate(mark);
+                    }
+                    answered
+                });
+            visiting.pop();
+
+            if !derived {
+                answers.insert(category.clone(), condition.value.clone());
+            }
+        }
+
+        true
+    }
+
+    /// Adds an answer contradicting every entry concluding another value of
+    /// the category of entry `id` that the answers match as well, returning
+    /// `false` when one cannot be ruled out without ruling out `id`.
+    fn rule_out_competitors(
+        &self,
+        id: usize,
+        answers: &mut IndexMap<String, String>,
+        budget: &Budget,
+    ) -> bool {
+        let target = &self.entries[id];
+        loop {
+            let matched = self.matched_entries(
+                Some(&target.category),
+                answers.iter().collect(),
+                true,
+                budget,
+            );
+            if budget.is_exhausted() || !matched.contains(&id) {
+                return false;
+            }
+            let Some(&rival) = matched
+                .iter()
+                .find(|&&other| self.entries[other].value != target.value)
+            else {
+                return true;
+            };
+
+            // Categories with a question first, as those are the ones the
+            // user answers.
+            let mut conditions = self.entries[rival]
+                .conditions
+                .iter()
+                .filter(|condition| !answers.contains_key(&condition.category))
+                .collect::<Vec<_>>();
+            conditions.sort_by_key(|condition| !self.questions.contains_key(&condition.category));
+
+            let ruled_out = conditions.into_iter().any(|condition| {
+                let category = &condition.category;
+                let values = self.categories.get(category).into_iter().flatten();
+                values
+                    .filter(|value| !condition.matches(category, value))
+                    .any(|value| {
+                        answers.insert(category.clone(), value.clone());
+                        let matched = self.matched_entries(
+                            Some(&target.category),
+                            answers.iter().collect(),
+                            true,
+                            budget,
+                        );
+                        if matched.contains(&id) && !matched.contains(&rival) {
+                            return true;
+                        }
+                        answers.pop();
+                        false
+                    })
+            });
+            if !ruled_out {
+                return false;
+            }
+        }
+    }
+
+    /// Categories whose answer can change which value of `target_category`
+    /// (or, without a target, of any conclusion category) is concluded: the
+    /// ones its entries constrain to different values, or constrain in some
+    /// entries only, followed through intermediate categories of that kind.
+    /// A category every entry requires the same value of only decides whether
+    /// anything is found at all.
+    pub fn influential_categories(&self, target_category: Option<&String>) -> HashSet<&String> {
+        let mut pending = match target_category {
+            Some(target) => vec![target],
+            None => self.conclusion_categories(),
+        };
+        let mut visited = HashSet::new();
+        let mut influential = HashSet::new();
+
+        while let Some(category) = pending.pop() {
+            if !visited.insert(category) {
+                continue;
+            }
+
+            let entries = self.conclusions_of(category);
+            // Values each category is constrained to, and by how many entries.
+            let mut constraints: IndexMap<&String, (HashSet<&String>, usize)> = IndexMap::new();
+            for &id in entries {
+                let mut seen = HashSet::new();
+                for condition in self.entries[id].conditions.iter() {
+                    let (values, count) = constraints.entry(&condition.category).or_default();
+                    values.insert(&condition.value);
+                    if seen.insert(&condition.category) {
+                        *count += 1;
+                    }
+                }
+            }
+
+            for (condition_category, (values, count)) in constraints {
+                if values.len() > 1 || count < entries.len() {
+                    influential.insert(condition_category);
+                    pending.push(condition_category);
+                }
+            }
+        }
+
+        influential
+    }
+
+    /// Categories the entries concluding `target_category` have conditions
+    /// on, directly or through the intermediate categories they use.
+    pub fn referenced_categories(&self, target_category: &String) -> HashSet<&String> {
+        let mut pending = vec![target_category];
+        let mut referenced = HashSet::new();
+
+        while let Some(category) = pending.pop() {
+            for &id in self.conclusions_of(category) {
+                for condition in self.entries[id].conditions.iter() {
+                    if referenced.insert(&condition.category) {
+                        pending.push(&condition.category);
+                    }
+                }
+            }
+        }
+
+        referenced
+    }
+
+    /// Values of each category that some entry concluding `target_category`
+    /// can be satisfied by, subtypes and aliases included. Intermediate
+    /// categories are followed only through the entries concluding a value
+    /// that is required of them. A category some of the followed entries
+    /// leave unconstrained stays open and is not in the map.
+    pub fn possible_values(&self, target_category: &String) -> HashMap<&String, HashSet<&String>> {
+        // Conclusions to follow; `None` stands for every value.
+        let mut pending: Vec<(&String, Option<&String>)> = vec![(target_category, None)];
+        let mut visited = HashSet::new();
+        let mut required: HashMap<&String, HashSet<&String>> = HashMap::new();
+        let mut open = HashSet::new();
+
+        while let Some((category, value)) = pending.pop() {
+            if !visited.insert((category, value)) {
+                continue;
+            }
+
+            let entries = self
+                .conclusions_of(category)
+                .iter()
+                .map(|&id| &self.entries[id])
+                .filter(|entry| {
+                    value.is_none_or(|value| {
+                        let concluded = self.canonical(&entry.value);
+                        concluded == value || self.ancestors(concluded).contains(&value)
+                    })
+                })
+                .collect::<Vec<_>>();
+            // Number of entries constraining each category.
+            let mut counts: HashMap<&String, usize> = HashMap::new();
+            for entry in entries.iter() {
+                let mut seen = HashSet::new();
+                for condition in entry.conditions.iter() {
+                    let value = self.canonical(&condition.value);
+                    required
+                        .entry(&condition.category)
+                        .or_default()
+                        .insert(value);
+                    if seen.insert(&condition.category) {
+                        *counts.entry(&condition.category).or_default() += 1;
+                    }
+                    pending.push((&condition.category, Some(value)));
+                }
+            }
+            open.extend(
+                counts
+                    .into_iter()
+                    .filter(|&(_, count)| count < entries.len())
+                    .map(|(category, _)| category),
+            );
+        }
+
+        required
+            .into_iter()
+            .filter(|(category, _)| !open.contains(category))
+            .filter_map(|(category, required)| {
+                let values = self.categories.get(category)?;
+                let possible = values
+                    .iter()
+                    .filter(|value| {
+                        let value = self.canonical(value);
+                        required.contains(value)
+                            || self.ancestors(value).iter().any(|x| required.contains(x))
+                    })
+                    .collect();
+                Some((category, possible))
+            })
+            .collect()
+    }
+
+    /// Naive-Bayes posterior of every value of `target_category`. Without a
+    /// target, every conclusion category that is not already known and that
+    /// the answers bear on is evaluated. Values are ordered by probability
+    /// within their category.
+    pub fn posteriors(
+        &self,
+        target_category: Option<&String>,
+        query: Vec<(&String, &String)>,
+        budget: &Budget,
+    ) -> Vec<Posterior> {
+        let facts = self.known_facts(query, budget);
+        let categories = match target_category {
+            Some(target) => vec![target],
+            None => self
+                .conclusion_categories()
+                .into_iter()
+                .filter(|category| !facts.iter().any(|&(cat, _)| cat == *category))
+                .collect(),
+        };
+
+        categories
+            .into_iter()
+            .take_while(|_| !budget.is_exhausted())
+            .flat_map(|category| {
+                self.category_posteriors(category, &facts, target_category.is_some())
+            })
+            .collect()
+    }
+
+    fn category_posteriors(
+        &self,
+        category: &String,
+        facts: &[(&String, &String)],
+        always: bool,
+    ) -> Vec<Posterior> {
+        // Several entries may conclude the same value; the value's prior is
+        // shared between them.
+        let mut likelihoods: IndexMap<&String, Vec<f64>> = IndexMap::new();
+        let mut has_evidence = false;
+        for &id in self.conclusions_of(category) {
+            let entry = &self.entries[id];
+            let likelihood = entry
+                .conditions
+                .iter()
+                .map(|condition| {
+                    let probability = condition.probability.unwrap_or(DEFAULT_LIKELIHOOD);
+                    if facts.iter().any(|&(cat, val)| condition.matches(cat, val)) {
+                        has_evidence = true;
+                        probability
+                    } else if facts.iter().any(|&(cat, _)| cat == &condition.category) {
+                        has_evidence = true;
+                        1.0 - probability
+                    } else {
+                        1.0
+                    }
+                })
+                .product::<f64>();
+            likelihoods
+                .entry(&entry.value)
+                .or_default()
+                .push(likelihood);
+        }
+        if !has_evidence && !always {
+            return Vec::new();
+        }
+
+        let declared = self.priors.get(category);
+        let undeclared = likelihoods
+            .keys()
+            .filter(|value| declared.is_none_or(|priors| !priors.contains_key(**value)))
+            .count();
+        let remaining =
+            (1.0 - declared.map_or(0.0, |priors| priors.values().sum::<f64>())).max(0.0);
+
+        let joint = likelihoods
+            .into_iter()
+            .map(|(value, likelihoods)| {
+                let prior = declared
+                    .and_then(|priors| priors.get(value).copied())
+                    .unwrap_or(remaining / undeclared as f64);
+                let likelihood = likelihoods.iter().sum::<f64>() / likelihoods.len() as f64;
+                (value, prior * likelihood)
+            })
+            .collect::<Vec<_>>();
+
+        let total = joint.iter().map(|(_, p)| p).sum::<f64>();
+        if total <= 0.0 {
+            return Vec::new();
+        }
+
+        let mut posteriors = joint
+            .into_iter()
+            .map(|(value, p)| Posterior {
+                category: category.clone(),
+                value: value.clone(),
+                probability: p / total,
+            })
+            .collect::<Vec<_>>();
+        posteriors.sort_by(|a, b| {
+            b.probability
+                .partial_cmp(&a.probability)
+                .unwrap_or(Ordering::Equal)
+        });
+        posteriors
+    }
+
+    /// Extends the answers with the parents of every answered value and with
+    /// conclusions of entries whose conditions are all satisfied, repeating
+    /// until nothing new follows, so rules can be chained through
+    /// intermediate categories. A category that is already known is never
+    /// derived again, so the user's answers take precedence.
+    fn known_facts<'a>(
+        &'a self,
+        query: Vec<(&'a String, &'a String)>,
+        budget: &Budget,
+    ) -> Vec<(&'a String, &'a String)> {
+        self.derive(query, budget).0
+    }
+
+    /// Intermediate entries whose conclusions the answers lead to, in the
+    /// order they follow, and answers contradicting such entries.
+    pub fn derivations(
+        &self,
+        query: Vec<(&String, &String)>,
+        budget: &Budget,
+    ) -> (Vec<usize>, Vec<Conflict>) {
+        let (facts, derived) = self.derive(query.clone(), budget);
+
+        let conflicts = self
+            .intermediate_entries()
+            .into_iter()
+            .filter_map(|id| {
+                let entry = &self.entries[id];
+                let &(_, answer) = query.iter().find(|&&(cat, _)| cat == &entry.category)?;
+                let answer = self.canonical(answer);
+                let agrees =
+                    answer == &entry.value || self.ancestors(answer).contains(&&entry.value);
+                let applies = !entry.conditions.is_empty()
+                    && entry.conditions.iter().all(|condition| {
+                        facts.iter().any(|&(cat, val)| condition.matches(cat, val))
+                    });
+                (!agrees && applies).then(|| Conflict {
+                    entry: id,
+                    answer: answer.clone(),
+                })
+            })
+            .collect();
+
+        (derived, conflicts)
+    }
+
+    /// Known facts with the entries derived ones follow from.
+    fn derive<'a>(
+        &'a self,
+        query: Vec<(&'a String, &'a String)>,
+        budget: &Budget,
+    ) -> (Vec<(&'a String, &'a String)>, Vec<usize>) {
+        let mut facts = Vec::with_capacity(query.len());
+        let mut derived = Vec::new();
+        for (cat, val) in query {
+            self.push_with_ancestors(&mut facts, cat, self.canonical(val));
+        }
+
+        let mut pending = self.intermediate_entries();
+
+        loop {
+            pending.retain(|&id| {
+                let category = &self.entries[id].category;
+                !facts.iter().any(|&(cat, _)| cat == category)
+            });
+
+            let ready = pending.iter().position(|&id| {
+                let conditions = &self.entries[id].conditions;
+                !conditions.is_empty()
+                    && conditions.iter().all(|condition| {
+                        facts.iter().any(|&(cat, val)| condition.matches(cat, val))
+                    })
+            });
+            let Some(position) = ready else {
+                break;
+            };
+            if budget.is_exhausted() {
+                break;
+            }
+
+            let id = pending.remove(position);
+            let entry = &self.entries[id];
+            self.push_with_ancestors(&mut facts, &entry.category, &entry.value);
+            derived.push(id);
+        }
+
+        (facts, derived)
+    }
+
+    fn push_with_ancestors<'a>(
+        &'a self,
+        facts: &mut Vec<(&'a String, &'a String)>,
+        category: &'a String,
+        value: &'a String,
+    ) {
+        facts.push((category, value));
+        for parent in self.ancestors(value) {
+            facts.push((category, parent));
+        }
+    }
+
+    /// Matching entries of every category the query can conclude. Without a
+    /// target, each conclusion category the user has not answered, derived
+    /// ones included, is searched on its own; categories none of the facts
+    /// bear on are skipped and the
+    /// rest go first by the number of answered categories supporting them.
+    fn matching_entries(
+        &self,
+        target_category: Option<&String>,
+        facts: &[(&String, &String)],
+        answered: &[&String],
+        budget: &Budget,
+    ) -> Vec<Vec<usize>> {
+        if let Some(target) = target_category {
+            return vec![self.entries_concluding(target, facts).1];
+        }
+
+        let mut matches = self
+            .conclusion_categories()
+            .into_iter()
+            .take_while(|_| !budget.is_exhausted())
+            .filter(|category| !answered.contains(category))
+            .map(|category| self.entries_concluding(category, facts))
+            .filter(|(support, ids)| *support > 0 && !ids.is_empty())
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(support, _)| Reverse(*support));
+
+        matches.into_iter().map(|(_, ids)| ids).collect()
+    }
+
+    /// Entries concluding `category` that satisfy the facts, with the number
+    /// of fact groups that constrained them. Candidates are checked in
+    /// parallel.
+    fn entries_concluding(
+        &self,
+        category: &String,
+        facts: &[(&String, &String)],
+    ) -> (usize, Vec<usize>) {
+        let required = self.required_conditions(category, facts);
+        let candidates = self
+            .conclusions_of(category)
+            .par_iter()
+            .copied()
+            .filter(|&id| {
+                required.iter().all(|alternatives| {
+                    alternatives
+                        .iter()
+                        .map(|&(cat, val)| self.entries_with(cat, val))
+                        .chain([self.entries_ignoring(alternatives[0].0)])
+                        .any(|ids| ids.binary_search(&id).is_ok())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        (required.len(), candidates)
+    }
+
+    /// Groups the facts relevant to `target_category` by category. An entry
+    /// has to satisfy at least one fact of every group, or declare the
+    /// group's category a don't-care, to match.
+    fn required_conditions<'a>(
+        &self,
+        target_category: &String,
+        facts: &[(&'a String, &'a String)],
+    ) -> Vec<Vec<(&'a String, &'a String)>> {
+        let mut groups: Vec<Vec<(&String, &String)>> = Vec::new();
+        for &(cat, val) in facts {
+            match groups.iter_mut().find(|group| group[0].0 == cat) {
+                Some(group) => group.push((cat, val)),
+                None => groups.push(vec![(cat, val)]),
+            }
+        }
+
+        groups
+            .into_iter()
+            .filter(|group| {
+                group.iter().any(|&(cat, val)| {
+                    self.entries_with(cat, val)
+                        .iter()
+                        .any(|&id| &self.entries[id].category == target_category)
+                })
+            })
+            .collect()
+    }
+}
+
+/// Scores `entry` against the facts, scaling the weight of a condition an
+/// answer satisfies by the `confidence` in the answer; derived facts are
+/// taken as certain.
+fn score_entry(
+    id: usize,
+    entry: &Entry,
+    query: &[(&String, &String)],
+    confidence: &HashMap<&String, f64>,
+) -> Score {
+    let satisfied = entry
+        .conditions
+        .iter()
+        .map(|condition| query.iter().any(|&(cat, val)| condition.matches(cat, val)))
+        .collect::<Vec<_>>();
+
+    let (score, total) = entry.conditions.iter().zip(satisfied.iter()).fold(
+        (0.0, 0.0),
+        |(score, total), (condition, &satisfied)| {
+            let certainty = confidence.get(&condition.category).copied().unwrap_or(1.0);
+            let gained = if satisfied {
+                condition.weight * certainty
+            } else {
+                0.0
+            };
+            (score + gained, total + condition.weight)
+        },
+    );
+
+    Score {
+        entry: id,
+        score,
+        total,
+        satisfied,
+    }
+}
+
+fn unique(values: Vec<String>) -> Vec<String> {
+    let mut result = Vec::with_capacity(values.len());
+    for value in values {
+        if !result.contains(&value) {
+            result.push(value);
+        }
+    }
+    result
+}
+
+fn intersect(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let (mut i, mut j) = (0, 0);
+    let mut result = Vec::new();
+
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                result.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    result
+}
@@ -0,0 +1,118 @@
+//! Dialog shown in place of the current tab when a knowledge base cannot be
+//! loaded at all.
+
+use std::io;
+
+use iced::{
+    theme,
+    widget::{button, column, container, row, text},
+    Element, Length,
+};
+
+use crate::a11y::Accessible;
+use crate::main_window::Message;
+use crate::store::Error;
+
+/// Without `editable` only another file can be picked.
+pub fn view(error: &Error, editable: bool) -> Element<'_, Message> {
+    let parsed = matches!(error, Error::Parse(..) | Error::ParseErrors(..));
+    let (summary, more) = match error {
+        Error::Parse(msg, position) => (describe(msg, *position), 0),
+        Error::ParseErrors(errors, _) => match errors.first() {
+            Some((msg, position)) => (describe(msg, *position), errors.len() - 1),
+            None => (String::new(), 0),
+        },
+        Error::IO(kind) => (describe_io(*kind), 0),
+        Error::Sqlite(msg) => (
+            format!("Файл не удалось прочитать как базу SQLite: {}", msg),
+            0,
+        ),
+        Error::Network(msg) => (
+            format!(
+                "Базу не удалось загрузить, сохранённой копии тоже нет: {}",
+                msg
+            ),
+            0,
+        ),
+        _ => (String::new(), 0),
+    };
+
+    let mut details = column![text(summary)].spacing(5);
+    if more > 0 {
+        details = details.push(text(format!("Ещё ошибок: {}", more)).size(14));
+    }
+    let parsed_entries = match error {
+        Error::ParseErrors(_, db) => db.entries.len(),
+        _ => 0,
+    };
+    if parsed_entries > 0 {
+        details =
+            details.push(text(format!("Остальное разобрано, правил: {}", parsed_entries)).size(14));
+    }
+
+    let dialog = container(
+        column![
+            text(if parsed || matches!(error, Error::Network(_)) {
+                "Не удалось загрузить базу знаний"
+            } else {
+                "Не удалось открыть файл"
+            })
+            .size(20),
+            details,
+            row![]
+                .push_maybe((editable && parsed).then(|| {
+                    button("Открыть в редакторе")
+                        .on_press(Message::LoadFailureEdit)
+                        .style(theme::Button::Primary)
+                        .named("failure-edit")
+                }))
+                .push_maybe((parsed_entries > 0).then(|| {
+                    button("Открыть разобранное")
+                        .on_press(Message::LoadFailurePartial)
+                        .style(theme::Button::Secondary)
+                        .named("failure-partial")
+                }))
+                .push(
+                    button("Выбрать другой файл")
+                        .on_press(Message::LoadFailureReopen)
+                        .style(theme::Button::Secondary)
+                        .named("failure-reopen"),
+                )
+                .spacing(10),
+        ]
+        .spacing(15)
+        .padding(20),
+    )
+    .max_width(600)
+    .style(theme::Container::Box);
+
+    container(dialog)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+}
+
+fn describe_io(kind: io::ErrorKind) -> String {
+    match kind {
+        io::ErrorKind::NotFound => "Файл не найден: возможно, он перемещён или удалён".to_string(),
+        io::ErrorKind::PermissionDenied => {
+            "Нет доступа к файлу: проверьте права на его чтение".to_string()
+        }
+        io::ErrorKind::InvalidData => {
+            "Файл не текстовый: выберите базу знаний в текстовом формате или SQLite".to_string()
+        }
+        io::ErrorKind::IsADirectory => "Выбрана папка, а не файл".to_string(),
+        kind => format!("Файл не удалось прочитать: {}", kind),
+    }
+}
+
+fn describe(msg: &str, (line, column): (usize, usize)) -> String {
+    format!(
+        "Строка {}, позиция {}: {}",
+        line,
+        column,
+        msg.lines().last().unwrap_or_default().trim()
+    )
+}
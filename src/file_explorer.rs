@@ -1,45 +1,458 @@
-use std::sync::Arc;
+use std::path::Path;
 
 use iced::{
-    widget::{column, container, scrollable, text, Column},
-    Element, Length,
+    theme,
+    widget::{button, checkbox, column, container, pick_list, row, text, Column, Row},
+    Alignment, Color, Element, Font, Length,
 };
 
-use crate::{main_window::Message, parser::DB};
+use indexmap::IndexMap;
+
+use crate::{
+    a11y::Accessible,
+    engine::{Budget, Interrupt},
+    formatter::{format_alias, format_entry, format_subtype, format_test},
+    generation::{Change, Subscriber},
+    main_window::{tab_scrollable, Message, Tabs},
+    parser::{merge_taxonomy, Provenance, DB},
+    rich_text,
+    usage::Usage,
+};
 
 #[derive(Debug, Default)]
 pub struct FileExplorer {
-    pub db: Arc<DB>,
+    pub merge: Merge,
+    /// Category picked to be removed, whose impact is shown first.
+    pub removal: Option<String>,
+    pub example: Option<Example>,
+}
+
+/// Answers leading to the conclusion of an entry, looked for in the
+/// background.
+#[derive(Debug)]
+pub struct Example {
+    pub entry: usize,
+    pub category: String,
+    pub value: String,
+    pub answers: ExampleAnswers,
+    /// Cancelled when the example is closed before it is found.
+    pub budget: Budget,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExampleAnswers {
+    Searching,
+    Found(IndexMap<String, String>),
+    /// No answers lead to the conclusion.
+    Unreachable,
+    Interrupted(Interrupt),
+}
+
+/// Value of a category picked to be merged into another one.
+#[derive(Debug, Default)]
+pub struct Merge {
+    pub category: Option<String>,
+    pub from: Option<String>,
+    pub into: Option<String>,
 }
 
 impl FileExplorer {
-    pub fn view(&self) -> Element<Message> {
-        if self.db.entries.is_empty() {
+    pub fn view<'a>(
+        &'a self,
+        db: &'a DB,
+        base: Option<&Path>,
+        usage: Option<&'a Usage>,
+        numbers: &[Option<u64>],
+        editable: bool,
+    ) -> Element<'a, Message> {
+        if db.entries.is_empty() {
             return text("Данных нет").into();
         }
 
-        view_db(&self.db)
+        // Bases without source, kept as tables, are numbered in order.
+        let number = |id: usize| {
+            numbers
+                .get(id)
+                .copied()
+                .flatten()
+                .map_or(id + 1, |number| number as usize)
+        };
+
+        column![]
+            .push_maybe(self.example.as_ref().map(view_example))
+            .push_maybe(editable.then(|| self.view_merge(db, number)))
+            .push_maybe(editable.then(|| self.view_removal(db, number)))
+            .push_maybe(usage.map(view_usage))
+            .push(view_db(db, base, usage, editable))
+            .spacing(24)
+            .into()
+    }
+
+    /// Values to merge, with the declarations, entries and tests the merge
+    /// rewrites.
+    fn view_merge<'a>(
+        &'a self,
+        db: &'a DB,
+        number: impl Fn(usize) -> usize,
+    ) -> Element<'a, Message> {
+        let merge = &self.merge;
+        let values = merge
+            .category
+            .as_ref()
+            .and_then(|category| db.categories.get(category))
+            .map_or(&[][..], Vec::as_slice);
+        let targets = values
+            .iter()
+            .filter(|x| Some(*x) != merge.from.as_ref())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let picked = match (&merge.category, &merge.from, &merge.into) {
+            (Some(category), Some(from), Some(into)) if from != into => {
+                Some((category, from, into))
+            }
+            _ => None,
+        };
+
+        let controls = row![
+            text("Объединить значения"),
+            pick_list(
+                db.categories.keys().cloned().collect::<Vec<_>>(),
+                merge.category.clone(),
+                Message::MergeCategorySelected,
+            )
+            .placeholder("категория")
+            .named("merge-category"),
+            pick_list(values, merge.from.clone(), Message::MergeFromSelected)
+                .placeholder("значение")
+                .named("merge-from"),
+            text("→"),
+            pick_list(targets, merge.into.clone(), Message::MergeIntoSelected)
+                .placeholder("заменить на")
+                .named("merge-into"),
+            button("Объединить")
+                .on_press_maybe(picked.map(|_| Message::MergeValues))
+                .style(theme::Button::Primary)
+                .named("merge"),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let Some((category, from, into)) = picked else {
+            return controls.into();
+        };
+
+        let mut preview = Column::new().spacing(10);
+        for (id, entry) in db.entries.iter().enumerate() {
+            let mut merged = entry.clone();
+            if merged.merge_value(category, from, into) {
+                let (mut before, mut after) = (String::new(), String::new());
+                format_entry(&mut before, number(id), entry);
+                format_entry(&mut after, number(id), &merged);
+                preview = preview.push(view_change(before, after));
+            }
+        }
+        for case in db.tests.iter() {
+            let mut merged = case.clone();
+            if merged.merge_value(category, from, into) {
+                let (mut before, mut after) = (String::new(), String::new());
+                format_test(&mut before, case);
+                format_test(&mut after, &merged);
+                preview = preview.push(view_change(before, after));
+            }
+        }
+        for (alias, value) in db.aliases.iter().filter(|(_, value)| *value == from) {
+            let (mut before, mut after) = (String::new(), String::new());
+            format_alias(&mut before, alias, value);
+            format_alias(&mut after, alias, into);
+            preview = preview.push(view_change(before, after));
+        }
+        let taxonomy = merge_taxonomy(db.taxonomy.clone(), from, into);
+        let rename = |x: &String| if x == from { into.clone() } else { x.clone() };
+        for (value, parent) in db.taxonomy.iter() {
+            if value != from && parent != from {
+                continue;
+            }
+            let (mut before, mut after) = (String::new(), String::new());
+            format_subtype(&mut before, value, parent);
+            let (value, parent) = (rename(value), rename(parent));
+            if taxonomy.get(&value) == Some(&parent) {
+                format_subtype(&mut after, &value, &parent);
+            }
+            preview = preview.push(view_change(before, after));
+        }
+
+        column![controls, text("Будут изменены:").size(14), preview]
+            .spacing(10)
+            .into()
+    }
+
+    /// Category to remove, with what happens to everything that uses it.
+    fn view_removal<'a>(
+        &'a self,
+        db: &'a DB,
+        number: impl Fn(usize) -> usize,
+    ) -> Element<'a, Message> {
+        let controls = row![
+            text("Удалить категорию"),
+            pick_list(
+                db.categories.keys().cloned().collect::<Vec<_>>(),
+                self.removal.clone(),
+                Message::RemovalCategorySelected,
+            )
+            .placeholder("категория")
+            .named("removal-category"),
+            button("Удалить")
+                .on_press_maybe(self.removal.as_ref().map(|_| Message::RemoveCategory))
+                .style(theme::Button::Destructive)
+                .named("remove-category"),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let Some(category) = self.removal.as_ref() else {
+            return controls.into();
+        };
+
+        let impact = db.category_impact(category);
+        let entry = |id: usize, outcome: &str| {
+            let entry = &db.entries[id];
+            text(format!(
+                "Правило {} ({}: {}): {}",
+                number(id),
+                entry.category,
+                entry.value,
+                outcome
+            ))
+            .size(14)
+        };
+
+        let mut consequences = Column::new().spacing(3);
+        for &id in impact.removed.iter() {
+            consequences = consequences.push(entry(id, "будет удалено"));
+        }
+        for &id in impact.trimmed.iter() {
+            consequences = consequences.push(entry(id, "условие будет убрано"));
+        }
+        let declarations = [
+            (impact.question, "Вопрос будет удалён"),
+            (impact.change, "Перевод будет удалён"),
+            (impact.tip, "Подсказка будет удалена"),
+        ];
+        for (_, outcome) in declarations.into_iter().filter(|(used, _)| *used) {
+            consequences = consequences.push(text(outcome).size(14));
+        }
+        if impact.tests > 0 {
+            consequences = consequences.push(
+                text(format!(
+                    "Встроенных тестов будет изменено: {}",
+                    impact.tests
+                ))
+                .size(14),
+            );
+        }
+
+        column![controls, consequences].spacing(10).into()
     }
 }
 
-fn view_db(db: &Arc<DB>) -> Element<Message> {
+impl Subscriber for FileExplorer {
+    /// Picks of categories the base no longer has are dropped.
+    fn refresh(&mut self, db: &DB, _: Change) {
+        let removed = |category: &Option<String>| {
+            category
+                .as_ref()
+                .is_some_and(|category| !db.categories.contains_key(category))
+        };
+        if removed(&self.merge.category) {
+            self.merge = Merge::default();
+        }
+        if removed(&self.removal) {
+            self.removal = None;
+        }
+    }
+}
+
+fn view_example(example: &Example) -> Element<'_, Message> {
+    let title = row![
+        text(format!(
+            "Пример ответов для {}: {}",
+            example.category, example.value
+        ))
+        .size(16)
+        .width(Length::Fill),
+        button("✕")
+            .on_press(Message::ExampleClosed)
+            .style(theme::Button::Text)
+            .named("example-close"),
+    ];
+
+    let note = match &example.answers {
+        ExampleAnswers::Found(answers) => return view_answers(title, answers),
+        ExampleAnswers::Searching => "Подбираем ответы…",
+        ExampleAnswers::Unreachable => "Вывод недостижим: ответы не подобрать",
+        ExampleAnswers::Interrupted(Interrupt::Cancelled) => "Подбор ответов отменён",
+        ExampleAnswers::Interrupted(Interrupt::TimedOut) => {
+            "Время подбора истекло, попробуйте увеличить время поиска в настройках"
+        }
+    };
+    column![title, text(note).size(14)].spacing(10).into()
+}
+
+fn view_answers<'a>(
+    title: Row<'a, Message>,
+    answers: &'a IndexMap<String, String>,
+) -> Element<'a, Message> {
+    let list = answers
+        .iter()
+        .fold(Column::new().spacing(3), |col, (category, answer)| {
+            col.push(text(format!("{}: {}", category, answer)).size(14))
+        });
+    column![
+        title,
+        list,
+        button("Подставить в вопросы")
+            .on_press(Message::ApplyExample)
+            .style(theme::Button::Secondary)
+            .named("example-apply"),
+    ]
+    .spacing(10)
+    .into()
+}
+
+/// Where the counters shown with entries and questions come from.
+fn view_usage(usage: &Usage) -> Element<'_, Message> {
+    row![
+        text(format!(
+            "Статистика консультаций хранится только на этом компьютере. Выводов сделано: {}",
+            usage.conclusions.values().sum::<u64>()
+        ))
+        .size(14)
+        .width(Length::Fill),
+        button(text("Сбросить статистику").size(14))
+            .on_press(Message::UsageReset)
+            .style(theme::Button::Secondary)
+            .named("usage-reset"),
+    ]
+    .spacing(10)
+    .align_items(Alignment::Center)
+    .into()
+}
+
+fn view_change<'a>(before: String, after: String) -> Element<'a, Message> {
+    let source = |x: String| {
+        text(x.trim_end().to_string())
+            .size(14)
+            .font(Font::MONOSPACE)
+            .width(Length::Fill)
+    };
+
+    row![source(before), text("→"), source(after)]
+        .spacing(10)
+        .into()
+}
+
+fn view_provenance(provenance: &Provenance) -> Element<'_, Message> {
+    let fields = [
+        ("Автор", &provenance.author),
+        ("Источник", &provenance.source),
+        ("Дата", &provenance.date),
+    ];
+
+    fields
+        .into_iter()
+        .filter_map(|(label, field)| Some((label, field.as_ref()?)))
+        .fold(Column::new().spacing(3), |col, (label, field)| {
+            col.push(text(format!("{}: {}", label, field)).size(12))
+        })
+        .into()
+}
+
+fn view_db<'a>(
+    db: &'a DB,
+    base: Option<&Path>,
+    usage: Option<&Usage>,
+    editable: bool,
+) -> Element<'a, Message> {
     let mut entries_column = Column::new().spacing(20);
     let mut questions_column = Column::new().spacing(10);
+    let mut changes_column = Column::new().spacing(10);
     let mut tips_column = Column::new().spacing(10);
+    let mut taxonomy_column = Column::new().spacing(10);
+    let mut aliases_column = Column::new().spacing(10);
+
+    for (id, entry) in db.entries.iter().enumerate() {
+        // Disabled entries stay listed, greyed out, so they can be enabled
+        // back.
+        let style = if entry.disabled {
+            theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))
+        } else {
+            theme::Text::Default
+        };
 
-    for entry in db.entries.iter() {
         let entry_container = column![
-            text(format!("{}: {}", entry.category, entry.value)).size(18),
+            row![text(format!("{}: {}", entry.category, entry.value))
+                .size(18)
+                .style(style)
+                .width(Length::Fill)]
+            .push_maybe(
+                entry
+                    .note
+                    .as_deref()
+                    .map(|note| rich_text::note(note, base))
+            )
+            .push_maybe(usage.map(|usage| {
+                text(format!("Сработало выводом: {}", usage.fired(entry)))
+                    .size(12)
+                    .style(style)
+            }))
+            .push(
+                button(text("Пример").size(14))
+                    .on_press(Message::ExampleRequested(id))
+                    .style(theme::Button::Secondary)
+                    .named(format!("example-{}", id)),
+            )
+            .push_maybe(editable.then(|| {
+                checkbox("Отключено", entry.disabled)
+                    .on_toggle(move |disabled| Message::EntryDisabled(id, disabled))
+                    .named(format!("disabled-{}", id))
+            }))
+            .spacing(10),
             entry
-                .categories
+                .conditions
                 .iter()
-                .fold(Column::new().spacing(3), |col, (cat, val)| {
+                .fold(Column::new().spacing(3), |col, condition| {
+                    let mut weight = if condition.weight == 1.0 {
+                        String::new()
+                    } else {
+                        format!(" (вес {})", condition.weight)
+                    };
+                    if let Some(probability) = condition.probability {
+                        weight.push_str(&format!(" (вероятность {})", probability));
+                    }
                     col.push(
-                        text(format!("{}: {}", cat, val))
-                            .size(14)
-                            .width(Length::Fill),
+                        text(format!(
+                            "{}: {}{}",
+                            condition.category, condition.value, weight
+                        ))
+                        .size(14)
+                        .style(style)
+                        .width(Length::Fill),
                     )
-                }),
+                })
+                .push(
+                    entry
+                        .ignored
+                        .iter()
+                        .fold(Column::new().spacing(3), |col, category| {
+                            col.push(
+                                text(format!("{}: не важно", category))
+                                    .size(14)
+                                    .style(style),
+                            )
+                        }),
+                ),
+            view_provenance(&entry.provenance),
         ]
         .spacing(10);
 
@@ -50,18 +463,83 @@ fn view_db(db: &Arc<DB>) -> Element<Message> {
         questions_column = questions_column.push(text("Вопросы: ").size(16));
     }
     for (question, answer) in db.questions.iter() {
-        questions_column =
-            questions_column.push(text(format!("{}: {}", question, answer)).size(16));
+        let answered = usage
+            .map(|usage| format!(" (ответов: {})", usage.answered(question)))
+            .unwrap_or_default();
+        questions_column = questions_column
+            .push(text(format!("{}: {}{}", question, answer.text, answered)).size(16));
     }
 
-    if !db.tips.is_empty() {
+    let value_tips = db.value_tips.iter().flat_map(|(category, tips)| {
+        tips.iter()
+            .map(move |(value, tip)| (format!("{} - {}", category, value), tip))
+    });
+    let value_changes = db.value_changes.iter().flat_map(|(category, changes)| {
+        changes
+            .iter()
+            .map(move |(value, change)| (format!("{} - {}", category, value), change))
+    });
+
+    if !db.tips.is_empty() || !db.value_tips.is_empty() {
         tips_column = tips_column.push(text("Подсказки: ").size(16));
     }
-    for (tip, detail) in db.tips.iter() {
-        tips_column = tips_column.push(text(format!("{}: {}", tip, detail)).size(16));
+    let tips = db
+        .tips
+        .iter()
+        .map(|(category, tip)| (category.clone(), tip));
+    for (tip, detail) in tips.chain(value_tips) {
+        tips_column = tips_column.push(
+            row![
+                text(format!("{}:", tip)).size(16),
+                rich_text::view(detail, base, 16)
+            ]
+            .spacing(5),
+        );
     }
 
-    scrollable(column![entries_column, questions_column, tips_column].spacing(24))
-        .width(Length::Fill)
-        .into()
+    if !db.changes.is_empty() || !db.value_changes.is_empty() {
+        changes_column = changes_column.push(text("Переводы: ").size(16));
+    }
+    let changes = db
+        .changes
+        .iter()
+        .map(|(category, change)| (category.clone(), change));
+    for (category, change) in changes.chain(value_changes) {
+        changes_column = changes_column.push(
+            row![
+                text(format!("{}:", category)).size(16),
+                rich_text::view(change, base, 16)
+            ]
+            .spacing(5),
+        );
+    }
+
+    if !db.taxonomy.is_empty() {
+        taxonomy_column = taxonomy_column.push(text("Подтипы: ").size(16));
+    }
+    for (value, parent) in db.taxonomy.iter() {
+        taxonomy_column = taxonomy_column.push(text(format!("{} → {}", value, parent)).size(16));
+    }
+
+    if !db.aliases.is_empty() {
+        aliases_column = aliases_column.push(text("Синонимы: ").size(16));
+    }
+    for (alias, value) in db.aliases.iter() {
+        aliases_column = aliases_column.push(text(format!("{} = {}", alias, value)).size(16));
+    }
+
+    tab_scrollable(
+        Tabs::Explorer,
+        column![
+            entries_column,
+            questions_column,
+            changes_column,
+            tips_column,
+            taxonomy_column,
+            aliases_column
+        ]
+        .spacing(24),
+    )
+    .width(Length::Fill)
+    .into()
 }
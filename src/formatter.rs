@@ -0,0 +1,204 @@
+use std::fmt::Write;
+
+use indexmap::IndexMap;
+
+use crate::cases::TestCase;
+use crate::parser::{Entry, Question, QuestionKind, TextKey, DB};
+use crate::strategy;
+
+pub fn format_db(db: &DB) -> String {
+    let mut out = String::new();
+
+    if let Some(hash) = db.lock.as_ref() {
+        let _ = writeln!(out, "защита {}", hash);
+    }
+
+    for (number, entry) in db.entries.iter().enumerate() {
+        format_entry(&mut out, number + 1, entry);
+    }
+
+    for (category, question) in db.questions.iter() {
+        format_question(&mut out, category, question);
+        format_variants(
+            &mut out,
+            db.variants.get(&TextKey::Question(category.clone())),
+        );
+    }
+    let texts = [
+        (
+            "перевод",
+            &db.changes,
+            &db.value_changes,
+            TextKey::Change as fn(_, _) -> _,
+        ),
+        ("подсказка", &db.tips, &db.value_tips, TextKey::Tip),
+    ];
+    for (keyword, texts, _, key) in texts {
+        for (category, text) in texts.iter() {
+            let _ = writeln!(out, "{} {}\n{}", keyword, category, text);
+            format_variants(&mut out, db.variants.get(&key(category.clone(), None)));
+        }
+    }
+    for (keyword, _, texts, key) in texts {
+        for (category, texts) in texts.iter() {
+            for (value, text) in texts.iter() {
+                format_value_text(&mut out, keyword, category, value, text);
+                let key = key(category.clone(), Some(value.clone()));
+                format_variants(&mut out, db.variants.get(&key));
+            }
+        }
+    }
+
+    for (value, parent) in db.taxonomy.iter() {
+        format_subtype(&mut out, value, parent);
+    }
+
+    for (alias, value) in db.aliases.iter() {
+        format_alias(&mut out, alias, value);
+    }
+
+    for (category, values) in db.schema.allowed.iter() {
+        format_domain(&mut out, category, values);
+    }
+
+    for category in db.schema.multi_valued.iter() {
+        let _ = writeln!(out, "многозначный {}", category);
+    }
+
+    if let Some(category) = db.target.as_ref() {
+        let _ = writeln!(out, "цель {}", category);
+    }
+
+    for (category, priors) in db.priors.iter() {
+        for (value, probability) in priors.iter() {
+            format_prior(&mut out, category, value, *probability);
+        }
+    }
+
+    for case in db.tests.iter() {
+        format_test(&mut out, case);
+    }
+
+    out
+}
+
+pub fn format_subtype(out: &mut String, value: &str, parent: &str) {
+    let _ = writeln!(out, "подтип {} - {}", value, parent);
+}
+
+pub fn format_alias(out: &mut String, alias: &str, value: &str) {
+    let _ = writeln!(out, "синоним {} = {}", alias, value);
+}
+
+pub fn format_domain(out: &mut String, category: &str, values: &[String]) {
+    let _ = writeln!(out, "значения {} - {}", category, values.join(", "));
+}
+
+pub fn format_prior(out: &mut String, category: &str, value: &str, probability: f64) {
+    let _ = writeln!(
+        out,
+        "вероятность {} - {} = {}",
+        category, value, probability
+    );
+}
+
+/// Writes `case` as a `тест` block; cases the DSL cannot express, without
+/// a target, answers or expected values, are skipped.
+pub fn format_test(out: &mut String, case: &TestCase) {
+    let Some(target) = case.target.as_ref() else {
+        return;
+    };
+    if case.answers.is_empty() || case.expected.is_empty() {
+        return;
+    }
+
+    let answers = case
+        .answers
+        .iter()
+        .map(|(category, value)| format!("{} - {}", category, value))
+        .collect::<Vec<_>>();
+    let strategy = case
+        .strategy
+        .as_deref()
+        .and_then(strategy::by_name)
+        .map(|strategy| format!(": {}", strategy.keyword()))
+        .unwrap_or_default();
+    let _ = writeln!(
+        out,
+        "тест{} если {} то {} - {}",
+        strategy,
+        answers.join(" и "),
+        target,
+        case.expected.join(", ")
+    );
+}
+
+pub fn format_entry(out: &mut String, number: usize, entry: &Entry) {
+    let _ = write!(out, "{} ", number);
+    for (i, condition) in entry.conditions.iter().enumerate() {
+        let keyword = if i == 0 { "если" } else { "и" };
+        let _ = write!(
+            out,
+            "{} {} - {}",
+            keyword, condition.category, condition.value
+        );
+        if condition.weight != 1.0 {
+            let _ = write!(out, " ({})", condition.weight);
+        }
+        if let Some(probability) = condition.probability {
+            let _ = write!(out, " [{}]", probability);
+        }
+        out.push('\n');
+    }
+    for (i, category) in entry.ignored.iter().enumerate() {
+        let keyword = if i + entry.conditions.len() == 0 {
+            "если"
+        } else {
+            "и"
+        };
+        let _ = writeln!(out, "{} {} - *", keyword, category);
+    }
+    let _ = writeln!(out, "то {} - {}", entry.category, entry.value);
+
+    let provenance = &entry.provenance;
+    if let Some(author) = provenance.author.as_ref() {
+        let _ = writeln!(out, "автор {}", author);
+    }
+    if let Some(source) = provenance.source.as_ref() {
+        let _ = writeln!(out, "источник {}", source);
+    }
+    if let Some(date) = provenance.date.as_ref() {
+        let _ = writeln!(out, "дата {}", date);
+    }
+    if let Some(note) = entry.note.as_ref() {
+        let _ = writeln!(out, "примечание {}", note);
+    }
+    if entry.disabled {
+        let _ = writeln!(out, "отключено");
+    }
+}
+
+pub fn format_question(out: &mut String, category: &str, question: &Question) {
+    let _ = write!(out, "вопрос {}", category);
+    if let Some(order) = question.order {
+        let _ = write!(out, " #{}", order);
+    }
+    if let Some(group) = question.group.as_ref() {
+        let _ = write!(out, " [{}]", group);
+    }
+    if question.kind != QuestionKind::Choice {
+        let _ = write!(out, " : {}", question.kind.keyword());
+    }
+    let _ = writeln!(out, "\n{}", question.text);
+}
+
+pub fn format_value_text(out: &mut String, keyword: &str, category: &str, value: &str, text: &str) {
+    let _ = writeln!(out, "{} {} = {}\n{}", keyword, category, value, text);
+}
+
+/// Writes the `variants` of the text just written, one language a line.
+pub fn format_variants(out: &mut String, variants: Option<&IndexMap<String, String>>) {
+    for (language, text) in variants.into_iter().flatten() {
+        let _ = writeln!(out, "{}: {}", language, text);
+    }
+}
@@ -0,0 +1,163 @@
+//! Fuzzy matching: every answer holds to a degree between 0 and 1, the
+//! conditions of an entry are combined with AND (minimum) and entries
+//! concluding the same value with OR (maximum).
+
+use std::cmp::Ordering;
+
+use crate::engine::Budget;
+use crate::parser::{Condition, DB};
+
+/// Degree to which a conclusion follows from the answers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub category: String,
+    pub value: String,
+    pub degree: f64,
+    /// Entries concluding the value to a degree above 0.
+    pub entries: Vec<usize>,
+}
+
+type Fact<'a> = (&'a String, &'a String, f64);
+
+impl DB {
+    /// Membership degree of every value of `target_category` (or, without a
+    /// target, of every conclusion category that is not already known) the
+    /// answers support, strongest first. Conditions on unanswered categories
+    /// hold to an unknown degree of one half.
+    pub fn fuzzy_matches(
+        &self,
+        target_category: Option<&String>,
+        query: Vec<Fact<'_>>,
+        budget: &Budget,
+    ) -> Vec<FuzzyMatch> {
+        let facts = self.fuzzy_facts(query, budget);
+        let categories = match target_category {
+            Some(target) => vec![target],
+            None => self
+                .conclusion_categories()
+                .into_iter()
+                .filter(|category| !facts.iter().any(|&(cat, ..)| cat == *category))
+                .collect(),
+        };
+
+        let mut matches: Vec<FuzzyMatch> = Vec::new();
+        for category in categories {
+            if budget.is_exhausted() {
+                break;
+            }
+            for &id in self.conclusions_of(category) {
+                let entry = &self.entries[id];
+                let Some(degree) = truth(&entry.conditions, &facts) else {
+                    continue;
+                };
+
+                let supporting = (degree > 0.0).then_some(id);
+                match matches
+                    .iter_mut()
+                    .find(|x| &x.category == category && x.value == entry.value)
+                {
+                    Some(existing) => {
+                        existing.degree = existing.degree.max(degree);
+                        existing.entries.extend(supporting);
+                    }
+                    None => matches.push(FuzzyMatch {
+                        category: category.clone(),
+                        value: entry.value.clone(),
+                        degree,
+                        entries: supporting.into_iter().collect(),
+                    }),
+                }
+            }
+        }
+
+        matches.retain(|x| x.degree > 0.0);
+        matches.sort_by(|a, b| b.degree.partial_cmp(&a.degree).unwrap_or(Ordering::Equal));
+        matches
+    }
+
+    /// Answers with the parents of their values, extended with conclusions of
+    /// intermediate entries whose conditions are all answered, each holding
+    /// to the degree of its entry.
+    fn fuzzy_facts<'a>(&'a self, query: Vec<Fact<'a>>, budget: &Budget) -> Vec<Fact<'a>> {
+        let mut facts = Vec::with_capacity(query.len());
+        for (cat, val, degree) in query {
+            let val = self.canonical(val);
+            facts.push((cat, val, degree));
+            for parent in self.ancestors(val) {
+                facts.push((cat, parent, degree));
+            }
+        }
+
+        let mut pending = self.intermediate_entries();
+
+        loop {
+            pending.retain(|&id| {
+                let category = &self.entries[id].category;
+                !facts.iter().any(|&(cat, ..)| cat == category)
+            });
+
+            let derived = pending.iter().enumerate().find_map(|(position, &id)| {
+                let conditions = &self.entries[id].conditions;
+                let answered = conditions
+                    .iter()
+                    .all(|condition| facts.iter().any(|&(cat, ..)| cat == &condition.category));
+                let degree = truth(conditions, &facts).filter(|x| *x > 0.0);
+                answered.then_some(degree).flatten().map(|x| (position, x))
+            });
+            let Some((position, degree)) = derived else {
+                break;
+            };
+            if budget.is_exhausted() {
+                break;
+            }
+
+            let entry = &self.entries[pending.remove(position)];
+            facts.push((&entry.category, &entry.value, degree));
+            for parent in self.ancestors(&entry.value) {
+                facts.push((&entry.category, parent, degree));
+            }
+        }
+
+        facts
+    }
+}
+
+/// Membership of a condition on a category that is not answered: it is as
+/// likely to hold as not.
+const UNKNOWN: f64 = 0.5;
+
+/// Minimum membership over the conditions, `None` when none of them is
+/// answered. A condition on an answered category holds to the strongest
+/// matching answer, or not at all; one on an unanswered category is unknown.
+fn truth(conditions: &[Condition], facts: &[Fact]) -> Option<f64> {
+    let answered =
+        |condition: &Condition| facts.iter().any(|&(cat, ..)| cat == &condition.category);
+    if !conditions.iter().any(answered) {
+        return None;
+    }
+
+    conditions
+        .iter()
+        .map(|condition| match answered(condition) {
+            true => facts
+                .iter()
+                .filter(|&&(cat, val, _)| condition.matches(cat, val))
+                .map(|&(.., degree)| degree)
+                .fold(0.0, f64::max),
+            false => UNKNOWN,
+        })
+        .reduce(f64::min)
+}
+
+/// Confidence in the strongest match: its degree as a share of the degrees
+/// of all values of its category, the weighted average of singleton outputs.
+pub fn confidence(matches: &[FuzzyMatch]) -> Option<f64> {
+    let best = matches.first()?;
+    let total = matches
+        .iter()
+        .filter(|x| x.category == best.category)
+        .map(|x| x.degree)
+        .sum::<f64>();
+
+    Some(best.degree / total)
+}
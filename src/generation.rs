@@ -0,0 +1,58 @@
+//! The opened base is never changed in place: every change builds the next
+//! generation and swaps it in. Queries and other work started on an earlier
+//! generation keep its snapshot, and their results are told apart by its
+//! number; the tabs showing the base are told to refresh.
+
+use std::sync::Arc;
+
+use crate::parser::DB;
+
+#[derive(Debug, Default)]
+pub struct Generation {
+    db: Arc<DB>,
+    /// Bumped on every published base.
+    number: u64,
+}
+
+impl Generation {
+    pub fn current(&self) -> &Arc<DB> {
+        &self.db
+    }
+
+    /// Current base with its number, for work that outlives the update
+    /// starting it.
+    pub fn snapshot(&self) -> (u64, Arc<DB>) {
+        (self.number, self.db.clone())
+    }
+
+    pub fn is_current(&self, number: u64) -> bool {
+        number == self.number
+    }
+
+    /// Copy of the current base to build the next generation from.
+    pub fn draft(&self) -> DB {
+        DB::clone(&self.db)
+    }
+
+    pub fn publish(&mut self, db: Arc<DB>) {
+        self.db = db;
+        self.number += 1;
+    }
+}
+
+/// How the published base differs from the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Another base was opened, or the source applied from the editor.
+    Replaced,
+    /// Entries or categories were added, disabled or removed.
+    Restructured,
+    /// The same entries read differently: in another language, with names
+    /// normalized, values merged or a question reworded.
+    Reworded,
+}
+
+/// Part of the window kept in step with the published base.
+pub trait Subscriber {
+    fn refresh(&mut self, db: &DB, change: Change);
+}
@@ -0,0 +1,79 @@
+//! Synthetic knowledge base generator used by the benchmarks.
+
+use std::fmt::Write;
+
+const CATEGORIES: usize = 64;
+const VALUES: usize = 12;
+const CONCLUSIONS: usize = 256;
+
+/// Deterministic linear congruential generator, good enough to spread
+/// conditions across categories without pulling in an RNG crate.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % bound as u64) as usize
+    }
+}
+
+pub fn category(n: usize) -> String {
+    format!("признак_{}", n)
+}
+
+pub fn value(n: usize) -> String {
+    format!("значение_{}", n)
+}
+
+/// Generates a knowledge base source with `entries` rules, a question for
+/// every condition category and a tip for every fifth one.
+pub fn generate_kb(entries: usize, seed: u64) -> String {
+    let mut rng = Lcg(seed);
+    let mut out = String::new();
+
+    for number in 1..=entries {
+        let conditions = 2 + rng.next(4);
+        let mut used = Vec::with_capacity(conditions);
+
+        let _ = write!(out, "{} ", number);
+        while used.len() < conditions {
+            let category = rng.next(CATEGORIES);
+            if used.contains(&category) {
+                continue;
+            }
+
+            let keyword = if used.is_empty() { "если" } else { "и" };
+            let _ = writeln!(
+                out,
+                "{} {} - {}",
+                keyword,
+                self::category(category),
+                value(rng.next(VALUES))
+            );
+            used.push(category);
+        }
+        let _ = writeln!(out, "то диагноз - вывод_{}", rng.next(CONCLUSIONS));
+    }
+
+    for category in 0..CATEGORIES {
+        let _ = writeln!(
+            out,
+            "вопрос {}\nкакое значение у признака {}?",
+            self::category(category),
+            category
+        );
+        if category % 5 == 0 {
+            let _ = writeln!(
+                out,
+                "подсказка {}\nодно из {} значений",
+                self::category(category),
+                VALUES
+            );
+        }
+    }
+
+    out
+}
@@ -0,0 +1,563 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+use crate::cases::TestCase;
+use crate::clips;
+use crate::parser::{
+    is_question_text, parse_db_from_file, Condition, Entry, ParserError, Provenance, Question,
+    TextKey, DB,
+};
+
+/// A syntax the knowledge base can be loaded from.
+pub trait KnowledgeSource {
+    fn parse(&self, contents: &str) -> Result<DB, ParserError>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KbFormat {
+    /// Picked by the file extension.
+    #[default]
+    Auto,
+    Dsl,
+    Json,
+    Yaml,
+    Csv,
+    Clips,
+}
+
+impl KbFormat {
+    pub const ALL: [KbFormat; 6] = [
+        KbFormat::Auto,
+        KbFormat::Dsl,
+        KbFormat::Json,
+        KbFormat::Yaml,
+        KbFormat::Csv,
+        KbFormat::Clips,
+    ];
+
+    /// Resolves [`KbFormat::Auto`] by the extension of `path`, falling back
+    /// to the DSL.
+    pub fn resolve(self, path: &Path) -> KbFormat {
+        if self != KbFormat::Auto {
+            return self;
+        }
+
+        let extension = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("json") => KbFormat::Json,
+            Some("yaml" | "yml") => KbFormat::Yaml,
+            Some("csv") => KbFormat::Csv,
+            Some("clp") => KbFormat::Clips,
+            _ => KbFormat::Dsl,
+        }
+    }
+
+    /// Extensions of files in this format; `kb` and `esdb` are the ones
+    /// registered for the DSL.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            KbFormat::Auto => &["kb", "esdb", "txt", "json", "yaml", "yml", "csv", "clp"],
+            KbFormat::Dsl => &["kb", "esdb", "txt"],
+            KbFormat::Json => &["json"],
+            KbFormat::Yaml => &["yaml", "yml"],
+            KbFormat::Csv => &["csv"],
+            KbFormat::Clips => &["clp"],
+        }
+    }
+
+    pub fn source(self) -> &'static dyn KnowledgeSource {
+        match self {
+            KbFormat::Auto | KbFormat::Dsl => &Dsl,
+            KbFormat::Json => &Json,
+            KbFormat::Yaml => &Yaml,
+            KbFormat::Csv => &Csv,
+            KbFormat::Clips => &clips::Clips,
+        }
+    }
+}
+
+impl fmt::Display for KbFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            KbFormat::Auto => "Авто",
+            KbFormat::Dsl => "DSL",
+            KbFormat::Json => "JSON",
+            KbFormat::Yaml => "YAML",
+            KbFormat::Csv => "CSV",
+            KbFormat::Clips => "CLIPS",
+        })
+    }
+}
+
+pub struct Dsl;
+
+impl KnowledgeSource for Dsl {
+    fn parse(&self, contents: &str) -> Result<DB, ParserError> {
+        parse_db_from_file(contents)
+    }
+}
+
+/// Layout shared by the JSON and YAML front-ends.
+#[derive(Deserialize)]
+struct Document {
+    #[serde(default)]
+    entries: Vec<EntryDocument>,
+    #[serde(default)]
+    questions: IndexMap<String, String>,
+    #[serde(default)]
+    changes: IndexMap<String, String>,
+    #[serde(default)]
+    tips: IndexMap<String, String>,
+    #[serde(default)]
+    value_changes: IndexMap<String, IndexMap<String, String>>,
+    #[serde(default)]
+    value_tips: IndexMap<String, IndexMap<String, String>>,
+    /// Texts in other languages, by language.
+    #[serde(default)]
+    languages: IndexMap<String, LanguageDocument>,
+    #[serde(default)]
+    subtypes: IndexMap<String, String>,
+    #[serde(default)]
+    aliases: IndexMap<String, String>,
+    #[serde(default)]
+    target: Option<String>,
+    #[serde(default)]
+    priors: IndexMap<String, IndexMap<String, f64>>,
+    #[serde(default)]
+    tests: Vec<TestCase>,
+}
+
+/// Questions, translations and tips of the base in one other language.
+#[derive(Deserialize)]
+struct LanguageDocument {
+    #[serde(default)]
+    questions: IndexMap<String, String>,
+    #[serde(default)]
+    changes: IndexMap<String, String>,
+    #[serde(default)]
+    tips: IndexMap<String, String>,
+    #[serde(default)]
+    value_changes: IndexMap<String, IndexMap<String, String>>,
+    #[serde(default)]
+    value_tips: IndexMap<String, IndexMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+struct EntryDocument {
+    conditions: Vec<ConditionDocument>,
+    #[serde(default)]
+    ignored: Vec<String>,
+    category: String,
+    value: String,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    date: Option<String>,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ConditionDocument {
+    category: String,
+    value: String,
+    #[serde(default = "default_weight")]
+    weight: f64,
+    #[serde(default)]
+    probability: Option<f64>,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl Document {
+    fn into_db(self) -> Result<DB, ParserError> {
+        let mut db = DB::new();
+
+        for (alias, value) in self.aliases {
+            db.aliases.insert(identifier(alias)?, identifier(value)?);
+        }
+        for entry in self.entries {
+            let conditions = entry
+                .conditions
+                .into_iter()
+                .map(|condition| {
+                    Ok(Condition {
+                        weight: condition.weight,
+                        probability: condition.probability.map(probability).transpose()?,
+                        ..Condition::new(
+                            identifier(condition.category)?,
+                            identifier(condition.value)?,
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>, ParserError>>()?;
+            let ignored = entry
+                .ignored
+                .into_iter()
+                .map(identifier)
+                .collect::<Result<Vec<_>, _>>()?;
+            if conditions.is_empty() && ignored.is_empty() {
+                return Err(invalid(format!(
+                    "entry `{} - {}` has no conditions",
+                    entry.category, entry.value
+                )));
+            }
+
+            let provenance = Provenance {
+                author: entry.author.map(line).transpose()?,
+                source: entry.source.map(line).transpose()?,
+                date: entry.date.map(date).transpose()?,
+            };
+
+            db.push_entry(Entry {
+                value: identifier(entry.value)?,
+                category: identifier(entry.category)?,
+                conditions,
+                ignored,
+                provenance,
+                disabled: entry.disabled,
+                note: entry.note.map(line).transpose()?,
+            });
+        }
+        for (value, parent) in self.subtypes {
+            let (value, parent) = (identifier(value)?, identifier(parent)?);
+            db.taxonomy
+                .insert(db.canonical(&value).clone(), db.canonical(&parent).clone());
+        }
+
+        for (category, text) in self.questions {
+            db.questions
+                .insert(identifier(category)?, Question::new(question(text)?));
+        }
+        for (category, text) in self.changes {
+            db.changes.insert(identifier(category)?, line(text)?);
+        }
+        for (category, text) in self.tips {
+            db.tips.insert(identifier(category)?, line(text)?);
+        }
+        for (category, texts) in self.value_changes {
+            let category = identifier(category)?;
+            for (value, text) in texts {
+                let value = db.canonical(&identifier(value)?).clone();
+                db.value_changes
+                    .entry(category.clone())
+                    .or_default()
+                    .insert(value, line(text)?);
+            }
+        }
+        for (category, texts) in self.value_tips {
+            let category = identifier(category)?;
+            for (value, text) in texts {
+                let value = db.canonical(&identifier(value)?).clone();
+                db.value_tips
+                    .entry(category.clone())
+                    .or_default()
+                    .insert(value, line(text)?);
+            }
+        }
+        for (language, document) in self.languages {
+            if language.len() != 2 || !language.chars().all(|x| x.is_ascii_lowercase()) {
+                return Err(invalid(format!("`{}` is not a language code", language)));
+            }
+            let texts =
+                document
+                    .questions
+                    .into_iter()
+                    .map(|(category, text)| Ok((TextKey::Question(identifier(category)?), text)))
+                    .chain(document.changes.into_iter().map(|(category, text)| {
+                        Ok((TextKey::Change(identifier(category)?, None), text))
+                    }))
+                    .chain(document.tips.into_iter().map(|(category, text)| {
+                        Ok((TextKey::Tip(identifier(category)?, None), text))
+                    }));
+            let value_texts = [
+                (document.value_changes, TextKey::Change as fn(_, _) -> _),
+                (document.value_tips, TextKey::Tip),
+            ]
+            .into_iter()
+            .flat_map(|(texts, key)| {
+                texts.into_iter().flat_map(move |(category, texts)| {
+                    texts.into_iter().map(move |(value, text)| {
+                        let value = identifier(value)?;
+                        Ok((key(identifier(category.clone())?, Some(value)), text))
+                    })
+                })
+            });
+
+            for text in texts.chain(value_texts) {
+                let (mut key, text): (TextKey, String) = text?;
+                let text = line(text)?;
+                if let TextKey::Change(_, Some(value)) | TextKey::Tip(_, Some(value)) = &mut key {
+                    *value = db.canonical(value).clone();
+                }
+                db.variants
+                    .entry(key)
+                    .or_default()
+                    .insert(language.clone(), text);
+            }
+        }
+        db.target = self.target.map(identifier).transpose()?;
+        for (category, priors) in self.priors {
+            let category = identifier(category)?;
+            for (value, probability) in priors {
+                let value = db.canonical(&identifier(value)?).clone();
+                db.priors
+                    .entry(category.clone())
+                    .or_default()
+                    .insert(value, self::probability(probability)?);
+            }
+        }
+        for case in self.tests {
+            let Some(target) = case.target else {
+                return Err(invalid(format!("test `{}` has no target", case.name)));
+            };
+            if case.answers.is_empty() || case.expected.is_empty() {
+                return Err(invalid(format!(
+                    "test `{}` has no answers or expected values",
+                    case.name
+                )));
+            }
+
+            db.tests.push(TestCase {
+                target: Some(identifier(target)?),
+                answers: case
+                    .answers
+                    .into_iter()
+                    .map(|(category, value)| Ok((identifier(category)?, identifier(value)?)))
+                    .collect::<Result<_, ParserError>>()?,
+                expected: case
+                    .expected
+                    .into_iter()
+                    .map(|value| Ok(db.canonical(&identifier(value)?).clone()))
+                    .collect::<Result<_, ParserError>>()?,
+                ..case
+            });
+        }
+
+        Ok(db)
+    }
+}
+
+pub struct Json;
+
+impl KnowledgeSource for Json {
+    fn parse(&self, contents: &str) -> Result<DB, ParserError> {
+        serde_json::from_str::<Document>(contents)
+            .map_err(|err| {
+                ParserError::Parse(Arc::new(err.to_string()), (err.line(), err.column()))
+            })?
+            .into_db()
+    }
+}
+
+pub struct Yaml;
+
+impl KnowledgeSource for Yaml {
+    fn parse(&self, contents: &str) -> Result<DB, ParserError> {
+        serde_yaml::from_str::<Document>(contents)
+            .map_err(|err| {
+                let position = err
+                    .location()
+                    .map_or((0, 0), |location| (location.line(), location.column()));
+                ParserError::Parse(Arc::new(err.to_string()), position)
+            })?
+            .into_db()
+    }
+}
+
+/// A table with a header row of categories; the last column holds the
+/// conclusion, empty cells are skipped and `*` marks a don't-care.
+pub struct Csv;
+
+impl KnowledgeSource for Csv {
+    fn parse(&self, contents: &str) -> Result<DB, ParserError> {
+        let error = |err: csv::Error| {
+            let line = err
+                .position()
+                .map_or(0, |position| position.line() as usize);
+            ParserError::Parse(Arc::new(err.to_string()), (line, 1))
+        };
+
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(contents.as_bytes());
+        let headers = reader
+            .headers()
+            .map_err(error)?
+            .iter()
+            .map(|header| identifier(header.trim().to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some((conclusion, conditions)) = headers.split_last() else {
+            return Err(invalid("CSV has no header row".to_string()));
+        };
+
+        let mut db = DB::new();
+        for record in reader.records() {
+            let record = record.map_err(error)?;
+            let value = record.get(conditions.len()).unwrap_or_default().trim();
+            if value.is_empty() {
+                continue;
+            }
+
+            let ignored = conditions
+                .iter()
+                .zip(record.iter())
+                .filter(|(_, cell)| cell.trim() == "*")
+                .map(|(category, _)| category.clone())
+                .collect::<Vec<_>>();
+            let conditions = conditions
+                .iter()
+                .zip(record.iter())
+                .filter(|(_, cell)| !cell.trim().is_empty() && cell.trim() != "*")
+                .map(|(category, cell)| {
+                    Ok(Condition::new(
+                        category.clone(),
+                        identifier(cell.trim().to_string())?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, ParserError>>()?;
+            if conditions.is_empty() {
+                continue;
+            }
+
+            db.push_entry(Entry {
+                value: identifier(value.to_string())?,
+                category: conclusion.clone(),
+                conditions,
+                ignored,
+                provenance: Provenance::default(),
+                disabled: false,
+                note: None,
+            });
+        }
+
+        Ok(db)
+    }
+}
+
+/// Writes the entries concluding `category` as the table [`Csv`] reads: a
+/// column per condition category and the conclusion last. Disabled entries
+/// and all but the first condition on a category are left out, as the table
+/// cannot hold them.
+pub fn format_csv(db: &DB, category: &str) -> String {
+    let entries = db
+        .entries
+        .iter()
+        .filter(|entry| entry.category == category && !entry.disabled)
+        .collect::<Vec<_>>();
+
+    let mut columns: Vec<&String> = Vec::new();
+    for entry in entries.iter() {
+        let categories = entry
+            .conditions
+            .iter()
+            .map(|condition| &condition.category)
+            .chain(entry.ignored.iter());
+        for category in categories {
+            if !columns.contains(&category) {
+                columns.push(category);
+            }
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let header = columns.iter().map(|x| x.as_str()).chain([category]);
+    let _ = writer.write_record(header);
+    for entry in entries {
+        let cells = columns.iter().map(|&column| {
+            if entry.ignored.contains(column) {
+                return "*";
+            }
+            entry
+                .conditions
+                .iter()
+                .find(|condition| &condition.category == column)
+                .map_or("", |condition| condition.value.as_str())
+        });
+        let _ = writer.write_record(cells.chain([entry.value.as_str()]));
+    }
+
+    writer
+        .into_inner()
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .unwrap_or_default()
+}
+
+/// Checks that `name` can be written back as a DSL category or value.
+pub(crate) fn identifier(name: String) -> Result<String, ParserError> {
+    let name = match name.trim() {
+        trimmed if trimmed.len() == name.len() => name,
+        trimmed => trimmed.to_string(),
+    };
+    if name.is_empty() || !name.chars().all(|x| x.is_alphanumeric() || x == '_') {
+        return Err(invalid(format!("`{}` is not a valid identifier", name)));
+    }
+
+    Ok(name)
+}
+
+/// Checks that `text` fits on the single line the DSL gives it.
+fn line(text: String) -> Result<String, ParserError> {
+    if text.trim().is_empty() || text.contains(['\n', '\r']) {
+        return Err(invalid(format!("`{}` is not a single line of text", text)));
+    }
+
+    Ok(text)
+}
+
+/// Checks that `text` is written in the words the DSL allows in questions.
+fn question(text: String) -> Result<String, ParserError> {
+    let text = match text.trim() {
+        trimmed if trimmed.len() == text.len() => text,
+        trimmed => trimmed.to_string(),
+    };
+    if !is_question_text(&text) {
+        return Err(invalid(format!("`{}` is not a valid question text", text)));
+    }
+
+    Ok(text)
+}
+
+/// Checks that `date` is written as `YYYY-MM-DD`.
+fn date(date: String) -> Result<String, ParserError> {
+    let well_formed = date.len() == 10
+        && date.bytes().enumerate().all(|(i, x)| match i {
+            4 | 7 => x == b'-',
+            _ => x.is_ascii_digit(),
+        });
+    if !well_formed {
+        return Err(invalid(format!(
+            "`{}` is not a date of the form YYYY-MM-DD",
+            date
+        )));
+    }
+
+    Ok(date)
+}
+
+/// Checks that `value` is a probability, between 0 and 1.
+fn probability(value: f64) -> Result<f64, ParserError> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(invalid(format!("`{}` is not a probability", value)));
+    }
+
+    Ok(value)
+}
+
+fn invalid(message: String) -> ParserError {
+    ParserError::Parse(Arc::new(message), (0, 0))
+}
@@ -0,0 +1,17 @@
+pub mod audit;
+pub mod builder;
+pub mod cases;
+pub mod clips;
+pub mod encoding;
+pub mod engine;
+pub mod formatter;
+pub mod fuzzy;
+#[cfg(feature = "generator")]
+pub mod generator;
+pub mod kb_format;
+pub mod lint;
+pub mod parser;
+pub mod schema;
+pub mod sqlite;
+pub mod strategy;
+pub mod template;
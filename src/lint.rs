@@ -0,0 +1,427 @@
+//! Checks of a knowledge base for mistakes the parser accepts, each of which
+//! can be turned off.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use indexmap::IndexMap;
+
+use crate::parser::{Block, ParseCache, DB};
+use crate::schema::{self, line_col, Warning};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Check {
+    /// Entry numbers out of order or repeated.
+    Numbering,
+    /// Entries and priors that break the declarations of the base.
+    Schema,
+    /// Categories the user is asked about that have no question.
+    MissingQuestion,
+    /// Declarations about categories no entry uses, and a target no entry
+    /// concludes.
+    OrphanCategory,
+    /// Tips about categories no entry uses, which are never shown.
+    UnusedTip,
+    /// Entries repeating or contradicting another one.
+    Duplicate,
+    /// Categories and values spelled the same but for case.
+    Casing,
+}
+
+impl Check {
+    pub const ALL: [Check; 7] = [
+        Check::Numbering,
+        Check::Schema,
+        Check::MissingQuestion,
+        Check::OrphanCategory,
+        Check::UnusedTip,
+        Check::Duplicate,
+        Check::Casing,
+    ];
+
+    fn run(self, cache: &ParseCache, db: &DB) -> Vec<Warning> {
+        match self {
+            Check::Numbering => numbering(cache),
+            Check::Schema => schema_violations(cache, db),
+            Check::MissingQuestion => missing_questions(cache, db),
+            Check::OrphanCategory => orphan_categories(cache, db),
+            Check::UnusedTip => unused_tips(cache, db),
+            Check::Duplicate => schema::conflicts(cache, db)
+                .into_iter()
+                .map(|conflict| Warning {
+                    message: conflict.message,
+                    position: (conflict.lines.start + 1, 1),
+                })
+                .collect(),
+            Check::Casing => casing(cache),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Check::Numbering => "Нумерация правил",
+            Check::Schema => "Допустимые значения",
+            Check::MissingQuestion => "Категории без вопроса",
+            Check::OrphanCategory => "Неиспользуемые категории",
+            Check::UnusedTip => "Неиспользуемые подсказки",
+            Check::Duplicate => "Повторы и противоречия",
+            Check::Casing => "Разный регистр",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub check: Check,
+    pub warning: Warning,
+}
+
+/// Runs the `checks` over the parsed base, returning the findings in source
+/// order.
+pub fn lint(cache: &ParseCache, db: &DB, checks: &[Check]) -> Vec<Finding> {
+    let mut findings = checks
+        .iter()
+        .flat_map(|&check| {
+            check
+                .run(cache, db)
+                .into_iter()
+                .map(move |warning| Finding { check, warning })
+        })
+        .collect::<Vec<_>>();
+    findings.sort_by_key(|finding| finding.warning.position);
+    findings
+}
+
+/// Spellings of names in the source that a [normalized](DB::normalized)
+/// base reads as another spelling, one per spelling at its first use.
+pub fn merged_spellings(cache: &ParseCache, db: &DB) -> Vec<Warning> {
+    let source = cache.source();
+    let mut merged: IndexMap<(&str, &str), Vec<(usize, usize)>> = IndexMap::new();
+
+    for parsed in cache.blocks() {
+        let start = parsed.span.start;
+        let text = &source[parsed.span.clone()];
+        // Names without a span of their own are looked for in the order
+        // the block has them, after its keyword.
+        let mut searched = text.find(char::is_whitespace).unwrap_or(0);
+        let at = |searched: &mut usize, name: &str| start + find_after(text, searched, name);
+
+        let names: Vec<(&String, usize)> = match &parsed.block {
+            Block::Entry(_, entry) => {
+                let pairs = entry
+                    .conditions
+                    .iter()
+                    .map(|condition| (&condition.category, &condition.value))
+                    .chain([(&entry.category, &entry.value)]);
+                let mut names = pairs
+                    .zip(parsed.pairs.iter())
+                    .flat_map(|((category, value), span)| {
+                        [(category, span.start), (value, span.end - value.len())]
+                    })
+                    .collect::<Vec<_>>();
+                for name in &entry.ignored {
+                    names.push((name, at(&mut searched, name)));
+                }
+                names
+            }
+            Block::Test(case) => {
+                // Answers repeated in the block are kept once, so each pair
+                // is matched to the answer it spells.
+                let mut names = Vec::new();
+                for span in &parsed.pairs {
+                    let pair = &source[span.clone()];
+                    let answer = case.answers.iter().find(|(category, value)| {
+                        pair.starts_with(category.as_str()) && pair.ends_with(value.as_str())
+                    });
+                    if let Some((category, value)) = answer {
+                        names.push((category, span.start));
+                        names.push((value, span.end - value.len()));
+                    }
+                }
+                if let Some(last) = parsed.pairs.last() {
+                    searched = last.end - start;
+                }
+                for name in case.target.iter().chain(&case.expected) {
+                    names.push((name, at(&mut searched, name)));
+                }
+                names
+            }
+            Block::Change(category, Some(value), _) | Block::Tip(category, Some(value), _) => {
+                vec![
+                    (category, at(&mut searched, category)),
+                    (value, at(&mut searched, value)),
+                ]
+            }
+            Block::Advice(category, _)
+            | Block::Change(category, None, _)
+            | Block::Tip(category, None, _)
+            | Block::MultiValued(category)
+            | Block::Target(category) => vec![(category, at(&mut searched, category))],
+            Block::Subtype(first, second)
+            | Block::Alias(first, second)
+            | Block::Prior(first, second, _) => vec![
+                (first, at(&mut searched, first)),
+                (second, at(&mut searched, second)),
+            ],
+            Block::Domain(category, values) => [category]
+                .into_iter()
+                .chain(values)
+                .map(|name| (name, at(&mut searched, name)))
+                .collect(),
+            Block::Lock(_) => continue,
+        };
+
+        for (name, offset) in names {
+            let spelling = db.spelling(name);
+            if spelling != name {
+                merged
+                    .entry((name, spelling))
+                    .or_default()
+                    .push(line_col(source, offset));
+            }
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|((name, spelling), positions)| {
+            let mut message = format!("`{}` is read as `{}`", name, spelling);
+            if positions.len() > 1 {
+                let others = positions[1..]
+                    .iter()
+                    .map(|(line, column)| format!("{}:{}", line, column))
+                    .collect::<Vec<_>>();
+                message.push_str(&format!(", also at {}", others.join(", ")));
+            }
+            Warning {
+                message,
+                position: positions[0],
+            }
+        })
+        .collect()
+}
+
+/// Offset of `name` in `text` at or after `searched`, which is moved past
+/// it; the start of the text when it is not there.
+fn find_after(text: &str, searched: &mut usize, name: &str) -> usize {
+    match text[*searched..].find(name) {
+        Some(at) => {
+            let at = *searched + at;
+            *searched = at + name.len();
+            at
+        }
+        None => 0,
+    }
+}
+
+fn numbering(cache: &ParseCache) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut numbers: HashMap<u64, (usize, usize)> = HashMap::new();
+    let mut expected = 1;
+
+    for parsed in cache.blocks() {
+        let Block::Entry(number, _) = &parsed.block else {
+            continue;
+        };
+        let position = line_col(cache.source(), parsed.span.start);
+        if let Some(message) = numbering_violation(&mut numbers, &mut expected, *number, position) {
+            warnings.push(Warning { message, position });
+        }
+    }
+
+    warnings
+}
+
+fn numbering_violation(
+    numbers: &mut HashMap<u64, (usize, usize)>,
+    expected: &mut u64,
+    number: Option<u64>,
+    position: (usize, usize),
+) -> Option<String> {
+    let Some(number) = number else {
+        return Some("entry number is too large".to_string());
+    };
+
+    if let Some((line, _)) = numbers.get(&number) {
+        return Some(format!(
+            "entry number {} is already used at line {}",
+            number, line
+        ));
+    }
+    numbers.insert(number, position);
+
+    let found = std::mem::replace(expected, number.saturating_add(1));
+    (number != found).then(|| format!("expected entry number {}, found {}", found, number))
+}
+
+fn schema_violations(cache: &ParseCache, db: &DB) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    for parsed in cache.blocks() {
+        let position = line_col(cache.source(), parsed.span.start);
+        match &parsed.block {
+            Block::Entry(_, entry) => {
+                for message in schema::entry_violations(db, entry) {
+                    warnings.push(Warning { message, position });
+                }
+            }
+            Block::Prior(category, value, probability) if *probability > 1.0 => {
+                warnings.push(Warning {
+                    message: format!("prior of `{} - {}` is greater than 1", category, value),
+                    position,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    warnings
+}
+
+/// Categories entries ask about that have no question and are not concluded
+/// by any entry either, reported once at their first use.
+fn missing_questions(cache: &ParseCache, db: &DB) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let concluded = db
+        .entries
+        .iter()
+        .map(|entry| &entry.category)
+        .collect::<HashSet<_>>();
+    let mut unasked: HashSet<&String> = HashSet::new();
+
+    for parsed in cache.blocks() {
+        let Block::Entry(_, entry) = &parsed.block else {
+            continue;
+        };
+        let position = line_col(cache.source(), parsed.span.start);
+        for condition in entry.conditions.iter() {
+            let category = &condition.category;
+            if !db.questions.contains_key(category)
+                && !concluded.contains(category)
+                && unasked.insert(category)
+            {
+                warnings.push(Warning {
+                    message: format!("category `{}` has no question", category),
+                    position,
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+fn orphan_categories(cache: &ParseCache, db: &DB) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let concluded = db
+        .entries
+        .iter()
+        .map(|entry| &entry.category)
+        .collect::<HashSet<_>>();
+
+    for parsed in cache.blocks() {
+        let position = line_col(cache.source(), parsed.span.start);
+        let (kind, category) = match &parsed.block {
+            Block::Advice(category, _) => ("question", category),
+            Block::Change(category, value, _) => {
+                if let Some(message) = unused_value(db, "translation", category, value) {
+                    warnings.push(Warning { message, position });
+                }
+                ("translation", category)
+            }
+            Block::Domain(category, _) => ("value list", category),
+            Block::MultiValued(category) => ("multi-valued declaration", category),
+            Block::Prior(category, ..) => ("prior", category),
+            Block::Target(category) => {
+                if !concluded.contains(category) {
+                    warnings.push(Warning {
+                        message: format!(
+                            "target category `{}` is not concluded by any entry",
+                            category
+                        ),
+                        position,
+                    });
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        if !db.categories.contains_key(category) {
+            warnings.push(Warning {
+                message: format!("{} refers to category `{}` no entry uses", kind, category),
+                position,
+            });
+        }
+    }
+
+    warnings
+}
+
+fn unused_tips(cache: &ParseCache, db: &DB) -> Vec<Warning> {
+    cache
+        .blocks()
+        .iter()
+        .filter_map(|parsed| match &parsed.block {
+            Block::Tip(category, value, _) => {
+                let message = match db.categories.contains_key(category) {
+                    false => format!("tip refers to category `{}` no entry uses", category),
+                    true => unused_value(db, "tip", category, value)?,
+                };
+                Some(Warning {
+                    message,
+                    position: line_col(cache.source(), parsed.span.start),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Complaint about a text written for a value of a used category that no
+/// entry uses, which is never shown.
+fn unused_value(db: &DB, kind: &str, category: &str, value: &Option<String>) -> Option<String> {
+    let value = db.canonical(value.as_ref()?);
+    let values = db.categories.get(category)?;
+    (!values.contains(value)).then(|| {
+        format!(
+            "{} refers to value `{}` of `{}` no entry uses",
+            kind, value, category
+        )
+    })
+}
+
+/// Names of categories and values that differ from an earlier one only in
+/// case, which the engine treats as different.
+fn casing(cache: &ParseCache) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut spellings: HashMap<String, &String> = HashMap::new();
+
+    for parsed in cache.blocks() {
+        let Block::Entry(_, entry) = &parsed.block else {
+            continue;
+        };
+        let position = line_col(cache.source(), parsed.span.start);
+
+        let names = entry
+            .conditions
+            .iter()
+            .flat_map(|condition| [&condition.category, &condition.value])
+            .chain(entry.ignored.iter())
+            .chain([&entry.category, &entry.value]);
+        for name in names {
+            let first = *spellings.entry(name.to_lowercase()).or_insert(name);
+            if first != name {
+                warnings.push(Warning {
+                    message: format!("`{}` differs from `{}` only in case", name, first),
+                    position,
+                });
+            }
+        }
+    }
+
+    warnings.dedup_by(|a, b| a.message == b.message && a.position == b.position);
+    warnings
+}
@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use iced::futures::{channel::mpsc::Sender, SinkExt};
+use tokio::io::AsyncReadExt;
+
+use crate::encoding::{self, FileEncoding, TextFormat};
+use crate::formatter::format_db;
+use crate::kb_format::KbFormat;
+use crate::parser::{parse_streaming, ParseCache, DB};
+use crate::settings::FileSizeLimit;
+use crate::store::Error;
+use crate::store::{self, FileStore, StoreEvent};
+
+/// Files are read in chunks of this size so progress can be shown.
+const CHUNK: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub enum ParseEvent {
+    /// Share of the source parsed.
+    Progress(f32),
+    Finished(Result<(Arc<ParseCache>, Arc<DB>), Error>),
+}
+
+/// Asks for a knowledge base file and loads it.
+pub async fn open(
+    dir: Option<PathBuf>,
+    encoding: FileEncoding,
+    format: KbFormat,
+    limit: FileSizeLimit,
+    mut sender: Sender<StoreEvent>,
+) {
+    let name = match format {
+        KbFormat::Auto => "База знаний".to_string(),
+        format => format.to_string(),
+    };
+    let mut dialog = rfd::AsyncFileDialog::new()
+        .set_title("Открыть базу знаний...")
+        .add_filter(name, format.extensions())
+        .add_filter("Все файлы", &["*"]);
+    if let Some(dir) = dir {
+        dialog = dialog.set_directory(dir);
+    }
+
+    match dialog.pick_file().await {
+        Some(picked_file) => {
+            let store = FileStore {
+                path: picked_file.path().to_owned(),
+                encoding,
+                format,
+                limit,
+            };
+            store::load(Arc::new(store), sender).await;
+        }
+        None => {
+            let _ = sender
+                .send(StoreEvent::Finished(Err(Error::DialogClosed)))
+                .await;
+        }
+    }
+}
+
+/// Reads a knowledge base in any supported format and returns it as DSL
+/// source, which is what the editor and the incremental parser work on.
+pub async fn read(
+    path: &Path,
+    encoding: FileEncoding,
+    format: KbFormat,
+    limit: FileSizeLimit,
+    sender: &mut Sender<StoreEvent>,
+) -> Result<(Arc<String>, TextFormat), Error> {
+    let io_error = |err: std::io::Error| Error::IO(err.kind());
+
+    let mut file = tokio::fs::File::open(path).await.map_err(io_error)?;
+    let total = file.metadata().await.map_err(io_error)?.len();
+    if limit.exceeded_by(total) && !confirm_large(path, total).await {
+        return Err(Error::DialogClosed);
+    }
+
+    let mut bytes = Vec::with_capacity(total as usize);
+    let mut chunk = vec![0; CHUNK];
+    loop {
+        let read = file.read(&mut chunk).await.map_err(io_error)?;
+        if read == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+
+        let _ = sender
+            .send(StoreEvent::Progress(bytes.len() as u64, Some(total)))
+            .await;
+    }
+    let (contents, text_format) = encoding::decode(&bytes, encoding);
+    // No text encoding leaves NUL characters in decoded text.
+    if contents.contains('\0') {
+        return Err(Error::IO(std::io::ErrorKind::InvalidData));
+    }
+
+    let format = format.resolve(path);
+    if format == KbFormat::Dsl {
+        return Ok((Arc::new(contents), text_format));
+    }
+
+    let contents = tokio::task::spawn_blocking(move || {
+        format.source().parse(&contents).map(|db| format_db(&db))
+    })
+    .await
+    .map_err(|err| Error::Parse(Arc::new(err.to_string()), (0, 0)))??;
+
+    Ok((Arc::new(contents), text_format))
+}
+
+/// Parses loaded DSL source block by block, reporting every percent done.
+pub async fn parse(contents: Arc<String>, mut sender: Sender<ParseEvent>) {
+    let mut progress = sender.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut reported = 0.0;
+        parse_streaming(&contents, |share| {
+            if share - reported >= 0.01 {
+                reported = share;
+                let _ = progress.try_send(ParseEvent::Progress(share));
+            }
+        })
+    })
+    .await
+    .map_err(|err| Error::Parse(Arc::new(err.to_string()), (0, 0)))
+    .and_then(|result| result.map_err(Error::from))
+    .map(|(cache, db)| (Arc::new(cache), Arc::new(db)));
+
+    let _ = sender.send(ParseEvent::Finished(result)).await;
+}
+
+/// Large files make the editor slow, so they are only loaded on request.
+async fn confirm_large(path: &Path, size: u64) -> bool {
+    let answer = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Большой файл")
+        .set_description(format!(
+            "Файл {} занимает {:.1} МБ, редактор может работать с ним медленно. Открыть?",
+            path.display(),
+            size as f64 / FileSizeLimit::MEGABYTE as f64
+        ))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        .await;
+
+    answer == rfd::MessageDialogResult::Yes
+}
@@ -0,0 +1,35 @@
+//! Passphrase prompt shown in place of the editor of a knowledge base that
+//! declares `защита`.
+
+use iced::{
+    theme,
+    widget::{button, column, container, text},
+    Element,
+};
+
+use crate::{
+    a11y::{self, Accessible},
+    main_window::Message,
+};
+
+pub fn view(passphrase: &str) -> Element<'_, Message> {
+    container(
+        column![
+            text("База знаний защищена").size(20),
+            text("Введите пароль, чтобы редактировать её.").size(14),
+            a11y::input("passphrase", "Пароль...", passphrase)
+                .secure(true)
+                .on_input(Message::PassphraseChanged)
+                .on_submit(Message::Unlock),
+            button("Разблокировать")
+                .on_press(Message::Unlock)
+                .style(theme::Button::Primary)
+                .named("unlock"),
+        ]
+        .spacing(10)
+        .padding(20)
+        .max_width(400),
+    )
+    .style(theme::Container::Box)
+    .into()
+}
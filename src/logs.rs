@@ -1,65 +1,251 @@
-use chrono::Local;
+use std::collections::HashSet;
+use std::fmt;
+
+use chrono::{DateTime, Local};
 use iced::{
-    theme,
-    widget::{button, column, horizontal_space, row, scrollable, text, Column},
-    Element,
+    theme::{self, palette},
+    widget::{button, column, container, horizontal_space, pick_list, row, text, tooltip, Column},
+    Alignment, Color, Element, Length, Theme,
 };
 
-use crate::{main_window::Error, main_window::Message};
+use crate::{
+    a11y::Accessible,
+    cases::CaseOutcome,
+    main_window::{tab_scrollable, Message, Tabs},
+    schema::Warning,
+    settings::LogTimes,
+    store::Error,
+};
 
 #[derive(Debug, Clone)]
 struct LogEntry {
     severity: LogSeverity,
-    timestamp: String,
+    time: DateTime<Local>,
+    source: LogSource,
     message: String,
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
+/// Part of the application a message comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSource {
+    /// Reading and editing the text of the base.
+    Parser,
+    /// Queries and test cases.
+    Engine,
+    /// Files, downloads and the clipboard.
+    Io,
+    /// Consultation and the rest of the interface.
+    Ui,
+}
+
+impl LogSource {
+    const ALL: [LogSource; 4] = [
+        LogSource::Parser,
+        LogSource::Engine,
+        LogSource::Io,
+        LogSource::Ui,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            LogSource::Parser => "parser",
+            LogSource::Engine => "engine",
+            LogSource::Io => "io",
+            LogSource::Ui => "ui",
+        }
+    }
+}
+
+impl fmt::Display for LogSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogSource::Parser => "База знаний",
+            LogSource::Engine => "Поиск",
+            LogSource::Io => "Файлы и сеть",
+            LogSource::Ui => "Интерфейс",
+        })
+    }
+}
+
+/// Messages shown in the log: from one source, or all of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SourceFilter(Option<LogSource>);
+
+impl SourceFilter {
+    const ALL: [SourceFilter; 5] = [
+        SourceFilter(None),
+        SourceFilter(Some(LogSource::ALL[0])),
+        SourceFilter(Some(LogSource::ALL[1])),
+        SourceFilter(Some(LogSource::ALL[2])),
+        SourceFilter(Some(LogSource::ALL[3])),
+    ];
+}
+
+impl fmt::Display for SourceFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(source) => source.fmt(f),
+            None => f.write_str("Все источники"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum LogSeverity {
     Info,
     Warning,
     Error,
 }
 
+impl LogSeverity {
+    fn label(&self) -> &'static str {
+        match self {
+            LogSeverity::Info => "INFO",
+            LogSeverity::Warning => "WARN",
+            LogSeverity::Error => "ERROR",
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            LogSeverity::Info => "ℹ",
+            LogSeverity::Warning => "!",
+            LogSeverity::Error => "×",
+        }
+    }
+
+    /// Color of the severity, readable on the background of `palette`.
+    fn color(self, palette: &palette::Extended) -> Color {
+        match self {
+            LogSeverity::Info => palette.primary.base.color,
+            LogSeverity::Warning if palette.is_dark => Color::from_rgb(0.92, 0.76, 0.45),
+            LogSeverity::Warning => Color::from_rgb(0.6, 0.4, 0.0),
+            LogSeverity::Error => palette.danger.base.color,
+        }
+    }
+
+    /// Background of a row: tinted with the color of warnings and errors,
+    /// every other row of the rest striped.
+    fn row_style(self, striped: bool) -> impl Fn(&Theme) -> container::Appearance {
+        move |theme| {
+            let palette = theme.extended_palette();
+            let background = match self {
+                LogSeverity::Info => striped.then_some(palette.background.weak.color),
+                _ => Some(Color {
+                    a: 0.15,
+                    ..self.color(palette)
+                }),
+            };
+            container::Appearance {
+                background: background.map(Into::into),
+                ..container::Appearance::default()
+            }
+        }
+    }
+
+    fn label_style(self) -> impl Fn(&Theme) -> container::Appearance {
+        move |theme| container::Appearance {
+            text_color: Some(self.color(theme.extended_palette())),
+            ..container::Appearance::default()
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Logs {
     stash: Vec<LogEntry>,
+    /// Groups of repeated messages listing each occurrence, by the index of
+    /// their first message.
+    expanded: HashSet<usize>,
+    filter: SourceFilter,
 }
 
 impl Logs {
-    pub fn view(&self) -> Element<Message> {
+    pub fn view(&self, times: LogTimes) -> Element<'_, Message> {
         if self.stash.is_empty() {
             return text("Сообщений нет").into();
         }
+        let now = Local::now();
+        let time = |entry: &LogEntry| {
+            tooltip(
+                text(match times {
+                    LogTimes::Relative => relative_time(entry.time, now),
+                    LogTimes::Absolute => entry.time.format("%H:%M").to_string(),
+                })
+                .width(110),
+                container(text(entry.time.format(FULL_TIME).to_string())).padding(8),
+                tooltip::Position::Top,
+            )
+            .style(theme::Container::Box)
+        };
 
-        let scrollable_column = scrollable(self.stash.iter().fold(
-            Column::new().spacing(5),
-            |column, log_entry| {
-                column.push(
-                    row![
-                        text(format!(
-                            "[{}]",
-                            match log_entry.severity {
-                                LogSeverity::Info => "INFO",
-                                LogSeverity::Warning => "WARN",
-                                LogSeverity::Error => "ERROR",
-                            }
-                        )),
-                        text(&log_entry.timestamp),
-                        text(&log_entry.message)
-                    ]
-                    .spacing(5),
-                )
-            },
-        ));
+        // Consecutive repeats of a message are shown as one row, which
+        // lists when each of them happened once expanded.
+        let shown = self
+            .stash
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| self.filter.0.is_none_or(|source| entry.source == source))
+            .collect::<Vec<_>>();
+        let groups = shown.chunk_by(|(_, a), (_, b)| {
+            a.severity == b.severity && a.source == b.source && a.message == b.message
+        });
+        let mut list = Column::new().spacing(2);
+        for (index, group) in groups.enumerate() {
+            let (start, _) = group[0];
+            let (_, last) = group[group.len() - 1];
+            let severity = last.severity;
+            let expanded = group.len() > 1 && self.expanded.contains(&start);
+
+            let label = row![
+                text(severity.icon()).width(16),
+                text(severity.label()).width(60)
+            ]
+            .spacing(5);
+            let count = (group.len() > 1).then(|| {
+                button(text(format!("×{}", group.len())).size(14))
+                    .on_press(Message::LogGroupToggled(start))
+                    .style(theme::Button::Text)
+                    .named(format!("log-group-{}", start))
+            });
+            let mut rows = column![row![
+                container(label).style(severity.label_style()),
+                time(last),
+                text(&last.message).width(Length::Fill)
+            ]
+            .push_maybe(count)
+            .spacing(5)
+            .align_items(Alignment::Center)];
+            if expanded {
+                rows = group.iter().fold(rows, |rows, (_, entry)| {
+                    rows.push(row![horizontal_space().width(81), time(entry)])
+                });
+            }
+
+            list = list.push(
+                container(rows.spacing(2))
+                    .style(severity.row_style(index % 2 == 1))
+                    .width(Length::Fill)
+                    .padding([2, 5]),
+            );
+        }
+        let scrollable_column = tab_scrollable(Tabs::Logs, list);
 
         column![
             row![
+                pick_list(&SourceFilter::ALL[..], Some(self.filter), |filter| {
+                    Message::LogSourceSelected(filter.0)
+                })
+                .named("log-source"),
                 horizontal_space(),
+                button("Сохранить лог...")
+                    .on_press(Message::ExportLogs)
+                    .style(theme::Button::Secondary)
+                    .named("logs-export"),
                 button("Очистить лог")
                     .on_press(Message::ClearLogs)
                     .style(theme::Button::Destructive)
+                    .named("logs-clear"),
             ]
             .spacing(5),
             scrollable_column
@@ -68,44 +254,185 @@ impl Logs {
         .into()
     }
 
-    #[allow(dead_code)]
-    pub fn debug(&mut self, msg: &str) {
+    pub fn debug(&mut self, source: LogSource, msg: &str) {
         self.stash.push(LogEntry {
             severity: LogSeverity::Info,
-            timestamp: Local::now().format("%H:%M").to_string(),
+            time: Local::now(),
+            source,
             message: msg.to_string(),
         })
     }
 
+    pub fn warning(&mut self, source: LogSource, warning: &Warning) {
+        let (line, col) = warning.position;
+        self.stash.push(LogEntry {
+            severity: LogSeverity::Warning,
+            time: Local::now(),
+            source,
+            message: format!("Validation ({}:{}): {}", line, col, warning.message),
+        })
+    }
+
+    pub fn case(&mut self, outcome: &CaseOutcome) {
+        self.stash.push(LogEntry {
+            severity: if outcome.passed() {
+                LogSeverity::Info
+            } else {
+                LogSeverity::Error
+            },
+            time: Local::now(),
+            source: LogSource::Engine,
+            message: if outcome.passed() {
+                format!("Case `{}`: passed", outcome.name)
+            } else {
+                format!(
+                    "Case `{}`: expected {:?}, found {:?}",
+                    outcome.name, outcome.expected, outcome.actual
+                )
+            },
+        })
+    }
+
     pub fn error(&mut self, err: Error) {
-        let stamp = Local::now().format("%H:%M").to_string();
+        let time = Local::now();
+        if let Error::ParseErrors(errors, _) = err {
+            for (msg, pos) in errors.iter() {
+                self.error(Error::Parse(msg.clone(), *pos));
+            }
+            return;
+        }
+        let source = match err {
+            Error::Parse(..) | Error::ParseErrors(..) | Error::Edit(_) => LogSource::Parser,
+            Error::Query(_) | Error::Cases(_) => LogSource::Engine,
+            Error::DialogClosed
+            | Error::IO(_)
+            | Error::Sqlite(_)
+            | Error::Network(_)
+            | Error::Xlsx(_)
+            | Error::Answers(_)
+            | Error::Encoding(_) => LogSource::Io,
+        };
+
         self.stash.push({
             match err {
                 Error::DialogClosed => LogEntry {
                     severity: LogSeverity::Info,
-                    timestamp: stamp,
+                    time,
+                    source,
                     message: "Dialog closed".to_string(),
                 },
                 Error::IO(kind) => LogEntry {
                     severity: LogSeverity::Error,
-                    timestamp: stamp,
+                    time,
+                    source,
                     message: format!("IO: {}", kind),
                 },
-                Error::Parse(msg, _) => LogEntry {
+                Error::Parse(msg, (line, col)) => LogEntry {
                     severity: LogSeverity::Error,
-                    timestamp: stamp,
-                    message: format!("Parser: {}", msg),
+                    time,
+                    source,
+                    message: format!("Parser ({}:{}): {}", line, col, msg),
                 },
+                Error::ParseErrors(..) => unreachable!(),
                 Error::Query(msg) => LogEntry {
                     severity: LogSeverity::Info,
-                    timestamp: stamp,
+                    time,
+                    source,
                     message: format!("Search: {}", msg),
                 },
+                Error::Sqlite(msg) => LogEntry {
+                    severity: LogSeverity::Error,
+                    time,
+                    source,
+                    message: format!("SQLite: {}", msg),
+                },
+                Error::Network(msg) => LogEntry {
+                    severity: LogSeverity::Error,
+                    time,
+                    source,
+                    message: format!("Network: {}", msg),
+                },
+                Error::Xlsx(msg) => LogEntry {
+                    severity: LogSeverity::Error,
+                    time,
+                    source,
+                    message: format!("XLSX: {}", msg),
+                },
+                Error::Cases(msg) => LogEntry {
+                    severity: LogSeverity::Error,
+                    time,
+                    source,
+                    message: format!("Test cases: {}", msg),
+                },
+                Error::Answers(msg) => LogEntry {
+                    severity: LogSeverity::Error,
+                    time,
+                    source,
+                    message: format!("Answers: {}", msg),
+                },
+                Error::Edit(msg) => LogEntry {
+                    severity: LogSeverity::Error,
+                    time,
+                    source,
+                    message: format!("Edit: {}", msg),
+                },
+                Error::Encoding(msg) => LogEntry {
+                    severity: LogSeverity::Error,
+                    time,
+                    source,
+                    message: format!("Encoding: {}", msg),
+                },
             }
         });
     }
 
+    /// Messages as plain text, one per line.
+    pub fn export(&self) -> String {
+        self.stash
+            .iter()
+            .map(|entry| {
+                format!(
+                    "[{}] [{}] {} {}\n",
+                    entry.severity.label(),
+                    entry.source.label(),
+                    entry.time.format(FULL_TIME),
+                    entry.message
+                )
+            })
+            .collect()
+    }
+
     pub fn clear_cache(&mut self) {
         self.stash.clear();
+        self.expanded.clear();
+    }
+
+    /// Shows only the messages from `source`, or all of them.
+    pub fn select_source(&mut self, source: Option<LogSource>) {
+        self.filter = SourceFilter(source);
+    }
+
+    pub fn toggle_group(&mut self, start: usize) {
+        if !self.expanded.remove(&start) {
+            self.expanded.insert(start);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stash.is_empty()
+    }
+}
+
+const FULL_TIME: &str = "%Y-%m-%d %H:%M:%S";
+
+/// How long ago `time` was, in whole minutes and hours, or the date and
+/// time after a day.
+fn relative_time(time: DateTime<Local>, now: DateTime<Local>) -> String {
+    let minutes = (now - time).num_minutes();
+    match minutes {
+        ..=0 => "только что".to_string(),
+        1..=59 => format!("{} мин назад", minutes),
+        60..=1439 => format!("{} ч назад", minutes / 60),
+        _ => time.format("%Y-%m-%d %H:%M").to_string(),
     }
 }
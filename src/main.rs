@@ -1,16 +1,82 @@
+use expert_kb::{
+    audit, cases, encoding, engine, formatter, kb_format, lint, parser, schema, sqlite, strategy,
+};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
 use iced::Application;
 
-use crate::main_window::MainWindow;
+use crate::{
+    main_window::{Flags, MainWindow},
+    settings::{Preferences, BUNDLED_FONT},
+};
 
+mod a11y;
+mod about;
+mod check;
+mod dependencies;
+mod download;
 mod editor;
+mod embedded;
+mod error_dialog;
 mod file_explorer;
+mod generation;
+mod loading;
+mod lock;
 mod logs;
 mod main_window;
-mod parser;
+mod print;
+mod problems;
 mod questions;
+mod recommendations;
+mod rich_text;
+mod settings;
+mod shortcuts;
+mod store;
+mod toasts;
+mod tutorial;
+mod usage;
+mod xlsx_import;
+
+fn main() -> ExitCode {
+    let args = std::env::args_os().skip(1).collect::<Vec<_>>();
+    if check::requested(&args) {
+        return check::run(&args);
+    }
+
+    match run_window() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_window() -> iced::Result {
+    let preferences = Preferences::load();
+    let default_font = preferences.font.font();
+    let default_text_size = preferences.text_size.pixels();
+    let mut viewer = false;
+    // Knowledge base to open, as passed by a file association.
+    let mut file = None;
+    for arg in std::env::args_os().skip(1) {
+        if arg == "--viewer" {
+            viewer = true;
+        } else if file.is_none() {
+            file = Some(PathBuf::from(arg));
+        }
+    }
 
-fn main() -> iced::Result {
     MainWindow::run(iced::Settings {
+        flags: Flags {
+            preferences,
+            file,
+            viewer,
+        },
+        fonts: vec![BUNDLED_FONT.into()],
+        default_font,
+        default_text_size,
         window: iced::window::Settings {
             resizable: true,
             decorations: true,
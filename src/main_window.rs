@@ -1,88 +1,402 @@
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
+use chrono::Local;
+use iced::futures::{channel::mpsc::Sender, SinkExt};
 use iced::{
-    widget::{button, column, container, row, text, text_editor, vertical_space},
-    {executor, theme, Application, Command, Element, Length, Theme},
+    widget::{
+        button, column, container, focus_next, focus_previous, pick_list, progress_bar, row,
+        scrollable::{self, AbsoluteOffset, Scrollable},
+        text, text_editor, vertical_space, Column,
+    },
+    {
+        event, executor, keyboard, theme, window, Application, Command, Element, Event, Length,
+        Subscription, Theme,
+    },
 };
+use indexmap::IndexMap;
+use sha2::{Digest, Sha256};
 use tokio::io;
 
 use crate::{
-    editor::TextEditor,
-    file_explorer::FileExplorer,
-    logs::Logs,
-    parser::{parse_db_from_file, ParserError, DB},
-    questions::Questions,
+    a11y::{self, Accessible},
+    about,
+    audit::{self, AuditKind},
+    cases::{self, cases_path, format_cases, parse_cases, CaseOutcome, TestCase},
+    dependencies::Dependencies,
+    editor::{TextEditor, CHECK_DELAY},
+    embedded::{self, EmbeddedBase},
+    encoding::{FileEncoding, TextFormat},
+    engine::{Budget, Interrupt},
+    error_dialog,
+    file_explorer::{Example, ExampleAnswers, FileExplorer, Merge},
+    formatter::format_db,
+    generation::{Change, Generation, Subscriber},
+    kb_format::{self, KbFormat},
+    lint::{self, Check, Finding},
+    loading::{self, ParseEvent},
+    lock,
+    logs::{LogSource, Logs},
+    parser::{
+        append_entry, merge_values, parse_with_cache, remove_category, reparse, set_entry_disabled,
+        set_question, EditError, ParseCache, ParserError, DB,
+    },
+    print, problems,
+    questions::{HistoryEntry, Questions, Training},
+    recommendations,
+    schema::{self, Conflict},
+    settings::{
+        FileSizeLimit, FontChoice, Language, LogTimes, MatchMode, MatchThreshold, Preferences,
+        QueryTimeout, ReloadMode, Settings, TextSize, ThreadCount, UiScale,
+    },
+    shortcuts,
+    store::{
+        self, Error, FileStore, KnowledgeStore, MemoryStore, Snapshot, Source, SqliteStore,
+        StoreEvent, Stored, UrlStore,
+    },
+    strategy::{MatchStrategy, Query, QueryResult},
+    toasts::Toasts,
+    tutorial::{self, Tutorial},
+    usage::Usage,
+    xlsx_import::{self, ColumnRole, Sheet, XlsxImport},
 };
 
 pub struct MainWindow {
-    db: Arc<DB>,
-    file: Option<PathBuf>,
+    db: Generation,
+    parse_cache: Arc<ParseCache>,
+    /// Findings of the enabled checks on the current base.
+    problems: Vec<Finding>,
+    source: Option<Source>,
+    /// Where the opened base is reloaded from and saved to.
+    store: Option<Arc<dyn KnowledgeStore>>,
+    /// Modification time of the opened file when it was last read or
+    /// written, to notice changes made by other programs.
+    modified: Option<SystemTime>,
+    /// Target and answers to restore once the reloaded base is parsed.
+    reloaded: Option<(Option<String>, IndexMap<String, String>)>,
+    /// Hash of the text of the base, under which its answers are kept in
+    /// the preferences.
+    progress_key: Option<String>,
+    /// Bumped on every change of the answers, to remember them only once
+    /// they settle.
+    answers_revision: u64,
+    encoding: FileEncoding,
+    format: KbFormat,
+
+    url: String,
+    /// Share of the download or file read done.
+    progress: Option<f32>,
+
+    xlsx_import: Option<XlsxImport>,
+    tutorial: Option<Tutorial>,
+    toasts: Toasts,
+    /// Error of a knowledge base that could not be loaded, shown over the
+    /// current tab until the user picks what to do.
+    load_failure: Option<Error>,
+    /// Cheatsheet of the keyboard shortcuts shown over the current tab.
+    show_shortcuts: bool,
+
+    settings: Settings,
+    /// Pool queries run in, `None` for the global one.
+    pool: Option<Arc<rayon::ThreadPool>>,
+    /// Budget of the running query, used to cancel it.
+    query_budget: Option<Budget>,
+    /// Passphrase being typed to unlock the editor.
+    passphrase: String,
+    /// Lock of the base the editor was unlocked for; a base with another
+    /// lock asks for its passphrase again.
+    unlocked: Option<String>,
+    window_width: f32,
 
     active_tab: Tabs,
 
     explorer: FileExplorer,
+    dependencies: Dependencies,
     logs: Logs,
     editor: TextEditor,
     questions: Questions,
+    /// Offsets of the main scrollables of the tabs, restored when a tab is
+    /// shown again.
+    scroll_offsets: HashMap<Tabs, AbsoluteOffset>,
+    /// Texts of the recommendations of the last result ticked as done.
+    recommendations_done: HashSet<String>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     EditorActionPerformed(text_editor::Action),
     TabChanged(Tabs),
+    Scrolled(Tabs, AbsoluteOffset),
+    InsertExample(&'static str),
 
     OpenFile,
-    FileOpened(Result<(PathBuf, Arc<String>), Error>),
-    FileParsed(Result<Arc<DB>, Error>),
+    EncodingChanged(FileEncoding),
+    FormatChanged(KbFormat),
+    StoreLoad(StoreEvent),
+    FileParse(ParseEvent),
+    StoreOpened(Result<(Arc<dyn KnowledgeStore>, Stored), Error>),
+    FileParsed(Result<(Arc<ParseCache>, Arc<DB>), Error>),
+    ApplyEditor,
+    Renumber,
+    Renumbered(Result<(Arc<ParseCache>, Arc<DB>), Error>),
+    /// Typing paused at a revision of the editor text.
+    EditorCheckDue(u64),
+    /// Result of checking the editor text at a revision.
+    EditorChecked(u64, Result<Vec<Conflict>, Error>),
+    /// Answers at a revision left unchanged for [`PROGRESS_DELAY`].
+    ProgressDue(u64),
+    /// Result of applying the editor text at a revision.
+    EditorApplied(u64, Result<(Arc<ParseCache>, Arc<DB>), Error>),
+    ToastTick(Instant),
+    LoadFailureEdit,
+    LoadFailurePartial,
+    LoadFailureReopen,
+
+    OpenSqlite,
+    SaveSqlite,
+    SqliteSaved(Result<PathBuf, Error>),
+
+    ImportXlsx,
+    XlsxOpened(Result<(PathBuf, Sheet), Error>),
+    XlsxCategoryChanged(usize, String),
+    XlsxRoleChanged(usize, ColumnRole),
+    XlsxConfirm,
+    XlsxCancel,
+
+    PasteFromClipboard,
+    ClipboardRead(Option<String>),
+    CopyDb,
+    CopyAnswer,
+    CopyResult,
+    CopyResultJson,
+    /// Link in a tip or translation was clicked.
+    LinkOpened(String),
+    OpenDemo,
+    OpenEmbedded(EmbeddedBase),
+    TutorialMoved(usize),
+    TutorialClosed,
+
+    UrlChanged(String),
+    OpenUrl,
 
     ClearLogs,
 
     FindAnswer,
-    FoundAnswer(Result<Arc<String>, Error>),
+    CancelQuery,
+    /// Result of a query with the generation of the base it ran against.
+    FoundAnswer(u64, Arc<QueryRequest>, Result<QueryResult, Error>),
+    FindAllToggled(bool),
+    ExportReport,
+    PrintReport,
+    RecommendationToggled(String, bool),
+    ExportRecommendations,
+    Printed(Result<PathBuf, Error>),
+    HistoryToggled,
+    WindowResized(u32),
+    /// Keyboard focus moves to the next input, or the previous one.
+    FocusMoved(bool),
+    UiScaleChanged(UiScale),
+    TextSizeChanged(TextSize),
+    FontChanged(FontChoice),
+    LanguageChanged(Language),
+    LogTimesChanged(LogTimes),
+    /// Redraw of the relative times in the log.
+    LogsTick,
+    /// Repeats of a message in the log listed or hidden, by the index of
+    /// the first one.
+    LogGroupToggled(usize),
+    LogSourceSelected(Option<LogSource>),
+    LargeControlsToggled(bool),
+    ViewerToggled(bool),
+    PassphraseChanged(String),
+    Unlock,
+    Lock,
+    PreferencesSaved(Result<(), Error>),
+    ConclusionToggled(usize),
+    EntryDisabled(usize, bool),
+    MergeCategorySelected(String),
+    MergeFromSelected(String),
+    MergeIntoSelected(String),
+    MergeValues,
+    RemovalCategorySelected(String),
+    DependencyTargetSelected(String),
+    ExampleRequested(usize),
+    ExampleFound(u64, usize, ExampleAnswers),
+    ExampleClosed,
+    ApplyExample,
+    UsageReset,
+    RemoveCategory,
+    CasesMerged(Result<usize, Error>),
+    RuleValueChanged(String),
+    EditQuestion(Arc<String>),
+    QuestionTextChanged(String),
+    SaveQuestion,
+    CancelQuestionEdit,
+    CreateRule,
+    RecordCase,
+    CaseRecorded(Result<PathBuf, Error>),
+    RunCases,
+    CasesRun(Result<Vec<CaseOutcome>, Error>),
+    RepeatQuery(usize),
+    ExportCsv,
+    ExportLogs,
+    SaveEditor,
+    SaveEditorAs,
+    TextFormatChanged(TextFormat),
+    ExportAnswers,
+    ImportAnswers,
+    AnswersImported(Result<IndexMap<String, String>, Error>),
+    EditorSaved(u64, Result<Arc<dyn KnowledgeStore>, Error>),
+    Exported(Result<PathBuf, Error>),
 
     SelectedCategory(Arc<String>),
+    ClearCategory,
+    ShowAllQuestions(bool),
+    ShortcutsToggled,
+    ShortcutsClosed,
     SelectedAnswer(Arc<String>, Arc<String>),
+    DegreeChanged(Arc<String>, f64),
+
+    QueryThreadsChanged(ThreadCount),
+    MatchModeChanged(MatchMode),
+    MatchThresholdChanged(MatchThreshold),
+    QueryTimeoutChanged(QueryTimeout),
+    FileSizeLimitChanged(FileSizeLimit),
+    ReloadModeChanged(ReloadMode),
+    WatchTick,
+    /// Modification time of the opened file as last checked.
+    FileChecked(Option<SystemTime>),
+    ReloadConfirmed(bool),
+    RestoreConfirmed(bool),
+    TrainingToggled(bool),
+    TrainingGuessChanged(String),
+    CheckToggled(Check, bool),
+    NormalizeNamesToggled(bool),
+    JumpToSource((usize, usize)),
+}
+
+/// What the window starts with.
+#[derive(Debug, Default)]
+pub struct Flags {
+    pub preferences: Preferences,
+    pub file: Option<PathBuf>,
+    /// Whether the application was started with `--viewer`.
+    pub viewer: bool,
 }
 
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub enum Tabs {
     #[default]
     Questions,
+    Recommendations,
     Explorer,
+    Dependencies,
     Logs,
+    Problems,
     Editor,
+    Settings,
+    About,
 }
 
-#[derive(Debug, Clone)]
-pub enum Error {
-    DialogClosed,
-    IO(io::ErrorKind),
-    Parse(Arc<String>, (usize, usize)),
-    Query(Arc<String>),
+impl Tabs {
+    /// Tabs in the order they are listed.
+    pub const ALL: [Tabs; 9] = [
+        Tabs::Questions,
+        Tabs::Recommendations,
+        Tabs::Explorer,
+        Tabs::Dependencies,
+        Tabs::Editor,
+        Tabs::Logs,
+        Tabs::Problems,
+        Tabs::Settings,
+        Tabs::About,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tabs::Questions => "Вопросы",
+            Tabs::Recommendations => "Рекомендации",
+            Tabs::Explorer => "Данные",
+            Tabs::Dependencies => "Зависимости",
+            Tabs::Editor => "Редактор",
+            Tabs::Logs => "Сообщения",
+            Tabs::Problems => "Проблемы",
+            Tabs::Settings => "Настройки",
+            Tabs::About => "Справка",
+        }
+    }
+
+    fn scroll_id(&self) -> scrollable::Id {
+        scrollable::Id::new(format!("tab-{:?}", self))
+    }
+}
+
+/// Main scrollable of `tab`, whose offset is kept while other tabs are
+/// shown.
+pub fn tab_scrollable<'a>(
+    tab: Tabs,
+    content: impl Into<Element<'a, Message>>,
+) -> Scrollable<'a, Message> {
+    Scrollable::new(content)
+        .id(tab.scroll_id())
+        .on_scroll(move |viewport| Message::Scrolled(tab.clone(), viewport.absolute_offset()))
 }
 
 impl Application for MainWindow {
     type Message = Message;
     type Theme = Theme;
-    type Flags = ();
+    type Flags = Flags;
     type Executor = executor::Default;
 
-    fn new(_flags: Self::Flags) -> (Self, Command<Message>) {
-        let db = Arc::new(DB::default());
+    fn new(flags: Self::Flags) -> (Self, Command<Message>) {
+        let mut settings = Settings::default();
+        settings.preferences = flags.preferences;
+        settings.viewer_forced = flags.viewer;
+        let pool = settings.build_pool();
+        let encoding = FileEncoding::default();
+        let format = KbFormat::default();
+        let open = match flags.file {
+            Some(path) => open_store(store_at(path, encoding, format, settings.file_size_limit)),
+            None => Command::none(),
+        };
         (
             Self {
-                db: Arc::clone(&db),
-                file: None,
+                db: Generation::default(),
+                parse_cache: Arc::default(),
+                problems: Vec::new(),
+                source: None,
+                store: None,
+                modified: None,
+                reloaded: None,
+                progress_key: None,
+                answers_revision: 0,
+                encoding,
+                format,
+                url: String::new(),
+                progress: None,
+                xlsx_import: None,
+                tutorial: None,
+                toasts: Toasts::default(),
+                load_failure: None,
+                show_shortcuts: false,
+                settings,
+                pool,
+                query_budget: None,
+                passphrase: String::new(),
+                unlocked: None,
+                window_width: window::Settings::default().size.width,
                 active_tab: Tabs::default(),
-                explorer: FileExplorer {
-                    db: Arc::clone(&db),
-                },
+                explorer: FileExplorer::default(),
+                dependencies: Dependencies::default(),
                 logs: Logs::default(),
                 editor: TextEditor::default(),
                 questions: Questions::default(),
+                scroll_offsets: HashMap::new(),
+                recommendations_done: HashSet::new(),
             },
-            Command::none(),
+            open,
         )
     }
 
@@ -94,51 +408,467 @@ impl Application for MainWindow {
         Theme::Nord
     }
 
+    fn scale_factor(&self) -> f64 {
+        let preferences = &self.settings.preferences;
+        let factor = preferences.scale.factor();
+        if preferences.large_controls {
+            factor * a11y::LARGE_SCALE
+        } else {
+            factor
+        }
+    }
+
     fn update(&mut self, message: Self::Message) -> Command<Message> {
         match message {
-            Message::OpenFile => Command::perform(open_file(), Message::FileOpened),
-            Message::FileOpened(result) => match result {
-                Ok((path, contents)) => {
-                    self.file = Some(path);
-                    self.editor.set_content(&contents);
+            Message::OpenFile => {
+                let dir = self.settings.preferences.last_dir.clone();
+                let (encoding, format) = (self.encoding, self.format);
+                let limit = self.settings.file_size_limit;
 
-                    Command::perform(parse_file(contents), Message::FileParsed)
+                iced::command::channel(16, move |sender| {
+                    loading::open(dir, encoding, format, limit, sender)
+                })
+                .map(Message::StoreLoad)
+            }
+            Message::StoreLoad(event) => match event {
+                StoreEvent::Progress(read, total) => {
+                    // Still loading when the size is unknown.
+                    let share = total
+                        .filter(|&total| total > 0)
+                        .map_or(0.0, |total| read as f32 / total as f32);
+                    self.progress = Some(share);
+
+                    Command::none()
+                }
+                StoreEvent::Finished(result) => {
+                    self.progress = None;
+
+                    self.update(Message::StoreOpened(result))
+                }
+            },
+            Message::FileParse(event) => match event {
+                ParseEvent::Progress(share) => {
+                    self.progress = Some(share);
+
+                    Command::none()
+                }
+                ParseEvent::Finished(result) => {
+                    self.progress = None;
+
+                    self.update(Message::FileParsed(result))
+                }
+            },
+            Message::EncodingChanged(encoding) => {
+                self.encoding = encoding;
+
+                Command::none()
+            }
+            Message::FormatChanged(format) => {
+                self.format = format;
+
+                Command::none()
+            }
+            Message::StoreOpened(result) => match result {
+                Ok((store, stored)) => {
+                    let remember = self.keep_store(store);
+                    let parse = match stored {
+                        Stored::Text(contents, format) => {
+                            self.editor.set_content(&contents);
+                            self.editor.format = format;
+
+                            self.parse_loaded(contents)
+                        }
+                        Stored::Db(db) => {
+                            self.editor.set_content("");
+                            self.editor.format = TextFormat::default();
+
+                            Command::perform(
+                                async { Ok((Arc::default(), db)) },
+                                Message::FileParsed,
+                            )
+                        }
+                    };
+                    self.editor.mark_saved(Some(self.editor.revision()));
+
+                    Command::batch([parse, remember])
+                }
+                Err(Error::DialogClosed) => {
+                    self.reloaded = None;
+                    self.logs.error(Error::DialogClosed);
+
+                    Command::none()
                 }
                 Err(error) => {
+                    self.reloaded = None;
+                    self.load_failure = Some(error.clone());
                     self.logs.error(error);
-                    self.active_tab = Tabs::Logs;
 
                     Command::none()
                 }
             },
             Message::FileParsed(result) => {
+                let mut command = Command::none();
                 match result {
-                    Ok(db) => {
-                        self.db = db.clone();
-                        self.explorer.db = db.clone();
-                        self.questions.db = db;
+                    Ok((cache, db)) => {
+                        self.parse_cache = cache;
+                        self.set_db(db, Change::Replaced);
+                        self.log_merged_spellings();
 
-                        self.questions.refresh_categories();
+                        let strategy = self.settings.match_mode.strategy();
+                        for warning in
+                            cases::validate(&self.parse_cache, self.db.current(), strategy)
+                        {
+                            self.logs.warning(LogSource::Engine, &warning);
+                        }
+                        for finding in self.problems.iter() {
+                            self.logs.warning(LogSource::Parser, &finding.warning);
+                        }
 
-                        self.active_tab = Tabs::Questions;
+                        let text = self.editor.text();
+                        self.progress_key = (!text.trim().is_empty())
+                            .then(|| format!("{:x}", Sha256::digest(text)));
+                        if let Some((target, answers)) = self.reloaded.take() {
+                            self.restore_answers(target, answers);
+                        } else {
+                            self.active_tab = Tabs::Questions;
+                            if let Some(answers) = self.saved_progress() {
+                                command = Command::perform(
+                                    confirm_restore(answers.len()),
+                                    Message::RestoreConfirmed,
+                                );
+                            }
+                        }
+
+                        self.load_failure = None;
                     }
                     Err(error) => {
-                        self.active_tab = Tabs::Logs;
+                        self.reloaded = None;
+                        self.load_failure = Some(error.clone());
+                        // The editor shows the text that failed, not the base.
+                        self.editor.mark_applied(None);
+
+                        self.logs.error(error);
+                    }
+                }
+                command
+            }
+            Message::LoadFailureEdit => {
+                if let Some(error) = self.load_failure.take() {
+                    self.editor.set_diagnostics(Err(error));
+                }
+                if self.tab_shown(&Tabs::Editor) {
+                    self.active_tab = Tabs::Editor;
+                }
+
+                Command::none()
+            }
+            Message::LoadFailurePartial => {
+                let Some(Error::ParseErrors(errors, db)) = self.load_failure.take() else {
+                    return Command::none();
+                };
+                self.logs.debug(
+                    LogSource::Parser,
+                    &format!("Opened the base without {} malformed blocks", errors.len()),
+                );
+                // The editor keeps the whole text, which the base is not
+                // parsed from.
+                self.parse_cache = Arc::default();
+                self.set_db(db, Change::Replaced);
+                self.active_tab = Tabs::Questions;
+
+                Command::none()
+            }
+            Message::LoadFailureReopen => {
+                self.load_failure = None;
+
+                self.update(Message::OpenFile)
+            }
+            Message::ApplyEditor => {
+                let revision = self.editor.revision();
+
+                Command::perform(
+                    reparse_file(self.parse_cache.clone(), Arc::new(self.editor.text())),
+                    move |result| Message::EditorApplied(revision, result),
+                )
+            }
+            Message::EditorApplied(revision, result) => match result {
+                Ok(parsed) => {
+                    self.toasts.push("База применена");
+                    self.editor.mark_applied(Some(revision));
+
+                    self.update(Message::FileParsed(Ok(parsed)))
+                }
+                // The text is already in the editor, which shows the error.
+                Err(error) => {
+                    self.editor.set_diagnostics(Err(error.clone()));
+                    self.logs.error(error);
+                    self.active_tab = Tabs::Logs;
+
+                    Command::none()
+                }
+            },
+            Message::ToastTick(now) => {
+                self.toasts.expire(now);
+
+                Command::none()
+            }
+            Message::Renumber => Command::perform(
+                reparse_file(self.parse_cache.clone(), Arc::new(self.editor.text())),
+                Message::Renumbered,
+            ),
+            Message::Renumbered(result) => match result {
+                Ok((_, db)) => {
+                    let contents = Arc::new(format_db(&db));
+                    self.editor.set_content(&contents);
+                    self.logs.debug(LogSource::Parser, "Entries renumbered");
+
+                    Command::perform(parse_file(contents), Message::FileParsed)
+                }
+                Err(error) => {
+                    self.editor.set_diagnostics(Err(error.clone()));
+                    self.logs.error(error);
+
+                    Command::none()
+                }
+            },
+            // Every edit starts its own delay, so only the last one, after
+            // which the user stopped typing, is checked.
+            Message::EditorCheckDue(revision) => {
+                if revision != self.editor.revision() {
+                    return Command::none();
+                }
+
+                Command::perform(
+                    check_file(self.parse_cache.clone(), Arc::new(self.editor.text())),
+                    move |result| Message::EditorChecked(revision, result),
+                )
+            }
+            // A check of text edited since would show stale errors.
+            Message::EditorChecked(revision, result) => {
+                if revision == self.editor.revision() {
+                    self.editor.set_diagnostics(result);
+                }
+
+                Command::none()
+            }
+            Message::OpenSqlite => {
+                let dir = self.settings.preferences.last_dir.clone();
+
+                iced::command::channel(16, move |sender| open_sqlite(dir, sender))
+                    .map(Message::StoreLoad)
+            }
+            Message::SaveSqlite => {
+                if !self.can_edit() {
+                    return Command::none();
+                }
+                Command::perform(save_sqlite(self.snapshot()), Message::SqliteSaved)
+            }
+            Message::SqliteSaved(result) => {
+                match result {
+                    Ok(path) => {
+                        self.logs.debug(
+                            LogSource::Io,
+                            &format!("Saved knowledge base to {}", path.display()),
+                        );
+                        self.toasts.push("Файл сохранён");
+                    }
+                    Err(error) => self.logs.error(error),
+                }
 
+                Command::none()
+            }
+            Message::ImportXlsx => Command::perform(open_xlsx(), Message::XlsxOpened),
+            Message::XlsxOpened(result) => {
+                match result {
+                    Ok((path, sheet)) => self.xlsx_import = Some(XlsxImport::new(path, sheet)),
+                    Err(error) => {
                         self.logs.error(error);
+                        self.active_tab = Tabs::Logs;
                     }
                 }
+
+                Command::none()
+            }
+            Message::XlsxCategoryChanged(column, category) => {
+                if let Some(import) = self.xlsx_import.as_mut() {
+                    import.set_category(column, category);
+                }
+
+                Command::none()
+            }
+            Message::XlsxRoleChanged(column, role) => {
+                if let Some(import) = self.xlsx_import.as_mut() {
+                    import.set_role(column, role);
+                }
+
+                Command::none()
+            }
+            Message::XlsxConfirm => match self.xlsx_import.take() {
+                Some(import) => {
+                    let mut db = self.db.current().as_ref().clone();
+                    import.import_into(&mut db);
+
+                    self.editor.set_content(&format_db(&db));
+                    self.logs.debug(
+                        LogSource::Io,
+                        &format!(
+                            "Imported {} entries from {}",
+                            db.entries.len() - self.db.current().entries.len(),
+                            import.path.display()
+                        ),
+                    );
+
+                    Command::perform(
+                        async { Ok((Arc::default(), Arc::new(db))) },
+                        Message::FileParsed,
+                    )
+                }
+                None => Command::none(),
+            },
+            Message::XlsxCancel => {
+                self.xlsx_import = None;
+
+                Command::none()
+            }
+            Message::PasteFromClipboard => iced::clipboard::read(Message::ClipboardRead),
+            Message::ClipboardRead(contents) => match contents.filter(|x| !x.trim().is_empty()) {
+                Some(contents) => {
+                    open_store(Arc::new(MemoryStore::new(Source::Clipboard, contents)))
+                }
+                None => {
+                    self.logs.debug(LogSource::Io, "Clipboard is empty");
+                    self.active_tab = Tabs::Logs;
+
+                    Command::none()
+                }
+            },
+            Message::OpenDemo => {
+                // The tour ends in the editor, which viewers do not have.
+                self.tutorial = (!self.settings.is_viewer()).then(Tutorial::default);
+
+                open_store(Arc::new(MemoryStore::new(
+                    Source::Demo,
+                    tutorial::DEMO.to_string(),
+                )))
+            }
+            Message::OpenEmbedded(base) => match base.source(self.encoding) {
+                Ok(contents) => open_store(Arc::new(MemoryStore::new(
+                    Source::Embedded(base.name),
+                    contents,
+                ))),
+                Err(error) => {
+                    self.logs.error(error.into());
+                    self.active_tab = Tabs::Logs;
+
+                    Command::none()
+                }
+            },
+            Message::TutorialMoved(step) => {
+                if let (Some(tutorial), Some(tab)) = (self.tutorial.as_mut(), Tutorial::tab(step)) {
+                    tutorial.go_to(step);
+                    self.active_tab = tab;
+                }
+
+                Command::none()
+            }
+            Message::TutorialClosed => {
+                self.tutorial = None;
+
+                Command::none()
+            }
+            Message::CopyDb => {
+                self.logs
+                    .debug(LogSource::Io, "Knowledge base copied to clipboard");
+                self.toasts.push("База скопирована");
+
+                iced::clipboard::write(format_db(self.db.current()))
+            }
+            Message::CopyAnswer => {
+                let Some(result) = self.questions.result.as_ref() else {
+                    return Command::none();
+                };
+                self.toasts.push("Ответ скопирован");
+
+                iced::clipboard::write(result.values().join(", "))
+            }
+            Message::CopyResult => {
+                if self.questions.result.is_none() {
+                    return Command::none();
+                }
+                self.toasts.push("Результат скопирован");
+
+                iced::clipboard::write(audit::summary(
+                    &self
+                        .questions
+                        .consultation(self.db.current(), self.source_name()),
+                ))
+            }
+            Message::CopyResultJson => {
+                if self.questions.result.is_none() {
+                    return Command::none();
+                }
+                self.toasts.push("Результат скопирован");
+
+                iced::clipboard::write(audit::summary_json(
+                    &self
+                        .questions
+                        .consultation(self.db.current(), self.source_name()),
+                ))
+            }
+            Message::LinkOpened(target) => {
+                if let Err(err) = open::that_detached(&target) {
+                    self.logs.error(Error::IO(err.kind()));
+                }
+
+                Command::none()
+            }
+            Message::UrlChanged(url) => {
+                self.url = url;
+
                 Command::none()
             }
+            Message::OpenUrl => {
+                if self.progress.is_some() || self.url.trim().is_empty() {
+                    return Command::none();
+                }
+                self.progress = Some(0.0);
+
+                open_store(Arc::new(UrlStore {
+                    url: self.url.trim().to_string(),
+                    encoding: self.encoding,
+                }))
+            }
+            Message::TabChanged(new_tab) if !self.tab_shown(&new_tab) => Command::none(),
             Message::TabChanged(new_tab) => {
+                let offset = self.scroll_offsets.get(&new_tab).copied();
+                let id = new_tab.scroll_id();
                 self.active_tab = new_tab;
 
+                match offset {
+                    Some(offset) => scrollable::scroll_to(id, offset),
+                    None => Command::none(),
+                }
+            }
+            Message::Scrolled(tab, offset) => {
+                self.scroll_offsets.insert(tab, offset);
+
                 Command::none()
             }
+            Message::InsertExample(_) if !self.tab_shown(&Tabs::Editor) => Command::none(),
+            Message::InsertExample(example) => {
+                self.editor.insert(example);
+                self.active_tab = Tabs::Editor;
+
+                self.schedule_check()
+            }
             Message::EditorActionPerformed(action) => {
+                let revision = self.editor.revision();
                 self.editor.perform_action(action);
+                if self.editor.revision() == revision {
+                    return Command::none();
+                }
 
-                Command::none()
+                self.schedule_check()
             }
             Message::ClearLogs => {
                 self.logs.clear_cache();
@@ -146,159 +876,1950 @@ impl Application for MainWindow {
                 Command::none()
             }
             Message::SelectedAnswer(category, answer) => {
-                let (_, answ) = self.questions.answers.get_mut(category.as_ref()).unwrap();
+                self.questions
+                    .set_answer(self.db.current(), &category, &answer);
 
-                *answ = Some(answer.to_string());
+                self.remember_progress()
+            }
+            Message::DegreeChanged(category, degree) => {
+                self.questions.degrees.insert(category.to_string(), degree);
 
-                Command::none()
+                self.remember_progress()
+            }
+            // Only the last change, after which the answers settled, is
+            // written to the preferences.
+            Message::ProgressDue(revision) => {
+                if revision != self.answers_revision {
+                    return Command::none();
+                }
+
+                self.save_progress()
             }
             Message::FindAnswer => {
                 self.questions.is_searching = true;
 
+                let answers = self
+                    .questions
+                    .answers
+                    .iter()
+                    .filter(|(_, (_, y))| y.is_some())
+                    .map(|(x, (_, y))| -> (String, String) { (x.to_string(), y.clone().unwrap()) })
+                    .collect::<Vec<_>>();
+                let budget = Budget::new(self.settings.query_timeout.duration());
+                self.query_budget = Some(budget.clone());
+                self.questions.audit.push(AuditKind::Query {
+                    target: self.questions.selected_category.clone(),
+                    answers: answers.clone(),
+                });
+                let answers = answers
+                    .into_iter()
+                    .map(|(cat, val)| {
+                        let degree = self.questions.degrees.get(&cat).copied();
+                        (cat, val, degree.unwrap_or(1.0))
+                    })
+                    .collect::<Vec<_>>();
+                self.questions.pending = Some(HistoryEntry {
+                    time: Local::now(),
+                    target: self.questions.selected_category.clone(),
+                    answers: answers.clone(),
+                    values: Vec::new(),
+                    entries: Vec::new(),
+                });
+
+                let (generation, db) = self.db.snapshot();
+                let request = Arc::new(QueryRequest {
+                    target: self.questions.selected_category.clone(),
+                    answers,
+                    find_all: self.questions.find_all,
+                    threshold: self.settings.match_threshold.share(),
+                    mode: self.settings.match_mode,
+                });
                 Command::perform(
-                    query_db(
-                        self.db.clone(),
-                        self.questions.selected_category.clone(),
-                        self.questions
-                            .answers
-                            .iter()
-                            .filter(|(_, (_, y))| y.is_some())
-                            .map(|(x, (_, y))| -> (String, String) {
-                                (x.to_string(), y.clone().unwrap())
-                            })
-                            .collect::<Vec<_>>(),
-                    ),
-                    Message::FoundAnswer,
+                    query_db(db, self.pool.clone(), QueryRequest::clone(&request), budget),
+                    move |result| Message::FoundAnswer(generation, request.clone(), result),
                 )
             }
-            Message::FoundAnswer(res) => {
+            Message::CancelQuery => {
+                if let Some(budget) = self.query_budget.as_ref() {
+                    budget.cancel();
+                }
+
+                Command::none()
+            }
+            Message::FoundAnswer(generation, _, _) if !self.db.is_current(generation) => {
+                self.query_budget = None;
+                self.questions.pending = None;
+                self.questions.is_searching = false;
+                self.logs.debug(
+                    LogSource::Engine,
+                    "Knowledge base changed during the query, result discarded",
+                );
+                self.toasts.push("Данные изменились — повторите поиск");
+
+                Command::none()
+            }
+            Message::FoundAnswer(_, request, res) => {
+                self.query_budget = None;
+                self.recommendations_done.clear();
+                let mut command = Command::none();
+                if let Some(mut entry) = self.questions.pending.take() {
+                    if let Ok(result) = res.as_ref() {
+                        entry.conclude(self.db.current(), result);
+                    }
+                    // Partial, empty and failed queries do not count as
+                    // consultations.
+                    let finished = res
+                        .as_ref()
+                        .is_ok_and(|x| x.interrupted.is_none() && !x.is_empty());
+                    if let Some(key) = self.usage_key().filter(|_| finished) {
+                        let usage = self.settings.preferences.usage_mut(&key);
+                        usage.record_answers(entry.answers.iter().map(|(cat, _, _)| cat));
+                        usage.record_conclusions(entry.entries.iter());
+                        command = self.save_preferences();
+                    }
+                    self.questions.history.push(entry);
+                }
+
                 match res {
-                    Ok(result) => self.questions.result = result,
+                    Ok(result) => {
+                        match result.interrupted {
+                            Some(Interrupt::Cancelled) => {
+                                self.logs.debug(LogSource::Engine, "Query was cancelled")
+                            }
+                            Some(Interrupt::TimedOut) => self.logs.debug(
+                                LogSource::Engine,
+                                &format!("Query timed out after {}", self.settings.query_timeout),
+                            ),
+                            None if result.is_empty() => {
+                                self.logs.error(Error::Query(Arc::new(format!(
+                                    "Query {:?} didn't find anything, target category {:?}",
+                                    request.answers, request.target
+                                ))))
+                            }
+                            None => {}
+                        }
+                        let values = result.values();
+                        self.questions.audit.push(AuditKind::Result(values));
+                        let case = request.case(&result);
+                        self.questions.set_result(result, case);
+                        self.questions.score_guess();
+                    }
                     Err(err) => {
-                        self.questions.result = Arc::new(String::from("Not found."));
+                        let reason = match &err {
+                            Error::Query(reason) => reason.to_string(),
+                            err => format!("{:?}", err),
+                        };
+                        self.questions.audit.push(AuditKind::Error(reason));
+                        self.questions.set_result(QueryResult::default(), None);
 
                         self.logs.error(err);
                     }
                 };
                 self.questions.is_searching = false;
 
-                Command::none()
+                command
             }
-            Message::SelectedCategory(category) => {
-                self.questions.selected_category = Some(category.to_string());
+            Message::FindAllToggled(find_all) => {
+                self.questions.find_all = find_all;
 
                 Command::none()
             }
-        }
-    }
+            Message::RecordCase => {
+                let Some(path) = self.source_path() else {
+                    return Command::none();
+                };
+                let Some(case) = self.questions.test_case() else {
+                    if self.questions.result.is_some() {
+                        self.logs.error(Error::Cases(Arc::new(
+                            "the query was interrupted, its result is not recorded".to_string(),
+                        )));
+                    }
+                    return Command::none();
+                };
 
-    fn view(&self) -> Element<Message> {
-        let tabs = self.tabs();
+                Command::perform(record_case(cases_path(path), case), Message::CaseRecorded)
+            }
+            Message::CaseRecorded(result) => {
+                match result {
+                    Ok(path) => {
+                        self.logs.debug(
+                            LogSource::Io,
+                            &format!("Recorded test case to {}", path.display()),
+                        );
+                        self.toasts.push("Кейс записан");
+                    }
+                    Err(error) => self.logs.error(error),
+                }
 
-        let file_indicator = text(
-            self.file
-                .as_ref()
-                .and_then(|path| path.file_name())
-                .and_then(|name| name.to_str())
-                .unwrap_or("Файл не выбран"),
-        );
+                Command::none()
+            }
+            Message::RunCases => {
+                let Some(path) = self.source_path() else {
+                    return Command::none();
+                };
 
-        let file_manager = container(
-            column![
-                button("Открыть файл...")
-                    .on_press(Message::OpenFile)
-                    .width(Length::Fill)
-                    .style(theme::Button::Primary),
-                file_indicator,
-            ]
-            .spacing(8)
-            .padding(8),
-        )
-        .style(theme::Container::Box);
+                Command::perform(
+                    run_cases(
+                        cases_path(path),
+                        self.db.current().clone(),
+                        self.settings.match_mode.strategy(),
+                    ),
+                    Message::CasesRun,
+                )
+            }
+            Message::CasesRun(result) => {
+                match result {
+                    Ok(outcomes) => {
+                        outcomes.iter().for_each(|outcome| self.logs.case(outcome));
+                        let passed = outcomes.iter().filter(|x| x.passed()).count();
+                        self.logs.debug(
+                            LogSource::Engine,
+                            &format!("{} of {} test cases passed", passed, outcomes.len()),
+                        );
+                    }
+                    Err(error) => self.logs.error(error),
+                }
+                self.active_tab = Tabs::Logs;
 
-        let left_pane = container(
-            column![tabs, vertical_space(), file_manager]
-                .width(Length::Fixed(240.0))
-                .spacing(20),
-        );
+                Command::none()
+            }
+            Message::EditQuestion(category) => {
+                let wording = self
+                    .db
+                    .current()
+                    .questions
+                    .get(category.as_str())
+                    .map(|question| question.text.clone())
+                    .unwrap_or_default();
+                self.questions.editing = Some((category.to_string(), wording));
 
-        let right_pane = match self.active_tab {
-            Tabs::Questions => self.questions.view(),
-            Tabs::Explorer => self.explorer.view(),
-            Tabs::Logs => self.logs.view(),
-            Tabs::Editor => self.editor.view(),
-        };
+                Command::none()
+            }
+            Message::QuestionTextChanged(wording) => {
+                if let Some((_, editing)) = self.questions.editing.as_mut() {
+                    *editing = wording;
+                }
 
-        container(row![left_pane, container(right_pane).padding(10)])
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .into()
-    }
-}
+                Command::none()
+            }
+            Message::CancelQuestionEdit => {
+                self.questions.editing = None;
 
-impl MainWindow {
-    fn tabs(&self) -> Element<Message> {
-        macro_rules! tab {
-            ($name: expr, $tab: expr) => {
-                button($name)
-                    .on_press_maybe((self.active_tab != $tab).then_some(Message::TabChanged($tab)))
+                Command::none()
+            }
+            Message::SaveQuestion => {
+                let Some((category, question)) = self.questions.edited_question(self.db.current())
+                else {
+                    return Command::none();
+                };
+                self.questions.editing = None;
+
+                let edited = self.edit_db(
+                    Change::Reworded,
+                    |db| db.set_question(&category, question.clone()),
+                    |cache| set_question(cache, &category, &question),
+                );
+                match edited {
+                    Ok(_) => self.logs.debug(
+                        LogSource::Parser,
+                        &format!("Question about `{}` reworded", category),
+                    ),
+                    Err(error) => {
+                        self.logs.error(error);
+                        self.active_tab = Tabs::Logs;
+                    }
+                }
+
+                Command::none()
+            }
+            Message::RuleValueChanged(value) => {
+                self.questions.rule_value = value;
+
+                Command::none()
+            }
+            Message::CreateRule => {
+                let Some(entry) = self.questions.rule(self.db.current()) else {
+                    return Command::none();
+                };
+                self.questions.rule_value.clear();
+
+                let edited = self.edit_db(
+                    Change::Restructured,
+                    |db| db.add_entry(entry.clone()),
+                    |cache| Some(append_entry(cache, &entry)),
+                );
+                match edited {
+                    Ok(_) => self
+                        .logs
+                        .debug(LogSource::Parser, "Rule created from the answers"),
+                    Err(error) => {
+                        self.logs.error(error);
+                        self.active_tab = Tabs::Logs;
+                    }
+                }
+
+                Command::none()
+            }
+            Message::EntryDisabled(id, disabled) => {
+                let edited = self.edit_db(
+                    Change::Restructured,
+                    |db| db.set_disabled(id, disabled),
+                    |cache| set_entry_disabled(cache, id, disabled),
+                );
+                if let Err(error) = edited {
+                    self.logs.error(error);
+                    self.active_tab = Tabs::Logs;
+                }
+
+                Command::none()
+            }
+            Message::MergeCategorySelected(category) => {
+                self.explorer.merge = Merge {
+                    category: Some(category),
+                    ..Merge::default()
+                };
+
+                Command::none()
+            }
+            Message::MergeFromSelected(value) => {
+                self.explorer.merge.from = Some(value);
+
+                Command::none()
+            }
+            Message::MergeIntoSelected(value) => {
+                self.explorer.merge.into = Some(value);
+
+                Command::none()
+            }
+            Message::MergeValues => {
+                let merge = &mut self.explorer.merge;
+                let (Some(category), Some(from), Some(into)) =
+                    (merge.category.clone(), merge.from.take(), merge.into.take())
+                else {
+                    return Command::none();
+                };
+
+                let mut answers = self.questions.answer_set(self.db.current());
+                let edited = self.edit_db(
+                    Change::Reworded,
+                    |db| db.merge_values(&category, &from, &into),
+                    |cache| Some(merge_values(cache, &category, &from, &into)),
+                );
+                let count = match edited {
+                    Ok(count) => count,
+                    Err(error) => {
+                        self.logs.error(error);
+                        self.active_tab = Tabs::Logs;
+                        return Command::none();
+                    }
+                };
+
+                if let Some(answer) = answers.get_mut(&category).filter(|x| **x == from) {
+                    answer.clone_from(&into);
+                }
+                let target = self.questions.selected_category.clone();
+                self.questions.select_category(self.db.current(), target);
+                self.questions.apply_answer_set(self.db.current(), answers);
+                for entry in self.questions.history.iter_mut() {
+                    entry.merge_value(&category, &from, &into);
+                }
+
+                self.logs.debug(
+                    LogSource::Parser,
+                    &format!(
+                        "Merged value `{}` of `{}` into `{}` in {} entries",
+                        from, category, into, count
+                    ),
+                );
+                self.toasts.push("Значения объединены");
+
+                // Answers remembered for the base and the cases recorded
+                // with it are rewritten too.
+                let saved = self
+                    .progress_key
+                    .as_ref()
+                    .and_then(|key| self.settings.preferences.progress.get_mut(key))
+                    .and_then(|answers| answers.get_mut(&category))
+                    .filter(|answer| **answer == from);
+                let progress = match saved {
+                    Some(answer) => {
+                        answer.clone_from(&into);
+                        self.save_preferences()
+                    }
+                    None => Command::none(),
+                };
+                match self.source_path() {
+                    Some(path) => Command::batch([
+                        progress,
+                        Command::perform(
+                            merge_cases(cases_path(path), category, from, into),
+                            Message::CasesMerged,
+                        ),
+                    ]),
+                    None => progress,
+                }
+            }
+            Message::ExampleRequested(id) => {
+                let Some(entry) = self.db.current().entries.get(id) else {
+                    return Command::none();
+                };
+
+                let (category, value) = (entry.category.clone(), entry.value.clone());
+                let budget = Budget::new(self.settings.query_timeout.duration());
+                if let Some(example) = self.explorer.example.replace(Example {
+                    entry: id,
+                    category: category.clone(),
+                    value: value.clone(),
+                    answers: ExampleAnswers::Searching,
+                    budget: budget.clone(),
+                }) {
+                    example.budget.cancel();
+                }
+
+                let (generation, db) = self.db.snapshot();
+                Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            let answers = db.example_answers(&category, &value, &budget);
+                            match (answers, budget.stopped()) {
+                                (Some(answers), _) => ExampleAnswers::Found(answers),
+                                (None, Some(interrupt)) => ExampleAnswers::Interrupted(interrupt),
+                                (None, None) => ExampleAnswers::Unreachable,
+                            }
+                        })
+                        .await
+                        .unwrap_or(ExampleAnswers::Unreachable)
+                    },
+                    move |answers| Message::ExampleFound(generation, id, answers),
+                )
+            }
+            Message::ExampleFound(generation, id, answers) => {
+                // Answers for an example closed or replaced since are dropped,
+                // and an example found for an earlier base is closed.
+                let Some(example) = self.explorer.example.as_mut().filter(|example| {
+                    example.entry == id && matches!(example.answers, ExampleAnswers::Searching)
+                }) else {
+                    return Command::none();
+                };
+                if self.db.is_current(generation) {
+                    example.answers = answers;
+                } else {
+                    self.explorer.example = None;
+                }
+
+                Command::none()
+            }
+            Message::ExampleClosed => {
+                if let Some(example) = self.explorer.example.take() {
+                    example.budget.cancel();
+                }
+
+                Command::none()
+            }
+            Message::ApplyExample => {
+                let Some(Example {
+                    category,
+                    answers: ExampleAnswers::Found(answers),
+                    ..
+                }) = self.explorer.example.as_ref()
+                else {
+                    return Command::none();
+                };
+
+                self.questions
+                    .select_category(self.db.current(), Some(category.clone()));
+                self.questions
+                    .apply_answer_set(self.db.current(), answers.clone());
+                self.active_tab = Tabs::Questions;
+
+                Command::none()
+            }
+            Message::UsageReset => {
+                let Some(key) = self.usage_key() else {
+                    return Command::none();
+                };
+                if self.settings.preferences.usage.shift_remove(&key).is_none() {
+                    return Command::none();
+                }
+
+                self.save_preferences()
+            }
+            Message::RemovalCategorySelected(category) => {
+                self.explorer.removal = Some(category);
+
+                Command::none()
+            }
+            Message::DependencyTargetSelected(category) => {
+                self.dependencies.target = Some(category);
+
+                Command::none()
+            }
+            Message::RemoveCategory => {
+                let Some(category) = self.explorer.removal.take() else {
+                    return Command::none();
+                };
+
+                let edited = self.edit_db(
+                    Change::Restructured,
+                    |db| db.remove_category(&category),
+                    |cache| remove_category(cache, &category),
+                );
+                match edited {
+                    Ok(impact) => {
+                        self.logs.debug(
+                            LogSource::Parser,
+                            &format!(
+                                "Removed category `{}`: {} entries removed, {} changed",
+                                category,
+                                impact.removed.len(),
+                                impact.trimmed.len()
+                            ),
+                        );
+                        self.toasts.push("Категория удалена");
+                    }
+                    Err(error) => {
+                        self.logs.error(error);
+                        self.active_tab = Tabs::Logs;
+                    }
+                }
+
+                Command::none()
+            }
+            Message::CasesMerged(result) => {
+                match result {
+                    Ok(0) => {}
+                    Ok(count) => self.logs.debug(
+                        LogSource::Io,
+                        &format!("Merged values in {} recorded test cases", count),
+                    ),
+                    Err(error) => self.logs.error(error),
+                }
+
+                Command::none()
+            }
+            Message::ConclusionToggled(index) => {
+                if !self.questions.expanded.remove(&index) {
+                    self.questions.expanded.insert(index);
+                }
+
+                Command::none()
+            }
+            Message::FocusMoved(backwards) => {
+                if backwards {
+                    focus_previous()
+                } else {
+                    focus_next()
+                }
+            }
+            Message::UiScaleChanged(scale) => {
+                self.settings.preferences.scale = scale;
+
+                self.save_preferences()
+            }
+            Message::TextSizeChanged(size) => {
+                self.settings.preferences.text_size = size;
+
+                self.save_preferences()
+            }
+            Message::FontChanged(font) => {
+                self.settings.preferences.font = font;
+
+                self.save_preferences()
+            }
+            Message::LanguageChanged(language) => {
+                self.settings.preferences.language = language;
+                self.set_db(self.db.current().clone(), Change::Reworded);
+
+                self.save_preferences()
+            }
+            Message::LogTimesChanged(times) => {
+                self.settings.preferences.log_times = times;
+
+                self.save_preferences()
+            }
+            Message::LogsTick => Command::none(),
+            Message::LogSourceSelected(source) => {
+                self.logs.select_source(source);
+
+                Command::none()
+            }
+            Message::LogGroupToggled(start) => {
+                self.logs.toggle_group(start);
+
+                Command::none()
+            }
+            Message::LargeControlsToggled(large) => {
+                self.settings.preferences.large_controls = large;
+
+                self.save_preferences()
+            }
+            Message::PassphraseChanged(passphrase) => {
+                self.passphrase = passphrase;
+
+                Command::none()
+            }
+            Message::Unlock => {
+                let passphrase = std::mem::take(&mut self.passphrase);
+                if self.db.current().unlocks(&passphrase) {
+                    self.unlocked = self.db.current().lock.clone();
+                    self.toasts.push("Редактор разблокирован");
+                } else {
+                    self.toasts.push("Неверный пароль");
+                }
+
+                Command::none()
+            }
+            Message::Lock => {
+                self.unlocked = None;
+                self.questions.editing = None;
+
+                Command::none()
+            }
+            Message::ViewerToggled(viewer) => {
+                self.settings.preferences.viewer = viewer;
+                if viewer {
+                    self.questions.editing = None;
+                }
+                if !self.tab_shown(&self.active_tab) {
+                    self.active_tab = Tabs::Questions;
+                }
+
+                self.save_preferences()
+            }
+            Message::PreferencesSaved(result) => {
+                if let Err(error) = result {
+                    self.logs.error(error);
+                }
+
+                Command::none()
+            }
+            Message::WindowResized(width) => {
+                self.window_width = width as f32;
+
+                Command::none()
+            }
+            Message::HistoryToggled => {
+                self.questions.show_history = !self.questions.show_history;
+
+                Command::none()
+            }
+            Message::RepeatQuery(index) => {
+                self.questions.restore(self.db.current(), index);
+
+                Command::none()
+            }
+            Message::ExportReport => {
+                let report = audit::report(
+                    &self
+                        .questions
+                        .consultation(self.db.current(), self.source_name()),
+                );
+
+                Command::perform(save_file(REPORT_TARGET, report), Message::Exported)
+            }
+            Message::PrintReport => {
+                let report = audit::report(
+                    &self
+                        .questions
+                        .consultation(self.db.current(), self.source_name()),
+                );
+
+                Command::perform(print::print(report), |result| {
+                    Message::Printed(result.map_err(|err| Error::IO(err.kind())))
+                })
+            }
+            Message::RecommendationToggled(text, done) => {
+                if done {
+                    self.recommendations_done.insert(text);
+                } else {
+                    self.recommendations_done.remove(&text);
+                }
+
+                Command::none()
+            }
+            Message::ExportRecommendations => {
+                let checklist = audit::checklist(
+                    &self
+                        .questions
+                        .consultation(self.db.current(), self.source_name()),
+                    &self.recommendations_done,
+                );
+
+                Command::perform(save_file(CHECKLIST_TARGET, checklist), Message::Exported)
+            }
+            Message::Printed(result) => {
+                match result {
+                    Ok(path) => {
+                        self.logs.debug(
+                            LogSource::Io,
+                            &format!("Report for printing saved to {}", path.display()),
+                        );
+                        self.toasts.push("Отчёт открыт для печати");
+                    }
+                    Err(error) => self.logs.error(error),
+                }
+
+                Command::none()
+            }
+            Message::ExportCsv => {
+                let category = self
+                    .questions
+                    .selected_category
+                    .as_ref()
+                    .or(self.db.current().target.as_ref())
+                    .or(self.db.current().conclusion_categories().first().copied());
+                let Some(category) = category else {
+                    return Command::none();
+                };
+                let contents = kb_format::format_csv(self.db.current(), category);
+
+                Command::perform(save_file(CSV_TARGET, contents), Message::Exported)
+            }
+            Message::ExportLogs => Command::perform(
+                save_file(LOGS_TARGET, self.logs.export()),
+                Message::Exported,
+            ),
+            Message::Exported(result) => {
+                match result {
+                    Ok(path) => {
+                        self.logs
+                            .debug(LogSource::Io, &format!("Saved {}", path.display()));
+                        self.toasts.push("Файл сохранён");
+                    }
+                    Err(error) => self.logs.error(error),
+                }
+
+                Command::none()
+            }
+            Message::ExportAnswers => {
+                let answers = self.questions.answer_set(self.db.current());
+                let contents = serde_json::to_string_pretty(&answers).unwrap_or_default();
+
+                Command::perform(save_file(ANSWERS_TARGET, contents), Message::Exported)
+            }
+            Message::ImportAnswers => Command::perform(
+                open_answers(self.settings.preferences.last_dir.clone()),
+                Message::AnswersImported,
+            ),
+            Message::AnswersImported(result) => {
+                match result {
+                    Ok(answers) => {
+                        let skipped = self.questions.apply_answer_set(self.db.current(), answers);
+                        if skipped.is_empty() {
+                            self.toasts.push("Ответы загружены");
+                        } else {
+                            self.logs.debug(
+                                LogSource::Ui,
+                                &format!(
+                                    "Answers skipped as unknown to the base: {}",
+                                    skipped.join(", ")
+                                ),
+                            );
+                            self.toasts
+                                .push(&format!("Ответы загружены, пропущено: {}", skipped.len()));
+                        }
+                    }
+                    Err(error) => {
+                        self.logs.error(error);
+                        return Command::none();
+                    }
+                }
+
+                self.remember_progress()
+            }
+            Message::SaveEditor => {
+                if !self.can_edit() {
+                    return Command::none();
+                }
+                let Some(store) = self.store.clone() else {
+                    return self.update(Message::SaveEditorAs);
+                };
+
+                let revision = self.editor.revision();
+                Command::perform(store::save(store, self.snapshot()), move |result| {
+                    Message::EditorSaved(revision, result)
+                })
+            }
+            Message::SaveEditorAs => {
+                let revision = self.editor.revision();
+                Command::perform(save_kb(self.snapshot()), move |result| {
+                    Message::EditorSaved(revision, result)
+                })
+            }
+            Message::TextFormatChanged(format) => {
+                self.editor.format = format;
+                self.logs.debug(
+                    LogSource::Io,
+                    &format!("Editor text will be saved as {}", format),
+                );
+
+                Command::none()
+            }
+            Message::EditorSaved(revision, result) => match result {
+                Ok(store) => {
+                    self.editor.mark_saved(Some(revision));
+                    let remember = self.keep_store(store);
+                    self.logs.debug(
+                        LogSource::Io,
+                        &format!("Saved knowledge base to {}", self.source_name()),
+                    );
+                    self.toasts.push("Файл сохранён");
+
+                    remember
+                }
+                // Bases downloaded or imported from other formats are saved
+                // as a new DSL file.
+                Err(Error::IO(io::ErrorKind::Unsupported)) => self.update(Message::SaveEditorAs),
+                Err(error) => {
+                    self.logs.error(error);
+
+                    Command::none()
+                }
+            },
+            Message::QueryThreadsChanged(threads) => {
+                self.settings.query_threads = threads;
+                self.pool = self.settings.build_pool();
+
+                Command::none()
+            }
+            Message::MatchModeChanged(mode) => {
+                self.settings.match_mode = mode;
+
+                Command::none()
+            }
+            Message::MatchThresholdChanged(threshold) => {
+                self.settings.match_threshold = threshold;
+
+                Command::none()
+            }
+            Message::CheckToggled(check, enabled) => {
+                self.settings.checks.retain(|x| *x != check);
+                if enabled {
+                    self.settings.checks.push(check);
+                }
+                self.relint();
+
+                Command::none()
+            }
+            Message::NormalizeNamesToggled(enabled) => {
+                self.settings.normalize_names = enabled;
+                if enabled {
+                    self.set_db(self.db.current().clone(), Change::Reworded);
+                    self.log_merged_spellings();
+                } else if !self.parse_cache.is_empty() {
+                    match reparse(&self.parse_cache, &self.editor.text()) {
+                        Ok((cache, db)) => {
+                            self.parse_cache = Arc::new(cache);
+                            self.set_db(Arc::new(db), Change::Reworded);
+                        }
+                        Err(err) => self.logs.error(Error::from(err)),
+                    }
+                }
+
+                Command::none()
+            }
+            Message::JumpToSource((line, column)) => {
+                self.editor.go_to(line, column);
+                if self.tab_shown(&Tabs::Editor) {
+                    self.active_tab = Tabs::Editor;
+                }
+
+                Command::none()
+            }
+            Message::QueryTimeoutChanged(timeout) => {
+                self.settings.query_timeout = timeout;
+
+                Command::none()
+            }
+            Message::FileSizeLimitChanged(limit) => {
+                self.settings.file_size_limit = limit;
+
+                Command::none()
+            }
+            Message::ReloadModeChanged(mode) => {
+                self.settings.reload_mode = mode;
+
+                Command::none()
+            }
+            Message::WatchTick => match self.watched_path() {
+                Some(path) => {
+                    Command::perform(modified_at(path.to_path_buf()), Message::FileChecked)
+                }
+                None => Command::none(),
+            },
+            Message::FileChecked(modified) => {
+                if modified.is_none() || modified == self.modified || self.progress.is_some() {
+                    return Command::none();
+                }
+                // Remembered before asking, so the change is reported once.
+                self.modified = modified;
+
+                // Edits made here are never dropped without asking.
+                let discards = !self.editor.is_saved() || !self.editor.is_applied();
+                match (self.settings.reload_mode, self.watched_path()) {
+                    (ReloadMode::Auto, Some(_)) if !discards => self.reload(),
+                    (ReloadMode::Auto | ReloadMode::Ask, Some(path)) => Command::perform(
+                        confirm_reload(path.to_path_buf(), discards),
+                        Message::ReloadConfirmed,
+                    ),
+                    _ => Command::none(),
+                }
+            }
+            Message::ReloadConfirmed(confirmed) => {
+                if confirmed {
+                    self.reload()
+                } else {
+                    Command::none()
+                }
+            }
+            Message::RestoreConfirmed(confirmed) => {
+                let answers = self.saved_progress().filter(|_| confirmed).cloned();
+                if let Some(answers) = answers {
+                    let degrees = self
+                        .progress_key
+                        .as_ref()
+                        .and_then(|key| self.settings.preferences.degrees.get(key))
+                        .cloned()
+                        .unwrap_or_default();
+                    let skipped = self.questions.apply_answer_set(self.db.current(), answers);
+                    for (category, degree) in degrees {
+                        if !skipped.contains(&category) {
+                            self.questions.degrees.insert(category, degree);
+                        }
+                    }
+                    if !skipped.is_empty() {
+                        self.logs.debug(
+                            LogSource::Ui,
+                            &format!(
+                                "Saved answers dropped as no longer valid: {}",
+                                skipped.join(", ")
+                            ),
+                        );
+                    }
+                    self.toasts.push("Ответы восстановлены");
+                }
+
+                Command::none()
+            }
+            Message::TrainingToggled(training) => {
+                self.settings.training = training;
+                if training {
+                    self.questions.training = Some(Training::new(self.db.current()));
+                    self.questions.training_summary = None;
+                    self.questions.editing = None;
+                    if !self.tab_shown(&self.active_tab) {
+                        self.active_tab = Tabs::Questions;
+                    }
+                } else if let Some(training) = self.questions.training.take() {
+                    self.logs.debug(
+                        LogSource::Ui,
+                        &format!(
+                            "Training finished: {} of {} conclusions right",
+                            training.correct, training.attempts
+                        ),
+                    );
+                    self.questions.training_summary = Some(training.summary());
+                    self.active_tab = Tabs::Questions;
+                }
+
+                Command::none()
+            }
+            Message::TrainingGuessChanged(guess) => {
+                if let Some(training) = self.questions.training.as_mut() {
+                    training.guess = guess;
+                }
+
+                Command::none()
+            }
+            Message::SelectedCategory(category) => {
+                self.questions
+                    .select_category(self.db.current(), Some(category.to_string()));
+
+                Command::none()
+            }
+            Message::ClearCategory => {
+                self.questions.select_category(self.db.current(), None);
+
+                Command::none()
+            }
+            Message::ShowAllQuestions(show) => {
+                self.questions.show_all = show;
+
+                Command::none()
+            }
+            Message::ShortcutsToggled => {
+                self.show_shortcuts = !self.show_shortcuts;
+
+                Command::none()
+            }
+            Message::ShortcutsClosed => {
+                self.show_shortcuts = false;
+
+                Command::none()
+            }
+        }
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let events = event::listen_with(|event, status| match (event, status) {
+            (Event::Window(_, window::Event::Resized { width, .. }), _) => {
+                Some(Message::WindowResized(width))
+            }
+            (
+                Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }),
+                event::Status::Ignored,
+            ) => shortcuts::find(&key, modifiers),
+            _ => None,
+        });
+
+        let mut subscriptions = vec![events];
+        if !self.toasts.is_empty() {
+            subscriptions
+                .push(iced::time::every(Duration::from_millis(250)).map(Message::ToastTick));
+        }
+        if self.active_tab == Tabs::Logs
+            && self.settings.preferences.log_times == LogTimes::Relative
+            && !self.logs.is_empty()
+        {
+            subscriptions
+                .push(iced::time::every(Duration::from_secs(30)).map(|_| Message::LogsTick));
+        }
+        if self.settings.reload_mode != ReloadMode::Off && self.watched_path().is_some() {
+            subscriptions.push(iced::time::every(WATCH_INTERVAL).map(|_| Message::WatchTick));
+        }
+
+        Subscription::batch(subscriptions)
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let tabs = self.tabs();
+
+        let file_indicator = text(self.source_name());
+
+        let is_downloading = self.progress.is_some();
+        let url_input = a11y::input("url", "https://...", &self.url)
+            .on_input(Message::UrlChanged)
+            .on_submit(Message::OpenUrl);
+        let url_button = button("Открыть по URL")
+            .on_press_maybe(
+                (!is_downloading && !self.url.trim().is_empty()).then_some(Message::OpenUrl),
+            )
+            .width(Length::Fill)
+            .style(theme::Button::Secondary)
+            .named("open-url");
+
+        let file_manager = container(
+            column![
+                button("Открыть файл...")
+                    .on_press(Message::OpenFile)
+                    .width(Length::Fill)
+                    .style(theme::Button::Primary)
+                    .named("open-file"),
+                row![
+                    text("Кодировка:"),
+                    pick_list(
+                        &FileEncoding::ALL[..],
+                        Some(self.encoding),
+                        Message::EncodingChanged
+                    )
+                    .width(Length::Fill)
+                    .named("encoding"),
+                ]
+                .spacing(8)
+                .align_items(iced::Alignment::Center),
+                row![
+                    text("Формат:"),
+                    pick_list(
+                        &KbFormat::ALL[..],
+                        Some(self.format),
+                        Message::FormatChanged
+                    )
+                    .width(Length::Fill)
+                    .named("format"),
+                ]
+                .spacing(8)
+                .align_items(iced::Alignment::Center),
+                button("Открыть SQLite...")
+                    .on_press(Message::OpenSqlite)
                     .width(Length::Fill)
                     .style(theme::Button::Secondary)
+                    .named("open-sqlite"),
+                button("Сохранить в SQLite...")
+                    .on_press_maybe(
+                        (self.can_edit() && !self.db.current().entries.is_empty())
+                            .then_some(Message::SaveSqlite)
+                    )
+                    .width(Length::Fill)
+                    .style(theme::Button::Secondary)
+                    .named("save-sqlite"),
+                button("Импорт XLSX...")
+                    .on_press(Message::ImportXlsx)
+                    .width(Length::Fill)
+                    .style(theme::Button::Secondary)
+                    .named("import-xlsx"),
+                button("Вставить из буфера")
+                    .on_press(Message::PasteFromClipboard)
+                    .width(Length::Fill)
+                    .style(theme::Button::Secondary)
+                    .named("paste"),
+                button("Демо")
+                    .on_press(Message::OpenDemo)
+                    .width(Length::Fill)
+                    .style(theme::Button::Secondary)
+                    .named("open-demo"),
+            ]
+            .push_maybe((!embedded::BASES.is_empty()).then(|| {
+                pick_list(embedded::BASES, None::<EmbeddedBase>, Message::OpenEmbedded)
+                    .placeholder("Встроенные базы")
+                    .width(Length::Fill)
+                    .named("open-embedded")
+            }))
+            .push(
+                button("Экспорт CSV...")
+                    .on_press_maybe(
+                        (!self.db.current().entries.is_empty()).then_some(Message::ExportCsv),
+                    )
+                    .width(Length::Fill)
+                    .style(theme::Button::Secondary)
+                    .named("export-csv"),
+            )
+            .push(
+                button("Копировать базу")
+                    .on_press_maybe(
+                        (!self.db.current().entries.is_empty()).then_some(Message::CopyDb),
+                    )
+                    .width(Length::Fill)
+                    .style(theme::Button::Secondary)
+                    .named("copy-db"),
+            )
+            .push(url_input)
+            .push(url_button)
+            .push_maybe(
+                self.progress
+                    .map(|progress| progress_bar(0.0..=1.0, progress).height(8)),
+            )
+            .push(file_indicator)
+            .spacing(8)
+            .padding(8),
+        )
+        .style(theme::Container::Box);
+
+        let left_pane = container(
+            column![tabs, vertical_space(), file_manager]
+                .width(Length::Fixed(240.0))
+                .spacing(20),
+        );
+
+        let right_pane = match (self.xlsx_import.as_ref(), &self.active_tab) {
+            (Some(import), _) => import.view(),
+            (None, Tabs::Questions) => self.questions.view(
+                self.db.current(),
+                self.base_dir(),
+                matches!(
+                    self.settings.match_mode,
+                    MatchMode::Fuzzy | MatchMode::Scored
+                ),
+                self.window_width,
+                self.can_edit(),
+            ),
+            (None, Tabs::Explorer) => self.explorer.view(
+                self.db.current(),
+                self.base_dir(),
+                self.usage(),
+                &self.parse_cache.entry_numbers(),
+                self.can_edit(),
+            ),
+            (None, Tabs::Recommendations) => recommendations::view(
+                audit::recommendations(
+                    &self
+                        .questions
+                        .consultation(self.db.current(), self.source_name()),
+                ),
+                &self.recommendations_done,
+                self.base_dir(),
+            ),
+            (None, Tabs::Dependencies) => self.dependencies.view(self.db.current()),
+            (None, Tabs::Logs) => self.logs.view(self.settings.preferences.log_times),
+            (None, Tabs::Problems) => problems::view(&self.problems, self.can_edit()),
+            (None, Tabs::Editor) if self.is_locked() => lock::view(&self.passphrase),
+            (None, Tabs::Editor) => self.editor.view(self.db.current().lock.is_some()),
+            (None, Tabs::Settings) => self.settings.view(),
+            (None, Tabs::About) => about::view(about::Loaded {
+                name: self.source_name(),
+                db: self.db.current(),
+                editable: self.can_edit(),
+            }),
+        };
+
+        let right_pane = match self.load_failure.as_ref() {
+            Some(error) => error_dialog::view(error, self.tab_shown(&Tabs::Editor)),
+            None => right_pane,
+        };
+        let right_pane = if self.show_shortcuts {
+            shortcuts::view(|tab| self.tab_shown(tab))
+        } else {
+            right_pane
+        };
+        let right_pane = column![]
+            .push_maybe(self.tutorial.as_ref().map(Tutorial::view))
+            .push(right_pane)
+            .spacing(10);
+
+        self.toasts.view(
+            container(row![left_pane, container(right_pane).padding(10)])
+                .width(Length::Fill)
+                .height(Length::Fill),
+        )
+    }
+}
+
+impl MainWindow {
+    fn is_locked(&self) -> bool {
+        self.db.current().lock.is_some() && self.db.current().lock != self.unlocked
+    }
+
+    /// Whether actions changing the base are offered.
+    fn can_edit(&self) -> bool {
+        !self.settings.is_viewer() && !self.settings.training && !self.is_locked()
+    }
+
+    fn source_name(&self) -> &str {
+        match self.source.as_ref() {
+            Some(Source::File(path)) => path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default(),
+            Some(Source::Url(url)) => url,
+            Some(Source::Clipboard) => "Буфер обмена (не сохранено)",
+            Some(Source::Demo) => "Демонстрационная база",
+            Some(Source::Embedded(name)) => name,
+            None => "Файл не выбран",
+        }
+    }
+
+    /// Applies `apply` to a draft of the next generation of the base and,
+    /// for bases read from source text, writes it into the text with
+    /// `edit`. Bases loaded without source text, such as SQLite ones, are
+    /// only changed in the new generation. Nothing changes unless every
+    /// step succeeds, and nothing is tried while the editor holds text not
+    /// yet applied: entries are picked by their number in the base, which
+    /// the text may no longer agree with.
+    fn edit_db<T>(
+        &mut self,
+        change: Change,
+        apply: impl FnOnce(&mut DB) -> Result<T, EditError>,
+        edit: impl FnOnce(&ParseCache) -> Option<String>,
+    ) -> Result<T, Error> {
+        if !self.editor.is_applied() {
+            return Err(Error::Edit(Arc::new(
+                "the editor has changes not applied yet, apply or reload them first".to_string(),
+            )));
+        }
+
+        let mut db = self.db.draft();
+        let value = apply(&mut db)?;
+        let revision = self.editor.revision();
+
+        if !self.parse_cache.is_empty() {
+            let (cache, _) = reparse(&self.parse_cache, &self.editor.text())?;
+            let cache = match edit(&cache) {
+                Some(contents) => {
+                    let (cache, _) = reparse(&cache, &contents)?;
+                    self.editor.set_content(&contents);
+                    cache
+                }
+                None => cache,
             };
+            self.parse_cache = Arc::new(cache);
+        }
+
+        if self.editor.revision() == revision {
+            // The base no longer matches the file even with the text kept.
+            self.editor.mark_saved(None);
+        }
+        self.set_db(Arc::new(db), change);
+        Ok(value)
+    }
+
+    /// Publishes the next generation of the base, so results of queries
+    /// still running against the old one are recognized as stale, and lets
+    /// the tabs showing it refresh.
+    fn set_db(&mut self, db: Arc<DB>, change: Change) {
+        let mut db = match self.settings.normalize_names {
+            true => Arc::new(db.normalized()),
+            false => db,
+        };
+        let language = self.settings.preferences.language.code();
+        if db.language() != Some(language) {
+            Arc::make_mut(&mut db).set_language(Some(language.to_string()));
+        }
+        self.db.publish(db);
+        self.relint();
+
+        let db = self.db.current();
+        self.questions.refresh(db, change);
+        self.explorer.refresh(db, change);
+        self.dependencies.refresh(db, change);
+    }
+
+    fn relint(&mut self) {
+        self.problems = lint::lint(&self.parse_cache, self.db.current(), &self.settings.checks);
+    }
+
+    /// Logs the names the normalized base reads as other spellings, so they
+    /// can be cleaned up in the source.
+    fn log_merged_spellings(&mut self) {
+        for warning in lint::merged_spellings(&self.parse_cache, self.db.current()) {
+            self.logs.warning(LogSource::Parser, &warning);
+        }
+    }
+
+    /// Keeps the directory of `path` for the next open dialog.
+    fn remember_dir(&mut self, path: &Path) -> Command<Message> {
+        let dir = path.parent().map(Path::to_path_buf);
+        if dir.is_none() || dir == self.settings.preferences.last_dir {
+            return Command::none();
+        }
+
+        self.settings.preferences.last_dir = dir;
+        self.save_preferences()
+    }
+
+    fn save_preferences(&self) -> Command<Message> {
+        let preferences = self.settings.preferences.clone();
+
+        Command::perform(
+            async move {
+                preferences
+                    .save()
+                    .await
+                    .map_err(|err| Error::IO(err.kind()))
+            },
+            Message::PreferencesSaved,
+        )
+    }
+
+    /// File whose changes on disk are watched: the opened text base.
+    fn watched_path(&self) -> Option<&Path> {
+        self.source_path().filter(|path| !is_sqlite(path))
+    }
+
+    /// Reads the opened file again, keeping the answers to restore them in
+    /// the new base.
+    fn reload(&mut self) -> Command<Message> {
+        let Some(store) = self.store.clone() else {
+            return Command::none();
+        };
+        self.reloaded = Some((
+            self.questions.selected_category.clone(),
+            self.questions.answer_set(self.db.current()),
+        ));
+
+        open_store(store)
+    }
+
+    /// Makes `store` the one the opened base is reloaded from and saved to.
+    fn keep_store(&mut self, store: Arc<dyn KnowledgeStore>) -> Command<Message> {
+        let source = store.source();
+        let remember = match &source {
+            Source::File(path) => {
+                self.modified = std::fs::metadata(path).and_then(|x| x.modified()).ok();
+                self.remember_dir(path)
+            }
+            _ => Command::none(),
+        };
+        self.source = Some(source);
+        self.store = Some(store);
+
+        remember
+    }
+
+    /// Opened base as it would be saved now.
+    fn snapshot(&self) -> Snapshot {
+        // Bases read from SQLite have no source in the editor; saving one
+        // as text writes it out formatted.
+        let text = match self.editor.text() {
+            text if text.trim().is_empty() && self.source_path().is_some_and(is_sqlite) => {
+                format_db(self.db.current())
+            }
+            text => text,
+        };
+        Snapshot {
+            text,
+            format: self.editor.format,
+            db: self.db.current().clone(),
+        }
+    }
+
+    /// Parses a base read from a file or downloaded, which may be large, with
+    /// the progress shown.
+    fn parse_loaded(&mut self, contents: Arc<String>) -> Command<Message> {
+        self.progress = Some(0.0);
+
+        iced::command::channel(16, move |sender| loading::parse(contents, sender))
+            .map(Message::FileParse)
+    }
+
+    /// Checks the editor text once the user stops typing for
+    /// [`CHECK_DELAY`].
+    fn schedule_check(&self) -> Command<Message> {
+        let revision = self.editor.revision();
+
+        Command::perform(tokio::time::sleep(CHECK_DELAY), move |_| {
+            Message::EditorCheckDue(revision)
+        })
+    }
+
+    /// Answers saved when the current base was last used.
+    fn saved_progress(&self) -> Option<&IndexMap<String, String>> {
+        self.settings
+            .preferences
+            .progress
+            .get(self.progress_key.as_ref()?)
+    }
+
+    /// Remembers the answers once they are left unchanged for
+    /// [`PROGRESS_DELAY`].
+    fn remember_progress(&mut self) -> Command<Message> {
+        self.answers_revision += 1;
+        let revision = self.answers_revision;
+
+        Command::perform(tokio::time::sleep(PROGRESS_DELAY), move |_| {
+            Message::ProgressDue(revision)
+        })
+    }
+
+    /// Keeps the current answers and their degrees in the preferences, to be
+    /// offered when the base is opened again.
+    fn save_progress(&mut self) -> Command<Message> {
+        let Some(key) = self.progress_key.as_ref() else {
+            return Command::none();
+        };
+        let answers = self.questions.answer_set(self.db.current());
+        let degrees = answers
+            .keys()
+            .filter_map(|category| {
+                let degree = *self.questions.degrees.get(category)?;
+                (degree < 1.0).then(|| (category.clone(), degree))
+            })
+            .collect::<IndexMap<_, _>>();
+        let preferences = &self.settings.preferences;
+        if preferences.progress.get(key) == Some(&answers)
+            && preferences
+                .degrees
+                .get(key)
+                .map_or(degrees.is_empty(), |x| *x == degrees)
+        {
+            return Command::none();
+        }
+
+        self.settings
+            .preferences
+            .save_progress(key, answers, degrees);
+        self.save_preferences()
+    }
+
+    /// Puts back the target and answers given before a reload, logging the
+    /// ones the new base no longer has.
+    fn restore_answers(&mut self, target: Option<String>, answers: IndexMap<String, String>) {
+        if target
+            .as_ref()
+            .is_none_or(|x| self.db.current().conclusion_categories().contains(&x))
+        {
+            self.questions.select_category(self.db.current(), target);
+        }
+        let skipped = self.questions.apply_answer_set(self.db.current(), answers);
+
+        self.logs.debug(
+            LogSource::Io,
+            &format!("Reloaded {} after it changed on disk", self.source_name()),
+        );
+        if !skipped.is_empty() {
+            self.logs.debug(
+                LogSource::Ui,
+                &format!("Answers dropped as no longer valid: {}", skipped.join(", ")),
+            );
+        }
+        self.toasts.push("База перезагружена");
+    }
+
+    fn source_path(&self) -> Option<&Path> {
+        match self.source.as_ref() {
+            Some(Source::File(path)) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Path or address of the opened base, under which its statistics are
+    /// kept; `None` for a base pasted from the clipboard.
+    fn usage_key(&self) -> Option<String> {
+        match self.source.as_ref()? {
+            Source::File(path) => Some(path.display().to_string()),
+            Source::Url(url) => Some(url.clone()),
+            Source::Clipboard => None,
+            Source::Demo => Some("demo".to_string()),
+            Source::Embedded(name) => Some(format!("embedded:{}", name)),
+        }
+    }
+
+    fn usage(&self) -> Option<&Usage> {
+        self.settings.preferences.usage.get(&self.usage_key()?)
+    }
+
+    /// Directory relative image paths in tips are resolved against.
+    fn base_dir(&self) -> Option<&Path> {
+        self.source_path().and_then(Path::parent)
+    }
+
+    /// Training mode hides the entries and their source from students,
+    /// viewer mode the editor from end users.
+    fn tab_shown(&self, tab: &Tabs) -> bool {
+        match tab {
+            Tabs::Recommendations | Tabs::Explorer | Tabs::Dependencies => !self.settings.training,
+            Tabs::Editor | Tabs::Problems => !self.settings.is_viewer() && !self.settings.training,
+            Tabs::Questions | Tabs::Logs | Tabs::Settings | Tabs::About => true,
+        }
+    }
+
+    fn tabs(&self) -> Element<'_, Message> {
+        macro_rules! tab {
+            ($tab: expr) => {{
+                let id = format!("open-tab-{:?}", $tab);
+                button($tab.name())
+                    .on_press_maybe((self.active_tab != $tab).then_some(Message::TabChanged($tab)))
+                    .width(Length::Fill)
+                    .style(theme::Button::Secondary)
+                    .named(id)
+            }};
         }
 
-        column![
-            tab!("Вопросы", Tabs::Questions),
-            tab!("Данные", Tabs::Explorer),
-            tab!("Редактор", Tabs::Editor),
-            tab!("Сообщения", Tabs::Logs),
-        ]
-        .spacing(5)
-        .width(Length::Fill)
-        .into()
+        Tabs::ALL
+            .into_iter()
+            .fold(Column::new(), |col, tab| {
+                col.push_maybe(self.tab_shown(&tab).then(|| tab!(tab)))
+            })
+            .spacing(5)
+            .width(Length::Fill)
+            .into()
     }
 }
 
-async fn open_file() -> Result<(PathBuf, Arc<String>), Error> {
+async fn parse_file(contents: Arc<String>) -> Result<(Arc<ParseCache>, Arc<DB>), Error> {
+    spawn_parser(move || parse_with_cache(&contents)).await
+}
+
+async fn reparse_file(
+    cache: Arc<ParseCache>,
+    contents: Arc<String>,
+) -> Result<(Arc<ParseCache>, Arc<DB>), Error> {
+    spawn_parser(move || reparse(&cache, &contents)).await
+}
+
+async fn check_file(cache: Arc<ParseCache>, contents: Arc<String>) -> Result<Vec<Conflict>, Error> {
+    spawn_parser(move || reparse(&cache, &contents))
+        .await
+        .map(|(cache, db)| schema::conflicts(&cache, &db))
+}
+
+async fn spawn_parser(
+    parse: impl FnOnce() -> Result<(ParseCache, DB), ParserError> + Send + 'static,
+) -> Result<(Arc<ParseCache>, Arc<DB>), Error> {
+    tokio::task::spawn_blocking(parse)
+        .await
+        .map_err(|err| Error::Parse(Arc::new(err.to_string()), (0, 0)))?
+        .map(|(cache, db)| (Arc::new(cache), Arc::new(db)))
+        .map_err(Error::from)
+}
+
+async fn open_xlsx() -> Result<(PathBuf, Sheet), Error> {
     let picked_file = rfd::AsyncFileDialog::new()
-        .set_title("Открыть базу знаний...")
+        .set_title("Импорт таблицы...")
+        .add_filter("Excel", &["xlsx", "xlsm", "xls", "ods"])
         .pick_file()
         .await
         .ok_or(Error::DialogClosed)?;
 
-    load_file(picked_file.path().to_owned()).await
+    let path = picked_file.path().to_owned();
+    tokio::task::spawn_blocking(move || {
+        xlsx_import::read_sheet(&path)
+            .map(|sheet| (path, sheet))
+            .map_err(|err| Error::Xlsx(Arc::new(err.to_string())))
+    })
+    .await
+    .map_err(|err| Error::Xlsx(Arc::new(err.to_string())))?
+}
+
+fn open_store(store: Arc<dyn KnowledgeStore>) -> Command<Message> {
+    iced::command::channel(16, move |sender| store::load(store, sender)).map(Message::StoreLoad)
+}
+
+/// Store of the base at `path`, picked by its extension.
+fn store_at(
+    path: PathBuf,
+    encoding: FileEncoding,
+    format: KbFormat,
+    limit: FileSizeLimit,
+) -> Arc<dyn KnowledgeStore> {
+    if is_sqlite(&path) {
+        Arc::new(SqliteStore { path })
+    } else {
+        Arc::new(FileStore {
+            path,
+            encoding,
+            format,
+            limit,
+        })
+    }
+}
+
+async fn open_sqlite(dir: Option<PathBuf>, mut sender: Sender<StoreEvent>) {
+    let mut dialog = rfd::AsyncFileDialog::new()
+        .set_title("Открыть базу знаний SQLite...")
+        .add_filter("SQLite", &SQLITE_EXTENSIONS);
+    if let Some(dir) = dir {
+        dialog = dialog.set_directory(dir);
+    }
+
+    match dialog.pick_file().await {
+        Some(picked_file) => {
+            let path = picked_file.path().to_owned();
+            store::load(Arc::new(SqliteStore { path }), sender).await;
+        }
+        None => {
+            let _ = sender
+                .send(StoreEvent::Finished(Err(Error::DialogClosed)))
+                .await;
+        }
+    }
+}
+
+/// How often the opened file is checked for changes made by other programs.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the answers are left unchanged before they are remembered.
+const PROGRESS_DELAY: Duration = Duration::from_secs(2);
+
+const SQLITE_EXTENSIONS: [&str; 2] = ["sqlite", "db"];
+
+fn is_sqlite(path: &Path) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| SQLITE_EXTENSIONS.contains(&x.to_lowercase().as_str()))
+}
+
+async fn modified_at(path: PathBuf) -> Option<SystemTime> {
+    tokio::fs::metadata(path).await.ok()?.modified().ok()
+}
+
+async fn confirm_reload(path: PathBuf, discards: bool) -> bool {
+    let lost = match discards {
+        true => " Несохранённые изменения будут потеряны.",
+        false => "",
+    };
+    let answer = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Info)
+        .set_title("Файл изменён")
+        .set_description(format!(
+            "Файл {} изменён другой программой. Загрузить его заново?{}",
+            path.display(),
+            lost
+        ))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        .await;
+
+    answer == rfd::MessageDialogResult::Yes
+}
+
+async fn confirm_restore(answers: usize) -> bool {
+    let answer = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Info)
+        .set_title("Незавершённая консультация")
+        .set_description(format!(
+            "С этой базой уже отвечали на вопросы (ответов: {}). Восстановить ответы?",
+            answers
+        ))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        .await;
+
+    answer == rfd::MessageDialogResult::Yes
+}
+
+async fn save_sqlite(snapshot: Snapshot) -> Result<PathBuf, Error> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Сохранить базу знаний в SQLite...")
+        .add_filter("SQLite", &SQLITE_EXTENSIONS)
+        .save_file()
+        .await
+        .ok_or(Error::DialogClosed)?;
+
+    let path = picked_file.path().to_owned();
+    store::save(Arc::new(SqliteStore { path: path.clone() }), snapshot).await?;
+
+    Ok(path)
+}
+
+/// Asks where to save the base and writes it there as DSL source.
+async fn save_kb(snapshot: Snapshot) -> Result<Arc<dyn KnowledgeStore>, Error> {
+    let store = FileStore {
+        path: pick_save_path(KB_TARGET).await?,
+        encoding: FileEncoding::default(),
+        format: KbFormat::Dsl,
+        limit: FileSizeLimit(None),
+    };
+
+    store::save(Arc::new(store), snapshot).await
+}
+
+/// File a save dialog offers to write.
+struct SaveTarget {
+    title: &'static str,
+    filter: &'static str,
+    extension: &'static str,
+    file_name: &'static str,
+}
+
+const REPORT_TARGET: SaveTarget = SaveTarget {
+    title: "Сохранить отчёт о консультации...",
+    filter: "Markdown",
+    extension: "md",
+    file_name: "отчёт.md",
+};
+
+const CHECKLIST_TARGET: SaveTarget = SaveTarget {
+    title: "Сохранить рекомендации...",
+    filter: "Markdown",
+    extension: "md",
+    file_name: "рекомендации.md",
+};
+
+const CSV_TARGET: SaveTarget = SaveTarget {
+    title: "Экспорт базы знаний в CSV...",
+    filter: "CSV",
+    extension: "csv",
+    file_name: "база.csv",
+};
+
+const LOGS_TARGET: SaveTarget = SaveTarget {
+    title: "Сохранить сообщения...",
+    filter: "Текст",
+    extension: "log",
+    file_name: "сообщения.log",
+};
+
+const ANSWERS_TARGET: SaveTarget = SaveTarget {
+    title: "Сохранить ответы...",
+    filter: "JSON",
+    extension: "json",
+    file_name: "ответы.json",
+};
+
+const KB_TARGET: SaveTarget = SaveTarget {
+    title: "Сохранить базу знаний как...",
+    filter: "База знаний",
+    extension: "kb",
+    file_name: "база.kb",
+};
+
+/// Asks where to save `contents` and writes them there.
+async fn save_file(target: SaveTarget, contents: impl AsRef<[u8]>) -> Result<PathBuf, Error> {
+    let path = pick_save_path(target).await?;
+    tokio::fs::write(&path, contents)
+        .await
+        .map_err(|err| Error::IO(err.kind()))?;
+
+    Ok(path)
+}
+
+async fn pick_save_path(target: SaveTarget) -> Result<PathBuf, Error> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title(target.title)
+        .add_filter(target.filter, &[target.extension])
+        .set_file_name(target.file_name)
+        .save_file()
+        .await
+        .ok_or(Error::DialogClosed)?;
+
+    // The dialog confirms overwriting the name it was given, but not the one
+    // with the extension added here.
+    let mut path = picked_file.path().to_owned();
+    if path.extension().is_none() {
+        path.set_extension(target.extension);
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) && !confirm_overwrite(&path).await {
+            return Err(Error::DialogClosed);
+        }
+    }
+
+    Ok(path)
+}
+
+async fn confirm_overwrite(path: &Path) -> bool {
+    let answer = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Файл уже существует")
+        .set_description(format!("Заменить файл {}?", path.display()))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        .await;
+
+    answer == rfd::MessageDialogResult::Yes
+}
+
+/// Answer set picked by the user: a JSON object mapping categories to
+/// values.
+async fn open_answers(dir: Option<PathBuf>) -> Result<IndexMap<String, String>, Error> {
+    let mut dialog = rfd::AsyncFileDialog::new()
+        .set_title("Загрузить ответы...")
+        .add_filter("JSON", &["json"]);
+    if let Some(dir) = dir {
+        dialog = dialog.set_directory(dir);
+    }
+    let picked_file = dialog.pick_file().await.ok_or(Error::DialogClosed)?;
+
+    let contents = tokio::fs::read_to_string(picked_file.path())
+        .await
+        .map_err(|err| Error::IO(err.kind()))?;
+
+    serde_json::from_str(&contents).map_err(|err| Error::Answers(Arc::new(err.to_string())))
+}
+
+async fn read_cases(path: &Path) -> Result<Vec<TestCase>, Error> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(contents) => {
+            parse_cases(&contents).map_err(|err| Error::Cases(Arc::new(err.to_string())))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(Error::IO(err.kind())),
+    }
+}
+
+async fn record_case(path: PathBuf, mut case: TestCase) -> Result<PathBuf, Error> {
+    let mut cases = read_cases(&path).await?;
+    case.name = format!("Кейс {}", cases.len() + 1);
+    cases.push(case);
+
+    tokio::fs::write(&path, format_cases(&cases))
+        .await
+        .map_err(|err| Error::IO(err.kind()))?;
+
+    Ok(path)
+}
+
+/// Merges the values in the cases recorded at `path`, returning the number
+/// of cases changed.
+async fn merge_cases(
+    path: PathBuf,
+    category: String,
+    from: String,
+    into: String,
+) -> Result<usize, Error> {
+    let mut cases = read_cases(&path).await?;
+    let count = cases
+        .iter_mut()
+        .map(|case| case.merge_value(&category, &from, &into))
+        .filter(|changed| *changed)
+        .count();
+    if count > 0 {
+        tokio::fs::write(&path, format_cases(&cases))
+            .await
+            .map_err(|err| Error::IO(err.kind()))?;
+    }
+
+    Ok(count)
 }
 
-async fn load_file(path: PathBuf) -> Result<(PathBuf, Arc<String>), Error> {
-    let contents = tokio::fs::read_to_string(&path)
+async fn run_cases(
+    path: PathBuf,
+    db: Arc<DB>,
+    strategy: &'static dyn MatchStrategy,
+) -> Result<Vec<CaseOutcome>, Error> {
+    let cases = read_cases(&path).await?;
+    if cases.is_empty() {
+        return Err(Error::Cases(Arc::new(format!(
+            "no cases recorded in {}",
+            path.display()
+        ))));
+    }
+
+    tokio::task::spawn_blocking(move || cases.iter().map(|case| case.run(&db, strategy)).collect())
         .await
-        .map(Arc::new)
-        .map_err(|err| err.kind())
-        .map_err(Error::IO)?;
+        .map_err(|err| Error::Cases(Arc::new(err.to_string())))
+}
 
-    Ok((path, contents))
+/// Query sent to the engine, owning what [`Query`] borrows.
+#[derive(Debug, Clone)]
+pub struct QueryRequest {
+    target: Option<String>,
+    answers: Vec<(String, String, f64)>,
+    find_all: bool,
+    threshold: f64,
+    mode: MatchMode,
 }
 
-async fn parse_file(contents: Arc<String>) -> Result<Arc<DB>, Error> {
-    parse_db_from_file(&contents)
-        .map(Arc::new)
-        .map_err(|err| match err {
-            ParserError::Parse(s, pos) => Error::Parse(Arc::new(s.to_string()), pos),
+impl QueryRequest {
+    /// Case replaying the query and expecting `result`, unless the query
+    /// stopped before finishing.
+    fn case(&self, result: &QueryResult) -> Option<TestCase> {
+        if result.interrupted.is_some() {
+            return None;
+        }
+        let mut answers = self
+            .answers
+            .iter()
+            .map(|(category, value, _)| (category.clone(), value.clone()))
+            .collect::<IndexMap<_, _>>();
+        answers.sort_keys();
+        let degrees = self
+            .answers
+            .iter()
+            .filter(|(_, _, degree)| *degree < 1.0)
+            .map(|(category, _, degree)| (category.clone(), *degree))
+            .collect();
+
+        Some(TestCase {
+            name: String::new(),
+            target: self.target.clone(),
+            answers,
+            degrees,
+            find_all: self.find_all,
+            strategy: Some(self.mode.strategy().name().to_string()),
+            threshold: Some(self.threshold),
+            expected: result.values(),
         })
+    }
 }
 
 async fn query_db(
     db: Arc<DB>,
-    target: Option<String>,
-    query: Vec<(String, String)>,
-) -> Result<Arc<String>, Error> {
-    db.find_value(
-        target.as_ref(),
-        query.iter().map(|(x, y)| (x, y)).collect::<Vec<_>>(),
-    )
-    .map(Arc::new)
-    .ok_or(Error::Query(Arc::new(format!(
-        "Query {:?} didn't find anything, target category {:?}",
-        query, target
-    ))))
+    pool: Option<Arc<rayon::ThreadPool>>,
+    request: QueryRequest,
+    budget: Budget,
+) -> Result<QueryResult, Error> {
+    let strategy = request.mode.strategy();
+    let result = tokio::task::spawn_blocking({
+        let request = request.clone();
+        move || {
+            let query = Query {
+                target: request.target.as_ref(),
+                answers: request
+                    .answers
+                    .iter()
+                    .map(|(cat, val, degree)| (cat, val, *degree))
+                    .collect(),
+                find_all: request.find_all,
+                threshold: request.threshold,
+                budget: &budget,
+            };
+            let run = || strategy.run(&db, &query);
+            match pool {
+                Some(pool) => pool.install(run),
+                None => run(),
+            }
+        }
+    })
+    .await
+    .map_err(|err| Error::Query(Arc::new(format!("query failed: {}", err))))?;
+
+    Ok(result)
 }
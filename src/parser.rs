@@ -1,70 +1,1575 @@
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
 
+use indexmap::IndexMap;
 use pest::error::LineColLocation;
-use pest::{iterators::Pairs, Parser};
+use pest::{
+    iterators::{Pair, Pairs},
+    Parser,
+};
 use pest_derive::Parser;
+use sha2::{Digest, Sha256};
 
-#[derive(Default, Debug)]
+use crate::cases::TestCase;
+use crate::schema::{self, Schema};
+use crate::strategy;
+
+#[derive(Default, Debug, Clone)]
 pub struct DB {
     pub entries: Vec<Entry>,
-    pub categories: HashMap<String, Vec<String>>,
-    pub questions: HashMap<String, String>,
-    pub changes: HashMap<String, String>,
-    pub tips: HashMap<String, String>,
+    pub categories: IndexMap<String, Vec<String>>,
+    pub questions: IndexMap<String, Question>,
+    pub changes: IndexMap<String, String>,
+    pub tips: IndexMap<String, String>,
+    /// Translations of single values, by category, shown instead of the
+    /// translation of the category when the value is concluded.
+    pub value_changes: IndexMap<String, IndexMap<String, String>>,
+    /// Tips about single values, by category, shown instead of the tip
+    /// about the category when the value is concluded.
+    pub value_tips: IndexMap<String, IndexMap<String, String>>,
+    /// Texts of questions, translations and tips in other languages, by
+    /// language.
+    pub variants: IndexMap<TextKey, IndexMap<String, String>>,
+    /// Parent of every value declared as a subtype of another value.
+    pub taxonomy: IndexMap<String, String>,
+    /// Canonical value of every declared alias.
+    pub aliases: IndexMap<String, String>,
+    pub schema: Schema,
+    /// Category searched for when the user does not pick one.
+    pub target: Option<String>,
+    /// SHA-256 of the passphrase editing the base requires, in lowercase
+    /// hex.
+    pub lock: Option<String>,
+    /// Prior probability of conclusion values, by category, for Bayesian
+    /// matching.
+    pub priors: IndexMap<String, IndexMap<String, f64>>,
+    /// Example consultations embedded in the base with `тест`.
+    pub tests: Vec<TestCase>,
+
+    index: HashMap<String, HashMap<String, Vec<usize>>>,
+    ignored: HashMap<String, Vec<usize>>,
+    conclusions: HashMap<String, Vec<usize>>,
+    /// Spelling of every name by its [`name_key`], set once the base is
+    /// [normalized](DB::normalized).
+    spellings: HashMap<String, String>,
+    /// Language texts are shown in, when they have a variant in it.
+    language: Option<String>,
+}
+
+/// How the enabled entries of the base use a category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CategoryRole {
+    /// Concluded by entries but never asked about.
+    Conclusion,
+    /// Asked about by entries but never concluded.
+    Condition,
+    /// Concluded by some entries and asked about by others.
+    Intermediate,
 }
 
-#[derive(Default, Debug)]
+/// What removing a category does to the declarations that use it.
+#[derive(Debug, Clone, Default)]
+pub struct CategoryImpact {
+    /// Entries concluding the category or left with no other condition,
+    /// which are removed.
+    pub removed: Vec<usize>,
+    /// Entries that only lose their condition on the category.
+    pub trimmed: Vec<usize>,
+    pub question: bool,
+    pub change: bool,
+    pub tip: bool,
+    /// Embedded tests that are removed, for searching the category, or that
+    /// lose their answer about it.
+    pub tests: usize,
+}
+
+impl CategoryRole {
+    pub fn is_conclusion(self) -> bool {
+        self != Self::Condition
+    }
+
+    pub fn is_condition(self) -> bool {
+        self != Self::Conclusion
+    }
+}
+
+#[derive(Default, Debug, Clone)]
 pub struct Entry {
     pub value: String,
     pub category: String,
-    pub categories: Vec<(String, String)>,
+    pub conditions: Vec<Condition>,
+    /// Categories the entry explicitly does not depend on, written
+    /// `category - *`.
+    pub ignored: Vec<String>,
+    pub provenance: Provenance,
+    /// Disabled entries are kept in the base but take no part in inference.
+    pub disabled: bool,
+    /// Why the entry holds, written `примечание`.
+    pub note: Option<String>,
+}
+
+impl Entry {
+    /// Replaces value `from` of `category` with `into` in the conditions and
+    /// the conclusion, returning whether anything changed.
+    pub fn merge_value(&mut self, category: &str, from: &str, into: &str) -> bool {
+        let mut changed = false;
+        for condition in self.conditions.iter_mut() {
+            if condition.category == category && condition.value == from {
+                condition.value = into.to_string();
+                changed = true;
+            }
+        }
+        // A condition the entry already had on `into` is now repeated.
+        let mut kept = false;
+        self.conditions.retain(|x| {
+            !(x.category == category && x.value == into && std::mem::replace(&mut kept, true))
+        });
+
+        if self.category == category && self.value == from {
+            self.value = into.to_string();
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Who an entry was written by and on what grounds, for bases that collect
+/// knowledge from several specialists.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Provenance {
+    pub author: Option<String>,
+    /// Literature or other reference the entry is based on.
+    pub source: Option<String>,
+    /// Date the entry was written, as `YYYY-MM-DD`.
+    pub date: Option<String>,
+}
+
+impl Provenance {
+    pub fn is_empty(&self) -> bool {
+        self.author.is_none() && self.source.is_none() && self.date.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub category: String,
+    pub value: String,
+    /// Contribution of the condition to the entry score in scored matching.
+    pub weight: f64,
+    /// Probability that the condition holds given the conclusion of the
+    /// entry, for Bayesian matching.
+    pub probability: Option<f64>,
+}
+
+impl Condition {
+    pub fn new(category: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            category: category.into(),
+            value: value.into(),
+            weight: 1.0,
+            probability: None,
+        }
+    }
+
+    pub fn matches(&self, category: &str, value: &str) -> bool {
+        self.category == category && self.value == value
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Question {
+    pub text: String,
+    /// Position of the question in the Questions tab; unordered questions
+    /// go last.
+    pub order: Option<u32>,
+    /// Section the question is shown under.
+    pub group: Option<String>,
+    pub kind: QuestionKind,
+}
+
+/// How the answer to a question is entered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuestionKind {
+    /// One of the values the category takes in the entries.
+    #[default]
+    Choice,
+    /// [`QuestionKind::YES`] or [`QuestionKind::NO`].
+    Boolean,
+    Number,
+    Text,
+}
+
+impl QuestionKind {
+    pub const YES: &'static str = "да";
+    pub const NO: &'static str = "нет";
+
+    pub fn keyword(self) -> &'static str {
+        match self {
+            QuestionKind::Choice => "выбор",
+            QuestionKind::Boolean => "да_нет",
+            QuestionKind::Number => "число",
+            QuestionKind::Text => "текст",
+        }
+    }
+
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        [
+            QuestionKind::Choice,
+            QuestionKind::Boolean,
+            QuestionKind::Number,
+            QuestionKind::Text,
+        ]
+        .into_iter()
+        .find(|kind| kind.keyword() == keyword)
+    }
+}
+
+/// Declaration whose text can have variants in other languages.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TextKey {
+    Question(String),
+    /// Translation of a category, or of one of its values.
+    Change(String, Option<String>),
+    /// Tip about a category, or about one of its values.
+    Tip(String, Option<String>),
+}
+
+impl TextKey {
+    pub fn category(&self) -> &String {
+        match self {
+            TextKey::Question(category)
+            | TextKey::Change(category, _)
+            | TextKey::Tip(category, _) => category,
+        }
+    }
+
+    fn of(block: &Block) -> Option<TextKey> {
+        Some(match block {
+            Block::Advice(category, _) => TextKey::Question(category.clone()),
+            Block::Change(category, value, _) => TextKey::Change(category.clone(), value.clone()),
+            Block::Tip(category, value, _) => TextKey::Tip(category.clone(), value.clone()),
+            _ => return None,
+        })
+    }
+}
+
+impl Question {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
 }
 
+#[derive(Debug, Clone)]
+pub enum Block {
+    /// Entry with its number, `None` when the number does not fit in `u64`.
+    Entry(Option<u64>, Entry),
+    Advice(String, Question),
+    /// Translation of a category, or of one of its values.
+    Change(String, Option<String>, String),
+    /// Tip about a category, or about one of its values.
+    Tip(String, Option<String>, String),
+    Subtype(String, String),
+    Alias(String, String),
+    Domain(String, Vec<String>),
+    MultiValued(String),
+    Target(String),
+    Lock(String),
+    Prior(String, String, f64),
+    Test(TestCase),
+}
+
+#[derive(Debug, Clone)]
+pub struct ParsedBlock {
+    pub span: Range<usize>,
+    pub block: Block,
+    /// Spans of the condition pairs of an entry block followed by the span
+    /// of its conclusion, or of the answers of a test; empty for other
+    /// blocks.
+    pub(crate) pairs: Vec<Range<usize>>,
+    /// Text of a question, translation or tip in other languages, by
+    /// language; empty for other blocks.
+    pub(crate) variants: IndexMap<String, String>,
+}
+
+/// Part of the source text, in bytes and as the line and column, both
+/// counted from 1, of its start and end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceSpan {
+    pub bytes: Range<usize>,
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl SourceSpan {
+    fn new(source: &str, bytes: Range<usize>) -> Self {
+        Self {
+            start: schema::line_col(source, bytes.start),
+            end: schema::line_col(source, bytes.end),
+            bytes,
+        }
+    }
+
+    /// Span of a block, leaving out the whitespace up to the next one.
+    fn block(source: &str, bytes: &Range<usize>) -> Self {
+        let end = bytes.start + source[bytes.clone()].trim_end().len();
+        Self::new(source, bytes.start..end)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EntrySpans {
+    pub block: SourceSpan,
+    /// Spans of the `category - value` pairs of the conditions, in order.
+    pub conditions: Vec<SourceSpan>,
+    pub conclusion: SourceSpan,
+}
+
+/// Where the entries, questions, translations and tips of a base are in its
+/// source. Entries are in the order of [`DB::entries`]; for categories
+/// declared more than once the declaration that takes effect is kept.
+#[derive(Debug, Clone, Default)]
+pub struct SpanIndex {
+    pub entries: Vec<EntrySpans>,
+    pub advice: IndexMap<String, SourceSpan>,
+    pub changes: IndexMap<String, SourceSpan>,
+    pub tips: IndexMap<String, SourceSpan>,
+}
+
+/// Source text and blocks of the last successful parse, used to reparse
+/// only the blocks touched by an edit.
+#[derive(Debug, Clone, Default)]
+pub struct ParseCache {
+    source: String,
+    blocks: Vec<ParsedBlock>,
+}
+
+impl ParseCache {
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Number following the largest entry number, wherever the entry is.
+    pub fn next_entry_number(&self) -> u64 {
+        self.blocks
+            .iter()
+            .filter_map(|parsed| match parsed.block {
+                Block::Entry(number, _) => number,
+                _ => None,
+            })
+            .max()
+            .map_or(1, |x| x.saturating_add(1))
+    }
+
+    /// Numbers the entries have in the source, in the order of
+    /// [`DB::entries`].
+    pub fn entry_numbers(&self) -> Vec<Option<u64>> {
+        self.blocks
+            .iter()
+            .filter_map(|parsed| match parsed.block {
+                Block::Entry(number, _) => Some(number),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn span_index(&self) -> SpanIndex {
+        let source = self.source.as_str();
+        let mut index = SpanIndex::default();
+        for parsed in self.blocks.iter() {
+            let block = SourceSpan::block(source, &parsed.span);
+            match &parsed.block {
+                Block::Entry(..) => {
+                    let mut pairs = parsed
+                        .pairs
+                        .iter()
+                        .map(|pair| SourceSpan::new(source, pair.clone()))
+                        .collect::<Vec<_>>();
+                    let Some(conclusion) = pairs.pop() else {
+                        continue;
+                    };
+                    index.entries.push(EntrySpans {
+                        block,
+                        conditions: pairs,
+                        conclusion,
+                    });
+                }
+                Block::Advice(category, _) => {
+                    index.advice.insert(category.clone(), block);
+                }
+                Block::Change(category, None, _) => {
+                    index.changes.insert(category.clone(), block);
+                }
+                Block::Tip(category, None, _) => {
+                    index.tips.insert(category.clone(), block);
+                }
+                _ => {}
+            }
+        }
+
+        index
+    }
+
+    pub(crate) fn blocks(&self) -> &[ParsedBlock] {
+        &self.blocks
+    }
+}
+
+/// Message and line/column of a syntax error.
+pub type SyntaxError = (Arc<String>, (usize, usize));
+
 pub enum ParserError {
-    Parse(Rc<String>, (usize, usize)),
+    Parse(Arc<String>, (usize, usize)),
+    /// Errors of every malformed block, in source order, with the base of
+    /// the blocks that did parse.
+    Recovered(Vec<SyntaxError>, Arc<DB>),
+}
+
+/// Reason a change to the base was refused; the base is left as it was.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditError {
+    NoSuchEntry(usize),
+    /// The entry or one of its conditions has an empty category or value.
+    Blank,
+    /// The entry has a condition on the category it concludes.
+    SelfReference(String),
+    /// Declarations of the base the entry does not agree with.
+    Schema(Vec<String>),
+    /// No entry uses the category the question is about.
+    UnknownCategory(String),
+    EmptyQuestion(String),
+    /// No entry uses the value of the category.
+    UnknownValue(String, String),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchEntry(id) => write!(f, "there is no entry {}", id + 1),
+            Self::Blank => f.write_str("entry has an empty category or value"),
+            Self::SelfReference(category) => write!(
+                f,
+                "entry concludes category `{}` it has a condition on",
+                category
+            ),
+            Self::Schema(violations) => f.write_str(&violations.join("; ")),
+            Self::UnknownCategory(category) => {
+                write!(f, "no entry uses category `{}`", category)
+            }
+            Self::EmptyQuestion(category) => {
+                write!(f, "question about `{}` has no text", category)
+            }
+            Self::UnknownValue(category, value) => {
+                write!(f, "no entry uses value `{}` of `{}`", value, category)
+            }
+        }
+    }
 }
 
+/// Words that can only start a block, used to resynchronize after an error.
+const BLOCK_KEYWORDS: [&str; 11] = [
+    "вопрос",
+    "перевод",
+    "подсказка",
+    "подтип",
+    "синоним",
+    "значения",
+    "многозначный",
+    "цель",
+    "защита",
+    "вероятность",
+    "тест",
+];
+
 #[derive(Parser)]
 #[grammar = "syn.pest"]
 struct LangParser;
 
+/// Whether `text` can be written as the text of a question.
+pub(crate) fn is_question_text(text: &str) -> bool {
+    LangParser::parse(Rule::text, text)
+        .is_ok_and(|mut pairs| pairs.next().is_some_and(|pair| pair.as_str() == text))
+}
+
 pub fn parse_db_from_file(contents: &str) -> Result<DB, ParserError> {
-    let file = LangParser::parse(Rule::file, contents)
+    parse_with_cache(contents).map(|(_, db)| db)
+}
+
+pub fn parse_with_cache(contents: &str) -> Result<(ParseCache, DB), ParserError> {
+    let blocks = parse_blocks(Rule::file, contents, 0).map_err(|err| recover(contents, err))?;
+    let db = DB::from_blocks(&blocks);
+
+    Ok((
+        ParseCache {
+            source: contents.to_string(),
+            blocks,
+        },
+        db,
+    ))
+}
+
+/// Reparses `contents` reusing every block of `cache` that lies outside the
+/// edited region. Falls back to a full parse whenever the reused blocks could
+/// have been affected by the edit, so the result always matches
+/// [`parse_with_cache`].
+pub fn reparse(cache: &ParseCache, contents: &str) -> Result<(ParseCache, DB), ParserError> {
+    if cache.blocks.is_empty() {
+        return parse_with_cache(contents);
+    }
+
+    let old = cache.source.as_str();
+    let prefix = common_prefix(old, contents);
+    let suffix = common_suffix(&old[prefix..], &contents[prefix..]);
+    let old_changed_end = old.len() - suffix;
+    let delta = contents.len() as isize - old.len() as isize;
+
+    let head = cache
+        .blocks
+        .iter()
+        .take_while(|block| block.span.end < prefix)
+        .cloned()
+        .collect::<Vec<_>>();
+    let tail = cache
+        .blocks
+        .iter()
+        .skip(head.len())
+        .skip_while(|block| block.span.start <= old_changed_end)
+        .map(|block| ParsedBlock {
+            span: shift(block.span.start, delta)..shift(block.span.end, delta),
+            block: block.block.clone(),
+            pairs: block
+                .pairs
+                .iter()
+                .map(|pair| shift(pair.start, delta)..shift(pair.end, delta))
+                .collect(),
+            variants: block.variants.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    let start = head.last().map_or(0, |block| block.span.end);
+    let end = tail
+        .first()
+        .map_or(contents.len(), |block| block.span.start);
+
+    let Ok(middle) = parse_blocks(Rule::blocks, &contents[start..end], start) else {
+        return parse_with_cache(contents);
+    };
+
+    let blocks = head
+        .into_iter()
+        .chain(middle)
+        .chain(tail)
+        .collect::<Vec<_>>();
+    if blocks.is_empty() || !separated(contents, &blocks) {
+        return parse_with_cache(contents);
+    }
+
+    let db = DB::from_blocks(&blocks);
+    Ok((
+        ParseCache {
+            source: contents.to_string(),
+            blocks,
+        },
+        db,
+    ))
+}
+
+fn parse_blocks(
+    rule: Rule,
+    contents: &str,
+    offset: usize,
+) -> Result<Vec<ParsedBlock>, ParserError> {
+    let file = LangParser::parse(rule, contents)
         .map_err(|err| {
             let pos = match err.line_col {
                 LineColLocation::Pos((x, y)) => (x, y),
                 LineColLocation::Span((start_x, start_y), _) => (start_x, start_y),
             };
-            ParserError::Parse(Rc::new(err.to_string()), pos)
+            ParserError::Parse(Arc::new(err.to_string()), pos)
         })?
         .next()
         .unwrap();
 
-    let mut db = DB::new();
+    let mut blocks = Vec::new();
     for data in file.into_inner() {
-        match data.as_rule() {
-            Rule::entry => parse_entry(&mut data.into_inner(), &mut db),
-            Rule::advice => parse_advice(&mut data.into_inner(), &mut db.questions),
-            Rule::change => parse_change(&mut data.into_inner(), &mut db.changes),
-            Rule::tip => parse_tip(&mut data.into_inner(), &mut db.tips),
+        let span = data.as_span();
+        let span = offset + span.start()..offset + span.end();
+
+        let mut pairs = Vec::new();
+        let mut variants = IndexMap::new();
+        let block = match data.as_rule() {
+            Rule::entry => {
+                let (number, entry, spans) = parse_entry(&mut data.into_inner());
+                pairs = spans
+                    .into_iter()
+                    .map(|x| offset + x.start..offset + x.end)
+                    .collect();
+                Block::Entry(number, entry)
+            }
+            Rule::advice => {
+                let (category, question);
+                (category, question, variants) = parse_advice(&mut data.into_inner());
+                Block::Advice(category, question)
+            }
+            Rule::change => {
+                let (category, value, text);
+                (category, value, text, variants) = parse_text(&mut data.into_inner());
+                Block::Change(category, value, text)
+            }
+            Rule::tip => {
+                let (category, value, text);
+                (category, value, text, variants) = parse_text(&mut data.into_inner());
+                Block::Tip(category, value, text)
+            }
+            Rule::subtype => {
+                let mut values = data.into_inner();
+                let value = values.next().unwrap().as_str().to_string();
+                let parent = values.next().unwrap().as_str().to_string();
+                Block::Subtype(value, parent)
+            }
+            Rule::alias => {
+                let mut values = data.into_inner();
+                let alias = values.next().unwrap().as_str().to_string();
+                let value = values.next().unwrap().as_str().to_string();
+                Block::Alias(alias, value)
+            }
+            Rule::domain => {
+                let mut inner = data.into_inner();
+                let category = inner.next().unwrap().as_str().to_string();
+                let values = inner.map(|value| value.as_str().to_string()).collect();
+                Block::Domain(category, values)
+            }
+            Rule::multi_valued => {
+                Block::MultiValued(data.into_inner().next().unwrap().as_str().to_string())
+            }
+            Rule::target => Block::Target(data.into_inner().next().unwrap().as_str().to_string()),
+            Rule::lock => Block::Lock(
+                data.into_inner()
+                    .next()
+                    .unwrap()
+                    .as_str()
+                    .to_ascii_lowercase(),
+            ),
+            Rule::prior => {
+                let mut inner = data.into_inner();
+                let mut pair = inner.next().unwrap().into_inner();
+                let category = pair.next().unwrap().as_str().to_string();
+                let value = pair.next().unwrap().as_str().to_string();
+                let probability = inner.next().unwrap().as_str().parse::<f64>().unwrap();
+                Block::Prior(category, value, probability)
+            }
+            Rule::test => {
+                let (case, spans) = parse_test(&mut data.into_inner());
+                pairs = spans
+                    .into_iter()
+                    .map(|x| offset + x.start..offset + x.end)
+                    .collect();
+                Block::Test(case)
+            }
             Rule::EOI => break,
             _ => unreachable!(),
+        };
+
+        blocks.push(ParsedBlock {
+            span,
+            block,
+            pairs,
+            variants,
+        });
+    }
+
+    Ok(blocks)
+}
+
+/// Parses every block of a malformed file on its own, to report all errors at
+/// once instead of only the first and keep the blocks that are fine.
+fn recover(contents: &str, first: ParserError) -> ParserError {
+    let mut starts = vec![0];
+    let mut offset = 0;
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if starts_block(trimmed) {
+            starts.push(offset + line.len() - trimmed.len());
+        }
+        offset += line.len();
+    }
+    starts.push(contents.len());
+    starts.dedup();
+
+    let mut blocks = Vec::new();
+    let mut errors = Vec::new();
+    for chunk in starts.windows(2) {
+        let chunk = chunk[0]..chunk[1];
+        match parse_blocks(Rule::blocks, &contents[chunk.clone()], chunk.start) {
+            Ok(parsed) => blocks.extend(parsed),
+            Err(_) => errors.extend(chunk_error(contents, chunk)),
         }
     }
 
-    Ok(db)
+    if errors.is_empty() {
+        first
+    } else {
+        ParserError::Recovered(errors, Arc::new(DB::from_blocks(&blocks)))
+    }
+}
+
+fn starts_block(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    match words.next() {
+        // A test names its strategy right after the keyword, like `тест: байес`.
+        Some(word) if BLOCK_KEYWORDS.contains(&word.trim_end_matches(':')) => true,
+        Some(word) => word.bytes().all(|x| x.is_ascii_digit()) && words.next() == Some("если"),
+        None => false,
+    }
+}
+
+/// Syntax error of the blocks in `chunk` of `contents`, positioned in the
+/// whole of `contents`.
+fn chunk_error(contents: &str, chunk: Range<usize>) -> Option<SyntaxError> {
+    let err = LangParser::parse(Rule::blocks, &contents[chunk.clone()]).err()?;
+    let (line, column) = match err.line_col {
+        LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+    };
+    let before = &contents[..chunk.start];
+    let column = if line == 1 {
+        column
+            + before
+                .rsplit('\n')
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .count()
+    } else {
+        column
+    };
+
+    Some((
+        Arc::new(err.variant.message().into_owned()),
+        (before.matches('\n').count() + line, column),
+    ))
+}
+
+/// Parses a source block by block instead of building the pest tree of the
+/// whole of it, so memory stays bounded by the largest block. A block that
+/// fails to parse is reported and skipped.
+pub struct BlockStream<'a> {
+    contents: &'a str,
+    position: usize,
+    parsed: std::vec::IntoIter<ParsedBlock>,
+}
+
+impl<'a> BlockStream<'a> {
+    pub fn new(contents: &'a str) -> Self {
+        Self {
+            contents,
+            position: 0,
+            parsed: Vec::new().into_iter(),
+        }
+    }
+
+    /// Share of the source parsed so far.
+    pub fn progress(&self) -> f32 {
+        if self.contents.is_empty() {
+            return 1.0;
+        }
+        self.position as f32 / self.contents.len() as f32
+    }
+
+    /// Start of the first line after the one at `from` that starts a block.
+    fn next_start(&self, from: usize) -> usize {
+        let mut lines = self.contents[from..].split_inclusive('\n');
+        let mut offset = from + lines.next().map_or(0, str::len);
+        for line in lines {
+            let trimmed = line.trim_start();
+            if starts_block(trimmed) {
+                return offset + line.len() - trimmed.len();
+            }
+            offset += line.len();
+        }
+        self.contents.len()
+    }
+}
+
+impl Iterator for BlockStream<'_> {
+    type Item = Result<ParsedBlock, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(block) = self.parsed.next() {
+                return Some(Ok(block));
+            }
+            if self.position >= self.contents.len() {
+                return None;
+            }
+
+            let start = self.position;
+            let mut end = self.next_start(start);
+            let mut parsed = parse_blocks(Rule::blocks, &self.contents[start..end], start);
+            // Text of a block may have a line beginning with a keyword, which
+            // splits the block in two; the halves are tried together.
+            if parsed.is_err() && end < self.contents.len() {
+                let joined = self.next_start(end);
+                if let Ok(blocks) = parse_blocks(Rule::blocks, &self.contents[start..joined], start)
+                {
+                    end = joined;
+                    parsed = Ok(blocks);
+                }
+            }
+            self.position = end;
+
+            match parsed {
+                Ok(blocks) => self.parsed = blocks.into_iter(),
+                Err(_) => {
+                    let error = chunk_error(self.contents, start..end).unwrap_or_else(|| {
+                        (
+                            Arc::new("invalid block".to_string()),
+                            schema::line_col(self.contents, start),
+                        )
+                    });
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+/// Same as [`parse_with_cache`], parsing through [`BlockStream`] and calling
+/// `progress` with the share of `contents` parsed after each block. Sources
+/// with errors are parsed again as a whole for the usual error report.
+pub fn parse_streaming(
+    contents: &str,
+    mut progress: impl FnMut(f32),
+) -> Result<(ParseCache, DB), ParserError> {
+    let mut stream = BlockStream::new(contents);
+    let mut blocks = Vec::new();
+    while let Some(block) = stream.next() {
+        let Ok(block) = block else {
+            return parse_with_cache(contents);
+        };
+        blocks.push(block);
+        progress(stream.progress());
+    }
+    if blocks.is_empty() || !separated(contents, &blocks) {
+        return parse_with_cache(contents);
+    }
+
+    let db = DB::from_blocks(&blocks);
+    Ok((
+        ParseCache {
+            source: contents.to_string(),
+            blocks,
+        },
+        db,
+    ))
+}
+
+/// Checks that reused and reparsed blocks are separated the same way a full
+/// parse would separate them: only whitespace between blocks, and a line
+/// break after free-text blocks, since their text may contain spaces.
+fn separated(contents: &str, blocks: &[ParsedBlock]) -> bool {
+    let mut position = 0;
+
+    for block in blocks {
+        let Some(gap) = contents.get(position..block.span.start) else {
+            return false;
+        };
+        if !gap.chars().all(|x| matches!(x, ' ' | '\n' | '\t' | '\r')) {
+            return false;
+        }
+
+        let ends_with_text = match &block.block {
+            Block::Advice(..) | Block::Change(..) | Block::Tip(..) => true,
+            Block::Entry(_, entry) => {
+                entry.provenance.author.is_some()
+                    || entry.provenance.source.is_some()
+                    || entry.note.is_some()
+            }
+            _ => false,
+        };
+        position = block.span.end;
+
+        let next_start = contents[position..]
+            .find(|x: char| !matches!(x, ' ' | '\n' | '\t' | '\r'))
+            .map_or(contents.len(), |x| position + x);
+        if ends_with_text
+            && next_start != contents.len()
+            && !contents[position..next_start].contains(['\n', '\t', '\r'])
+        {
+            return false;
+        }
+    }
+
+    contents[position..]
+        .chars()
+        .all(|x| matches!(x, ' ' | '\n' | '\t' | '\r'))
+}
+
+fn common_prefix(a: &str, b: &str) -> usize {
+    let mut len = a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count();
+    while !a.is_char_boundary(len) || !b.is_char_boundary(len) {
+        len -= 1;
+    }
+    len
+}
+
+fn common_suffix(a: &str, b: &str) -> usize {
+    let mut len = a
+        .bytes()
+        .rev()
+        .zip(b.bytes().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    while !a.is_char_boundary(a.len() - len) || !b.is_char_boundary(b.len() - len) {
+        len -= 1;
+    }
+    len
+}
+
+fn shift(position: usize, delta: isize) -> usize {
+    (position as isize + delta) as usize
 }
 
 impl DB {
+    pub fn from_blocks(blocks: &[ParsedBlock]) -> Self {
+        let mut db = DB::new();
+
+        // Aliases apply to the whole base, so they are known before any
+        // entry is normalized.
+        for parsed in blocks {
+            if let Block::Alias(alias, value) = &parsed.block {
+                db.aliases.insert(alias.clone(), value.clone());
+            }
+        }
+
+        for parsed in blocks {
+            match &parsed.block {
+                Block::Entry(_, entry) => db.push_entry(entry.clone()),
+                Block::Advice(category, question) => {
+                    db.questions.insert(category.clone(), question.clone());
+                }
+                Block::Change(category, None, text) => {
+                    db.changes.insert(category.clone(), text.clone());
+                }
+                Block::Change(category, Some(value), text) => {
+                    let value = db.canonical(value).clone();
+                    db.value_changes
+                        .entry(category.clone())
+                        .or_default()
+                        .insert(value, text.clone());
+                }
+                Block::Tip(category, None, text) => {
+                    db.tips.insert(category.clone(), text.clone());
+                }
+                Block::Tip(category, Some(value), text) => {
+                    let value = db.canonical(value).clone();
+                    db.value_tips
+                        .entry(category.clone())
+                        .or_default()
+                        .insert(value, text.clone());
+                }
+                Block::Subtype(value, parent) => {
+                    db.taxonomy
+                        .insert(db.canonical(value).clone(), db.canonical(parent).clone());
+                }
+                Block::Alias(..) => {}
+                Block::Domain(category, values) => {
+                    db.schema.allowed.insert(category.clone(), values.clone());
+                }
+                Block::MultiValued(category) => {
+                    db.schema.multi_valued.insert(category.clone());
+                }
+                Block::Target(category) => db.target = Some(category.clone()),
+                Block::Lock(hash) => db.lock = Some(hash.clone()),
+                Block::Prior(category, value, probability) => {
+                    let value = db.canonical(value).clone();
+                    db.priors
+                        .entry(category.clone())
+                        .or_default()
+                        .insert(value, *probability);
+                }
+                Block::Test(case) => {
+                    let mut case = case.clone();
+                    for value in case.expected.iter_mut() {
+                        *value = db.canonical(value).clone();
+                    }
+                    db.tests.push(case);
+                }
+            }
+
+            if let Some(mut key) = TextKey::of(&parsed.block) {
+                if let TextKey::Change(_, Some(value)) | TextKey::Tip(_, Some(value)) = &mut key {
+                    *value = db.canonical(value).clone();
+                }
+                if !parsed.variants.is_empty() {
+                    db.variants.insert(key, parsed.variants.clone());
+                }
+            }
+        }
+
+        db
+    }
+
     pub fn new() -> Self {
         Self {
             entries: Vec::new(),
-            categories: HashMap::new(),
-            questions: HashMap::new(),
-            changes: HashMap::new(),
-            tips: HashMap::new(),
+            categories: IndexMap::new(),
+            questions: IndexMap::new(),
+            changes: IndexMap::new(),
+            tips: IndexMap::new(),
+            value_changes: IndexMap::new(),
+            value_tips: IndexMap::new(),
+            variants: IndexMap::new(),
+            taxonomy: IndexMap::new(),
+            aliases: IndexMap::new(),
+            schema: Schema::default(),
+            target: None,
+            lock: None,
+            priors: IndexMap::new(),
+            tests: Vec::new(),
+            index: HashMap::new(),
+            ignored: HashMap::new(),
+            conclusions: HashMap::new(),
+            spellings: HashMap::new(),
+            language: None,
+        }
+    }
+
+    /// Copy of the base with categories and values that differ only in case
+    /// or whitespace spelled the way they first appear.
+    pub fn normalized(&self) -> DB {
+        let mut db = self.clone();
+        let mut spellings = std::mem::take(&mut db.spellings);
+        let mut spell = |name: &mut String| {
+            *name = spellings
+                .entry(name_key(name))
+                .or_insert_with(|| name.split_whitespace().collect::<Vec<_>>().join(" "))
+                .clone();
+        };
+        let respell = |names: IndexMap<String, String>, spell: &mut dyn FnMut(&mut String)| {
+            names
+                .into_iter()
+                .map(|(mut key, mut value)| {
+                    spell(&mut key);
+                    spell(&mut value);
+                    (key, value)
+                })
+                .collect::<IndexMap<_, _>>()
+        };
+
+        for entry in db.entries.iter_mut() {
+            for condition in entry.conditions.iter_mut() {
+                spell(&mut condition.category);
+                spell(&mut condition.value);
+            }
+            entry.ignored.iter_mut().for_each(&mut spell);
+            spell(&mut entry.category);
+            spell(&mut entry.value);
+        }
+        db.taxonomy = respell(std::mem::take(&mut db.taxonomy), &mut spell);
+        db.aliases = respell(std::mem::take(&mut db.aliases), &mut spell);
+
+        macro_rules! respell_keys {
+            ($map: expr) => {
+                $map = std::mem::take(&mut $map)
+                    .into_iter()
+                    .map(|(mut key, value)| {
+                        spell(&mut key);
+                        (key, value)
+                    })
+                    .collect();
+            };
+        }
+        respell_keys!(db.questions);
+        respell_keys!(db.changes);
+        respell_keys!(db.tips);
+        respell_keys!(db.schema.allowed);
+        for values in db.schema.allowed.values_mut() {
+            values.iter_mut().for_each(&mut spell);
+            let mut seen = HashSet::new();
+            values.retain(|x| seen.insert(x.clone()));
+        }
+        db.schema.multi_valued = std::mem::take(&mut db.schema.multi_valued)
+            .into_iter()
+            .map(|mut category| {
+                spell(&mut category);
+                category
+            })
+            .collect();
+        db.target.iter_mut().for_each(&mut spell);
+        respell_keys!(db.priors);
+        for priors in db.priors.values_mut() {
+            respell_keys!(*priors);
+        }
+        respell_keys!(db.value_changes);
+        for changes in db.value_changes.values_mut() {
+            respell_keys!(*changes);
+        }
+        respell_keys!(db.value_tips);
+        for tips in db.value_tips.values_mut() {
+            respell_keys!(*tips);
+        }
+        db.variants = std::mem::take(&mut db.variants)
+            .into_iter()
+            .map(|(mut key, variants)| {
+                match &mut key {
+                    TextKey::Question(category)
+                    | TextKey::Change(category, None)
+                    | TextKey::Tip(category, None) => spell(category),
+                    TextKey::Change(category, Some(value))
+                    | TextKey::Tip(category, Some(value)) => {
+                        spell(category);
+                        spell(value);
+                    }
+                }
+                (key, variants)
+            })
+            .collect();
+        for case in db.tests.iter_mut() {
+            case.target.iter_mut().for_each(&mut spell);
+            case.answers = respell(std::mem::take(&mut case.answers), &mut spell);
+            respell_keys!(case.degrees);
+            case.expected.iter_mut().for_each(&mut spell);
+        }
+
+        db.spellings = spellings;
+        db.rebuild();
+        db
+    }
+
+    /// How a [normalized](DB::normalized) base spells `name`; other bases
+    /// take names as they are.
+    pub fn spelling<'a>(&'a self, name: &'a str) -> &'a str {
+        if self.spellings.is_empty() {
+            return name;
+        }
+        self.spellings
+            .get(&name_key(name))
+            .map_or(name, String::as_str)
+    }
+
+    /// Adds an entry, replacing aliased values with their canonical values.
+    pub fn push_entry(&mut self, mut entry: Entry) {
+        let id = self.entries.len();
+
+        for condition in entry.conditions.iter_mut() {
+            condition.value = self.canonical(&condition.value).clone();
+        }
+        entry.value = self.canonical(&entry.value).clone();
+
+        for condition in entry.conditions.iter() {
+            self.add_category(&condition.category, &condition.value);
+            if entry.disabled {
+                continue;
+            }
+
+            let ids = self
+                .index
+                .entry(condition.category.clone())
+                .or_default()
+                .entry(condition.value.clone())
+                .or_default();
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        }
+        for category in entry.ignored.iter().filter(|_| !entry.disabled) {
+            let ids = self.ignored.entry(category.clone()).or_default();
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        }
+        self.add_category(&entry.category, &entry.value);
+        if !entry.disabled {
+            self.conclusions
+                .entry(entry.category.clone())
+                .or_default()
+                .push(id);
+        }
+
+        self.entries.push(entry);
+    }
+
+    /// Checks `entry` against the schema and appends it, returning its
+    /// index.
+    pub fn add_entry(&mut self, entry: Entry) -> Result<usize, EditError> {
+        self.check_entry(&entry)?;
+        self.push_entry(entry);
+        Ok(self.entries.len() - 1)
+    }
+
+    /// Removes entry `id`, shifting the entries after it, and forgets the
+    /// values only it used.
+    pub fn remove_entry(&mut self, id: usize) -> Result<Entry, EditError> {
+        if id >= self.entries.len() {
+            return Err(EditError::NoSuchEntry(id));
+        }
+
+        let entry = self.entries.remove(id);
+        self.rebuild();
+        Ok(entry)
+    }
+
+    /// Disables or enables entry `id`.
+    pub fn set_disabled(&mut self, id: usize, disabled: bool) -> Result<(), EditError> {
+        let entry = self.entries.get_mut(id).ok_or(EditError::NoSuchEntry(id))?;
+        entry.disabled = disabled;
+
+        self.rebuild();
+        Ok(())
+    }
+
+    /// Sets the question asked about `category`, returning the one it
+    /// replaces.
+    pub fn set_question(
+        &mut self,
+        category: &str,
+        question: Question,
+    ) -> Result<Option<Question>, EditError> {
+        if !self.categories.contains_key(category) {
+            return Err(EditError::UnknownCategory(category.to_string()));
+        }
+        if question.text.trim().is_empty() {
+            return Err(EditError::EmptyQuestion(category.to_string()));
+        }
+
+        Ok(self.questions.insert(category.to_string(), question))
+    }
+
+    /// Replaces value `from` of `category` with `into` everywhere in the
+    /// base, returning the number of entries changed. Both values must be
+    /// used by some entry. The merge is done on a copy, so a merged entry
+    /// breaking the schema leaves the base untouched.
+    pub fn merge_values(
+        &mut self,
+        category: &str,
+        from: &str,
+        into: &str,
+    ) -> Result<usize, EditError> {
+        for value in [from, into] {
+            let values = self.categories.get(category).map_or(&[][..], Vec::as_slice);
+            if !values.iter().any(|x| x == value) {
+                return Err(EditError::UnknownValue(
+                    category.to_string(),
+                    value.to_string(),
+                ));
+            }
+        }
+
+        let mut merged = self.clone();
+        let mut changed = Vec::new();
+        for (id, entry) in merged.entries.iter_mut().enumerate() {
+            if entry.merge_value(category, from, into) {
+                changed.push(id);
+            }
+        }
+        for case in merged.tests.iter_mut() {
+            case.merge_value(category, from, into);
+        }
+        if let Some(values) = merged.schema.allowed.get_mut(category) {
+            merge_list(values, from, into);
+        }
+        if let Some(priors) = merged.priors.get_mut(category) {
+            if let Some(prior) = priors.shift_remove(from) {
+                priors.entry(into.to_string()).or_insert(prior);
+            }
+        }
+        for value in merged.aliases.values_mut().filter(|x| *x == from) {
+            *value = into.to_string();
+        }
+        merged.taxonomy = merge_taxonomy(std::mem::take(&mut merged.taxonomy), from, into);
+        for texts in [&mut merged.value_changes, &mut merged.value_tips] {
+            if let Some(texts) = texts.get_mut(category) {
+                if let Some(text) = texts.shift_remove(from) {
+                    texts.entry(into.to_string()).or_insert(text);
+                }
+            }
+        }
+        for key in [
+            TextKey::Change(category.to_string(), Some(from.to_string())),
+            TextKey::Tip(category.to_string(), Some(from.to_string())),
+        ] {
+            let Some(variants) = merged.variants.shift_remove(&key) else {
+                continue;
+            };
+            let key = match key {
+                TextKey::Change(category, _) => TextKey::Change(category, Some(into.to_string())),
+                TextKey::Tip(category, _) => TextKey::Tip(category, Some(into.to_string())),
+                TextKey::Question(_) => unreachable!(),
+            };
+            merged.variants.entry(key).or_insert(variants);
+        }
+
+        for &id in changed.iter() {
+            merged.check_entry(&merged.entries[id])?;
+        }
+        merged.rebuild();
+        *self = merged;
+        Ok(changed.len())
+    }
+
+    /// Declarations that removing `category` would remove or change.
+    pub fn category_impact(&self, category: &str) -> CategoryImpact {
+        let mut impact = CategoryImpact {
+            question: self.questions.contains_key(category),
+            change: self.changes.contains_key(category)
+                || self.value_changes.contains_key(category),
+            tip: self.tips.contains_key(category) || self.value_tips.contains_key(category),
+            ..CategoryImpact::default()
+        };
+
+        for (id, entry) in self.entries.iter().enumerate() {
+            let kept = entry
+                .conditions
+                .iter()
+                .filter(|x| x.category != category)
+                .count()
+                + entry.ignored.iter().filter(|x| *x != category).count();
+            let used = kept < entry.conditions.len() + entry.ignored.len();
+            if entry.category == category || (used && kept == 0) {
+                impact.removed.push(id);
+            } else if used {
+                impact.trimmed.push(id);
+            }
+        }
+        impact.tests = self
+            .tests
+            .iter()
+            .filter(|case| {
+                case.target.as_deref() == Some(category) || case.answers.contains_key(category)
+            })
+            .count();
+
+        impact
+    }
+
+    /// Removes `category` from the base along with the entries concluding
+    /// it, dropping the conditions other entries have on it.
+    pub fn remove_category(&mut self, category: &str) -> Result<CategoryImpact, EditError> {
+        if !self.categories.contains_key(category) {
+            return Err(EditError::UnknownCategory(category.to_string()));
+        }
+
+        let impact = self.category_impact(category);
+        let mut id = 0;
+        self.entries.retain(|_| {
+            id += 1;
+            !impact.removed.contains(&(id - 1))
+        });
+        for entry in self.entries.iter_mut() {
+            entry.conditions.retain(|x| x.category != category);
+            entry.ignored.retain(|x| x != category);
+        }
+
+        self.questions.shift_remove(category);
+        self.changes.shift_remove(category);
+        self.tips.shift_remove(category);
+        self.value_changes.shift_remove(category);
+        self.value_tips.shift_remove(category);
+        self.variants.retain(|key, _| key.category() != category);
+        self.schema.allowed.shift_remove(category);
+        self.schema.multi_valued.shift_remove(category);
+        self.priors.shift_remove(category);
+        if self.target.as_deref() == Some(category) {
+            self.target = None;
+        }
+        self.tests.retain_mut(|case| {
+            case.answers.shift_remove(category);
+            case.degrees.shift_remove(category);
+            case.target.as_deref() != Some(category) && !case.answers.is_empty()
+        });
+
+        self.rebuild();
+        Ok(impact)
+    }
+
+    fn check_entry(&self, entry: &Entry) -> Result<(), EditError> {
+        let blank = std::iter::once((&entry.category, &entry.value))
+            .chain(entry.conditions.iter().map(|x| (&x.category, &x.value)))
+            .any(|(category, value)| category.trim().is_empty() || value.trim().is_empty());
+        if blank {
+            return Err(EditError::Blank);
         }
+        if entry
+            .conditions
+            .iter()
+            .any(|x| x.category == entry.category)
+        {
+            return Err(EditError::SelfReference(entry.category.clone()));
+        }
+
+        let violations = schema::entry_violations(self, entry);
+        if !violations.is_empty() {
+            return Err(EditError::Schema(violations));
+        }
+        Ok(())
+    }
+
+    /// Rebuilds the categories and lookup tables from the entries.
+    fn rebuild(&mut self) {
+        let entries = std::mem::take(&mut self.entries);
+        self.categories.clear();
+        self.index.clear();
+        self.ignored.clear();
+        self.conclusions.clear();
+        entries.into_iter().for_each(|entry| self.push_entry(entry));
+    }
+
+    pub(crate) fn entries_with(&self, category: &str, value: &str) -> &[usize] {
+        self.index
+            .get(category)
+            .and_then(|values| values.get(value))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Translation shown when `value` of `category` is concluded: the one of
+    /// the value, or else the one of the category.
+    pub fn change_of(&self, category: &str, value: &str) -> Option<&String> {
+        if let Some(change) = self.value_changes.get(category).and_then(|x| x.get(value)) {
+            let key = TextKey::Change(category.to_string(), Some(value.to_string()));
+            return Some(self.localized(&key, change));
+        }
+        let change = self.changes.get(category)?;
+        Some(self.localized(&TextKey::Change(category.to_string(), None), change))
+    }
+
+    /// Tip shown when `value` of `category` is concluded: the one about the
+    /// value, or else the one about the category.
+    pub fn tip_of(&self, category: &str, value: &str) -> Option<&String> {
+        if let Some(tip) = self.value_tips.get(category).and_then(|x| x.get(value)) {
+            let key = TextKey::Tip(category.to_string(), Some(value.to_string()));
+            return Some(self.localized(&key, tip));
+        }
+        let tip = self.tips.get(category)?;
+        Some(self.localized(&TextKey::Tip(category.to_string(), None), tip))
+    }
+
+    /// Text of the question about `category` in the language the base is
+    /// shown in.
+    pub fn question_text(&self, category: &str) -> Option<&String> {
+        let question = self.questions.get(category)?;
+        Some(self.localized(&TextKey::Question(category.to_string()), &question.text))
+    }
+
+    /// Variant of `text`, written for `key`, in the language the base is
+    /// shown in, falling back to `text` itself.
+    fn localized<'a>(&'a self, key: &TextKey, text: &'a String) -> &'a String {
+        self.language
+            .as_ref()
+            .and_then(|language| self.variants.get(key)?.get(language))
+            .unwrap_or(text)
+    }
+
+    /// Shows the texts in `language` where they have a variant in it, and
+    /// as written elsewhere.
+    pub fn set_language(&mut self, language: Option<String>) {
+        self.language = language;
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Follows aliases of `value` to the canonical value. Values on an alias
+    /// cycle resolve to the smallest value of the cycle.
+    pub fn canonical<'a>(&'a self, value: &'a String) -> &'a String {
+        let mut chain = vec![value];
+
+        while let Some(next) = self.aliases.get(*chain.last().unwrap()) {
+            if let Some(start) = chain.iter().position(|x| *x == next) {
+                return chain[start..].iter().min().unwrap();
+            }
+            chain.push(next);
+        }
+
+        chain.last().unwrap()
+    }
+
+    /// Values `value` is a subtype of, from the closest parent up.
+    pub fn ancestors(&self, value: &str) -> Vec<&String> {
+        let mut ancestors: Vec<&String> = Vec::new();
+        let mut current = value;
+
+        while let Some(parent) = self.taxonomy.get(current) {
+            if parent == value || ancestors.contains(&parent) {
+                break;
+            }
+            ancestors.push(parent);
+            current = parent;
+        }
+
+        ancestors
+    }
+
+    /// Categories concluded by at least one entry, in file order.
+    pub fn conclusion_categories(&self) -> Vec<&String> {
+        self.categories
+            .keys()
+            .filter(|category| self.role(category).is_some_and(CategoryRole::is_conclusion))
+            .collect()
+    }
+
+    /// Role of `category` in the entries, `None` when no enabled entry uses
+    /// it.
+    pub fn role(&self, category: &str) -> Option<CategoryRole> {
+        match (
+            self.conclusions.contains_key(category),
+            self.index.contains_key(category),
+        ) {
+            (true, true) => Some(CategoryRole::Intermediate),
+            (true, false) => Some(CategoryRole::Conclusion),
+            (false, true) => Some(CategoryRole::Condition),
+            (false, false) => None,
+        }
+    }
+
+    /// Whether `passphrase` is the one the base is locked with; any
+    /// passphrase opens a base without a lock.
+    pub fn unlocks(&self, passphrase: &str) -> bool {
+        self.lock
+            .as_ref()
+            .is_none_or(|hash| *hash == passphrase_hash(passphrase))
+    }
+
+    /// Number of entries concluding a value of `category`.
+    pub fn conclusion_count(&self, category: &str) -> usize {
+        self.conclusions_of(category).len()
+    }
+
+    /// Entries that do not depend on `category`.
+    pub(crate) fn entries_ignoring(&self, category: &str) -> &[usize] {
+        self.ignored
+            .get(category)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn conclusions_of(&self, category: &str) -> &[usize] {
+        self.conclusions
+            .get(category)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Entries concluding a category that other entries use as a condition,
+    /// in file order.
+    pub(crate) fn intermediate_entries(&self) -> Vec<usize> {
+        let mut ids = self
+            .conclusions
+            .iter()
+            .filter(|(category, _)| self.index.contains_key(*category))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
     }
 
     fn add_category(&mut self, category: &str, value: &str) {
@@ -78,86 +1583,454 @@ impl DB {
         self.categories
             .insert(category.to_string(), vec![value.to_string()]);
     }
+}
+
+/// Parses an entry, along with the spans of its condition pairs and of its
+/// conclusion.
+fn parse_entry(entry: &mut Pairs<'_, Rule>) -> (Option<u64>, Entry, Vec<Range<usize>>) {
+    let number = entry.next().unwrap().as_str().parse::<u64>().ok();
+
+    let mut spans = Vec::new();
+    let mut conditions = Vec::new();
+    let mut ignored = Vec::new();
+    entry.next().unwrap().into_inner().for_each(|x| {
+        if x.as_rule() == Rule::dont_care {
+            ignored.push(x.into_inner().next().unwrap().as_str().to_string());
+            return;
+        }
+
+        let mut condition = x.into_inner();
+        let pair = condition.next().unwrap();
+        spans.push(pair.as_span().start()..pair.as_span().end());
+        let mut pair = pair.into_inner();
+        let category = pair.next().unwrap().as_str();
+        let value = pair.next().unwrap().as_str();
+        let mut parsed = Condition::new(category, value);
+        for pair in condition {
+            let rule = pair.as_rule();
+            let number = pair.into_inner().as_str().parse::<f64>().unwrap();
+            match rule {
+                Rule::weight => parsed.weight = number,
+                _ => parsed.probability = Some(number),
+            }
+        }
+
+        conditions.push(parsed);
+    });
+
+    let pair = entry.next().unwrap();
+    spans.push(pair.as_span().start()..pair.as_span().end());
+    let mut pair = pair.into_inner();
+    let category = pair.next().unwrap().as_str().to_string();
+    let value = pair.next().unwrap().as_str().to_string();
+
+    let mut provenance = Provenance::default();
+    let mut disabled = false;
+    let mut note = None;
+    for annotation in entry {
+        let rule = annotation.as_rule();
+        if rule == Rule::disabled {
+            disabled = true;
+            continue;
+        }
+        let text = Some(annotation.into_inner().next().unwrap().as_str().to_string());
+        match rule {
+            Rule::author => provenance.author = text,
+            Rule::reference => provenance.source = text,
+            Rule::note => note = text,
+            _ => provenance.date = text,
+        }
+    }
+
+    (
+        number,
+        Entry {
+            value,
+            category,
+            conditions,
+            ignored,
+            provenance,
+            disabled,
+            note,
+        },
+        spans,
+    )
+}
+
+/// Form of a name shared by all its spellings that differ only in case and
+/// whitespace: trimmed, with runs of whitespace made one space, lowercase.
+pub fn name_key(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Source of `cache` with the question about `category` replaced by
+/// `question`, `None` when the source declares no such question.
+pub fn set_question(cache: &ParseCache, category: &str, question: &Question) -> Option<String> {
+    let parsed = cache
+        .blocks
+        .iter()
+        .rev()
+        .find(|parsed| matches!(&parsed.block, Block::Advice(x, _) if x == category))?;
+
+    let mut block = String::new();
+    crate::formatter::format_question(&mut block, category, question);
+    crate::formatter::format_variants(&mut block, Some(&parsed.variants));
+
+    let mut contents = cache.source.clone();
+    contents.replace_range(parsed.span.clone(), block.trim_end_matches('\n'));
+    Some(contents)
+}
+
+/// SHA-256 of `passphrase` in lowercase hex, as written after `защита`.
+pub fn passphrase_hash(passphrase: &str) -> String {
+    Sha256::digest(passphrase.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Source of `cache` with `entry` appended under the next free number.
+pub fn append_entry(cache: &ParseCache, entry: &Entry) -> String {
+    let mut contents = cache.source.clone();
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    let number = cache.next_entry_number();
+    crate::formatter::format_entry(&mut contents, number as usize, entry);
+    contents
+}
+
+/// Source of `cache` with entry `id` marked as disabled or enabled, `None`
+/// when there is no such entry or it already is.
+pub fn set_entry_disabled(cache: &ParseCache, id: usize, disabled: bool) -> Option<String> {
+    const MARK: &str = "отключено";
+
+    let parsed = cache
+        .blocks
+        .iter()
+        .filter(|parsed| matches!(parsed.block, Block::Entry(..)))
+        .nth(id)?;
+    let Block::Entry(_, entry) = &parsed.block else {
+        unreachable!()
+    };
+    if entry.disabled == disabled {
+        return None;
+    }
+
+    let mut contents = cache.source.clone();
+    if disabled {
+        contents.insert_str(parsed.span.end, &format!("\n{}", MARK));
+    } else {
+        // Notes and sources may mention the word too, so the flag is found
+        // among the annotations of the entry.
+        let block = &cache.source[parsed.span.clone()];
+        let mark = LangParser::parse(Rule::entry, block)
+            .ok()?
+            .next()?
+            .into_inner()
+            .find(|pair| pair.as_rule() == Rule::disabled)?
+            .as_span();
+        let from = block[..mark.start()].trim_end().len();
+        contents.replace_range(parsed.span.start + from..parsed.span.start + mark.end(), "");
+    }
 
-    pub fn find_value(
-        &self,
-        target_category: Option<&String>,
-        query: Vec<(&String, &String)>,
-    ) -> Option<String> {
-        let mut sub_categories_to_match = Vec::new();
-
-        if let Some(target_cat) = target_category {
-            for entry in &self.entries {
-                if &entry.category == target_cat {
-                    for (cat, val) in &entry.categories {
-                        if query
-                            .iter()
-                            .any(|&(q_cat, q_val)| q_cat == cat && q_val == val)
-                        {
-                            sub_categories_to_match.push((cat.clone(), val.clone()));
-                        }
+    Some(contents)
+}
+
+/// Source of `cache` with value `from` of `category` replaced by `into`,
+/// rewriting the blocks that use it like [`DB::merge_values`] does.
+pub fn merge_values(cache: &ParseCache, category: &str, from: &str, into: &str) -> String {
+    let has_prior = |value: &str| {
+        cache.blocks.iter().any(
+            |parsed| matches!(&parsed.block, Block::Prior(x, y, _) if x == category && y == value),
+        )
+    };
+    let has_text = |change: bool, value: &str| {
+        cache.blocks.iter().any(|parsed| match &parsed.block {
+            Block::Change(x, Some(y), _) => change && x == category && y == value,
+            Block::Tip(x, Some(y), _) => !change && x == category && y == value,
+            _ => false,
+        })
+    };
+
+    let mut contents = cache.source.clone();
+    for parsed in cache.blocks.iter().rev() {
+        let mut block = String::new();
+        match &parsed.block {
+            Block::Entry(Some(number), entry) => {
+                let mut entry = entry.clone();
+                if !entry.merge_value(category, from, into) {
+                    continue;
+                }
+                crate::formatter::format_entry(&mut block, *number as usize, &entry);
+            }
+            Block::Domain(x, values) if x == category && values.iter().any(|x| x == from) => {
+                let mut values = values.clone();
+                merge_list(&mut values, from, into);
+                crate::formatter::format_domain(&mut block, category, &values);
+            }
+            Block::Subtype(value, parent) if value == from || parent == from => {
+                let has_parent = cache
+                    .blocks
+                    .iter()
+                    .any(|parsed| matches!(&parsed.block, Block::Subtype(x, _) if x == into));
+                let rename = |x: &String| {
+                    if x == from {
+                        into.to_string()
+                    } else {
+                        x.clone()
                     }
+                };
+                let (renamed, parent) = (rename(value), rename(parent));
+                if !(value == from && has_parent) && renamed != parent {
+                    crate::formatter::format_subtype(&mut block, &renamed, &parent);
+                }
+            }
+            Block::Alias(alias, value) if value == from => {
+                crate::formatter::format_alias(&mut block, alias, into);
+            }
+            Block::Prior(x, value, probability) if x == category && value == from => {
+                if !has_prior(into) {
+                    crate::formatter::format_prior(&mut block, category, into, *probability);
+                }
+            }
+            Block::Change(x, Some(value), text) if x == category && value == from => {
+                if !has_text(true, into) {
+                    crate::formatter::format_value_text(
+                        &mut block,
+                        "перевод",
+                        category,
+                        into,
+                        text,
+                    );
+                    crate::formatter::format_variants(&mut block, Some(&parsed.variants));
+                }
+            }
+            Block::Tip(x, Some(value), text) if x == category && value == from => {
+                if !has_text(false, into) {
+                    crate::formatter::format_value_text(
+                        &mut block,
+                        "подсказка",
+                        category,
+                        into,
+                        text,
+                    );
+                    crate::formatter::format_variants(&mut block, Some(&parsed.variants));
+                }
+            }
+            Block::Test(case) => {
+                let mut case = case.clone();
+                if !case.merge_value(category, from, into) {
+                    continue;
                 }
+                crate::formatter::format_test(&mut block, &case);
             }
+            _ => continue,
         }
-
-        self.entries
-            .iter()
-            .find(|entry| {
-                sub_categories_to_match.iter().all(|(sub_cat, sub_val)| {
-                    entry
-                        .categories
-                        .iter()
-                        .any(|(cat, val)| cat == sub_cat && val == sub_val)
-                })
-            })
-            .map(|entry| entry.value.clone())
+        replace_block(&mut contents, cache, parsed, &block);
     }
+
+    contents
 }
 
-fn parse_entry(entry: &mut Pairs<'_, Rule>, db: &mut DB) {
-    let _number = entry.next().unwrap().as_str().parse::<i32>().unwrap();
+/// Source of `cache` without `category`, rewriting the blocks that use it
+/// like [`DB::remove_category`] does; `None` when no entry uses it.
+pub fn remove_category(cache: &ParseCache, category: &str) -> Option<String> {
+    let (_, db) = reparse(cache, &cache.source).ok()?;
+    if !db.categories.contains_key(category) {
+        return None;
+    }
 
-    let mut pairs = Vec::<(String, String)>::new();
-    entry.next().unwrap().into_inner().for_each(|x| {
-        let mut pair = x.into_inner();
-        let category = pair.next().unwrap().as_str().to_string();
-        let value = pair.next().unwrap().as_str().to_string();
+    let mut contents = cache.source.clone();
+    for parsed in cache.blocks.iter().rev() {
+        let mut block = String::new();
+        match &parsed.block {
+            Block::Entry(number, entry) => {
+                let mut entry = entry.clone();
+                let used = entry.conditions.len() + entry.ignored.len();
+                entry.conditions.retain(|x| x.category != category);
+                entry.ignored.retain(|x| x != category);
+                let kept = entry.conditions.len() + entry.ignored.len();
+                let removed = entry.category == category || (kept < used && kept == 0);
+                if !removed {
+                    let Some(number) = number.filter(|_| kept < used) else {
+                        continue;
+                    };
+                    crate::formatter::format_entry(&mut block, number as usize, &entry);
+                }
+            }
+            Block::Advice(x, _)
+            | Block::Change(x, _, _)
+            | Block::Tip(x, _, _)
+            | Block::Domain(x, _)
+            | Block::MultiValued(x)
+            | Block::Target(x)
+            | Block::Prior(x, _, _)
+                if x == category => {}
+            Block::Test(case) => {
+                let mut case = case.clone();
+                if case.answers.shift_remove(category).is_none()
+                    && case.target.as_deref() != Some(category)
+                {
+                    continue;
+                }
+                case.degrees.shift_remove(category);
+                if case.target.as_deref() != Some(category) && !case.answers.is_empty() {
+                    crate::formatter::format_test(&mut block, &case);
+                }
+            }
+            _ => continue,
+        }
+        replace_block(&mut contents, cache, parsed, &block);
+    }
 
-        db.add_category(&category, &value);
-        pairs.push((category, value));
-    });
+    Some(contents)
+}
 
-    let mut pair = entry.next().unwrap().into_inner();
-    let category = pair.next().unwrap().as_str().to_string();
-    let value = pair.next().unwrap().as_str().to_string();
+/// Puts `block` in place of `parsed` in `contents`, which has the blocks
+/// after it already replaced; an empty `block` removes it.
+fn replace_block(contents: &mut String, cache: &ParseCache, parsed: &ParsedBlock, block: &str) {
+    // Spans of entries run over the blank lines after them, which stay
+    // unless the block is removed.
+    let end = if block.is_empty() {
+        cache.source.len() - cache.source[parsed.span.end..].trim_start().len()
+    } else {
+        parsed.span.start + cache.source[parsed.span.clone()].trim_end().len()
+    };
+    contents.replace_range(parsed.span.start..end, block.trim_end_matches('\n'));
+}
 
-    db.add_category(&category, &value);
+/// Subtypes with `from` replaced by `into`. A parent `into` already has
+/// stays, and the subtype `into` would become of itself is dropped.
+pub fn merge_taxonomy(
+    taxonomy: IndexMap<String, String>,
+    from: &str,
+    into: &str,
+) -> IndexMap<String, String> {
+    let has_parent = taxonomy.contains_key(into);
+    let rename = |value: String| {
+        if value == from {
+            into.to_string()
+        } else {
+            value
+        }
+    };
 
-    db.entries.push(Entry {
-        value,
-        category,
-        categories: pairs,
-    });
+    let mut merged = IndexMap::new();
+    for (value, parent) in taxonomy {
+        if value == from && has_parent {
+            continue;
+        }
+        let (value, parent) = (rename(value), rename(parent));
+        if value != parent {
+            merged.insert(value, parent);
+        }
+    }
+    merged
+}
+
+/// Replaces `from` with `into` in `values`, keeping `into` once.
+pub(crate) fn merge_list(values: &mut Vec<String>, from: &str, into: &str) {
+    if values.iter().any(|x| x == into) {
+        values.retain(|x| x != from);
+    } else if let Some(value) = values.iter_mut().find(|x| *x == from) {
+        *value = into.to_string();
+    }
 }
 
-fn parse_advice(advice: &mut Pairs<'_, Rule>, questions: &mut HashMap<String, String>) {
-    let category = advice.next().unwrap().as_str().to_string();
-    let question = advice.next().unwrap().as_str().to_string();
+/// Test with the spans of its answers. A category answered twice keeps the
+/// last answer.
+fn parse_test(block: &mut Pairs<'_, Rule>) -> (TestCase, Vec<Range<usize>>) {
+    let mut next = block.next().unwrap();
+    let strategy = match next.as_rule() {
+        Rule::test_strategy => {
+            let keyword = next.as_str();
+            next = block.next().unwrap();
+            strategy::by_keyword(keyword).map(|strategy| strategy.name().to_string())
+        }
+        _ => None,
+    };
+
+    let mut spans = Vec::new();
+    let answers = next
+        .into_inner()
+        .map(|pair| {
+            spans.push(pair.as_span().start()..pair.as_span().end());
+            let mut pair = pair.into_inner();
+            let category = pair.next().unwrap().as_str().to_string();
+            let value = pair.next().unwrap().as_str().to_string();
+            (category, value)
+        })
+        .collect();
 
-    questions.insert(category, question);
+    let mut expected = block.next().unwrap().into_inner();
+    let target = expected.next().unwrap().as_str().to_string();
+    let expected = expected
+        .map(|value| value.as_str().to_string())
+        .collect::<Vec<_>>();
+
+    let case = TestCase {
+        name: String::new(),
+        target: Some(target),
+        answers,
+        degrees: IndexMap::new(),
+        find_all: expected.len() > 1,
+        strategy,
+        threshold: None,
+        expected,
+    };
+    (case, spans)
 }
 
-fn parse_change(change: &mut Pairs<'_, Rule>, changes: &mut HashMap<String, String>) {
-    let category = change.next().unwrap().as_str().to_string();
-    let text = change.next().unwrap().as_str().to_string();
+fn parse_advice(block: &mut Pairs<'_, Rule>) -> (String, Question, IndexMap<String, String>) {
+    let category = block.next().unwrap().as_str().to_string();
+    let mut question = Question::default();
+    let mut variants = IndexMap::new();
+
+    for pair in block {
+        match pair.as_rule() {
+            Rule::order => question.order = pair.as_str().parse().ok(),
+            Rule::group => question.group = Some(pair.as_str().to_string()),
+            Rule::kind => question.kind = QuestionKind::from_keyword(pair.as_str()).unwrap(),
+            _ => {
+                let mut text = pair.into_inner();
+                question.text = text.next().unwrap().as_str().to_string();
+                variants = parse_variants(text);
+            }
+        }
+    }
 
-    changes.insert(category, text);
+    (category, question, variants)
 }
 
-fn parse_tip(change: &mut Pairs<'_, Rule>, tips: &mut HashMap<String, String>) {
-    let category = change.next().unwrap().as_str().to_string();
-    let text = change.next().unwrap().as_str().to_string();
+fn parse_text(
+    block: &mut Pairs<'_, Rule>,
+) -> (String, Option<String>, String, IndexMap<String, String>) {
+    let category = block.next().unwrap().as_str().to_string();
+    let mut value = None;
+    let mut text = block.next().unwrap();
+    if text.as_rule() == Rule::text_value {
+        value = Some(text.into_inner().next().unwrap().as_str().to_string());
+        text = block.next().unwrap();
+    }
+
+    (
+        category,
+        value,
+        text.as_str().to_string(),
+        parse_variants(block),
+    )
+}
 
-    tips.insert(category, text);
+fn parse_variants<'a>(variants: impl Iterator<Item = Pair<'a, Rule>>) -> IndexMap<String, String> {
+    variants
+        .map(|variant| {
+            let mut variant = variant.into_inner();
+            let language = variant.next().unwrap().as_str().to_string();
+            (language, variant.next().unwrap().as_str().to_string())
+        })
+        .collect()
 }
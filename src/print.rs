@@ -0,0 +1,236 @@
+//! Consultation reports laid out on A4 pages of a PDF for printing. The
+//! bundled font is embedded so Cyrillic prints without fonts installed.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, UnicodeCmap};
+use pdf_writer::{Content, Name, Pdf, Rect, Ref, Str};
+use ttf_parser::Face;
+
+use crate::settings::BUNDLED_FONT;
+
+/// A4 in points.
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const FONT: Name = Name(b"F1");
+
+const SYSTEM_INFO: SystemInfo = SystemInfo {
+    registry: Str(b"Adobe"),
+    ordering: Str(b"Identity"),
+    supplement: 0,
+};
+
+/// Line of text placed on a page, its glyphs encoded for the font.
+struct Line {
+    glyphs: Vec<u8>,
+    size: f32,
+    y: f32,
+}
+
+struct Layout<'a> {
+    face: Face<'a>,
+    /// Glyphs used, with the character each stands for.
+    used: BTreeMap<u16, char>,
+    pages: Vec<Vec<Line>>,
+    y: f32,
+}
+
+impl Layout<'_> {
+    fn width(&self, text: &str, size: f32) -> f32 {
+        text.chars()
+            .map(|c| self.advance(self.glyph(c)))
+            .sum::<f32>()
+            * size
+    }
+
+    fn glyph(&self, c: char) -> u16 {
+        self.face.glyph_index(c).map_or(0, |glyph| glyph.0)
+    }
+
+    /// Advance of `glyph` as a share of the font size.
+    fn advance(&self, glyph: u16) -> f32 {
+        let advance = self
+            .face
+            .glyph_hor_advance(ttf_parser::GlyphId(glyph))
+            .unwrap_or(0);
+        f32::from(advance) / f32::from(self.face.units_per_em())
+    }
+
+    fn skip(&mut self, height: f32) {
+        self.y -= height;
+        if self.y < MARGIN {
+            self.pages.push(Vec::new());
+            self.y = PAGE_HEIGHT - MARGIN - height;
+        }
+    }
+
+    /// Places `text` wrapped at word boundaries to the width of the page.
+    fn paragraph(&mut self, text: &str, size: f32) {
+        let mut line = String::new();
+        for word in text.split(' ') {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if !line.is_empty() && self.width(&candidate, size) > PAGE_WIDTH - 2.0 * MARGIN {
+                self.line(&line, size);
+                line = word.to_string();
+            } else {
+                line = candidate;
+            }
+        }
+        self.line(&line, size);
+    }
+
+    fn line(&mut self, text: &str, size: f32) {
+        self.skip(size * 1.4);
+        let mut glyphs = Vec::with_capacity(text.len() * 2);
+        for c in text.chars() {
+            let glyph = self.glyph(c);
+            self.used.entry(glyph).or_insert(c);
+            glyphs.extend(glyph.to_be_bytes());
+        }
+
+        let y = self.y;
+        if let Some(page) = self.pages.last_mut() {
+            page.push(Line { glyphs, size, y });
+        }
+    }
+}
+
+/// Renders the Markdown `report` of a consultation as a PDF, with headings
+/// set larger and long lines wrapped.
+pub fn render(report: &str) -> Vec<u8> {
+    let face = Face::parse(BUNDLED_FONT, 0).expect("bundled font is valid");
+    let mut layout = Layout {
+        face,
+        used: BTreeMap::new(),
+        pages: vec![Vec::new()],
+        y: PAGE_HEIGHT - MARGIN,
+    };
+
+    for line in report.lines() {
+        if let Some(heading) = line.strip_prefix("# ") {
+            layout.paragraph(heading, 18.0);
+        } else if let Some(heading) = line.strip_prefix("## ") {
+            layout.skip(6.0);
+            layout.paragraph(heading, 14.0);
+        } else if line.is_empty() {
+            layout.skip(5.0);
+        } else {
+            layout.paragraph(line, 11.0);
+        }
+    }
+
+    write(&layout)
+}
+
+fn write(layout: &Layout) -> Vec<u8> {
+    let catalog_id = Ref::new(1);
+    let tree_id = Ref::new(2);
+    let font_id = Ref::new(3);
+    let cid_id = Ref::new(4);
+    let descriptor_id = Ref::new(5);
+    let file_id = Ref::new(6);
+    let cmap_id = Ref::new(7);
+    let page_ids = (0..layout.pages.len())
+        .map(|i| (Ref::new(8 + 2 * i as i32), Ref::new(9 + 2 * i as i32)))
+        .collect::<Vec<_>>();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(tree_id);
+    let mut tree = pdf.pages(tree_id);
+    tree.kids(page_ids.iter().map(|&(page, _)| page))
+        .count(page_ids.len() as i32);
+    tree.resources().fonts().pair(FONT, font_id);
+    drop(tree);
+
+    for (lines, &(page_id, content_id)) in layout.pages.iter().zip(page_ids.iter()) {
+        let mut page = pdf.page(page_id);
+        page.parent(tree_id)
+            .media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+            .contents(content_id);
+        drop(page);
+
+        let mut content = Content::new();
+        for line in lines {
+            content
+                .begin_text()
+                .set_font(FONT, line.size)
+                .next_line(MARGIN, line.y)
+                .show(Str(&line.glyphs))
+                .end_text();
+        }
+        pdf.stream(content_id, &content.finish());
+    }
+
+    let face = &layout.face;
+    let scale = |units: i16| f32::from(units) * 1000.0 / f32::from(face.units_per_em());
+    let name = Name(b"NotoSans-Regular");
+
+    pdf.type0_font(font_id)
+        .base_font(name)
+        .encoding_predefined(Name(b"Identity-H"))
+        .descendant_font(cid_id)
+        .to_unicode(cmap_id);
+
+    let mut cid = pdf.cid_font(cid_id);
+    cid.subtype(CidFontType::Type2)
+        .base_font(name)
+        .system_info(SYSTEM_INFO)
+        .font_descriptor(descriptor_id)
+        .cid_to_gid_map_predefined(Name(b"Identity"));
+    let mut widths = cid.widths();
+    for &glyph in layout.used.keys() {
+        widths.consecutive(glyph, [layout.advance(glyph) * 1000.0]);
+    }
+    drop(widths);
+    drop(cid);
+
+    let bbox = face.global_bounding_box();
+    pdf.font_descriptor(descriptor_id)
+        .name(name)
+        .flags(FontFlags::NON_SYMBOLIC)
+        .bbox(Rect::new(
+            scale(bbox.x_min),
+            scale(bbox.y_min),
+            scale(bbox.x_max),
+            scale(bbox.y_max),
+        ))
+        .italic_angle(0.0)
+        .ascent(scale(face.ascender()))
+        .descent(scale(face.descender()))
+        .cap_height(scale(face.capital_height().unwrap_or(face.ascender())))
+        .stem_v(80.0)
+        .font_file2(file_id);
+    pdf.stream(file_id, BUNDLED_FONT)
+        .pair(Name(b"Length1"), BUNDLED_FONT.len() as i32);
+
+    let mut cmap = UnicodeCmap::new(Name(b"Custom"), SYSTEM_INFO);
+    for (&glyph, &c) in layout.used.iter() {
+        cmap.pair(glyph, c);
+    }
+    pdf.cmap(cmap_id, &cmap.finish());
+
+    pdf.finish()
+}
+
+/// Writes the rendered report to a temporary file and opens it in the
+/// system viewer, which offers preview and printing.
+pub async fn print(report: String) -> std::io::Result<PathBuf> {
+    tokio::task::spawn_blocking(move || {
+        let name = format!(
+            "consultation-{}.pdf",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        );
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, render(&report))?;
+        open::that_detached(&path)?;
+        Ok(path)
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
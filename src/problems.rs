@@ -0,0 +1,42 @@
+//! Findings of the checks of the opened base, each leading to the block it
+//! is about in the editor.
+
+use iced::{
+    theme,
+    widget::{button, column, row, scrollable, text, Column},
+    Element, Length,
+};
+
+use crate::{a11y::Accessible, lint::Finding, main_window::Message};
+
+pub fn view(findings: &[Finding], editable: bool) -> Element<'_, Message> {
+    if findings.is_empty() {
+        return text("Проблем не найдено").into();
+    }
+
+    let list = findings
+        .iter()
+        .fold(Column::new().spacing(5), |column, finding| {
+            let (line, col) = finding.warning.position;
+            column.push(
+                row![
+                    button(text(format!("{}:{}", line, col)))
+                        .on_press_maybe(editable.then_some(Message::JumpToSource((line, col))))
+                        .style(theme::Button::Secondary)
+                        .width(80)
+                        .named(format!("problem-{}-{}", line, col)),
+                    text(finding.check.to_string()).width(220),
+                    text(&finding.warning.message).width(Length::Fill),
+                ]
+                .spacing(10),
+            )
+        });
+
+    column![
+        text(format!("Найдено проблем: {}", findings.len())).size(20),
+        scrollable(list)
+    ]
+    .spacing(10)
+    .padding(5)
+    .into()
+}
@@ -1,100 +1,1346 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::{BuildHasher, Hasher, RandomState},
+    path::Path,
+    sync::Arc,
+};
+
+use chrono::{DateTime, Local};
+use indexmap::IndexMap;
 
 use iced::{
-    widget::{button, column, combo_box, text, Column},
-    Element,
+    theme,
+    widget::{
+        button, checkbox, column, combo_box, container, row, scrollable, slider, text, toggler,
+        Column,
+    },
+    Color, Element, Length,
 };
 
-use crate::{main_window::Message, parser::DB};
+use crate::{
+    a11y::{self, Accessible},
+    audit::{self, AuditLog, Consultation},
+    cases::TestCase,
+    engine::Budget,
+    generation::{Change, Subscriber},
+    main_window::{tab_scrollable, Message, Tabs},
+    parser::{CategoryRole, Condition, Entry, Question, QuestionKind, DB},
+    rich_text,
+    strategy::{Conclusion, QueryResult},
+};
 
-#[derive(Debug)]
-pub struct Questions {
-    pub db: Arc<DB>,
-    pub is_searching: bool,
+/// Value offered by an answer selector. Typing filters by the whole label,
+/// so the group name finds every value of the group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueOption {
+    pub value: String,
+    /// Value this one is a subtype of.
+    pub group: Option<String>,
+    pub recent: bool,
+}
+
+impl fmt::Display for ValueOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value)?;
+        match (self.recent, self.group.as_ref()) {
+            (true, _) => f.write_str(" (недавно)"),
+            (false, Some(group)) => write!(f, " ({})", group),
+            (false, None) => Ok(()),
+        }
+    }
+}
 
-    pub answers: HashMap<String, (combo_box::State<String>, Option<String>)>,
-    pub result: Arc<String>,
+/// Practice session of training mode, where questions come in random order
+/// and the student guesses the conclusion before searching.
+#[derive(Debug, Default)]
+pub struct Training {
+    /// Order the questions are asked in, shuffled for every base.
+    order: Vec<String>,
+    pub guess: String,
+    pub attempts: usize,
+    pub correct: usize,
+    /// Answers given over all attempts.
+    pub answers: usize,
+    /// Whether the last guess was right, with the conclusion found.
+    pub last: Option<(bool, String)>,
+}
 
-    pub selected_category: Option<String>,
+impl Training {
+    pub fn new(db: &DB) -> Self {
+        let mut training = Self::default();
+        training.shuffle(db);
+        training
+    }
 
-    categories: combo_box::State<String>,
+    pub fn shuffle(&mut self, db: &DB) {
+        self.order = asked_questions(db)
+            .map(|(category, _)| category.clone())
+            .collect();
+        shuffle(&mut self.order);
+    }
+
+    fn ordered<'a>(&self, db: &'a DB) -> Vec<(&'a String, &'a Question)> {
+        self.order
+            .iter()
+            .filter_map(|category| db.questions.get_key_value(category))
+            .collect()
+    }
+
+    pub fn summary(&self) -> String {
+        if self.attempts == 0 {
+            return "Обучение завершено, попыток не было".to_string();
+        }
+
+        format!(
+            "Обучение завершено: верных выводов {} из {} ({:.0}%), в среднем {:.1} ответа на попытку",
+            self.correct,
+            self.attempts,
+            self.correct as f64 * 100.0 / self.attempts as f64,
+            self.answers as f64 / self.attempts as f64
+        )
+    }
 }
 
-impl Default for Questions {
-    fn default() -> Self {
-        Self {
-            db: Arc::new(DB::default()),
-            answers: HashMap::default(),
-            result: Arc::new(String::default()),
-            categories: combo_box::State::new(vec![]),
-            selected_category: None,
-            is_searching: false,
+/// A query run earlier in the session.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub time: DateTime<Local>,
+    pub target: Option<String>,
+    /// Answers with their degrees.
+    pub answers: Vec<(String, String, f64)>,
+    pub values: Vec<String>,
+    /// Entries the values found were concluded by.
+    pub entries: Vec<Entry>,
+}
+
+impl HistoryEntry {
+    /// Replaces value `from` of `category` with `into` after the values
+    /// were merged in the base.
+    pub fn merge_value(&mut self, category: &str, from: &str, into: &str) {
+        for (_, answer, _) in self.answers.iter_mut().filter(|x| x.0 == category) {
+            if answer == from {
+                *answer = into.to_string();
+            }
+        }
+        if self.entries.iter().any(|x| x.category == category) {
+            for value in self.values.iter_mut().filter(|x| *x == from) {
+                *value = into.to_string();
+            }
         }
+        for entry in self.entries.iter_mut() {
+            entry.merge_value(category, from, into);
+        }
+    }
+
+    /// Records the values found, with the entries they were concluded by.
+    pub fn conclude(&mut self, db: &DB, result: &QueryResult) {
+        self.entries = result
+            .matched
+            .iter()
+            .filter_map(|&id| db.entries.get(id))
+            .cloned()
+            .collect();
+        self.values = result.values();
     }
 }
 
+#[derive(Debug, Default)]
+pub struct Questions {
+    pub is_searching: bool,
+    pub find_all: bool,
+
+    pub answers: HashMap<String, (combo_box::State<ValueOption>, Option<String>)>,
+    /// Values recently picked in each category, latest first.
+    recent: HashMap<String, Vec<String>>,
+    /// When each current answer was given.
+    pub answered_at: HashMap<String, DateTime<Local>>,
+    /// Degree each answer holds to in fuzzy matching, or the confidence in
+    /// it in scored matching; 1 when not set.
+    pub degrees: HashMap<String, f64>,
+    pub result: Option<Arc<QueryResult>>,
+    /// Query the result was found for, as a case expecting it.
+    case: Option<TestCase>,
+    pub audit: AuditLog,
+
+    pub selected_category: Option<String>,
+    /// Unanswered questions that would narrow down the result the most.
+    pub recommended: Vec<String>,
+    /// Questions whose answer cannot change the result for the selected
+    /// category, hidden unless `show_all` is set.
+    pruned: HashSet<String>,
+    pub show_all: bool,
+    /// Values that can still lead to the selected category, for the
+    /// categories its entries restrict.
+    possible: HashMap<String, HashSet<String>>,
+    /// Categories the entries of the selected category ask about; the
+    /// other questions are not shown while it is selected.
+    referenced: Option<HashSet<String>>,
+
+    pub history: Vec<HistoryEntry>,
+    pub show_history: bool,
+    /// History entries whose matched entries are shown in the result area.
+    pub expanded: HashSet<usize>,
+    /// Query waiting for its result before it goes to the history.
+    pub pending: Option<HistoryEntry>,
+
+    /// Conclusion value of the rule to be created from the answers.
+    pub rule_value: String,
+    /// Category whose question is being reworded, with the new wording.
+    pub editing: Option<(String, String)>,
+    pub training: Option<Training>,
+    /// Score of the last finished training session.
+    pub training_summary: Option<String>,
+}
+
+/// Number of questions highlighted as recommended.
+const RECOMMENDED: usize = 3;
+/// Window width from which questions are laid out in two columns.
+pub const TWO_COLUMNS_WIDTH: f32 = 1200.0;
+/// Number of past conclusions shown under the result.
+const CONCLUSIONS: usize = 5;
+/// Width of the list of conclusion categories.
+const SIDEBAR_WIDTH: f32 = 220.0;
+/// Height the result takes before it scrolls.
+const RESULT_HEIGHT: f32 = 300.0;
+/// Number of values from which a selector offers the recently used ones
+/// first.
+const MANY_VALUES: usize = 20;
+const RECENT: usize = 5;
+
 impl Questions {
-    pub fn view(&self) -> Element<Message> {
-        if self.db.entries.is_empty() {
+    pub fn view<'a>(
+        &'a self,
+        db: &'a DB,
+        base: Option<&Path>,
+        degrees: bool,
+        width: f32,
+        editable: bool,
+    ) -> Element<'a, Message> {
+        if db.entries.is_empty() {
             return text("Нет данных").into();
         }
 
-        let find_category = combo_box(
-            &self.categories,
-            "Выберите категорию для поиска...",
-            self.selected_category.as_ref(),
-            |cat| Message::SelectedCategory(Arc::new(cat)),
+        let width = width - SIDEBAR_WIDTH;
+        let find_button = if self.is_searching {
+            button("Отменить")
+                .on_press(Message::CancelQuery)
+                .style(theme::Button::Destructive)
+                .named("cancel-query")
+        } else {
+            button("Найти")
+                .on_press(Message::FindAnswer)
+                .named("find-answer")
+        };
+
+        // Group headings are marked so a column never ends with one.
+        let mut group = None;
+        let mut questions: Vec<(bool, Element<'a, Message>)> = Vec::new();
+        let ordered = match self.training.as_ref() {
+            Some(training) => training.ordered(db),
+            None => ordered_questions(db),
+        };
+        let mut hidden = 0;
+        for (category, question) in ordered {
+            if self.selected_category.as_ref() == Some(category) {
+                continue;
+            }
+            let answered = self.answers.get(category).is_some_and(|(_, x)| x.is_some());
+            let referenced = self
+                .referenced
+                .as_ref()
+                .is_none_or(|referenced| referenced.contains(category));
+            if !answered && !referenced {
+                continue;
+            }
+            if !answered && self.pruned.contains(category) {
+                hidden += 1;
+                if !self.show_all {
+                    continue;
+                }
+            }
+
+            if let Some(name) = question
+                .group
+                .as_ref()
+                .filter(|name| self.training.is_none() && group != Some(*name))
+            {
+                questions.push((true, text(name).size(20).into()));
+            }
+            group = question.group.as_ref();
+
+            let Some(answer) = self.answer_widget(category, question.kind) else {
+                continue;
+            };
+            let title = match self.editing.as_ref() {
+                Some((editing, wording)) if editing == category => self.wording_editor(wording),
+                _ => {
+                    let wording = db.question_text(category).unwrap_or(&question.text);
+                    let title = if self.recommended.contains(category) {
+                        text(format!("★ {}", wording))
+                            .style(theme::Text::Color(Color::from_rgb(0.9, 0.7, 0.2)))
+                    } else {
+                        text(wording)
+                    };
+                    let edit = editable.then(|| {
+                        button(text("✎").size(14))
+                            .on_press(Message::EditQuestion(Arc::new(category.clone())))
+                            .style(theme::Button::Text)
+                            .named(format!("edit-question-{}", category))
+                    });
+                    row![title].push_maybe(edit).spacing(5).into()
+                }
+            };
+            let mut question = column![title, answer].spacing(3);
+            if degrees && self.answers.get(category).is_some_and(|(_, x)| x.is_some()) {
+                question = question.push(self.degree_slider(category));
+            }
+            questions.push((false, question.into()));
+        }
+        let questions = if width >= TWO_COLUMNS_WIDTH {
+            let mut split = questions.len().div_ceil(2);
+            while split > 0 && questions[split - 1].0 {
+                split -= 1;
+            }
+            let second = questions.split_off(split);
+            let column = |items: Vec<(bool, Element<'a, Message>)>| {
+                Column::with_children(items.into_iter().map(|(_, item)| item))
+                    .spacing(10)
+                    .width(Length::Fill)
+            };
+            Element::from(row![column(questions), column(second)].spacing(20))
+        } else {
+            Column::with_children(questions.into_iter().map(|(_, item)| item))
+                .spacing(10)
+                .into()
+        };
+
+        let find_all = checkbox("Все варианты", self.find_all)
+            .on_toggle(Message::FindAllToggled)
+            .named("find-all");
+        let has_answer = self
+            .result
+            .as_ref()
+            .is_some_and(|result| !result.conclusions.is_empty());
+        let copy_answer = button("Копировать ответ")
+            .on_press_maybe(has_answer.then_some(Message::CopyAnswer))
+            .named("copy-answer");
+        let copy_result = button("Копировать результат")
+            .on_press_maybe(self.result.is_some().then_some(Message::CopyResult))
+            .named("copy-result");
+        let copy_json = button("Копировать JSON")
+            .on_press_maybe(self.result.is_some().then_some(Message::CopyResultJson))
+            .named("copy-json");
+        let export_report = button("Сохранить отчёт")
+            .on_press_maybe(self.result.is_some().then_some(Message::ExportReport))
+            .named("export-report");
+        let print_report = button("Печать")
+            .on_press_maybe(self.result.is_some().then_some(Message::PrintReport))
+            .named("print-report");
+        // Cases are kept next to the knowledge base file.
+        let has_file = base.is_some();
+        let record_case = editable.then(|| {
+            button("Записать кейс")
+                .on_press_maybe((has_file && self.result.is_some()).then_some(Message::RecordCase))
+                .named("record-case")
+        });
+        let run_cases = button("Проверить кейсы")
+            .on_press_maybe(has_file.then_some(Message::RunCases))
+            .named("run-cases");
+
+        let show_all = (hidden > 0).then(|| {
+            row![
+                text(format!("Не влияют на результат: {}", hidden)).size(14),
+                checkbox("Показать все", self.show_all)
+                    .on_toggle(Message::ShowAllQuestions)
+                    .named("show-all"),
+            ]
+            .spacing(10)
+            .align_items(iced::Alignment::Center)
+        });
+
+        let recommended = self
+            .recommended
+            .iter()
+            .filter_map(|category| db.question_text(category))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let recommended = text(if recommended.is_empty() {
+            String::new()
+        } else {
+            format!("Рекомендованные вопросы: {}", recommended.join("; "))
+        });
+
+        let rule_placeholder = match self.selected_category.as_ref() {
+            Some(category) => format!("Значение «{}»...", category),
+            None => "Сначала выберите категорию...".to_string(),
+        };
+        let answers_file = row![
+            button("Экспорт ответов...")
+                .on_press(Message::ExportAnswers)
+                .named("export-answers"),
+            button("Импорт ответов...")
+                .on_press(Message::ImportAnswers)
+                .named("import-answers"),
+        ]
+        .spacing(10);
+
+        let create_rule = row![
+            a11y::input("rule-value", &rule_placeholder, &self.rule_value)
+                .on_input(Message::RuleValueChanged)
+                .width(Length::Fixed(300.0)),
+            button("Создать правило из ответов")
+                .on_press_maybe(self.rule(db).is_some().then_some(Message::CreateRule))
+                .named("create-rule"),
+        ]
+        .spacing(10);
+
+        // The questions scroll while the buttons and the result stay at the
+        // bottom.
+        let mut form = column![questions]
+            .push_maybe(show_all)
+            .spacing(10)
+            .padding([0, 15, 0, 0]);
+        if !self.history.is_empty() {
+            form = form.push(self.view_history());
+        }
+
+        let mut actions = column![
+            row![
+                find_button,
+                find_all,
+                copy_answer,
+                copy_result,
+                copy_json,
+                export_report,
+                print_report
+            ]
+            .push_maybe(record_case)
+            .push(run_cases)
+            .spacing(20),
+            row![]
+                .push_maybe(editable.then_some(create_rule))
+                .push(answers_file)
+                .spacing(20),
+        ]
+        .spacing(10);
+        if let Some(training) = self.training.as_ref() {
+            actions = actions.push(view_training(training));
+        }
+        if self.result.is_some() || !self.history.is_empty() {
+            let outcome = column![]
+                .push_maybe(
+                    self.result
+                        .as_ref()
+                        .map(|result| view_result(result, &self.consultation(db, ""), base)),
+                )
+                .push_maybe((!self.history.is_empty()).then(|| self.view_conclusions()))
+                .spacing(10);
+            actions = actions
+                .push(container(scrollable(outcome).width(Length::Fill)).max_height(RESULT_HEIGHT));
+        }
+
+        let main = column![recommended]
+            .push_maybe(self.training_summary.as_ref().map(text))
+            .push(tab_scrollable(Tabs::Questions, form).height(Length::Fill))
+            .push(
+                container(actions)
+                    .width(Length::Fill)
+                    .padding(8)
+                    .style(theme::Container::Box),
+            )
+            .spacing(10);
+
+        row![self.view_targets(db), main].spacing(15).into()
+    }
+
+    /// Conclusion categories with the number of entries concluding each; the
+    /// selected one is the target of the search.
+    fn view_targets<'a>(&'a self, db: &'a DB) -> Element<'a, Message> {
+        let style = |selected: bool| {
+            if selected {
+                theme::Button::Primary
+            } else {
+                theme::Button::Text
+            }
+        };
+
+        let all = button("Все категории")
+            .on_press(Message::ClearCategory)
+            .style(style(self.selected_category.is_none()))
+            .width(Length::Fill)
+            .named("all-categories");
+        let targets = db.conclusion_categories().into_iter().fold(
+            column![all].spacing(3),
+            |col, category| {
+                let selected = self.selected_category.as_ref() == Some(category);
+                col.push(
+                    button(text(format!(
+                        "{} ({})",
+                        category,
+                        db.conclusion_count(category)
+                    )))
+                    .on_press(Message::SelectedCategory(Arc::new(category.clone())))
+                    .style(style(selected))
+                    .width(Length::Fill)
+                    .named(format!("target-{}", category)),
+                )
+            },
         );
 
-        let find_button =
-            button("Найти").on_press_maybe((!self.is_searching).then_some(Message::FindAnswer));
+        column![
+            text("Что ищем").size(20),
+            scrollable(targets).height(Length::Fill)
+        ]
+        .spacing(10)
+        .width(Length::Fixed(SIDEBAR_WIDTH))
+        .into()
+    }
 
-        let questions = self
-            .db
-            .questions
+    /// The latest conclusions, each expandable to the entries behind it.
+    fn view_conclusions(&self) -> Element<'_, Message> {
+        let conclusions = self
+            .history
             .iter()
-            .filter(|(category, _)| match self.selected_category {
-                Some(ref cat) => category != &cat,
-                None => true,
-            })
-            .fold(Column::new().spacing(10), |column, (category, question)| {
-                let (state, selected) = self.answers.get(category).unwrap();
-                let category = category.clone();
+            .enumerate()
+            .rev()
+            .take(CONCLUSIONS)
+            .fold(Column::new().spacing(3), |col, (index, entry)| {
+                let expanded = self.expanded.contains(&index);
+                let values = if entry.values.is_empty() {
+                    "ничего не найдено".to_string()
+                } else {
+                    entry.values.join(", ")
+                };
+                let toggle = button(text(format!(
+                    "{} {} {}",
+                    if expanded { "▾" } else { "▸" },
+                    entry.time.format("%H:%M:%S"),
+                    values
+                )))
+                .on_press(Message::ConclusionToggled(index))
+                .style(theme::Button::Text)
+                .named(format!("conclusion-{}", index));
+
+                let col = col.push(toggle);
+                if !expanded {
+                    return col;
+                }
+                entry.entries.iter().fold(col, |col, matched| {
+                    col.push(text(describe_entry(matched)).size(14))
+                })
+            });
+
+        column![text("Последние выводы:").size(16), conclusions]
+            .spacing(5)
+            .into()
+    }
+
+    fn view_history(&self) -> Element<'_, Message> {
+        let toggle = button(text(format!(
+            "{} История ({})",
+            if self.show_history { "▾" } else { "▸" },
+            self.history.len()
+        )))
+        .on_press(Message::HistoryToggled)
+        .style(theme::Button::Text)
+        .named("history");
+        if !self.show_history {
+            return toggle;
+        }
+
+        let entries = self.history.iter().enumerate().rev().fold(
+            Column::new().spacing(5),
+            |column, (index, entry)| {
+                let answers = entry
+                    .answers
+                    .iter()
+                    .map(|(category, answer, degree)| match *degree {
+                        degree if degree < 1.0 => {
+                            format!("{} - {} ({:.2})", category, answer, degree)
+                        }
+                        _ => format!("{} - {}", category, answer),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let values = if entry.values.is_empty() {
+                    "ничего не найдено".to_string()
+                } else {
+                    entry.values.join(", ")
+                };
 
                 column.push(
-                    column![
-                        text(question),
-                        combo_box(state, "Ответ...", selected.as_ref(), move |val| {
-                            Message::SelectedAnswer(Arc::new(category.to_string()), Arc::new(val))
-                        })
+                    row![
+                        text(format!(
+                            "[{}] {}{} → {}",
+                            entry.time.format("%H:%M:%S"),
+                            entry
+                                .target
+                                .as_ref()
+                                .map_or(String::new(), |target| format!("{}: ", target)),
+                            answers,
+                            values
+                        ))
+                        .size(14)
+                        .width(Length::Fill),
+                        button("Повторить")
+                            .on_press(Message::RepeatQuery(index))
+                            .named(format!("repeat-query-{}", index)),
                     ]
-                    .spacing(3),
+                    .spacing(10),
                 )
-            });
+            },
+        );
+
+        column![toggle, entries].spacing(5).into()
+    }
+
+    /// The query of the last result with that result as the expected one,
+    /// `None` when the query was interrupted.
+    pub fn test_case(&self) -> Option<TestCase> {
+        self.case.clone()
+    }
+
+    /// Entry concluding the typed value of the selected category from the
+    /// current answers, `None` unless every part can be written in the DSL.
+    pub fn rule(&self, db: &DB) -> Option<Entry> {
+        let category = self.selected_category.as_ref()?;
+        let value = self.rule_value.trim();
+        if !is_identifier(value) {
+            return None;
+        }
+
+        let conditions = ordered_questions(db)
+            .into_iter()
+            .map(|(category, _)| category)
+            .chain(
+                db.categories
+                    .keys()
+                    .filter(|x| !db.questions.contains_key(*x)),
+            )
+            .filter(|x| *x != category)
+            .filter_map(|category| {
+                let answer = self.answers.get(category)?.1.as_ref()?;
+                Some(Condition::new(category, answer))
+            })
+            .collect::<Vec<_>>();
+        if conditions.is_empty() || !conditions.iter().all(|x| is_identifier(&x.value)) {
+            return None;
+        }
+
+        Some(Entry {
+            value: value.to_string(),
+            category: category.clone(),
+            conditions,
+            ..Entry::default()
+        })
+    }
+
+    /// Puts the answers and target of a past query back into the form.
+    pub fn restore(&mut self, db: &DB, index: usize) {
+        let Some(entry) = self.history.get(index).cloned() else {
+            return;
+        };
+
+        let answered = self
+            .answers
+            .iter()
+            .filter(|(_, (_, answer))| answer.is_some())
+            .map(|(category, _)| category.clone())
+            .collect::<Vec<_>>();
+        for category in answered {
+            self.set_answer(db, &category, "");
+        }
+
+        self.select_category(db, entry.target);
+        for (category, answer, degree) in entry.answers {
+            if !db.categories.contains_key(&category) {
+                continue;
+            }
+            self.set_answer(db, &category, &answer);
+            self.degrees.insert(category, degree);
+        }
+        self.refresh_recommendations(db);
+    }
+
+    fn answer_widget<'a>(
+        &'a self,
+        category: &String,
+        kind: QuestionKind,
+    ) -> Option<Element<'a, Message>> {
+        let answer = self.answers.get(category);
+        let selected = answer.and_then(|(_, selected)| selected.as_ref());
+        let id = format!("answer-{}", category);
+        let category = Arc::new(category.clone());
+        let on_answer = move |val: String| Message::SelectedAnswer(category.clone(), Arc::new(val));
+
+        Some(match kind {
+            QuestionKind::Choice => {
+                let selected = selected.map(|value| ValueOption {
+                    value: value.clone(),
+                    group: None,
+                    recent: false,
+                });
+                combo_box(&answer?.0, "Ответ...", selected.as_ref(), move |x| {
+                    on_answer(x.value)
+                })
+                .into()
+            }
+            QuestionKind::Boolean => toggler(
+                None,
+                selected.is_some_and(|x| x == QuestionKind::YES),
+                move |on| {
+                    on_answer(
+                        if on {
+                            QuestionKind::YES
+                        } else {
+                            QuestionKind::NO
+                        }
+                        .to_string(),
+                    )
+                },
+            )
+            .named(id),
+            QuestionKind::Number => {
+                a11y::input(id, "Число...", selected.map_or("", |x| x.as_str()))
+                    .on_input(on_answer)
+                    .into()
+            }
+            QuestionKind::Text => a11y::input(id, "Ответ...", selected.map_or("", |x| x.as_str()))
+                .on_input(on_answer)
+                .into(),
+        })
+    }
+
+    fn wording_editor<'a>(&'a self, wording: &'a str) -> Element<'a, Message> {
+        let valid = is_question_text(wording);
+
+        row![
+            a11y::input("question-wording", "Текст вопроса...", wording)
+                .on_input(Message::QuestionTextChanged)
+                .on_submit(Message::SaveQuestion),
+            button("Сохранить")
+                .on_press_maybe(valid.then_some(Message::SaveQuestion))
+                .named("question-save"),
+            button("Отмена")
+                .on_press(Message::CancelQuestionEdit)
+                .named("question-cancel"),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    /// The question being edited with its new wording, when the wording can
+    /// be written in the DSL.
+    pub fn edited_question(&self, db: &DB) -> Option<(String, Question)> {
+        let (category, wording) = self.editing.as_ref()?;
+        let wording = wording.trim();
+        if !is_question_text(wording) {
+            return None;
+        }
+
+        let question = Question {
+            text: wording.to_string(),
+            ..db.questions.get(category)?.clone()
+        };
+        Some((category.clone(), question))
+    }
+
+    fn degree_slider<'a>(&'a self, category: &String) -> Element<'a, Message> {
+        let degree = self.degrees.get(category).copied().unwrap_or(1.0);
+        let category = Arc::new(category.clone());
+
+        row![
+            slider(0.0..=1.0, degree, move |x| Message::DegreeChanged(
+                category.clone(),
+                x
+            ))
+            .step(0.05),
+            text(format!("{} ({:.2})", hedge(degree), degree))
+                .size(14)
+                .width(Length::Fixed(150.0)),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    /// Stores an answer; an empty answer clears it and input that is not a
+    /// number is ignored for numeric questions.
+    pub fn set_answer(&mut self, db: &DB, category: &str, answer: &str) {
+        let kind = db
+            .questions
+            .get(category)
+            .map(|question| question.kind)
+            .unwrap_or_default();
+        if kind == QuestionKind::Number && !is_partial_number(answer) {
+            return;
+        }
+
+        if kind == QuestionKind::Choice && !answer.is_empty() {
+            let recent = self.recent.entry(category.to_string()).or_default();
+            recent.retain(|x| x != answer);
+            recent.insert(0, answer.to_string());
+            recent.truncate(RECENT);
+        }
+        let options = value_options(db, category, &self.recent, self.possible.get(category));
+
+        let (state, selected) = self
+            .answers
+            .entry(category.to_string())
+            .or_insert_with(|| (combo_box::State::new(Vec::new()), None));
+        if kind == QuestionKind::Choice && !answer.is_empty() {
+            *state = combo_box::State::new(options);
+        }
+        *selected = (!answer.is_empty()).then(|| answer.to_string());
+
+        match selected {
+            Some(_) => {
+                self.answered_at.insert(category.to_string(), Local::now());
+            }
+            None => {
+                self.answered_at.remove(category);
+                // A new answer starts out certain again.
+                self.degrees.remove(category);
+            }
+        }
+        self.audit.answer(category, selected.clone());
+
+        self.refresh_recommendations(db);
+    }
 
-        let mut form = column![find_category, questions, find_button].spacing(10);
+    /// Sets the category searched for and offers only the values that can
+    /// lead to it.
+    pub fn select_category(&mut self, db: &DB, category: Option<String>) {
+        self.selected_category = category;
+        self.possible = match self.selected_category.as_ref() {
+            Some(target) => db
+                .possible_values(target)
+                .into_iter()
+                .map(|(category, values)| (category.clone(), values.into_iter().cloned().collect()))
+                .collect(),
+            None => HashMap::new(),
+        };
+        self.referenced = self.selected_category.as_ref().map(|target| {
+            db.referenced_categories(target)
+                .into_iter()
+                .cloned()
+                .collect()
+        });
 
-        if !self.result.is_empty() {
-            form = form.push(text(&self.result));
+        for category in db.categories.keys() {
+            let options = value_options(db, category, &self.recent, self.possible.get(category));
+            let (state, _) = self
+                .answers
+                .entry(category.clone())
+                .or_insert_with(|| (combo_box::State::new(Vec::new()), None));
+            *state = combo_box::State::new(options);
         }
 
-        form.into()
+        self.refresh_recommendations(db);
     }
 
-    pub fn refresh_categories(&mut self) {
-        self.selected_category = None;
+    /// Picks the questions worth asking next given the current answers.
+    pub fn refresh_recommendations(&mut self, db: &DB) {
+        let answers = self
+            .answers
+            .iter()
+            .filter_map(|(category, (_, answer))| Some((category, answer.as_ref()?)))
+            .collect();
+
+        let recommended = db
+            .next_questions(self.selected_category.as_ref(), answers, &Budget::default())
+            .into_iter()
+            .filter(|category| db.questions.contains_key(category))
+            .take(RECOMMENDED)
+            .collect();
+        self.recommended = recommended;
 
-        self.categories = combo_box::State::new(
-            self.db
+        let influential = db.influential_categories(self.selected_category.as_ref());
+        self.pruned = db
+            .questions
+            .keys()
+            .filter(|category| !influential.contains(category))
+            .cloned()
+            .collect();
+    }
+
+    /// Current answers in question order, with the result and the audit trail.
+    pub fn consultation<'a>(&'a self, db: &'a DB, source: &'a str) -> Consultation<'a> {
+        let answers = ordered_questions(db)
+            .into_iter()
+            .map(|(category, _)| category)
+            .chain(
+                db.categories
+                    .keys()
+                    .filter(|x| !db.questions.contains_key(*x)),
+            )
+            .filter_map(|category| {
+                let answer = self.answers.get(category)?.1.as_ref()?;
+                Some((category, answer, *self.answered_at.get(category)?))
+            })
+            .collect();
+
+        Consultation {
+            db,
+            source,
+            answers,
+            result: self.result.as_deref(),
+            audit: &self.audit,
+        }
+    }
+
+    /// Current answers, by category in question order, as shared in answer
+    /// set files.
+    pub fn answer_set(&self, db: &DB) -> IndexMap<String, String> {
+        self.consultation(db, "")
+            .answers
+            .into_iter()
+            .map(|(category, answer, _)| (category.clone(), answer.clone()))
+            .collect()
+    }
+
+    /// Replaces the answers with `answers`, returning the categories left
+    /// out because the base does not have them or the value.
+    pub fn apply_answer_set(&mut self, db: &DB, answers: IndexMap<String, String>) -> Vec<String> {
+        let answered = self
+            .answers
+            .iter()
+            .filter(|(_, (_, answer))| answer.is_some())
+            .map(|(category, _)| category.clone())
+            .collect::<Vec<_>>();
+        for category in answered {
+            self.set_answer(db, &category, "");
+        }
+
+        let mut skipped = Vec::new();
+        for (category, answer) in answers {
+            let kind = db
+                .questions
+                .get(&category)
+                .map(|question| question.kind)
+                .unwrap_or_default();
+            let known = db
                 .categories
-                .keys()
-                .map(|x| x.to_string())
-                .collect::<Vec<_>>(),
+                .get(&category)
+                .is_some_and(|values| match kind {
+                    QuestionKind::Choice => values.contains(db.canonical(&answer)),
+                    QuestionKind::Boolean => {
+                        [QuestionKind::YES, QuestionKind::NO].contains(&answer.as_str())
+                    }
+                    QuestionKind::Number => answer.parse::<f64>().is_ok(),
+                    QuestionKind::Text => true,
+                });
+            if known {
+                self.set_answer(db, &category, &answer);
+            } else {
+                skipped.push(category);
+            }
+        }
+
+        skipped
+    }
+
+    /// Scores the guess of the student against the conclusion found.
+    pub fn score_guess(&mut self) {
+        let (Some(training), Some(result)) = (self.training.as_mut(), self.result.as_ref()) else {
+            return;
+        };
+        let guess = std::mem::take(&mut training.guess);
+        let guess = guess.trim();
+        if guess.is_empty() {
+            return;
+        }
+
+        let right = result
+            .values()
+            .iter()
+            .any(|x| x.to_lowercase() == guess.to_lowercase());
+        training.attempts += 1;
+        training.correct += usize::from(right);
+        training.answers += self.answers.values().filter(|(_, x)| x.is_some()).count();
+        let conclusion = if result.conclusions.is_empty() {
+            "не найден".to_string()
+        } else {
+            result.values().join(", ")
+        };
+        training.last = Some((right, conclusion));
+    }
+
+    pub fn set_result(&mut self, result: QueryResult, case: Option<TestCase>) {
+        self.result = Some(Arc::new(result));
+        self.case = case;
+    }
+
+    /// Resets the form for a freshly loaded base, preselecting its target
+    /// category.
+    pub fn refresh_categories(&mut self, db: &DB) {
+        self.result = None;
+        self.case = None;
+        self.answered_at.clear();
+        self.degrees.clear();
+        self.audit.clear();
+        self.answers.clear();
+
+        if let Some(training) = self.training.as_mut() {
+            training.shuffle(db);
+        }
+
+        self.select_category(db, db.target.clone());
+    }
+}
+
+impl Subscriber for Questions {
+    /// The form is reset for another base; answers and the result are kept
+    /// while the same base is only edited.
+    fn refresh(&mut self, db: &DB, change: Change) {
+        match change {
+            Change::Replaced => self.refresh_categories(db),
+            Change::Restructured => {
+                self.answers
+                    .retain(|category, _| db.categories.contains_key(category));
+                self.answered_at
+                    .retain(|category, _| db.categories.contains_key(category));
+                self.degrees
+                    .retain(|category, _| db.categories.contains_key(category));
+                let selected = self
+                    .selected_category
+                    .clone()
+                    .filter(|category| db.categories.contains_key(category));
+                self.select_category(db, selected);
+            }
+            Change::Reworded => {}
+        }
+    }
+}
+
+/// Values of `category` grouped by the value they are a subtype of, after
+/// the recently used ones when there are many values. Only the `possible`
+/// values are offered when they are given.
+fn value_options(
+    db: &DB,
+    category: &str,
+    recent: &HashMap<String, Vec<String>>,
+    possible: Option<&HashSet<String>>,
+) -> Vec<ValueOption> {
+    let values = db
+        .categories
+        .get(category)
+        .map_or(&[][..], Vec::as_slice)
+        .iter()
+        .filter(|value| possible.is_none_or(|possible| possible.contains(*value)))
+        .collect::<Vec<_>>();
+
+    let mut groups: Vec<Option<&String>> = Vec::new();
+    let mut options = values
+        .iter()
+        .map(|&value| {
+            let group = db.taxonomy.get(value);
+            if !groups.contains(&group) {
+                groups.push(group);
+            }
+            ValueOption {
+                value: value.clone(),
+                group: group.cloned(),
+                recent: false,
+            }
+        })
+        .collect::<Vec<_>>();
+    options.sort_by_key(|option| {
+        let group = groups.iter().position(|x| *x == option.group.as_ref());
+        (option.group.is_some(), group)
+    });
+
+    if values.len() >= MANY_VALUES {
+        let recent = recent
+            .get(category)
+            .into_iter()
+            .flatten()
+            .filter(|value| values.contains(value))
+            .map(|value| ValueOption {
+                value: value.clone(),
+                group: None,
+                recent: true,
+            });
+        options.splice(0..0, recent);
+    }
+
+    options
+}
+
+/// Word describing how strongly an answer holds.
+fn hedge(degree: f64) -> &'static str {
+    match degree {
+        x if x >= 0.95 => "точно",
+        x if x >= 0.7 => "скорее",
+        x if x >= 0.4 => "возможно",
+        x if x > 0.0 => "вряд ли",
+        _ => "нет",
+    }
+}
+
+/// Whether `wording` fits the text of a question in the DSL.
+fn is_question_text(wording: &str) -> bool {
+    !wording.trim().is_empty()
+        && wording
+            .chars()
+            .all(|x| x.is_alphanumeric() || matches!(x, '-' | '_' | ' ' | '?' | '(' | ')' | '/'))
+}
+
+/// Shuffles `items` in place, seeded from the per-process random keys of
+/// the standard hasher.
+fn shuffle<T>(items: &mut [T]) {
+    let mut state = RandomState::new().build_hasher().finish() | 1;
+    for i in (1..items.len()).rev() {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        items.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+}
+
+fn describe_entry(entry: &Entry) -> String {
+    let conditions = entry
+        .conditions
+        .iter()
+        .map(|condition| format!("{} - {}", condition.category, condition.value))
+        .collect::<Vec<_>>();
+
+    format!(
+        "    если {} то {} - {}",
+        conditions.join(" и "),
+        entry.category,
+        entry.value
+    )
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|x| x.is_alphanumeric() || x == '_')
+}
+
+fn is_partial_number(input: &str) -> bool {
+    let digits = input.strip_prefix('-').unwrap_or(input);
+    digits.chars().all(|x| x.is_ascii_digit() || x == '.') && digits.matches('.').count() <= 1
+}
+
+/// Questions in the declared order, each group kept together and placed at
+/// its first ordered question; unordered questions go last, in file order.
+fn ordered_questions(db: &DB) -> Vec<(&String, &Question)> {
+    let mut group_order: HashMap<Option<&String>, u32> = HashMap::new();
+    for question in db.questions.values() {
+        let order = question.order.unwrap_or(u32::MAX);
+        group_order
+            .entry(question.group.as_ref())
+            .and_modify(|x| *x = (*x).min(order))
+            .or_insert(order);
+    }
+
+    let mut questions = asked_questions(db).collect::<Vec<_>>();
+    questions.sort_by_key(|(_, question)| {
+        let group = question.group.as_ref();
+        (
+            group_order[&group],
+            group,
+            question.order.unwrap_or(u32::MAX),
+        )
+    });
+    questions
+}
+
+/// Questions about the categories entries have conditions on; a category
+/// that is only ever concluded is searched for, not asked about.
+fn asked_questions(db: &DB) -> impl Iterator<Item = (&String, &Question)> {
+    db.questions
+        .iter()
+        .filter(|(category, _)| db.role(category).is_some_and(CategoryRole::is_condition))
+}
+
+fn view_training(training: &Training) -> Element<'_, Message> {
+    let mut view = column![row![
+        text("Ваш вывод:"),
+        a11y::input("training-guess", "Значение...", &training.guess)
+            .on_input(Message::TrainingGuessChanged)
+            .width(Length::Fixed(300.0)),
+        text(format!(
+            "Верно {} из {}",
+            training.correct, training.attempts
+        )),
+        button("Завершить обучение")
+            .on_press(Message::TrainingToggled(false))
+            .named("training-finish"),
+    ]
+    .spacing(10)
+    .align_items(iced::Alignment::Center)]
+    .spacing(5);
+
+    if let Some((right, conclusion)) = training.last.as_ref() {
+        view = view.push(if *right {
+            text("Верно!").style(theme::Text::Color(Color::from_rgb(0.3, 0.8, 0.3)))
+        } else {
+            text(format!("Неверно, правильный вывод: {}", conclusion))
+                .style(theme::Text::Color(Color::from_rgb(0.9, 0.3, 0.3)))
+        });
+    }
+
+    view.into()
+}
+
+fn view_result<'a>(
+    result: &QueryResult,
+    consultation: &Consultation<'a>,
+    base: Option<&Path>,
+) -> Element<'a, Message> {
+    let db = consultation.db;
+    let mut details = column![].spacing(5);
+    if let Some(interrupt) = result.interrupted {
+        details = details.push(
+            text(audit::interrupt_message(interrupt))
+                .style(theme::Text::Color(Color::from_rgb(0.9, 0.5, 0.2))),
         );
+    }
+
+    match result.conclusions.split_first() {
+        Some((Conclusion { category, value }, alternatives)) => {
+            if let Some(change) = db.change_of(category, value) {
+                let change = audit::fill_placeholders(change, consultation);
+                details = details.push(rich_text::view(&change, base, 16));
+            }
+            let notes = db
+                .entries
+                .iter()
+                .filter(|entry| !entry.disabled)
+                .filter(|entry| &entry.category == category && &entry.value == value)
+                .filter_map(|entry| entry.note.as_deref());
+            details = details.push(
+                notes
+                    .fold(row![text(value.clone())], |row, note| {
+                        row.push(rich_text::note(note, base))
+                    })
+                    .spacing(5),
+            );
+            if let Some(tip) = db.tip_of(category, value) {
+                let tip = audit::fill_placeholders(tip, consultation);
+                details = details.push(rich_text::view(&tip, base, 16));
+            }
+
+            if !alternatives.is_empty() {
+                let alternatives = alternatives
+                    .iter()
+                    .map(|conclusion| conclusion.value.as_str())
+                    .collect::<Vec<_>>();
+                details =
+                    details.push(text(format!("Также подходят: {}", alternatives.join(", "))));
+            }
+        }
+        None if result.interrupted.is_some() => {}
+        None => details = details.push(text("Not found.")),
+    }
+
+    if !result.derived.is_empty() {
+        details = details.push(text("Выведено из ответов:"));
+        details = result.derived.iter().fold(details, |details, &id| {
+            let entry = &db.entries[id];
+            details.push(
+                text(format!(
+                    "{} - {} (правило {})",
+                    entry.category,
+                    entry.value,
+                    id + 1
+                ))
+                .size(14),
+            )
+        });
+    }
+    for conflict in result.conflicts.iter() {
+        let entry = &db.entries[conflict.entry];
+        details = details.push(
+            text(format!(
+                "Ответ «{} - {}» противоречит правилу {}, по которому {} - {}",
+                entry.category,
+                conflict.answer,
+                conflict.entry + 1,
+                entry.category,
+                entry.value
+            ))
+            .size(14)
+            .style(theme::Text::Color(Color::from_rgb(0.9, 0.5, 0.2))),
+        );
+    }
+
+    if let Some(confidence) = result.confidence {
+        details = details.push(text(format!("Уверенность: {:.1}%", confidence * 100.0)));
+    }
+    if !result.memberships.is_empty() {
+        details = details.push(text("Степени принадлежности:"));
+        details = result
+            .memberships
+            .iter()
+            .fold(details, |details, membership| {
+                details.push(
+                    text(format!(
+                        "{} - {}: {:.2}",
+                        membership.category, membership.value, membership.degree
+                    ))
+                    .size(14),
+                )
+            });
+    }
 
-        self.db.categories.iter().for_each(|(x, y)| {
-            self.answers
-                .insert(x.to_string(), (combo_box::State::new(y.clone()), None));
+    if !result.posteriors.is_empty() {
+        details = details.push(text("Вероятности:"));
+        details = result
+            .posteriors
+            .iter()
+            .fold(details, |details, posterior| {
+                details.push(
+                    text(format!(
+                        "{} - {}: {:.1}%",
+                        posterior.category,
+                        posterior.value,
+                        posterior.probability * 100.0
+                    ))
+                    .size(14),
+                )
+            });
+    }
+
+    if let Some(score) = result.score.as_ref() {
+        let entry = &db.entries[score.entry];
+        details = details.push(text(format!("Счёт: {} из {}", score.score, score.total)));
+        details = entry.conditions.iter().zip(score.satisfied.iter()).fold(
+            details,
+            |details, (condition, &satisfied)| {
+                details.push(
+                    text(format!(
+                        "{} {} - {} ({})",
+                        if satisfied { "✓" } else { "✗" },
+                        condition.category,
+                        condition.value,
+                        condition.weight
+                    ))
+                    .size(14),
+                )
+            },
+        );
+    }
+
+    if !result.near_misses.is_empty() {
+        details = details.push(text("Почти подошло:"));
+        details = result.near_misses.iter().fold(details, |details, score| {
+            let entry = &db.entries[score.entry];
+            details.push(
+                text(format!(
+                    "{} - {}: {} из {} ({:.0}%)",
+                    entry.category,
+                    entry.value,
+                    score.score,
+                    score.total,
+                    score.share() * 100.0
+                ))
+                .size(14),
+            )
         });
     }
+
+    details.into()
 }
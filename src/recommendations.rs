@@ -0,0 +1,87 @@
+//! Translations and tips about the conclusion of the consultation and the
+//! answers it follows from, gathered into a checklist.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use iced::{
+    theme,
+    widget::{button, checkbox, column, row, text, Column},
+    Alignment, Element, Length,
+};
+
+use crate::{
+    a11y::Accessible,
+    audit::Recommendation,
+    main_window::{tab_scrollable, Message, Tabs},
+    rich_text,
+};
+
+pub fn view<'a>(
+    recommendations: Vec<Recommendation>,
+    done: &HashSet<String>,
+    base: Option<&Path>,
+) -> Element<'a, Message> {
+    if recommendations.is_empty() {
+        return text("Рекомендаций нет: найдите вывод на вкладке «Вопросы»").into();
+    }
+
+    let finished = recommendations
+        .iter()
+        .filter(|recommendation| done.contains(&recommendation.text))
+        .count();
+    let header = row![
+        text(format!(
+            "Выполнено: {} из {}",
+            finished,
+            recommendations.len()
+        ))
+        .size(20)
+        .width(Length::Fill),
+        button("Сохранить список")
+            .on_press(Message::ExportRecommendations)
+            .style(theme::Button::Secondary)
+            .named("recommendations-export"),
+    ]
+    .spacing(10)
+    .align_items(Alignment::Center);
+
+    let list =
+        recommendations
+            .into_iter()
+            .fold(Column::new().spacing(15), |column, recommendation| {
+                let checked = done.contains(&recommendation.text);
+                let details = column![
+                    text(format!(
+                        "{} - {}",
+                        recommendation.category, recommendation.value
+                    ))
+                    .size(12),
+                    rich_text::view(&recommendation.text, base, 16),
+                ]
+                .spacing(3)
+                .width(Length::Fill);
+                let id = format!(
+                    "recommendation-{}-{}",
+                    recommendation.category, recommendation.value
+                );
+                let text = recommendation.text;
+                column.push(
+                    row![
+                        checkbox("", checked)
+                            .on_toggle(move |done| Message::RecommendationToggled(
+                                text.clone(),
+                                done
+                            ))
+                            .named(id),
+                        details
+                    ]
+                    .spacing(10),
+                )
+            });
+
+    column![header, tab_scrollable(Tabs::Recommendations, list)]
+        .spacing(10)
+        .padding(5)
+        .into()
+}
@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+use iced::{
+    font::{self, Font},
+    theme,
+    widget::{button, column, container, image, text, tooltip, Row},
+    Color, Element,
+};
+
+use crate::a11y::Accessible;
+use crate::main_window::Message;
+
+/// Inline Markdown element understood by the renderer.
+#[derive(Debug, PartialEq)]
+enum Span<'a> {
+    Plain(&'a str),
+    Bold(&'a str),
+    Italic(&'a str),
+    Code(&'a str),
+    /// Label with the target it opens.
+    Link(&'a str, &'a str),
+    Image(&'a str),
+}
+
+/// Renders a line of minimal Markdown: `**bold**`, `*italic*`, `` `code` ``,
+/// `[links](url)` and `![images](path)`. Relative image paths and link
+/// targets are resolved against `base`, the directory of the knowledge base.
+pub fn view<'a>(source: &str, base: Option<&Path>, size: u16) -> Element<'a, Message> {
+    let (source, size) = match source.strip_prefix("# ") {
+        Some(heading) => (heading, size + 4),
+        None => (source, size),
+    };
+
+    let mut line = Row::new();
+    let mut images = column![].spacing(5);
+    for span in parse(source) {
+        let segment = match span {
+            Span::Plain(x) => text(x).size(size),
+            Span::Bold(x) => text(x).size(size).font(Font {
+                weight: font::Weight::Bold,
+                ..Font::DEFAULT
+            }),
+            Span::Italic(x) => text(x).size(size).font(Font {
+                style: font::Style::Italic,
+                ..Font::DEFAULT
+            }),
+            Span::Code(x) => text(x).size(size).font(Font::MONOSPACE),
+            Span::Link(label, target) => {
+                let target = if target.contains("://") || target.starts_with("mailto:") {
+                    target.to_string()
+                } else {
+                    resolve(base, target).to_string_lossy().into_owned()
+                };
+                let label = text(label)
+                    .size(size)
+                    .style(theme::Text::Color(Color::from_rgb(0.5, 0.7, 1.0)));
+                let id = format!("link-{}", target);
+                line = line.push(
+                    button(label)
+                        .padding(0)
+                        .style(theme::Button::Text)
+                        .on_press(Message::LinkOpened(target))
+                        .named(id),
+                );
+                continue;
+            }
+            Span::Image(path) => {
+                images = images.push(image(resolve(base, path)));
+                continue;
+            }
+        };
+        line = line.push(segment);
+    }
+
+    column![line, images].spacing(5).into()
+}
+
+fn parse(mut source: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+
+    while !source.is_empty() {
+        let (span, rest) = if let Some(rest) = source.strip_prefix("![") {
+            link(rest).map(|(_, target, rest)| (Span::Image(target), rest))
+        } else if let Some(rest) = source.strip_prefix('[') {
+            link(rest).map(|(label, target, rest)| (Span::Link(label, target), rest))
+        } else if let Some(rest) = source.strip_prefix("**") {
+            enclosed(rest, "**").map(|(x, rest)| (Span::Bold(x), rest))
+        } else if let Some(rest) = source.strip_prefix('*') {
+            enclosed(rest, "*").map(|(x, rest)| (Span::Italic(x), rest))
+        } else if let Some(rest) = source.strip_prefix('`') {
+            enclosed(rest, "`").map(|(x, rest)| (Span::Code(x), rest))
+        } else {
+            None
+        }
+        .unwrap_or_else(|| {
+            // Plain text runs up to the next markup character; an unmatched
+            // one is kept as plain text.
+            let end = source
+                .char_indices()
+                .skip(1)
+                .find(|(_, x)| matches!(x, '!' | '[' | '*' | '`'))
+                .map_or(source.len(), |(x, _)| x);
+            (Span::Plain(&source[..end]), &source[end..])
+        });
+
+        spans.push(span);
+        source = rest;
+    }
+
+    spans
+}
+
+/// Marker that shows `note` in a tooltip when hovered.
+pub fn note<'a>(note: &'a str, base: Option<&Path>) -> Element<'a, Message> {
+    tooltip(
+        text("ⓘ").size(16),
+        container(view(note, base, 14)).padding(8).max_width(400),
+        tooltip::Position::Bottom,
+    )
+    .style(theme::Container::Box)
+    .into()
+}
+
+fn resolve(base: Option<&Path>, path: &str) -> PathBuf {
+    match base {
+        Some(base) => base.join(path),
+        None => path.into(),
+    }
+}
+
+fn enclosed<'a>(source: &'a str, delimiter: &str) -> Option<(&'a str, &'a str)> {
+    let end = source.find(delimiter).filter(|x| *x > 0)?;
+    Some((&source[..end], &source[end + delimiter.len()..]))
+}
+
+/// Splits `label](target)rest` into its parts.
+fn link(source: &str) -> Option<(&str, &str, &str)> {
+    let (label, rest) = source.split_once("](")?;
+    let (target, rest) = rest.split_once(')')?;
+    Some((label, target, rest))
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::parser::{Block, Entry, ParseCache, DB};
+
+/// Constraints the knowledge base declares on its categories.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    /// Values a category may take, for categories declared with `значения`.
+    pub allowed: IndexMap<String, Vec<String>>,
+    /// Categories that may appear in several conditions of one entry.
+    pub multi_valued: IndexSet<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub message: String,
+    /// Line and column of the offending block.
+    pub position: (usize, usize),
+}
+
+/// Entry block that repeats or contradicts another one.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub message: String,
+    /// Lines of the block, counted from 0.
+    pub lines: Range<usize>,
+    /// Bytes of the block in the source, without the blank lines after it.
+    pub span: Range<usize>,
+}
+
+impl Schema {
+    fn allows(&self, db: &DB, category: &str, value: &String) -> bool {
+        self.allowed.get(category).is_none_or(|values| {
+            let value = db.canonical(value);
+            values.iter().any(|allowed| db.canonical(allowed) == value)
+        })
+    }
+}
+
+/// Enabled entries with the same conditions as another one, which either
+/// conclude the same (duplicates) or a different value of a single-valued
+/// category (contradictions). Both entries of a pair are reported.
+pub fn conflicts(cache: &ParseCache, db: &DB) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    let mut seen: HashMap<_, Vec<(usize, &Entry, Conflict)>> = HashMap::new();
+
+    for parsed in cache.blocks() {
+        let Block::Entry(_, entry) = &parsed.block else {
+            continue;
+        };
+        if entry.disabled {
+            continue;
+        }
+
+        // Spans run up to the next block, over the blank lines between them.
+        let source = cache.source();
+        let block = source[parsed.span.clone()].trim_end();
+        let (line, _) = line_col(source, parsed.span.start);
+        // Where the block is, for the conflicts found with it.
+        let at = Conflict {
+            message: String::new(),
+            lines: line - 1..line + block.matches('\n').count(),
+            span: parsed.span.start..parsed.span.start + block.len(),
+        };
+
+        let mut conditions = entry
+            .conditions
+            .iter()
+            .map(|condition| (&condition.category, db.canonical(&condition.value)))
+            .collect::<Vec<_>>();
+        conditions.sort_unstable();
+        let mut ignored = entry.ignored.iter().collect::<Vec<_>>();
+        ignored.sort_unstable();
+
+        let earlier = seen.entry((conditions, ignored)).or_default();
+        for (other_line, other, other_at) in earlier.iter() {
+            if other.category != entry.category {
+                continue;
+            }
+            let message = if db.canonical(&other.value) == db.canonical(&entry.value) {
+                "duplicates the entry at line"
+            } else if !db.schema.multi_valued.contains(&entry.category) {
+                "contradicts the entry at line"
+            } else {
+                continue;
+            };
+
+            conflicts.push(Conflict {
+                message: format!(
+                    "{} {}, which concludes `{} - {}`",
+                    message, other_line, other.category, other.value
+                ),
+                ..at.clone()
+            });
+            conflicts.push(Conflict {
+                message: format!(
+                    "{} {}, which concludes `{} - {}`",
+                    message, line, entry.category, entry.value
+                ),
+                ..other_at.clone()
+            });
+        }
+        earlier.push((line, entry, at));
+    }
+
+    conflicts.sort_by_key(|conflict| conflict.lines.start);
+    conflicts
+}
+
+pub(crate) fn entry_violations(db: &DB, entry: &Entry) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    let pairs = entry
+        .conditions
+        .iter()
+        .map(|condition| (&condition.category, &condition.value))
+        .chain(std::iter::once((&entry.category, &entry.value)));
+    for (category, value) in pairs {
+        if !db.schema.allows(db, category, value) {
+            violations.push(format!(
+                "value `{}` is not allowed for category `{}`",
+                value, category
+            ));
+        }
+    }
+
+    for condition in entry.conditions.iter() {
+        if condition.probability.is_some_and(|x| x > 1.0) {
+            violations.push(format!(
+                "probability of condition `{} - {}` is greater than 1",
+                condition.category, condition.value
+            ));
+        }
+    }
+
+    for category in entry.ignored.iter() {
+        if entry.conditions.iter().any(|x| &x.category == category) {
+            violations.push(format!(
+                "category `{}` is both a condition and a don't-care",
+                category
+            ));
+        }
+    }
+
+    let mut seen: Vec<&String> = Vec::new();
+    for condition in entry.conditions.iter() {
+        let category = &condition.category;
+        let repeats = seen.iter().filter(|seen| **seen == category).count();
+        if repeats == 1 && !db.schema.multi_valued.contains(category) {
+            violations.push(format!(
+                "category `{}` is single-valued but has several conditions",
+                category
+            ));
+        }
+        seen.push(category);
+    }
+
+    violations
+}
+
+pub(crate) fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before, |start| &before[start + 1..])
+        .chars()
+        .count()
+        + 1;
+
+    (line, column)
+}
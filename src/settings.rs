@@ -0,0 +1,661 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced::{
+    theme,
+    widget::{checkbox, column, pick_list, row, slider, text, toggler, Column},
+    Color, Element, Font, Length, Pixels,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    a11y::Accessible,
+    lint::Check,
+    main_window::Message,
+    strategy::{self, MatchStrategy},
+    usage::Usage,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThreadCount(pub usize);
+
+impl fmt::Display for ThreadCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => f.write_str("Авто"),
+            n => write!(f, "{}", n),
+        }
+    }
+}
+
+/// Longest a query may run, in seconds; `None` is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryTimeout(pub Option<u64>);
+
+impl QueryTimeout {
+    const ALL: [QueryTimeout; 6] = [
+        QueryTimeout(None),
+        QueryTimeout(Some(1)),
+        QueryTimeout(Some(5)),
+        QueryTimeout(Some(10)),
+        QueryTimeout(Some(30)),
+        QueryTimeout(Some(60)),
+    ];
+
+    pub fn duration(self) -> Option<Duration> {
+        self.0.map(Duration::from_secs)
+    }
+}
+
+impl fmt::Display for QueryTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            None => f.write_str("Без ограничения"),
+            Some(secs) => write!(f, "{} с", secs),
+        }
+    }
+}
+
+/// Share of the weight of its conditions, in percent, an entry must satisfy
+/// to count as a result in scored matching.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchThreshold(pub u8);
+
+impl MatchThreshold {
+    pub fn share(self) -> f64 {
+        f64::from(self.0) / 100.0
+    }
+}
+
+impl fmt::Display for MatchThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+/// Size of files, in megabytes, from which loading into the editor asks for
+/// confirmation; `None` never asks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileSizeLimit(pub Option<u64>);
+
+impl FileSizeLimit {
+    pub const MEGABYTE: u64 = 1024 * 1024;
+
+    const ALL: [FileSizeLimit; 5] = [
+        FileSizeLimit(None),
+        FileSizeLimit(Some(1)),
+        FileSizeLimit(Some(5)),
+        FileSizeLimit(Some(20)),
+        FileSizeLimit(Some(100)),
+    ];
+
+    pub fn exceeded_by(self, bytes: u64) -> bool {
+        self.0.is_some_and(|limit| bytes > limit * Self::MEGABYTE)
+    }
+}
+
+impl Default for FileSizeLimit {
+    fn default() -> Self {
+        FileSizeLimit(Some(5))
+    }
+}
+
+impl fmt::Display for FileSizeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            None => f.write_str("Не предупреждать"),
+            Some(megabytes) => write!(f, "{} МБ", megabytes),
+        }
+    }
+}
+
+/// What happens when the opened knowledge base file changes on disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReloadMode {
+    Off,
+    #[default]
+    Ask,
+    /// Reloaded right away, keeping the answers that still apply.
+    Auto,
+}
+
+impl ReloadMode {
+    const ALL: [ReloadMode; 3] = [ReloadMode::Off, ReloadMode::Ask, ReloadMode::Auto];
+}
+
+impl fmt::Display for ReloadMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReloadMode::Off => "Ничего не делать",
+            ReloadMode::Ask => "Спрашивать",
+            ReloadMode::Auto => "Перезагружать",
+        })
+    }
+}
+
+/// Interface scale, in percent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UiScale(pub u16);
+
+impl UiScale {
+    const ALL: [UiScale; 7] = [
+        UiScale(75),
+        UiScale(100),
+        UiScale(125),
+        UiScale(150),
+        UiScale(175),
+        UiScale(200),
+        UiScale(250),
+    ];
+
+    pub fn factor(self) -> f64 {
+        f64::from(self.0) / 100.0
+    }
+}
+
+impl Default for UiScale {
+    fn default() -> Self {
+        UiScale(100)
+    }
+}
+
+impl fmt::Display for UiScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+/// Default size of text, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextSize(pub u16);
+
+impl TextSize {
+    const ALL: [TextSize; 7] = [
+        TextSize(12),
+        TextSize(14),
+        TextSize(16),
+        TextSize(18),
+        TextSize(20),
+        TextSize(24),
+        TextSize(28),
+    ];
+
+    /// Default text size of the application; the renderer keeps the one it
+    /// was started with, so a new size applies after a restart.
+    pub fn pixels(self) -> Pixels {
+        Pixels(f32::from(self.0))
+    }
+}
+
+impl Default for TextSize {
+    fn default() -> Self {
+        TextSize(16)
+    }
+}
+
+impl fmt::Display for TextSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} px", self.0)
+    }
+}
+
+/// Noto Sans, bundled so Cyrillic renders the same whatever fonts the system
+/// has. Glyphs it lacks fall back to the system fonts.
+pub const BUNDLED_FONT: &[u8] = include_bytes!("../fonts/NotoSans-Regular.ttf");
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FontChoice {
+    #[default]
+    Bundled,
+    System,
+}
+
+impl FontChoice {
+    const ALL: [FontChoice; 2] = [FontChoice::Bundled, FontChoice::System];
+
+    pub fn font(self) -> Font {
+        match self {
+            FontChoice::Bundled => Font::with_name("Noto Sans"),
+            FontChoice::System => Font::DEFAULT,
+        }
+    }
+}
+
+impl fmt::Display for FontChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FontChoice::Bundled => "Встроенный (Noto Sans)",
+            FontChoice::System => "Системный",
+        })
+    }
+}
+
+/// How the time of each message is shown in the log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogTimes {
+    /// How long ago, like `2 мин назад`.
+    #[default]
+    Relative,
+    Absolute,
+}
+
+impl LogTimes {
+    const ALL: [LogTimes; 2] = [LogTimes::Relative, LogTimes::Absolute];
+}
+
+impl fmt::Display for LogTimes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogTimes::Relative => "Сколько прошло",
+            LogTimes::Absolute => "Время",
+        })
+    }
+}
+
+/// Language the questions, translations and tips of the base are shown in
+/// when it has variants of them in that language.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    Russian,
+    English,
+}
+
+impl Language {
+    const ALL: [Language; 2] = [Language::Russian, Language::English];
+
+    /// Code marking the variants in this language, as in `en:`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Russian => "ru",
+            Language::English => "en",
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Language::Russian => "Русский",
+            Language::English => "English",
+        })
+    }
+}
+
+/// Appearance settings kept between runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub scale: UiScale,
+    pub text_size: TextSize,
+    pub font: FontChoice,
+    pub language: Language,
+    pub log_times: LogTimes,
+    /// Interface scaled up further for touch screens and low vision.
+    pub large_controls: bool,
+    /// Directory the last knowledge base was opened from.
+    pub last_dir: Option<PathBuf>,
+    /// Editor and every action changing the base hidden, for end users
+    /// who only run consultations.
+    pub viewer: bool,
+    /// Answers last given with each knowledge base, by hash of its text,
+    /// least recently used first.
+    pub progress: IndexMap<String, IndexMap<String, String>>,
+    /// Degrees of the answers in `progress` given with less than full
+    /// certainty, by the same hash.
+    pub degrees: IndexMap<String, IndexMap<String, f64>>,
+    /// Statistics of consultations with each knowledge base, by its path or
+    /// address, least recently used first.
+    pub usage: IndexMap<String, Usage>,
+}
+
+/// Knowledge bases whose answers are remembered.
+const SAVED_PROGRESS: usize = 20;
+
+/// Knowledge bases whose statistics are kept.
+const SAVED_USAGE: usize = 50;
+
+impl Preferences {
+    /// Remembers `answers` and their `degrees` as the progress with the base
+    /// `key`, forgetting it when there are no answers.
+    pub fn save_progress(
+        &mut self,
+        key: &str,
+        answers: IndexMap<String, String>,
+        degrees: IndexMap<String, f64>,
+    ) {
+        self.progress.shift_remove(key);
+        self.degrees.shift_remove(key);
+        if answers.is_empty() {
+            return;
+        }
+
+        self.progress.insert(key.to_string(), answers);
+        if !degrees.is_empty() {
+            self.degrees.insert(key.to_string(), degrees);
+        }
+        while self.progress.len() > SAVED_PROGRESS {
+            if let Some((key, _)) = self.progress.shift_remove_index(0) {
+                self.degrees.shift_remove(&key);
+            }
+        }
+    }
+
+    /// Statistics of the base `key`, made the most recently used.
+    pub fn usage_mut(&mut self, key: &str) -> &mut Usage {
+        let usage = self.usage.shift_remove(key).unwrap_or_default();
+        self.usage.insert(key.to_string(), usage);
+        while self.usage.len() > SAVED_USAGE {
+            self.usage.shift_remove_index(0);
+        }
+
+        &mut self.usage[key]
+    }
+
+    /// Reads the saved preferences, falling back to the defaults when there
+    /// are none or they cannot be read.
+    pub fn load() -> Self {
+        std::fs::read_to_string(preferences_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub async fn save(self) -> std::io::Result<()> {
+        let path = preferences_path();
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let contents = serde_json::to_string_pretty(&self).map_err(std::io::Error::other)?;
+
+        tokio::fs::write(path, contents).await
+    }
+}
+
+fn preferences_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("expert_kb")
+        .join("settings.json")
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    #[default]
+    Exact,
+    Scored,
+    /// Exact matching alongside naive-Bayes posteriors.
+    Bayes,
+    /// Answers hold to a degree set by the user.
+    Fuzzy,
+}
+
+impl MatchMode {
+    const ALL: [MatchMode; 4] = [
+        MatchMode::Exact,
+        MatchMode::Scored,
+        MatchMode::Bayes,
+        MatchMode::Fuzzy,
+    ];
+
+    pub fn strategy(self) -> &'static dyn MatchStrategy {
+        match self {
+            MatchMode::Exact => &strategy::Exact,
+            MatchMode::Scored => &strategy::Scored,
+            MatchMode::Bayes => &strategy::Bayes,
+            MatchMode::Fuzzy => &strategy::Fuzzy,
+        }
+    }
+}
+
+impl fmt::Display for MatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MatchMode::Exact => "Точное совпадение",
+            MatchMode::Scored => "По весам условий",
+            MatchMode::Bayes => "Байесовский",
+            MatchMode::Fuzzy => "Нечёткая логика",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Settings {
+    pub query_threads: ThreadCount,
+    pub match_mode: MatchMode,
+    pub match_threshold: MatchThreshold,
+    pub query_timeout: QueryTimeout,
+    pub file_size_limit: FileSizeLimit,
+    pub reload_mode: ReloadMode,
+    /// Questions in random order and the explorer hidden, for students.
+    pub training: bool,
+    /// Checks run over the base when it is opened.
+    pub checks: Vec<Check>,
+    /// Names differing only in case and whitespace taken as the same.
+    pub normalize_names: bool,
+    pub preferences: Preferences,
+    /// Viewer mode asked for on the command line, which cannot be turned
+    /// off from the settings.
+    pub viewer_forced: bool,
+
+    thread_options: Vec<ThreadCount>,
+    /// Why the pool of the chosen number of threads could not be built.
+    pool_error: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let available = std::thread::available_parallelism()
+            .map(|x| x.get())
+            .unwrap_or(1);
+
+        Self {
+            query_threads: ThreadCount::default(),
+            match_mode: MatchMode::default(),
+            match_threshold: MatchThreshold::default(),
+            query_timeout: QueryTimeout::default(),
+            file_size_limit: FileSizeLimit::default(),
+            reload_mode: ReloadMode::default(),
+            training: false,
+            checks: Check::ALL.to_vec(),
+            normalize_names: false,
+            preferences: Preferences::default(),
+            viewer_forced: false,
+            thread_options: (0..=available).map(ThreadCount).collect(),
+            pool_error: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn is_viewer(&self) -> bool {
+        self.viewer_forced || self.preferences.viewer
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let threshold = (self.match_mode == MatchMode::Scored).then(|| {
+            row![
+                text(format!(
+                    "Минимальная доля совпавших условий: {}",
+                    self.match_threshold
+                ))
+                .width(Length::Fill),
+                slider(0..=100, self.match_threshold.0, |x| {
+                    Message::MatchThresholdChanged(MatchThreshold(x))
+                })
+                .step(5)
+                .width(160),
+            ]
+            .spacing(10)
+        });
+        let viewer = (!self.viewer_forced).then(|| {
+            toggler(
+                Some("Режим просмотра (без редактирования)".to_string()),
+                self.preferences.viewer,
+                Message::ViewerToggled,
+            )
+            .named("viewer")
+        });
+
+        let checks = Check::ALL.iter().fold(
+            Column::new().push(text("Проверки базы:")).spacing(5),
+            |column, &check| {
+                column.push(
+                    checkbox(check.to_string(), self.checks.contains(&check))
+                        .on_toggle(move |enabled| Message::CheckToggled(check, enabled))
+                        .named(format!("check-{:?}", check)),
+                )
+            },
+        );
+
+        let search = column![row![
+            text("Потоков для поиска:").width(Length::Fill),
+            pick_list(
+                self.thread_options.as_slice(),
+                Some(self.query_threads),
+                Message::QueryThreadsChanged,
+            )
+            .named("query-threads"),
+        ]
+        .spacing(10),]
+        .push_maybe(self.pool_error.as_ref().map(|error| {
+            text(format!("Потоки не созданы, поиск идёт в общих: {}", error))
+                .style(theme::Text::Color(Color::from_rgb(0.9, 0.4, 0.4)))
+        }))
+        .push(
+            row![
+                text("Режим поиска:").width(Length::Fill),
+                pick_list(
+                    &MatchMode::ALL[..],
+                    Some(self.match_mode),
+                    Message::MatchModeChanged,
+                )
+                .named("match-mode"),
+            ]
+            .spacing(10),
+        )
+        .push_maybe(threshold)
+        .spacing(10);
+
+        column![
+            search,
+            row![
+                text("Ограничение времени поиска:").width(Length::Fill),
+                pick_list(
+                    &QueryTimeout::ALL[..],
+                    Some(self.query_timeout),
+                    Message::QueryTimeoutChanged,
+                )
+                .named("query-timeout"),
+            ]
+            .spacing(10),
+            row![
+                text("Предупреждать о файлах больше:").width(Length::Fill),
+                pick_list(
+                    &FileSizeLimit::ALL[..],
+                    Some(self.file_size_limit),
+                    Message::FileSizeLimitChanged,
+                )
+                .named("file-size-limit"),
+            ]
+            .spacing(10),
+            row![
+                text("Когда файл базы изменён на диске:").width(Length::Fill),
+                pick_list(
+                    &ReloadMode::ALL[..],
+                    Some(self.reload_mode),
+                    Message::ReloadModeChanged,
+                )
+                .named("reload-mode"),
+            ]
+            .spacing(10),
+            toggler(
+                Some("Режим обучения".to_string()),
+                self.training,
+                Message::TrainingToggled
+            )
+            .named("training"),
+            checks,
+            toggler(
+                Some("Не различать регистр и лишние пробелы в именах".to_string()),
+                self.normalize_names,
+                Message::NormalizeNamesToggled
+            )
+            .named("normalize-names"),
+            row![
+                text("Масштаб интерфейса:").width(Length::Fill),
+                pick_list(
+                    &UiScale::ALL[..],
+                    Some(self.preferences.scale),
+                    Message::UiScaleChanged,
+                )
+                .named("ui-scale"),
+            ]
+            .spacing(10),
+            row![
+                text("Размер шрифта (после перезапуска):").width(Length::Fill),
+                pick_list(
+                    &TextSize::ALL[..],
+                    Some(self.preferences.text_size),
+                    Message::TextSizeChanged,
+                )
+                .named("text-size"),
+            ]
+            .spacing(10),
+            row![
+                text("Язык текстов базы:").width(Length::Fill),
+                pick_list(
+                    &Language::ALL[..],
+                    Some(self.preferences.language),
+                    Message::LanguageChanged,
+                )
+                .named("language"),
+            ]
+            .spacing(10),
+            row![
+                text("Время в сообщениях:").width(Length::Fill),
+                pick_list(
+                    &LogTimes::ALL[..],
+                    Some(self.preferences.log_times),
+                    Message::LogTimesChanged,
+                )
+                .named("log-times"),
+            ]
+            .spacing(10),
+            row![
+                text("Шрифт (после перезапуска):").width(Length::Fill),
+                pick_list(
+                    &FontChoice::ALL[..],
+                    Some(self.preferences.font),
+                    Message::FontChanged,
+                )
+                .named("font"),
+            ]
+            .spacing(10),
+            toggler(
+                Some("Крупные элементы управления".to_string()),
+                self.preferences.large_controls,
+                Message::LargeControlsToggled
+            )
+            .named("large-controls"),
+        ]
+        .push_maybe(viewer)
+        .spacing(10)
+        .max_width(480)
+        .into()
+    }
+
+    /// Pool of the chosen number of threads queries run in, or `None` for
+    /// the global pool when it cannot be built; the error is then shown
+    /// with the setting.
+    pub fn build_pool(&mut self) -> Option<Arc<rayon::ThreadPool>> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.query_threads.0)
+            .build();
+        self.pool_error = pool.as_ref().err().map(ToString::to_string);
+        pool.ok().map(Arc::new)
+    }
+}
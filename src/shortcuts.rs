@@ -0,0 +1,228 @@
+//! Keyboard shortcuts of the application. Key presses are handled and the
+//! cheatsheet is drawn from the same [`SHORTCUTS`], so one cannot miss what
+//! the other does.
+
+use std::fmt;
+
+use iced::{
+    keyboard::{key::Named, Key, Modifiers},
+    theme,
+    widget::{button, column, container, horizontal_space, row, text, Column},
+    Element, Font, Length,
+};
+
+use crate::a11y::Accessible;
+use crate::main_window::{Message, Tabs};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Area {
+    Navigation,
+    Files,
+    Help,
+}
+
+impl Area {
+    const ALL: [Area; 3] = [Area::Navigation, Area::Files, Area::Help];
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Area::Navigation => "Навигация",
+            Area::Files => "Файлы",
+            Area::Help => "Справка",
+        })
+    }
+}
+
+/// Key of a shortcut, pressed together with its modifiers.
+#[derive(Debug, Clone, Copy)]
+pub enum Trigger {
+    Named(Named),
+    /// Character as typed, so Shift needed to type it is not a modifier.
+    Character(&'static str),
+}
+
+/// What a shortcut does, as told in the cheatsheet.
+#[derive(Debug, Clone)]
+pub enum Description {
+    Text(&'static str),
+    /// Opens the tab, so it is left out while the tab is hidden.
+    Tab(Tabs),
+}
+
+impl fmt::Display for Description {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Description::Text(text) => f.write_str(text),
+            Description::Tab(tab) => write!(f, "Вкладка «{}»", tab.name()),
+        }
+    }
+}
+
+pub struct Shortcut {
+    pub area: Area,
+    pub trigger: Trigger,
+    pub modifiers: Modifiers,
+    pub description: Description,
+    pub message: fn() -> Message,
+}
+
+impl Shortcut {
+    fn matches(&self, key: &Key, modifiers: Modifiers) -> bool {
+        match (self.trigger, key) {
+            (Trigger::Named(named), Key::Named(pressed)) => {
+                named == *pressed && modifiers == self.modifiers
+            }
+            (Trigger::Character(character), Key::Character(pressed)) => {
+                character == pressed.as_str() && modifiers - Modifiers::SHIFT == self.modifiers
+            }
+            _ => false,
+        }
+    }
+
+    /// Keys to press, e.g. `Ctrl+O`.
+    pub fn keys(&self) -> String {
+        let mut keys = String::new();
+        if self.modifiers.command() {
+            keys.push_str("Ctrl+");
+        }
+        if self.modifiers.alt() {
+            keys.push_str("Alt+");
+        }
+        if self.modifiers.shift() {
+            keys.push_str("Shift+");
+        }
+        match self.trigger {
+            Trigger::Named(Named::Escape) => keys.push_str("Esc"),
+            Trigger::Named(named) => keys.push_str(&format!("{:?}", named)),
+            Trigger::Character(character) => keys.push_str(&character.to_uppercase()),
+        }
+        keys
+    }
+}
+
+macro_rules! tab {
+    ($key: expr, $tab: expr) => {
+        Shortcut {
+            area: Area::Navigation,
+            trigger: Trigger::Character($key),
+            modifiers: Modifiers::COMMAND,
+            description: Description::Tab($tab),
+            message: || Message::TabChanged($tab),
+        }
+    };
+}
+
+pub static SHORTCUTS: &[Shortcut] = &[
+    Shortcut {
+        area: Area::Navigation,
+        trigger: Trigger::Named(Named::Tab),
+        modifiers: Modifiers::empty(),
+        description: Description::Text("Следующее поле"),
+        message: || Message::FocusMoved(false),
+    },
+    Shortcut {
+        area: Area::Navigation,
+        trigger: Trigger::Named(Named::Tab),
+        modifiers: Modifiers::SHIFT,
+        description: Description::Text("Предыдущее поле"),
+        message: || Message::FocusMoved(true),
+    },
+    tab!("1", Tabs::Questions),
+    tab!("2", Tabs::Recommendations),
+    tab!("3", Tabs::Explorer),
+    tab!("4", Tabs::Dependencies),
+    tab!("5", Tabs::Editor),
+    tab!("6", Tabs::Logs),
+    tab!("7", Tabs::Problems),
+    tab!("8", Tabs::Settings),
+    tab!("9", Tabs::About),
+    Shortcut {
+        area: Area::Files,
+        trigger: Trigger::Character("o"),
+        modifiers: Modifiers::COMMAND,
+        description: Description::Text("Открыть файл"),
+        message: || Message::OpenFile,
+    },
+    Shortcut {
+        area: Area::Files,
+        trigger: Trigger::Character("s"),
+        modifiers: Modifiers::COMMAND,
+        description: Description::Text("Сохранить базу"),
+        message: || Message::SaveEditor,
+    },
+    Shortcut {
+        area: Area::Help,
+        trigger: Trigger::Character("?"),
+        modifiers: Modifiers::empty(),
+        description: Description::Text("Показать или скрыть сочетания клавиш"),
+        message: || Message::ShortcutsToggled,
+    },
+    Shortcut {
+        area: Area::Help,
+        trigger: Trigger::Named(Named::Escape),
+        modifiers: Modifiers::empty(),
+        description: Description::Text("Скрыть сочетания клавиш"),
+        message: || Message::ShortcutsClosed,
+    },
+];
+
+/// Message of the shortcut pressed, if any.
+pub fn find(key: &Key, modifiers: Modifiers) -> Option<Message> {
+    SHORTCUTS
+        .iter()
+        .find(|shortcut| shortcut.matches(key, modifiers))
+        .map(|shortcut| (shortcut.message)())
+}
+
+/// Cheatsheet shown in place of the current tab, without the shortcuts of
+/// tabs that are not `shown`.
+pub fn view<'a>(shown: impl Fn(&Tabs) -> bool) -> Element<'a, Message> {
+    let groups = Area::ALL
+        .iter()
+        .fold(Column::new().spacing(15), |col, &area| {
+            let group = SHORTCUTS
+                .iter()
+                .filter(|shortcut| shortcut.area == area)
+                .filter(|shortcut| match &shortcut.description {
+                    Description::Tab(tab) => shown(tab),
+                    Description::Text(_) => true,
+                })
+                .fold(
+                    column![text(area.to_string()).size(16)].spacing(4),
+                    |group, shortcut| {
+                        group.push(row![
+                            text(shortcut.keys()).font(Font::MONOSPACE).width(120),
+                            text(shortcut.description.to_string()),
+                        ])
+                    },
+                );
+            col.push(group)
+        });
+
+    let sheet = container(
+        column![
+            row![
+                text("Сочетания клавиш").size(20),
+                horizontal_space(),
+                button("Закрыть")
+                    .on_press(Message::ShortcutsClosed)
+                    .style(theme::Button::Secondary)
+                    .named("shortcuts-close"),
+            ],
+            groups,
+        ]
+        .spacing(15)
+        .padding(20),
+    )
+    .max_width(600)
+    .style(theme::Container::Box);
+
+    container(sheet)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+}
@@ -0,0 +1,562 @@
+use std::path::Path;
+
+use indexmap::IndexMap;
+use rusqlite::{params, Connection};
+
+use crate::cases::TestCase;
+use crate::parser::{Condition, Entry, Provenance, Question, QuestionKind, TextKey, DB};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        id       INTEGER PRIMARY KEY,
+        category TEXT NOT NULL,
+        value    TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS conditions (
+        entry_id INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        category TEXT NOT NULL,
+        value    TEXT NOT NULL,
+        weight   REAL NOT NULL DEFAULT 1,
+        probability REAL,
+        PRIMARY KEY (entry_id, position)
+    );
+    CREATE TABLE IF NOT EXISTS ignored (
+        entry_id INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        category TEXT NOT NULL,
+        PRIMARY KEY (entry_id, position)
+    );
+    CREATE TABLE IF NOT EXISTS provenance (
+        entry_id INTEGER PRIMARY KEY REFERENCES entries(id) ON DELETE CASCADE,
+        author   TEXT,
+        source   TEXT,
+        date     TEXT
+    );
+    CREATE TABLE IF NOT EXISTS disabled (
+        entry_id INTEGER PRIMARY KEY REFERENCES entries(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS notes (
+        entry_id INTEGER PRIMARY KEY REFERENCES entries(id) ON DELETE CASCADE,
+        note     TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS questions (
+        category TEXT PRIMARY KEY,
+        text     TEXT NOT NULL,
+        position INTEGER,
+        section  TEXT,
+        kind     TEXT NOT NULL DEFAULT 'выбор'
+    );
+    CREATE TABLE IF NOT EXISTS changes (
+        category TEXT PRIMARY KEY,
+        text     TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tips (
+        category TEXT PRIMARY KEY,
+        text     TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS value_changes (
+        category TEXT NOT NULL,
+        value    TEXT NOT NULL,
+        text     TEXT NOT NULL,
+        PRIMARY KEY (category, value)
+    );
+    CREATE TABLE IF NOT EXISTS value_tips (
+        category TEXT NOT NULL,
+        value    TEXT NOT NULL,
+        text     TEXT NOT NULL,
+        PRIMARY KEY (category, value)
+    );
+    CREATE TABLE IF NOT EXISTS variants (
+        kind     TEXT NOT NULL,
+        category TEXT NOT NULL,
+        value    TEXT,
+        language TEXT NOT NULL,
+        text     TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS taxonomy (
+        value  TEXT PRIMARY KEY,
+        parent TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS aliases (
+        alias TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS allowed_values (
+        category TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        value    TEXT NOT NULL,
+        PRIMARY KEY (category, position)
+    );
+    CREATE TABLE IF NOT EXISTS multi_valued (
+        category TEXT PRIMARY KEY
+    );
+    CREATE TABLE IF NOT EXISTS target (
+        category TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS lock (
+        hash TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS priors (
+        category    TEXT NOT NULL,
+        value       TEXT NOT NULL,
+        probability REAL NOT NULL,
+        PRIMARY KEY (category, value)
+    );
+    CREATE TABLE IF NOT EXISTS tests (
+        id        INTEGER PRIMARY KEY,
+        name      TEXT NOT NULL DEFAULT '',
+        target    TEXT,
+        find_all  INTEGER NOT NULL,
+        strategy  TEXT,
+        threshold REAL
+    );
+    CREATE TABLE IF NOT EXISTS test_answers (
+        test_id  INTEGER NOT NULL REFERENCES tests(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        category TEXT NOT NULL,
+        value    TEXT NOT NULL,
+        PRIMARY KEY (test_id, position)
+    );
+    CREATE TABLE IF NOT EXISTS test_degrees (
+        test_id  INTEGER NOT NULL REFERENCES tests(id) ON DELETE CASCADE,
+        category TEXT NOT NULL,
+        degree   REAL NOT NULL,
+        PRIMARY KEY (test_id, category)
+    );
+    CREATE TABLE IF NOT EXISTS test_expected (
+        test_id  INTEGER NOT NULL REFERENCES tests(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        value    TEXT NOT NULL,
+        PRIMARY KEY (test_id, position)
+    );
+";
+
+pub fn save_db(db: &DB, path: &Path) -> rusqlite::Result<()> {
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    tx.execute_batch(
+        "DELETE FROM conditions; DELETE FROM ignored; DELETE FROM provenance; DELETE FROM disabled;
+         DELETE FROM notes;
+         DELETE FROM entries;
+         DELETE FROM questions; DELETE FROM changes; DELETE FROM tips;
+         DELETE FROM value_changes; DELETE FROM value_tips; DELETE FROM variants;
+         DELETE FROM taxonomy; DELETE FROM aliases;
+         DELETE FROM allowed_values; DELETE FROM multi_valued;
+         DELETE FROM target; DELETE FROM lock; DELETE FROM priors;
+         DELETE FROM test_answers; DELETE FROM test_degrees; DELETE FROM test_expected;
+         DELETE FROM tests;",
+    )?;
+
+    {
+        let mut insert_entry =
+            tx.prepare("INSERT INTO entries (id, category, value) VALUES (?1, ?2, ?3)")?;
+        let mut insert_condition = tx.prepare(
+            "INSERT INTO conditions (entry_id, position, category, value, weight, probability)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        let mut insert_ignored =
+            tx.prepare("INSERT INTO ignored (entry_id, position, category) VALUES (?1, ?2, ?3)")?;
+        let mut insert_provenance = tx.prepare(
+            "INSERT INTO provenance (entry_id, author, source, date) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut insert_disabled = tx.prepare("INSERT INTO disabled (entry_id) VALUES (?1)")?;
+        let mut insert_note = tx.prepare("INSERT INTO notes (entry_id, note) VALUES (?1, ?2)")?;
+        for (id, entry) in (0_i64..).zip(db.entries.iter()) {
+            insert_entry.execute(params![id, entry.category, entry.value])?;
+            if entry.disabled {
+                insert_disabled.execute(params![id])?;
+            }
+            if let Some(note) = entry.note.as_ref() {
+                insert_note.execute(params![id, note])?;
+            }
+            let provenance = &entry.provenance;
+            if !provenance.is_empty() {
+                insert_provenance.execute(params![
+                    id,
+                    provenance.author,
+                    provenance.source,
+                    provenance.date
+                ])?;
+            }
+            for (position, condition) in (0_i64..).zip(entry.conditions.iter()) {
+                insert_condition.execute(params![
+                    id,
+                    position,
+                    condition.category,
+                    condition.value,
+                    condition.weight,
+                    condition.probability
+                ])?;
+            }
+            for (position, category) in (0_i64..).zip(entry.ignored.iter()) {
+                insert_ignored.execute(params![id, position, category])?;
+            }
+        }
+
+        let mut insert_question = tx.prepare(
+            "INSERT INTO questions (category, text, position, section, kind)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (category, question) in db.questions.iter() {
+            insert_question.execute(params![
+                category,
+                question.text,
+                question.order,
+                question.group,
+                question.kind.keyword()
+            ])?;
+        }
+
+        for (table, map) in [("changes", &db.changes), ("tips", &db.tips)] {
+            let mut insert = tx.prepare(&format!(
+                "INSERT INTO {table} (category, text) VALUES (?1, ?2)"
+            ))?;
+            for (category, text) in map.iter() {
+                insert.execute(params![category, text])?;
+            }
+        }
+        for (table, map) in [
+            ("value_changes", &db.value_changes),
+            ("value_tips", &db.value_tips),
+        ] {
+            let mut insert = tx.prepare(&format!(
+                "INSERT INTO {table} (category, value, text) VALUES (?1, ?2, ?3)"
+            ))?;
+            for (category, texts) in map.iter() {
+                for (value, text) in texts.iter() {
+                    insert.execute(params![category, value, text])?;
+                }
+            }
+        }
+
+        let mut insert_variant = tx.prepare(
+            "INSERT INTO variants (kind, category, value, language, text)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (key, variants) in db.variants.iter() {
+            let (kind, category, value) = match key {
+                TextKey::Question(category) => ("question", category, None),
+                TextKey::Change(category, value) => ("change", category, value.as_ref()),
+                TextKey::Tip(category, value) => ("tip", category, value.as_ref()),
+            };
+            for (language, text) in variants.iter() {
+                insert_variant.execute(params![kind, category, value, language, text])?;
+            }
+        }
+
+        let mut insert_subtype =
+            tx.prepare("INSERT INTO taxonomy (value, parent) VALUES (?1, ?2)")?;
+        for (value, parent) in db.taxonomy.iter() {
+            insert_subtype.execute(params![value, parent])?;
+        }
+
+        let mut insert_alias = tx.prepare("INSERT INTO aliases (alias, value) VALUES (?1, ?2)")?;
+        for (alias, value) in db.aliases.iter() {
+            insert_alias.execute(params![alias, value])?;
+        }
+
+        let mut insert_allowed = tx.prepare(
+            "INSERT INTO allowed_values (category, position, value) VALUES (?1, ?2, ?3)",
+        )?;
+        for (category, values) in db.schema.allowed.iter() {
+            for (position, value) in (0_i64..).zip(values.iter()) {
+                insert_allowed.execute(params![category, position, value])?;
+            }
+        }
+
+        let mut insert_multi = tx.prepare("INSERT INTO multi_valued (category) VALUES (?1)")?;
+        for category in db.schema.multi_valued.iter() {
+            insert_multi.execute(params![category])?;
+        }
+
+        if let Some(category) = db.target.as_ref() {
+            tx.execute(
+                "INSERT INTO target (category) VALUES (?1)",
+                params![category],
+            )?;
+        }
+
+        if let Some(hash) = db.lock.as_ref() {
+            tx.execute("INSERT INTO lock (hash) VALUES (?1)", params![hash])?;
+        }
+
+        let mut insert_prior =
+            tx.prepare("INSERT INTO priors (category, value, probability) VALUES (?1, ?2, ?3)")?;
+        for (category, priors) in db.priors.iter() {
+            for (value, probability) in priors.iter() {
+                insert_prior.execute(params![category, value, probability])?;
+            }
+        }
+
+        let mut insert_test = tx.prepare(
+            "INSERT INTO tests (id, name, target, find_all, strategy, threshold)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        let mut insert_answer = tx.prepare(
+            "INSERT INTO test_answers (test_id, position, category, value) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut insert_degree =
+            tx.prepare("INSERT INTO test_degrees (test_id, category, degree) VALUES (?1, ?2, ?3)")?;
+        let mut insert_expected =
+            tx.prepare("INSERT INTO test_expected (test_id, position, value) VALUES (?1, ?2, ?3)")?;
+        for (id, case) in (0_i64..).zip(db.tests.iter()) {
+            insert_test.execute(params![
+                id,
+                case.name,
+                case.target,
+                case.find_all,
+                case.strategy,
+                case.threshold
+            ])?;
+            for (position, (category, value)) in (0_i64..).zip(case.answers.iter()) {
+                insert_answer.execute(params![id, position, category, value])?;
+            }
+            for (category, degree) in case.degrees.iter() {
+                insert_degree.execute(params![id, category, degree])?;
+            }
+            for (position, value) in (0_i64..).zip(case.expected.iter()) {
+                insert_expected.execute(params![id, position, value])?;
+            }
+        }
+    }
+
+    tx.commit()
+}
+
+pub fn load_db(path: &Path) -> rusqlite::Result<DB> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+
+    let mut db = DB::new();
+
+    let mut select_aliases = conn.prepare("SELECT alias, value FROM aliases ORDER BY rowid")?;
+    db.aliases = select_aliases
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut select_entries = conn.prepare("SELECT id, category, value FROM entries ORDER BY id")?;
+    let mut select_conditions = conn.prepare(
+        "SELECT category, value, weight, probability FROM conditions
+         WHERE entry_id = ?1 ORDER BY position",
+    )?;
+    let mut select_ignored =
+        conn.prepare("SELECT category FROM ignored WHERE entry_id = ?1 ORDER BY position")?;
+    let mut select_provenance =
+        conn.prepare("SELECT author, source, date FROM provenance WHERE entry_id = ?1")?;
+    let mut select_disabled = conn.prepare("SELECT 1 FROM disabled WHERE entry_id = ?1")?;
+    let mut select_note = conn.prepare("SELECT note FROM notes WHERE entry_id = ?1")?;
+    let entries = select_entries.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+    for entry in entries {
+        let (id, category, value) = entry?;
+        let conditions = select_conditions
+            .query_map([id], |row| {
+                Ok(Condition {
+                    category: row.get(0)?,
+                    value: row.get(1)?,
+                    weight: row.get(2)?,
+                    probability: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let ignored = select_ignored
+            .query_map([id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let provenance = select_provenance
+            .query_map([id], |row| {
+                Ok(Provenance {
+                    author: row.get(0)?,
+                    source: row.get(1)?,
+                    date: row.get(2)?,
+                })
+            })?
+            .next()
+            .transpose()?
+            .unwrap_or_default();
+
+        db.push_entry(Entry {
+            value,
+            category,
+            conditions,
+            ignored,
+            provenance,
+            disabled: select_disabled.exists([id])?,
+            note: select_note
+                .query_map([id], |row| row.get(0))?
+                .next()
+                .transpose()?,
+        });
+    }
+
+    let mut select_questions = conn
+        .prepare("SELECT category, text, position, section, kind FROM questions ORDER BY rowid")?;
+    db.questions = select_questions
+        .query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                Question {
+                    text: row.get(1)?,
+                    order: row.get(2)?,
+                    group: row.get(3)?,
+                    kind: QuestionKind::from_keyword(&row.get::<_, String>(4)?).unwrap_or_default(),
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    db.changes = load_texts(&conn, "changes")?;
+    db.tips = load_texts(&conn, "tips")?;
+    db.value_changes = load_value_texts(&conn, "value_changes")?;
+    db.value_tips = load_value_texts(&conn, "value_tips")?;
+
+    let mut select_variants =
+        conn.prepare("SELECT kind, category, value, language, text FROM variants ORDER BY rowid")?;
+    let variants = select_variants.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+        ))
+    })?;
+    for row in variants {
+        let (kind, category, value, language, text) = row?;
+        let key = match kind.as_str() {
+            "question" => TextKey::Question(category),
+            "change" => TextKey::Change(category, value),
+            _ => TextKey::Tip(category, value),
+        };
+        db.variants.entry(key).or_default().insert(language, text);
+    }
+
+    let mut select_taxonomy = conn.prepare("SELECT value, parent FROM taxonomy ORDER BY rowid")?;
+    db.taxonomy = select_taxonomy
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut select_allowed =
+        conn.prepare("SELECT category, value FROM allowed_values ORDER BY rowid")?;
+    let allowed = select_allowed.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for row in allowed {
+        let (category, value) = row?;
+        db.schema.allowed.entry(category).or_default().push(value);
+    }
+
+    let mut select_multi = conn.prepare("SELECT category FROM multi_valued ORDER BY rowid")?;
+    db.schema.multi_valued = select_multi
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut select_target = conn.prepare("SELECT category FROM target ORDER BY rowid")?;
+    db.target = select_target
+        .query_map([], |row| row.get(0))?
+        .last()
+        .transpose()?;
+
+    let mut select_lock = conn.prepare("SELECT hash FROM lock ORDER BY rowid")?;
+    db.lock = select_lock
+        .query_map([], |row| row.get(0))?
+        .last()
+        .transpose()?;
+
+    let mut select_priors =
+        conn.prepare("SELECT category, value, probability FROM priors ORDER BY rowid")?;
+    let priors = select_priors.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, f64>(2)?,
+        ))
+    })?;
+    for row in priors {
+        let (category, value, probability) = row?;
+        db.priors
+            .entry(category)
+            .or_default()
+            .insert(value, probability);
+    }
+
+    let mut select_tests = conn
+        .prepare("SELECT id, name, target, find_all, strategy, threshold FROM tests ORDER BY id")?;
+    let mut select_answers = conn
+        .prepare("SELECT category, value FROM test_answers WHERE test_id = ?1 ORDER BY position")?;
+    let mut select_degrees = conn
+        .prepare("SELECT category, degree FROM test_degrees WHERE test_id = ?1 ORDER BY rowid")?;
+    let mut select_expected =
+        conn.prepare("SELECT value FROM test_expected WHERE test_id = ?1 ORDER BY position")?;
+    let tests = select_tests.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, bool>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, Option<f64>>(5)?,
+        ))
+    })?;
+    for test in tests {
+        let (id, name, target, find_all, strategy, threshold) = test?;
+        db.tests.push(TestCase {
+            name,
+            target,
+            answers: select_answers
+                .query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()?,
+            degrees: select_degrees
+                .query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()?,
+            find_all,
+            strategy,
+            threshold,
+            expected: select_expected
+                .query_map([id], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?,
+        });
+    }
+
+    Ok(db)
+}
+
+fn load_texts(conn: &Connection, table: &str) -> rusqlite::Result<IndexMap<String, String>> {
+    let mut select = conn.prepare(&format!(
+        "SELECT category, text FROM {table} ORDER BY rowid"
+    ))?;
+    let rows = select.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    rows.collect()
+}
+
+fn load_value_texts(
+    conn: &Connection,
+    table: &str,
+) -> rusqlite::Result<IndexMap<String, IndexMap<String, String>>> {
+    let mut select = conn.prepare(&format!(
+        "SELECT category, value, text FROM {table} ORDER BY rowid"
+    ))?;
+    let rows = select.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+
+    let mut texts: IndexMap<String, IndexMap<String, String>> = IndexMap::new();
+    for row in rows {
+        let (category, value, text) = row?;
+        texts.entry(category).or_default().insert(value, text);
+    }
+    Ok(texts)
+}
@@ -0,0 +1,314 @@
+//! Places a knowledge base is read from and written to. Opening, reloading
+//! and saving go through [`KnowledgeStore`] wherever the base lives.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use iced::futures::{channel::mpsc::Sender, future::BoxFuture, SinkExt};
+
+use crate::download;
+use crate::encoding::{FileEncoding, TextFormat};
+use crate::kb_format::KbFormat;
+use crate::loading;
+use crate::parser::{EditError, ParserError, SyntaxError, DB};
+use crate::settings::FileSizeLimit;
+use crate::sqlite;
+
+#[derive(Debug, Clone)]
+pub enum Source {
+    File(PathBuf),
+    Url(String),
+    Clipboard,
+    Demo,
+    Embedded(&'static str),
+}
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    DialogClosed,
+    IO(io::ErrorKind),
+    Parse(Arc<String>, (usize, usize)),
+    /// Errors of the malformed blocks, with the base of the rest.
+    ParseErrors(Arc<Vec<SyntaxError>>, Arc<DB>),
+    Query(Arc<String>),
+    Sqlite(Arc<String>),
+    Network(Arc<String>),
+    Xlsx(Arc<String>),
+    Cases(Arc<String>),
+    Answers(Arc<String>),
+    Edit(Arc<String>),
+    Encoding(Arc<String>),
+}
+
+impl From<EditError> for Error {
+    fn from(err: EditError) -> Self {
+        Error::Edit(Arc::new(err.to_string()))
+    }
+}
+
+impl From<ParserError> for Error {
+    fn from(err: ParserError) -> Self {
+        match err {
+            ParserError::Parse(msg, pos) => Error::Parse(msg, pos),
+            ParserError::Recovered(errors, db) => Error::ParseErrors(Arc::new(errors), db),
+        }
+    }
+}
+
+/// Base as a store keeps it.
+#[derive(Debug, Clone)]
+pub enum Stored {
+    /// DSL source, with the encoding it was read in.
+    Text(Arc<String>, TextFormat),
+    /// Base kept as tables, which has no source to edit.
+    Db(Arc<DB>),
+}
+
+#[derive(Debug, Clone)]
+pub enum StoreEvent {
+    /// Bytes read and the size of the base, when known.
+    Progress(u64, Option<u64>),
+    /// Base read, with the store it is reloaded from and saved to.
+    Finished(Result<(Arc<dyn KnowledgeStore>, Stored), Error>),
+}
+
+/// Opened base to be saved: the text of the editor for stores keeping text,
+/// the parsed base for the rest.
+pub struct Snapshot {
+    pub text: String,
+    pub format: TextFormat,
+    pub db: Arc<DB>,
+}
+
+pub trait KnowledgeStore: Send + Sync + fmt::Debug {
+    /// Where the base is, shown to the user and remembered with the
+    /// preferences.
+    fn source(&self) -> Source;
+
+    /// Reads the base, sending progress to `sender`.
+    fn load<'a>(
+        &'a self,
+        sender: &'a mut Sender<StoreEvent>,
+    ) -> BoxFuture<'a, Result<Stored, Error>>;
+
+    fn save(&self, snapshot: Snapshot) -> BoxFuture<'_, Result<(), Error>>;
+}
+
+/// Loads the base from `store` and sends it as the last event.
+pub async fn load(store: Arc<dyn KnowledgeStore>, mut sender: Sender<StoreEvent>) {
+    let result = store
+        .load(&mut sender)
+        .await
+        .map(|stored| (store.clone(), stored));
+
+    let _ = sender.send(StoreEvent::Finished(result)).await;
+}
+
+/// Saves the base to `store`, returning the store it is now kept in.
+pub async fn save(
+    store: Arc<dyn KnowledgeStore>,
+    snapshot: Snapshot,
+) -> Result<Arc<dyn KnowledgeStore>, Error> {
+    store.save(snapshot).await?;
+    Ok(store)
+}
+
+/// Knowledge base file in any supported text format, kept as DSL source
+/// once loaded.
+#[derive(Debug)]
+pub struct FileStore {
+    pub path: PathBuf,
+    pub encoding: FileEncoding,
+    pub format: KbFormat,
+    pub limit: FileSizeLimit,
+}
+
+impl KnowledgeStore for FileStore {
+    fn source(&self) -> Source {
+        Source::File(self.path.clone())
+    }
+
+    fn load<'a>(
+        &'a self,
+        sender: &'a mut Sender<StoreEvent>,
+    ) -> BoxFuture<'a, Result<Stored, Error>> {
+        Box::pin(async move {
+            let (contents, format) =
+                loading::read(&self.path, self.encoding, self.format, self.limit, sender).await?;
+            Ok(Stored::Text(contents, format))
+        })
+    }
+
+    fn save(&self, snapshot: Snapshot) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            // Other formats are only imported.
+            if self.format.resolve(&self.path) != KbFormat::Dsl {
+                return Err(Error::IO(io::ErrorKind::Unsupported));
+            }
+            let contents = snapshot
+                .format
+                .encode(&snapshot.text)
+                .map_err(|err| Error::Encoding(Arc::new(err)))?;
+
+            tokio::fs::write(&self.path, contents)
+                .await
+                .map_err(|err| Error::IO(err.kind()))
+        })
+    }
+}
+
+/// Base downloaded over HTTP, which cannot be saved back.
+#[derive(Debug)]
+pub struct UrlStore {
+    pub url: String,
+    pub encoding: FileEncoding,
+}
+
+impl KnowledgeStore for UrlStore {
+    fn source(&self) -> Source {
+        Source::Url(self.url.clone())
+    }
+
+    fn load<'a>(
+        &'a self,
+        sender: &'a mut Sender<StoreEvent>,
+    ) -> BoxFuture<'a, Result<Stored, Error>> {
+        Box::pin(async move {
+            let contents = download::fetch(&self.url, self.encoding, sender).await?;
+            Ok(Stored::Text(contents, TextFormat::default()))
+        })
+    }
+
+    fn save(&self, _: Snapshot) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async { Err(Error::IO(io::ErrorKind::Unsupported)) })
+    }
+}
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    pub path: PathBuf,
+}
+
+impl KnowledgeStore for SqliteStore {
+    fn source(&self) -> Source {
+        Source::File(self.path.clone())
+    }
+
+    fn load<'a>(&'a self, _: &'a mut Sender<StoreEvent>) -> BoxFuture<'a, Result<Stored, Error>> {
+        let path = self.path.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || sqlite::load_db(&path))
+                .await
+                .map_err(sqlite_error)?
+                .map(|db| Stored::Db(Arc::new(db)))
+                .map_err(sqlite_error)
+        })
+    }
+
+    fn save(&self, snapshot: Snapshot) -> BoxFuture<'_, Result<(), Error>> {
+        let path = self.path.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || sqlite::save_db(&snapshot.db, &path))
+                .await
+                .map_err(sqlite_error)?
+                .map_err(sqlite_error)
+        })
+    }
+}
+
+fn sqlite_error(err: impl ToString) -> Error {
+    Error::Sqlite(Arc::new(err.to_string()))
+}
+
+/// DSL source held in memory: pasted, bundled with the program or built
+/// in code. Saving keeps the text for the next reload.
+#[derive(Debug)]
+pub struct MemoryStore {
+    pub source: Source,
+    pub contents: Mutex<Arc<String>>,
+}
+
+impl MemoryStore {
+    pub fn new(source: Source, contents: String) -> Self {
+        Self {
+            source,
+            contents: Mutex::new(Arc::new(contents)),
+        }
+    }
+}
+
+impl KnowledgeStore for MemoryStore {
+    fn source(&self) -> Source {
+        self.source.clone()
+    }
+
+    fn load<'a>(&'a self, _: &'a mut Sender<StoreEvent>) -> BoxFuture<'a, Result<Stored, Error>> {
+        let contents = self.contents.lock().unwrap().clone();
+        Box::pin(async move { Ok(Stored::Text(contents, TextFormat::default())) })
+    }
+
+    fn save(&self, snapshot: Snapshot) -> BoxFuture<'_, Result<(), Error>> {
+        *self.contents.lock().unwrap() = Arc::new(snapshot.text);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::futures::channel::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn memory_store_loads_what_was_saved() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let store: Arc<dyn KnowledgeStore> = Arc::new(MemoryStore::new(
+            Source::Clipboard,
+            "1 если ствол - один то тип - дерево\n".to_string(),
+        ));
+        let text = "1 если ствол - несколько то тип - куст\n";
+        let snapshot = Snapshot {
+            text: text.to_string(),
+            format: TextFormat::default(),
+            db: Arc::default(),
+        };
+
+        let (mut sender, _receiver) = mpsc::channel(1);
+        let loaded = runtime.block_on(async {
+            let store = save(store, snapshot).await.ok()?;
+            store.load(&mut sender).await.ok()
+        });
+
+        assert!(matches!(loaded, Some(Stored::Text(contents, _)) if *contents == text));
+    }
+
+    #[test]
+    fn cleared_text_is_saved_as_given() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let text = "1 если ствол - один то тип - дерево\n";
+        let store: Arc<dyn KnowledgeStore> =
+            Arc::new(MemoryStore::new(Source::Clipboard, text.to_string()));
+        let Ok((_, db)) = crate::parser::parse_with_cache(text) else {
+            panic!("base failed to parse");
+        };
+        let snapshot = Snapshot {
+            text: String::new(),
+            format: TextFormat::default(),
+            db: Arc::new(db),
+        };
+
+        let (mut sender, _receiver) = mpsc::channel(1);
+        let loaded = runtime.block_on(async {
+            let store = save(store, snapshot).await.ok()?;
+            store.load(&mut sender).await.ok()
+        });
+
+        assert!(matches!(loaded, Some(Stored::Text(contents, _)) if contents.is_empty()));
+    }
+}
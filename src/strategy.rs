@@ -0,0 +1,289 @@
+//! Inference approaches the query path can run, behind one trait so new ones
+//! can be tried without touching the callers.
+
+use crate::engine::{Budget, Conflict, Interrupt, Posterior, Score};
+use crate::fuzzy::{self, FuzzyMatch};
+use crate::parser::DB;
+
+#[derive(Debug, Clone, Default)]
+pub struct QueryResult {
+    pub conclusions: Vec<Conclusion>,
+    /// Entries the conclusions were drawn by.
+    pub matched: Vec<usize>,
+    pub score: Option<Score>,
+    pub posteriors: Vec<Posterior>,
+    pub memberships: Vec<FuzzyMatch>,
+    /// Entries scored just below the threshold of scored matching.
+    pub near_misses: Vec<Score>,
+    /// Defuzzified confidence in the first value.
+    pub confidence: Option<f64>,
+    /// Intermediate entries whose conclusions were added to the answers.
+    pub derived: Vec<usize>,
+    pub conflicts: Vec<Conflict>,
+    /// Set when the query stopped early and the result may be partial.
+    pub interrupted: Option<Interrupt>,
+}
+
+impl QueryResult {
+    /// Whether nothing was found; entries that nearly matched are no
+    /// finding.
+    pub fn is_empty(&self) -> bool {
+        self.conclusions.is_empty() && self.posteriors.is_empty()
+    }
+
+    /// Values concluded, best first.
+    pub fn values(&self) -> Vec<String> {
+        self.conclusions
+            .iter()
+            .map(|conclusion| conclusion.value.clone())
+            .collect()
+    }
+}
+
+/// Value found, with the category it is a value of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conclusion {
+    pub category: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Query<'a> {
+    pub target: Option<&'a String>,
+    /// Answers with the degree each holds to, which fuzzy matching takes
+    /// as a membership and scored matching as the confidence in the answer.
+    pub answers: Vec<(&'a String, &'a String, f64)>,
+    /// Whether every matching value is wanted rather than the best one.
+    pub find_all: bool,
+    /// Share of the weight of its conditions an entry must satisfy to count
+    /// in scored matching.
+    pub threshold: f64,
+    pub budget: &'a Budget,
+}
+
+impl<'a> Query<'a> {
+    pub fn facts(&self) -> Vec<(&'a String, &'a String)> {
+        self.answers
+            .iter()
+            .map(|&(cat, val, _)| (cat, val))
+            .collect()
+    }
+}
+
+pub trait MatchStrategy: Send + Sync {
+    /// Name the strategy is recorded under in test cases.
+    fn name(&self) -> &'static str;
+
+    /// Word naming the strategy in the tests of a base, like `тест: байес`.
+    fn keyword(&self) -> &'static str;
+
+    fn evaluate(&self, db: &DB, query: &Query) -> QueryResult;
+
+    /// Evaluates the query with its names spelled the way the base spells
+    /// them, marking the result as partial when the budget of the query ran
+    /// out before the work was done. Nothing is matched when intermediate
+    /// conclusions were left underived, as matching without them is wrong
+    /// rather than partial.
+    fn run(&self, db: &DB, query: &Query) -> QueryResult {
+        let target = query.target.map(|x| db.spelling(x).to_string());
+        let answers = query
+            .answers
+            .iter()
+            .map(|&(cat, val, degree)| {
+                (
+                    db.spelling(cat).to_string(),
+                    db.spelling(val).to_string(),
+                    degree,
+                )
+            })
+            .collect::<Vec<_>>();
+        let query = Query {
+            target: target.as_ref(),
+            answers: answers
+                .iter()
+                .map(|(cat, val, degree)| (cat, val, *degree))
+                .collect(),
+            ..query.clone()
+        };
+
+        let (derived, conflicts) = db.derivations(query.facts(), query.budget);
+        let mut result = match query.budget.stopped() {
+            Some(_) => QueryResult::default(),
+            None => self.evaluate(db, &query),
+        };
+        (result.derived, result.conflicts) = (derived, conflicts);
+        result.interrupted = query.budget.stopped();
+        result
+    }
+}
+
+/// Entries whose conditions agree with every relevant answer.
+pub struct Exact;
+
+impl MatchStrategy for Exact {
+    fn name(&self) -> &'static str {
+        "exact"
+    }
+
+    fn keyword(&self) -> &'static str {
+        "точно"
+    }
+
+    fn evaluate(&self, db: &DB, query: &Query) -> QueryResult {
+        let (conclusions, matched) = exact_conclusions(db, query);
+        QueryResult {
+            conclusions,
+            matched,
+            ..QueryResult::default()
+        }
+    }
+}
+
+/// How far below the threshold of scored matching an entry may score and
+/// still be shown as a near miss.
+const NEAR_MISS: f64 = 0.25;
+
+/// Entries ranked by the weights of the conditions the answers satisfy.
+pub struct Scored;
+
+impl MatchStrategy for Scored {
+    fn name(&self) -> &'static str {
+        "scored"
+    }
+
+    fn keyword(&self) -> &'static str {
+        "по_весам"
+    }
+
+    fn evaluate(&self, db: &DB, query: &Query) -> QueryResult {
+        let (scores, below): (Vec<_>, Vec<_>) = db
+            .score_entries(query.target, &query.answers, query.budget)
+            .into_iter()
+            .partition(|score| score.share() >= query.threshold);
+        let mut conclusions = conclusions_of(db, scores.iter().map(|score| score.entry));
+        if !query.find_all {
+            conclusions.truncate(1);
+        }
+        let matched = scores
+            .iter()
+            .map(|score| score.entry)
+            .filter(|&id| conclusions.contains(&conclusion_of(db, id)))
+            .collect();
+
+        QueryResult {
+            conclusions,
+            matched,
+            score: scores.first().cloned(),
+            near_misses: below
+                .into_iter()
+                .filter(|score| score.share() >= query.threshold - NEAR_MISS)
+                .collect(),
+            ..QueryResult::default()
+        }
+    }
+}
+
+/// Exact matching alongside naive-Bayes posteriors.
+pub struct Bayes;
+
+impl MatchStrategy for Bayes {
+    fn name(&self) -> &'static str {
+        "bayes"
+    }
+
+    fn keyword(&self) -> &'static str {
+        "байес"
+    }
+
+    fn evaluate(&self, db: &DB, query: &Query) -> QueryResult {
+        let (conclusions, matched) = exact_conclusions(db, query);
+        QueryResult {
+            conclusions,
+            matched,
+            posteriors: db.posteriors(query.target, query.facts(), query.budget),
+            ..QueryResult::default()
+        }
+    }
+}
+
+/// Fuzzy matching over answers that hold to a degree.
+pub struct Fuzzy;
+
+impl MatchStrategy for Fuzzy {
+    fn name(&self) -> &'static str {
+        "fuzzy"
+    }
+
+    fn keyword(&self) -> &'static str {
+        "нечётко"
+    }
+
+    fn evaluate(&self, db: &DB, query: &Query) -> QueryResult {
+        let memberships = db.fuzzy_matches(query.target, query.answers.clone(), query.budget);
+        let mut conclusions = memberships
+            .iter()
+            .map(|x| Conclusion {
+                category: x.category.clone(),
+                value: x.value.clone(),
+            })
+            .collect::<Vec<_>>();
+        if !query.find_all {
+            conclusions.truncate(1);
+        }
+        let matched = memberships
+            .iter()
+            .take(conclusions.len())
+            .flat_map(|x| x.entries.iter().copied())
+            .collect();
+
+        QueryResult {
+            conclusions,
+            matched,
+            confidence: fuzzy::confidence(&memberships),
+            memberships,
+            ..QueryResult::default()
+        }
+    }
+}
+
+const STRATEGIES: [&dyn MatchStrategy; 4] = [&Exact, &Scored, &Bayes, &Fuzzy];
+
+/// Strategy recorded under `name`.
+pub fn by_name(name: &str) -> Option<&'static dyn MatchStrategy> {
+    STRATEGIES
+        .into_iter()
+        .find(|strategy| strategy.name() == name)
+}
+
+/// Strategy named by `keyword` in a test of a base.
+pub fn by_keyword(keyword: &str) -> Option<&'static dyn MatchStrategy> {
+    STRATEGIES
+        .into_iter()
+        .find(|strategy| strategy.keyword() == keyword)
+}
+
+/// Conclusions exact matching draws, with the entries drawing them.
+fn exact_conclusions(db: &DB, query: &Query) -> (Vec<Conclusion>, Vec<usize>) {
+    let matched = db.matched_entries(query.target, query.facts(), query.find_all, query.budget);
+    (conclusions_of(db, matched.iter().copied()), matched)
+}
+
+/// Conclusions of the entries `ids`, each once, in their order.
+fn conclusions_of(db: &DB, ids: impl Iterator<Item = usize>) -> Vec<Conclusion> {
+    let mut conclusions = Vec::new();
+    for id in ids {
+        let conclusion = conclusion_of(db, id);
+        if !conclusions.contains(&conclusion) {
+            conclusions.push(conclusion);
+        }
+    }
+    conclusions
+}
+
+fn conclusion_of(db: &DB, id: usize) -> Conclusion {
+    let entry = &db.entries[id];
+    Conclusion {
+        category: entry.category.clone(),
+        value: entry.value.clone(),
+    }
+}
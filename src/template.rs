@@ -0,0 +1,71 @@
+//! Placeholders in translations and tips, like `{цвет}`, filled in with the
+//! answers of the consultation when the result is shown.
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// Replaces every `{category}` in `text` with what `lookup` gives for the
+/// category. Placeholders `lookup` knows nothing about are kept as written,
+/// and `{{` and `}}` stand for literal braces.
+pub fn render<'a>(text: &str, lookup: impl Fn(&str) -> Option<&'a str>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    let mut state = State::Text;
+
+    while let Some((at, c)) = chars.next() {
+        state = match (state, c) {
+            (State::Text, '{') => open(at, &mut chars, &mut out),
+            // A lone `}` is kept as written, like a doubled one.
+            (State::Text, '}') => {
+                chars.next_if(|&(_, next)| next == '}');
+                out.push('}');
+                State::Text
+            }
+            (State::Text, c) => {
+                out.push(c);
+                State::Text
+            }
+            (State::Placeholder(start), '}') => {
+                let name = &text[start + 1..at];
+                match is_name(name).then(|| lookup(name.trim())).flatten() {
+                    Some(value) => out.push_str(value),
+                    None => out.push_str(&text[start..=at]),
+                }
+                State::Text
+            }
+            // A brace opened again leaves the one before as written.
+            (State::Placeholder(start), '{') => {
+                out.push_str(&text[start..at]);
+                open(at, &mut chars, &mut out)
+            }
+            (state, _) => state,
+        };
+    }
+
+    if let State::Placeholder(start) = state {
+        out.push_str(&text[start..]);
+    }
+    out
+}
+
+/// Where [`render`] is in the text.
+#[derive(Clone, Copy)]
+enum State {
+    Text,
+    /// Inside a placeholder whose `{` is at the offset.
+    Placeholder(usize),
+}
+
+/// Reads the `{` at `at`, which is a literal brace when doubled.
+fn open(at: usize, chars: &mut Peekable<CharIndices<'_>>, out: &mut String) -> State {
+    if chars.next_if(|&(_, next)| next == '{').is_some() {
+        out.push('{');
+        return State::Text;
+    }
+    State::Placeholder(at)
+}
+
+fn is_name(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty() && name.chars().all(|x| x.is_alphanumeric() || x == '_')
+}
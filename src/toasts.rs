@@ -0,0 +1,240 @@
+//! Short notices about actions that went as expected, dismissed on their own
+//! so the Logs tab keeps only what needs attention. They are laid over the
+//! window, so showing one moves nothing under it.
+
+use std::time::{Duration, Instant};
+
+use iced::{
+    advanced::{
+        layout::{self, Layout},
+        overlay, renderer,
+        widget::{self, Tree, Widget},
+        Clipboard, Shell,
+    },
+    event, mouse, theme,
+    widget::{container, text, Column},
+    Alignment, Element, Event, Length, Point, Rectangle, Renderer, Size, Theme, Vector,
+};
+
+use crate::main_window::Message;
+
+/// How long a notice stays on screen.
+pub const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+/// Notices shown at once; older ones are dropped first.
+const MAX_TOASTS: usize = 3;
+
+const WIDTH: f32 = 280.0;
+
+#[derive(Debug, Default)]
+pub struct Toasts {
+    items: Vec<(String, Instant)>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, message: &str) {
+        if self.items.len() == MAX_TOASTS {
+            self.items.remove(0);
+        }
+        self.items.push((message.to_string(), Instant::now()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Dismisses the notices shown for longer than [`TOAST_DURATION`].
+    pub fn expire(&mut self, now: Instant) {
+        self.items
+            .retain(|(_, shown)| now.duration_since(*shown) < TOAST_DURATION);
+    }
+
+    /// Lays the notices over `content`, in the bottom right corner of the
+    /// window.
+    pub fn view<'a>(&'a self, content: impl Into<Element<'a, Message>>) -> Element<'a, Message> {
+        let notices = Column::with_children(self.items.iter().map(|(message, _)| {
+            container(text(message).size(14))
+                .padding(8)
+                .width(Length::Fill)
+                .style(theme::Container::Box)
+                .into()
+        }))
+        .spacing(5)
+        .padding(10)
+        .width(WIDTH);
+
+        Element::new(Layered {
+            content: content.into(),
+            notices: notices.into(),
+            shown: !self.is_empty(),
+        })
+    }
+}
+
+struct Layered<'a> {
+    content: Element<'a, Message>,
+    notices: Element<'a, Message>,
+    shown: bool,
+}
+
+impl<'a> Widget<Message, Theme, Renderer> for Layered<'a> {
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.content
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            viewport,
+        );
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content), Tree::new(&self.notices)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[&self.content, &self.notices]);
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn widget::Operation<Message>,
+    ) {
+        self.content
+            .as_widget()
+            .operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        let [content_tree, notices_tree] = tree.children.as_mut_slice() else {
+            return None;
+        };
+        let content =
+            self.content
+                .as_widget_mut()
+                .overlay(content_tree, layout, renderer, translation);
+        let notices = self.shown.then(|| {
+            overlay::Element::new(Box::new(Notices {
+                notices: &mut self.notices,
+                tree: notices_tree,
+            }))
+        });
+
+        let overlays = content.into_iter().chain(notices).collect::<Vec<_>>();
+        (!overlays.is_empty()).then(|| overlay::Group::with_children(overlays).overlay())
+    }
+}
+
+/// Notices drawn above everything else; the pointer goes through them.
+struct Notices<'a, 'b> {
+    notices: &'b mut Element<'a, Message>,
+    tree: &'b mut Tree,
+}
+
+impl<'a, 'b> overlay::Overlay<Message, Theme, Renderer> for Notices<'a, 'b> {
+    fn layout(&mut self, renderer: &Renderer, bounds: Size) -> layout::Node {
+        let limits = layout::Limits::new(Size::ZERO, bounds);
+        self.notices
+            .as_widget()
+            .layout(self.tree, renderer, &limits)
+            .align(Alignment::End, Alignment::End, bounds)
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+    ) {
+        self.notices.as_widget().draw(
+            self.tree,
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            &layout.bounds(),
+        );
+    }
+
+    fn is_over(&self, _: Layout<'_>, _: &Renderer, _: Point) -> bool {
+        false
+    }
+}
@@ -0,0 +1,98 @@
+//! Guided tour over a demo knowledge base, for users who open the program
+//! for the first time.
+
+use iced::{
+    theme,
+    widget::{button, column, container, horizontal_space, row, text},
+    Element, Length,
+};
+
+use crate::a11y::Accessible;
+use crate::main_window::{Message, Tabs};
+
+/// Knowledge base loaded by the tour.
+pub const DEMO: &str = include_str!("../examples/botany.txt");
+
+/// Every step of the tour: the tab it shows and the hint over it.
+const STEPS: [(Tabs, &str); 5] = [
+    (
+        Tabs::Questions,
+        "Загружена демонстрационная база об определении растений. Ответьте на \
+         вопросы, например выберите древесный стебель, и нажмите «Найти».",
+    ),
+    (
+        Tabs::Questions,
+        "Неизвестные ответы можно не заполнять. Цель поиска выбирается в списке \
+         слева, «Все категории» сбрасывает её, а история ниже хранит найденные \
+         выводы.",
+    ),
+    (
+        Tabs::Recommendations,
+        "Во вкладке «Рекомендации» собраны переводы и подсказки к найденному \
+         выводу. Отмечайте выполненное и сохраняйте список в файл.",
+    ),
+    (
+        Tabs::Explorer,
+        "Во вкладке «Данные» собраны правила, вопросы и подсказки базы. Правило \
+         можно отключить, не удаляя его.",
+    ),
+    (
+        Tabs::Editor,
+        "В редакторе база открыта как текст. Измените правило и нажмите \
+         «Применить»; синтаксис с примерами описан во вкладке «Справка».",
+    ),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tutorial {
+    step: usize,
+}
+
+impl Tutorial {
+    /// Tab of the step `step`, `None` past the last one.
+    pub fn tab(step: usize) -> Option<Tabs> {
+        STEPS.get(step).map(|(tab, _)| tab.clone())
+    }
+
+    pub fn go_to(&mut self, step: usize) {
+        self.step = step.min(STEPS.len() - 1);
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let (_, hint) = STEPS[self.step];
+        let is_last = self.step + 1 == STEPS.len();
+
+        let actions = row![
+            text(format!("Шаг {} из {}", self.step + 1, STEPS.len())).size(14),
+            horizontal_space(),
+        ]
+        .push_maybe((self.step > 0).then(|| {
+            button("Назад")
+                .on_press(Message::TutorialMoved(self.step - 1))
+                .style(theme::Button::Secondary)
+                .named("tutorial-back")
+        }))
+        .push(if is_last {
+            button("Завершить")
+                .on_press(Message::TutorialClosed)
+                .named("tutorial-finish")
+        } else {
+            button("Далее")
+                .on_press(Message::TutorialMoved(self.step + 1))
+                .named("tutorial-next")
+        })
+        .push_maybe((!is_last).then(|| {
+            button("Пропустить")
+                .on_press(Message::TutorialClosed)
+                .style(theme::Button::Secondary)
+                .named("tutorial-skip")
+        }))
+        .spacing(10)
+        .align_items(iced::Alignment::Center);
+
+        container(column![text(hint), actions].spacing(10).padding(10))
+            .width(Length::Fill)
+            .style(theme::Container::Box)
+            .into()
+    }
+}
@@ -0,0 +1,59 @@
+//! Counters of how a knowledge base is used in consultations, kept with the
+//! preferences on this computer and never sent anywhere.
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::parser::Entry;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    /// Times each entry, by [`entry_key`], gave the conclusion of a query.
+    pub conclusions: IndexMap<String, u64>,
+    /// Times each category was answered in a query.
+    pub answers: IndexMap<String, u64>,
+}
+
+impl Usage {
+    pub fn record_answers<'a>(&mut self, categories: impl IntoIterator<Item = &'a String>) {
+        for category in categories {
+            *self.answers.entry(category.clone()).or_default() += 1;
+        }
+    }
+
+    pub fn record_conclusions<'a>(&mut self, entries: impl IntoIterator<Item = &'a Entry>) {
+        for entry in entries {
+            *self.conclusions.entry(entry_key(entry)).or_default() += 1;
+        }
+    }
+
+    pub fn fired(&self, entry: &Entry) -> u64 {
+        self.conclusions
+            .get(&entry_key(entry))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn answered(&self, category: &str) -> u64 {
+        self.answers.get(category).copied().unwrap_or_default()
+    }
+}
+
+/// Names an entry by what it concludes from which conditions, so counters
+/// survive renumbering and edits of other entries.
+fn entry_key(entry: &Entry) -> String {
+    let mut conditions = entry
+        .conditions
+        .iter()
+        .map(|condition| format!("{} - {}", condition.category, condition.value))
+        .collect::<Vec<_>>();
+    conditions.sort();
+
+    format!(
+        "{} => {} - {}",
+        conditions.join(", "),
+        entry.category,
+        entry.value
+    )
+}
@@ -0,0 +1,222 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use calamine::{open_workbook_auto, Reader};
+use iced::{
+    theme,
+    widget::{button, column, horizontal_space, pick_list, row, scrollable, text},
+    Element, Length,
+};
+
+use crate::{
+    a11y::{self, Accessible},
+    main_window::Message,
+    parser::{Condition, Entry, Provenance, DB},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Sheet {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnRole {
+    #[default]
+    Skip,
+    Condition,
+    Conclusion,
+}
+
+impl ColumnRole {
+    const ALL: [ColumnRole; 3] = [
+        ColumnRole::Skip,
+        ColumnRole::Condition,
+        ColumnRole::Conclusion,
+    ];
+}
+
+impl fmt::Display for ColumnRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ColumnRole::Skip => "Пропустить",
+            ColumnRole::Condition => "Условие",
+            ColumnRole::Conclusion => "Заключение",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct XlsxImport {
+    pub path: PathBuf,
+    sheet: Sheet,
+    roles: Vec<ColumnRole>,
+    categories: Vec<String>,
+}
+
+pub fn read_sheet(path: &Path) -> Result<Sheet, calamine::Error> {
+    let mut workbook = open_workbook_auto(path)?;
+    let range = match workbook.worksheet_range_at(0) {
+        Some(range) => range?,
+        None => return Ok(Sheet::default()),
+    };
+
+    let mut rows = range
+        .rows()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect::<Vec<_>>());
+    let headers = rows.next().unwrap_or_default();
+
+    Ok(Sheet {
+        headers,
+        rows: rows.collect(),
+    })
+}
+
+impl XlsxImport {
+    pub fn new(path: PathBuf, sheet: Sheet) -> Self {
+        let columns = sheet.headers.len();
+        let mut roles = vec![ColumnRole::Condition; columns];
+        if let Some(last) = roles.last_mut() {
+            *last = ColumnRole::Conclusion;
+        }
+
+        Self {
+            path,
+            categories: sheet.headers.iter().map(|x| to_identifier(x)).collect(),
+            sheet,
+            roles,
+        }
+    }
+
+    pub fn set_role(&mut self, column: usize, role: ColumnRole) {
+        if role == ColumnRole::Conclusion {
+            self.roles
+                .iter_mut()
+                .filter(|x| **x == ColumnRole::Conclusion)
+                .for_each(|x| *x = ColumnRole::Condition);
+        }
+        self.roles[column] = role;
+    }
+
+    pub fn set_category(&mut self, column: usize, category: String) {
+        self.categories[column] = category;
+    }
+
+    fn is_valid(&self) -> bool {
+        let has_condition = self.roles.contains(&ColumnRole::Condition);
+        let has_conclusion = self.roles.contains(&ColumnRole::Conclusion);
+        let names_valid = self
+            .roles
+            .iter()
+            .zip(self.categories.iter())
+            .all(|(role, name)| *role == ColumnRole::Skip || !to_identifier(name).is_empty());
+
+        has_condition && has_conclusion && names_valid
+    }
+
+    pub fn import_into(&self, db: &mut DB) {
+        let Some(conclusion) = self.roles.iter().position(|x| *x == ColumnRole::Conclusion) else {
+            return;
+        };
+        let categories = self
+            .categories
+            .iter()
+            .map(|x| to_identifier(x))
+            .collect::<Vec<_>>();
+
+        for row in self.sheet.rows.iter() {
+            let value = row
+                .get(conclusion)
+                .map(|x| to_identifier(x))
+                .unwrap_or_default();
+            if value.is_empty() {
+                continue;
+            }
+
+            let conditions = self
+                .roles
+                .iter()
+                .enumerate()
+                .filter(|(_, role)| **role == ColumnRole::Condition)
+                .filter_map(|(column, _)| {
+                    let value = to_identifier(row.get(column)?);
+                    (!value.is_empty()).then(|| Condition::new(&categories[column], value))
+                })
+                .collect::<Vec<_>>();
+            if conditions.is_empty() {
+                continue;
+            }
+
+            db.push_entry(Entry {
+                value,
+                category: categories[conclusion].clone(),
+                conditions,
+                ignored: Vec::new(),
+                provenance: Provenance::default(),
+                disabled: false,
+                note: None,
+            });
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let columns =
+            self.sheet
+                .headers
+                .iter()
+                .enumerate()
+                .fold(column![].spacing(8), |col, (i, header)| {
+                    col.push(
+                        row![
+                            text(header).width(Length::FillPortion(2)),
+                            a11y::input(
+                                format!("xlsx-category-{}", i),
+                                "Категория",
+                                &self.categories[i]
+                            )
+                            .on_input(move |x| Message::XlsxCategoryChanged(i, x))
+                            .width(Length::FillPortion(3)),
+                            pick_list(&ColumnRole::ALL[..], Some(self.roles[i]), move |x| {
+                                Message::XlsxRoleChanged(i, x)
+                            })
+                            .width(Length::FillPortion(2))
+                            .named(format!("xlsx-role-{}", i)),
+                        ]
+                        .spacing(10),
+                    )
+                });
+
+        column![
+            text(format!(
+                "Импорт {}: {} строк",
+                self.path.display(),
+                self.sheet.rows.len()
+            ))
+            .size(18),
+            scrollable(columns).height(Length::Fill),
+            row![
+                horizontal_space(),
+                button("Отмена")
+                    .on_press(Message::XlsxCancel)
+                    .style(theme::Button::Secondary)
+                    .named("xlsx-cancel"),
+                button("Импортировать")
+                    .on_press_maybe(self.is_valid().then_some(Message::XlsxConfirm))
+                    .style(theme::Button::Primary)
+                    .named("xlsx-confirm"),
+            ]
+            .spacing(10),
+        ]
+        .spacing(10)
+        .into()
+    }
+}
+
+fn to_identifier(cell: &str) -> String {
+    cell.split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_")
+        .chars()
+        .filter(|x| x.is_alphanumeric() || *x == '_')
+        .collect()
+}
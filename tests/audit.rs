@@ -0,0 +1,56 @@
+use expert_kb::audit::{report, AuditKind, AuditLog, Consultation};
+use expert_kb::builder::KbBuilder;
+
+fn consultation_report(audit: &AuditLog) -> String {
+    let db = KbBuilder::new()
+        .entry(&[("ствол", "один")], ("тип", "дерево"))
+        .build();
+    report(&Consultation {
+        db: &db,
+        source: "растения.kb",
+        answers: Vec::new(),
+        result: None,
+        audit,
+    })
+}
+
+#[test]
+fn edits_of_one_answer_are_kept_as_one_event() {
+    let mut audit = AuditLog::default();
+    audit.answer("высота", Some("1".to_string()));
+    audit.answer("высота", Some("12".to_string()));
+    audit.answer("высота", None);
+    audit.answer("высота", Some("3".to_string()));
+
+    let answers = audit
+        .events()
+        .iter()
+        .map(|event| match &event.kind {
+            AuditKind::Answer(category, answer) => (category.as_str(), answer.as_deref()),
+            kind => panic!("unexpected event {:?}", kind),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        answers,
+        [
+            ("высота", Some("12")),
+            ("высота", None),
+            ("высота", Some("3"))
+        ]
+    );
+}
+
+#[test]
+fn failed_queries_are_not_reported_as_empty_results() {
+    let mut audit = AuditLog::default();
+    audit.push(AuditKind::Result(Vec::new()));
+    let report = consultation_report(&audit);
+    assert!(report.contains("ничего не найдено"));
+    assert!(report.contains("Не найдено"));
+
+    audit.push(AuditKind::Error("query failed: task panicked".to_string()));
+    let report = consultation_report(&audit);
+    assert!(report.contains("поиск не удался: query failed: task panicked"));
+    assert!(report.contains("Поиск не удался: query failed: task panicked"));
+    assert!(!report.contains("Не найдено"));
+}
@@ -0,0 +1,441 @@
+use expert_kb::builder::{assert_concludes, assert_tests_pass, KbBuilder};
+use expert_kb::cases::TestCase;
+use expert_kb::engine::{Budget, Conflict, Interrupt, Score};
+use expert_kb::parser::{Condition, Entry, DB};
+use expert_kb::strategy::{Bayes, Exact, Fuzzy, MatchStrategy, Query, QueryResult, Scored};
+
+fn plants() -> KbBuilder {
+    KbBuilder::new()
+        .entry(
+            &[("стебель", "зеленый"), ("ствол", "нет")],
+            ("тип", "травянистые"),
+        )
+        .entry(&[("ствол", "один")], ("тип", "дерево"))
+        .entry(&[("ствол", "несколько")], ("тип", "кустарник"))
+        .entry(
+            &[("тип", "дерево"), ("форма", "коническая")],
+            ("класс", "хвойное"),
+        )
+        .question("стебель", "Какого цвета стебель?")
+        .question("ствол", "Сколько стволов?")
+        .question("форма", "Какой формы крона?")
+        .target("класс")
+}
+
+#[test]
+fn exact_matching_chains_entries() {
+    let db = plants().build();
+    assert_concludes(
+        &db,
+        &Exact,
+        Some("класс"),
+        &[("ствол", "один"), ("форма", "коническая")],
+        &["хвойное"],
+    );
+    assert_concludes(&db, &Exact, Some("тип"), &[("ствол", "один")], &["дерево"]);
+    assert_concludes(
+        &db,
+        &Exact,
+        Some("тип"),
+        &[("стебель", "зеленый"), ("ствол", "один")],
+        &[],
+    );
+}
+
+#[test]
+fn aliases_apply_to_entries_added_before_them() {
+    let db = KbBuilder::new()
+        .entry(&[("ствол", "один")], ("тип", "древесное"))
+        .alias("древесное", "дерево")
+        .build();
+    assert_eq!(db.entries[0].value, "дерево");
+    assert_concludes(&db, &Exact, Some("тип"), &[("ствол", "один")], &["дерево"]);
+}
+
+#[test]
+fn scored_matching_accepts_partial_answers() {
+    let db = plants().build();
+    assert_concludes(
+        &db,
+        &Scored,
+        Some("тип"),
+        &[("стебель", "зеленый")],
+        &["травянистые"],
+    );
+}
+
+#[test]
+fn cases_run_with_the_strategy_they_were_recorded_with() {
+    let db = plants().build();
+    let case = TestCase {
+        name: String::new(),
+        target: Some("тип".to_string()),
+        answers: [("стебель", "зеленый"), ("ствол", "один")]
+            .into_iter()
+            .map(|(category, value)| (category.to_string(), value.to_string()))
+            .collect(),
+        degrees: Default::default(),
+        find_all: false,
+        strategy: Some(Exact.name().to_string()),
+        threshold: None,
+        expected: Vec::new(),
+    };
+
+    assert!(case.run(&db, &Scored).passed());
+    let unrecorded = TestCase {
+        strategy: None,
+        ..case
+    };
+    assert!(!unrecorded.run(&db, &Scored).passed());
+}
+
+#[test]
+fn results_name_the_entries_concluding_them() {
+    let db = plants().build();
+    let (category, value) = ("ствол".to_string(), "один".to_string());
+    let target = "тип".to_string();
+    let budget = Budget::default();
+    let query = Query {
+        target: Some(&target),
+        answers: vec![(&category, &value, 1.0)],
+        find_all: true,
+        threshold: 0.5,
+        budget: &budget,
+    };
+
+    let strategies: [&dyn MatchStrategy; 4] = [&Exact, &Scored, &Bayes, &Fuzzy];
+    for strategy in strategies {
+        let result = strategy.run(&db, &query);
+        assert_eq!(result.values(), ["дерево"], "{}", strategy.name());
+        assert_eq!(result.matched, [1], "{}", strategy.name());
+    }
+}
+
+#[test]
+fn results_keep_the_category_of_each_value() {
+    let db = KbBuilder::new()
+        .entry(&[("ствол", "один")], ("рост", "высокий"))
+        .entry(&[("ствол", "один")], ("спрос", "высокий"))
+        .build();
+    let (category, value) = ("ствол".to_string(), "один".to_string());
+    let budget = Budget::default();
+    let query = Query {
+        target: None,
+        answers: vec![(&category, &value, 1.0)],
+        find_all: true,
+        threshold: 0.5,
+        budget: &budget,
+    };
+
+    let strategies: [&dyn MatchStrategy; 3] = [&Exact, &Scored, &Fuzzy];
+    for strategy in strategies {
+        let result = strategy.run(&db, &query);
+        let conclusions = result
+            .conclusions
+            .iter()
+            .map(|x| (x.category.as_str(), x.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            conclusions,
+            [("рост", "высокий"), ("спрос", "высокий")],
+            "{}",
+            strategy.name()
+        );
+    }
+}
+
+#[test]
+fn fuzzy_matching_takes_unanswered_conditions_as_unknown() {
+    let db = plants().build();
+    let target = "тип".to_string();
+    let memberships = |answers: &[(&str, &str, f64)]| {
+        let answers = answers
+            .iter()
+            .map(|&(category, value, degree)| (category.to_string(), value.to_string(), degree))
+            .collect::<Vec<_>>();
+        let answers = answers
+            .iter()
+            .map(|(category, value, degree)| (category, value, *degree))
+            .collect();
+        db.fuzzy_matches(Some(&target), answers, &Budget::default())
+            .into_iter()
+            .map(|x| (x.value, x.degree))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        memberships(&[("стебель", "зеленый", 1.0)]),
+        [("травянистые".to_string(), 0.5)]
+    );
+    assert_eq!(
+        memberships(&[("стебель", "зеленый", 1.0), ("ствол", "нет", 0.25)]),
+        [("травянистые".to_string(), 0.25)]
+    );
+    assert_eq!(
+        memberships(&[("ствол", "один", 0.8)]),
+        [("дерево".to_string(), 0.8)]
+    );
+}
+
+#[test]
+fn spent_budgets_interrupt_only_work_left_undone() {
+    let (trunk, one, form, conical) = (
+        "ствол".to_string(),
+        "один".to_string(),
+        "форма".to_string(),
+        "коническая".to_string(),
+    );
+    let run = |db: &DB, target: &str, answers: Vec<(&String, &String, f64)>| {
+        let target = target.to_string();
+        let budget = Budget::default();
+        budget.cancel();
+        Exact.run(
+            db,
+            &Query {
+                target: Some(&target),
+                answers,
+                find_all: false,
+                threshold: 0.0,
+                budget: &budget,
+            },
+        )
+    };
+
+    // Matching without the derived type would be wrong, not partial.
+    let chained = run(
+        &plants().build(),
+        "класс",
+        vec![(&trunk, &one, 1.0), (&form, &conical, 1.0)],
+    );
+    assert!(chained.conclusions.is_empty());
+    assert_eq!(chained.interrupted, Some(Interrupt::Cancelled));
+
+    let direct = KbBuilder::new()
+        .entry(&[("ствол", "один")], ("тип", "дерево"))
+        .build();
+    let finished = run(&direct, "тип", vec![(&trunk, &one, 1.0)]);
+    assert_eq!(finished.values(), ["дерево"]);
+    assert_eq!(finished.interrupted, None);
+}
+
+fn conifers(probabilities: Option<(f64, f64)>) -> KbBuilder {
+    let entry = |value: &str, form: &str, probability| Entry {
+        value: value.to_string(),
+        category: "класс".to_string(),
+        conditions: vec![Condition {
+            probability,
+            ..Condition::new("форма", form)
+        }],
+        ..Entry::default()
+    };
+    let (coniferous, deciduous) = probabilities.unzip();
+    KbBuilder::new()
+        .with_entry(entry("хвойное", "коническая", coniferous))
+        .with_entry(entry("лиственное", "округлая", deciduous))
+}
+
+fn posteriors(db: &DB) -> Vec<(String, f64)> {
+    let (target, category, value) = (
+        "класс".to_string(),
+        "форма".to_string(),
+        "коническая".to_string(),
+    );
+    db.posteriors(Some(&target), vec![(&category, &value)], &Budget::default())
+        .into_iter()
+        .map(|posterior| (posterior.value, posterior.probability))
+        .collect()
+}
+
+fn assert_posteriors(actual: &[(String, f64)], expected: &[(&str, f64)]) {
+    assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+    for ((value, probability), (expected_value, expected_probability)) in
+        actual.iter().zip(expected)
+    {
+        assert_eq!(value, expected_value);
+        assert!(
+            (probability - expected_probability).abs() < 1e-9,
+            "{} is {}, not {}",
+            value,
+            probability,
+            expected_probability
+        );
+    }
+}
+
+#[test]
+fn posteriors_default_to_even_priors_and_likelihoods() {
+    let db = conifers(None).build();
+
+    assert_posteriors(&posteriors(&db), &[("хвойное", 0.9), ("лиственное", 0.1)]);
+}
+
+#[test]
+fn posteriors_weigh_declared_priors_and_probabilities() {
+    let db = conifers(Some((0.8, 0.7)))
+        .prior("класс", "хвойное", 0.25)
+        .build();
+
+    // 0.25 * 0.8 against the remaining 0.75 * (1 - 0.7).
+    assert_posteriors(
+        &posteriors(&db),
+        &[("лиственное", 0.225 / 0.425), ("хвойное", 0.2 / 0.425)],
+    );
+}
+
+#[test]
+fn tests_of_the_base_pass() {
+    let db = plants()
+        .test(&[("ствол", "несколько")], "тип", &["кустарник"])
+        .test(
+            &[("ствол", "один"), ("форма", "коническая")],
+            "класс",
+            &["хвойное"],
+        )
+        .build();
+    assert_eq!(db.tests.len(), 2);
+    assert_tests_pass(&db, &Exact);
+}
+
+#[test]
+#[should_panic(expected = "should conclude")]
+fn failing_test_of_the_base_panics() {
+    let db = plants()
+        .test(&[("ствол", "несколько")], "тип", &["дерево"])
+        .build();
+    assert_tests_pass(&db, &Exact);
+}
+
+#[test]
+fn derivations_name_their_entries_and_conflicting_answers() {
+    let db = plants().build();
+    let (trunk, one) = ("ствол".to_string(), "один".to_string());
+    let (kind, shrub) = ("тип".to_string(), "кустарник".to_string());
+    let budget = Budget::default();
+
+    let (derived, conflicts) = db.derivations(vec![(&trunk, &one)], &budget);
+    assert_eq!(derived, vec![1]);
+    assert!(conflicts.is_empty());
+
+    let (derived, conflicts) = db.derivations(vec![(&trunk, &one), (&kind, &shrub)], &budget);
+    assert!(derived.is_empty());
+    assert_eq!(
+        conflicts,
+        vec![Conflict {
+            entry: 1,
+            answer: shrub
+        }]
+    );
+}
+
+#[test]
+fn examples_rule_out_entries_concluding_other_values() {
+    let db = KbBuilder::new()
+        .entry(
+            &[("ствол", "один"), ("форма", "коническая")],
+            ("класс", "хвойное"),
+        )
+        .with_entry(Entry {
+            value: "лиственное".to_string(),
+            category: "класс".to_string(),
+            conditions: vec![Condition::new("ствол", "один")],
+            ignored: vec!["форма".to_string()],
+            ..Entry::default()
+        })
+        .entry(
+            &[("ствол", "несколько"), ("форма", "округлая")],
+            ("класс", "кустарник"),
+        )
+        .question("ствол", "Сколько стволов?")
+        .question("форма", "Какой формы крона?")
+        .build();
+
+    let answers = db
+        .example_answers(
+            &"класс".to_string(),
+            &"лиственное".to_string(),
+            &Budget::default(),
+        )
+        .unwrap();
+    let answers = answers
+        .iter()
+        .map(|(cat, val)| (cat.as_str(), val.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(answers, [("ствол", "один"), ("форма", "округлая")]);
+    assert_concludes(&db, &Exact, Some("класс"), &answers, &["лиственное"]);
+}
+
+#[test]
+fn entries_without_weight_score_nothing() {
+    let score = Score {
+        entry: 0,
+        score: 0.0,
+        total: 0.0,
+        satisfied: Vec::new(),
+    };
+
+    assert_eq!(score.share(), 0.0);
+}
+
+#[test]
+fn near_misses_alone_are_no_result() {
+    let near_miss = Score {
+        entry: 0,
+        score: 1.0,
+        total: 2.0,
+        satisfied: vec![true, false],
+    };
+    let result = QueryResult {
+        near_misses: vec![near_miss],
+        ..QueryResult::default()
+    };
+
+    assert!(result.is_empty());
+}
+
+#[test]
+fn scored_matching_weighs_answers_by_confidence() {
+    let db = plants().build();
+    let (trunk, one) = ("ствол".to_string(), "один".to_string());
+    let (shape, cone) = ("форма".to_string(), "коническая".to_string());
+    let target = "класс".to_string();
+    let budget = Budget::default();
+    let query = |degree| Query {
+        target: Some(&target),
+        answers: vec![(&trunk, &one, 1.0), (&shape, &cone, degree)],
+        find_all: false,
+        threshold: 0.8,
+        budget: &budget,
+    };
+
+    let certain = Scored.run(&db, &query(1.0));
+    assert_eq!(certain.values(), ["хвойное"]);
+
+    let doubtful = Scored.run(&db, &query(0.5));
+    assert!(doubtful.values().is_empty());
+    let scores = doubtful
+        .near_misses
+        .iter()
+        .map(|score| (score.score, score.total))
+        .collect::<Vec<_>>();
+    assert_eq!(scores, [(1.5, 2.0)]);
+}
+
+#[test]
+fn queries_without_a_target_search_every_conclusion() {
+    let db = plants().build();
+    assert_concludes(
+        &db,
+        &Exact,
+        None,
+        &[("стебель", "зеленый"), ("ствол", "нет")],
+        &["травянистые"],
+    );
+    // Categories already answered are not concluded again.
+    assert_concludes(
+        &db,
+        &Scored,
+        None,
+        &[("тип", "дерево"), ("форма", "коническая")],
+        &["хвойное"],
+    );
+}
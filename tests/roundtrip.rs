@@ -0,0 +1,737 @@
+use expert_kb::builder::KbBuilder;
+use expert_kb::cases::{validate, TestCase};
+use expert_kb::encoding::{decode, FileEncoding, LineEnding, TextFormat};
+use expert_kb::formatter::format_db;
+use expert_kb::kb_format::{Json, KnowledgeSource, Yaml};
+use expert_kb::lint::{lint, merged_spellings, Check};
+use expert_kb::parser::{
+    append_entry, merge_values, parse_db_from_file, parse_streaming, parse_with_cache,
+    passphrase_hash, remove_category, set_entry_disabled, Condition, EditError, Entry, ParserError,
+    Provenance, Question, QuestionKind, TextKey, DB,
+};
+use expert_kb::schema::conflicts;
+use expert_kb::sqlite::{load_db, save_db};
+use expert_kb::strategy::Exact;
+use expert_kb::template::render;
+use indexmap::IndexMap;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use std::path::PathBuf;
+
+fn identifier() -> impl Strategy<Value = String> {
+    "[а-яА-Яa-zA-Z0-9_]{1,12}"
+}
+
+fn text() -> impl Strategy<Value = String> {
+    "[а-яА-Яa-zA-Z0-9_?()/-][а-яА-Яa-zA-Z0-9 _?()/-]{0,40}"
+}
+
+fn rich_text() -> impl Strategy<Value = String> {
+    "[а-яА-Яa-zA-Z0-9_?()/*`!#.:\\[\\]-][а-яА-Яa-zA-Z0-9 _?()/*`!#.:\\[\\]-]{0,40}"
+}
+
+fn condition() -> impl Strategy<Value = Condition> {
+    (
+        identifier(),
+        identifier(),
+        prop_oneof![Just(1.0), Just(2.0), Just(0.5), Just(0.25)],
+        proptest::option::of(prop_oneof![Just(0.0), Just(0.9), Just(1.0), Just(0.125)]),
+    )
+        .prop_map(|(category, value, weight, probability)| Condition {
+            weight,
+            probability,
+            ..Condition::new(category, value)
+        })
+}
+
+fn question() -> impl Strategy<Value = Question> {
+    (
+        text(),
+        proptest::option::of(0..1000_u32),
+        proptest::option::of("[а-яА-Яa-zA-Z0-9_-]([а-яА-Яa-zA-Z0-9 _-]{0,10}[а-яА-Яa-zA-Z0-9_-])?"),
+        prop_oneof![
+            Just(QuestionKind::Choice),
+            Just(QuestionKind::Boolean),
+            Just(QuestionKind::Number),
+            Just(QuestionKind::Text),
+        ],
+    )
+        .prop_map(|(text, order, group, kind)| Question {
+            text,
+            order,
+            group,
+            kind,
+        })
+}
+
+fn provenance() -> impl Strategy<Value = Provenance> {
+    (
+        proptest::option::of(rich_text()),
+        proptest::option::of(rich_text()),
+        proptest::option::of("[0-9]{4}-[0-9]{2}-[0-9]{2}"),
+    )
+        .prop_map(|(author, source, date)| Provenance {
+            author,
+            source,
+            date,
+        })
+}
+
+fn entry() -> impl Strategy<Value = Entry> {
+    (
+        vec(condition(), 1..6),
+        vec(identifier(), 0..3),
+        identifier(),
+        identifier(),
+        provenance(),
+        any::<bool>(),
+        proptest::option::of(rich_text()),
+    )
+        .prop_map(
+            |(conditions, ignored, category, value, provenance, disabled, note)| Entry {
+                value,
+                category,
+                conditions,
+                ignored,
+                provenance,
+                disabled,
+                note,
+            },
+        )
+}
+
+fn test_case() -> impl Strategy<Value = TestCase> {
+    (
+        vec((identifier(), identifier()), 1..4),
+        identifier(),
+        vec(identifier(), 1..4),
+        prop::option::of(prop::sample::select(vec![
+            "exact", "scored", "bayes", "fuzzy",
+        ])),
+    )
+        .prop_map(|(answers, target, expected, strategy)| TestCase {
+            name: String::new(),
+            target: Some(target),
+            answers: answers.into_iter().collect(),
+            degrees: IndexMap::new(),
+            find_all: expected.len() > 1,
+            strategy: strategy.map(String::from),
+            threshold: None,
+            expected,
+        })
+}
+
+/// Path in the temporary directory no other test run writes to.
+fn scratch_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("expert-kb-{}-{}", std::process::id(), name))
+}
+
+fn db() -> impl Strategy<Value = DB> {
+    (
+        vec(entry(), 1..20),
+        vec((identifier(), question()), 0..6),
+        vec((identifier(), rich_text()), 0..6),
+        vec((identifier(), rich_text()), 0..6),
+        (
+            vec(
+                (any::<bool>(), identifier(), identifier(), rich_text()),
+                0..4,
+            ),
+            vec((any::<usize>(), "[a-z]{2}", rich_text()), 0..6),
+        ),
+        vec((identifier(), identifier()), 0..6),
+        vec((identifier(), identifier()), 0..6),
+        vec((identifier(), vec(identifier(), 1..4)), 0..4),
+        vec(identifier(), 0..4),
+        proptest::option::of(identifier()),
+        vec((identifier(), identifier(), 0..=100_u32), 0..4),
+        vec(test_case(), 0..4),
+    )
+        .prop_map(
+            |(
+                entries,
+                questions,
+                changes,
+                tips,
+                (value_texts, variants),
+                taxonomy,
+                aliases,
+                allowed,
+                multi_valued,
+                target,
+                priors,
+                tests,
+            )| {
+                let mut db = DB::new();
+                db.aliases.extend(aliases);
+                entries.into_iter().for_each(|entry| db.push_entry(entry));
+                db.questions.extend(questions);
+                db.changes.extend(changes);
+                db.tips.extend(tips);
+                for (change, category, value, text) in value_texts {
+                    let value = db.canonical(&value).clone();
+                    let texts = match change {
+                        true => &mut db.value_changes,
+                        false => &mut db.value_tips,
+                    };
+                    texts.entry(category).or_default().insert(value, text);
+                }
+                let keys = db
+                    .questions
+                    .keys()
+                    .map(|x| TextKey::Question(x.clone()))
+                    .chain(db.changes.keys().map(|x| TextKey::Change(x.clone(), None)))
+                    .chain(db.tips.keys().map(|x| TextKey::Tip(x.clone(), None)))
+                    .chain(db.value_changes.iter().flat_map(|(category, texts)| {
+                        texts
+                            .keys()
+                            .map(|value| TextKey::Change(category.clone(), Some(value.clone())))
+                    }))
+                    .chain(db.value_tips.iter().flat_map(|(category, texts)| {
+                        texts
+                            .keys()
+                            .map(|value| TextKey::Tip(category.clone(), Some(value.clone())))
+                    }))
+                    .collect::<Vec<_>>();
+                for (index, language, text) in variants {
+                    if keys.is_empty() {
+                        break;
+                    }
+                    let key = keys[index % keys.len()].clone();
+                    db.variants.entry(key).or_default().insert(language, text);
+                }
+                let taxonomy = taxonomy
+                    .iter()
+                    .map(|(value, parent)| {
+                        (db.canonical(value).clone(), db.canonical(parent).clone())
+                    })
+                    .collect::<Vec<_>>();
+                db.taxonomy.extend(taxonomy);
+                db.schema.allowed.extend(allowed);
+                db.schema.multi_valued.extend(multi_valued);
+                db.target = target;
+                for (category, value, percent) in priors {
+                    let value = db.canonical(&value).clone();
+                    db.priors
+                        .entry(category)
+                        .or_default()
+                        .insert(value, f64::from(percent) / 100.0);
+                }
+                for mut case in tests {
+                    for value in case.expected.iter_mut() {
+                        *value = db.canonical(value).clone();
+                    }
+                    db.tests.push(case);
+                }
+                db
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn format_parse_format_is_fixpoint(db in db()) {
+        let formatted = format_db(&db);
+        let reparsed = parse_db_from_file(&formatted);
+
+        prop_assert!(reparsed.is_ok(), "formatted base failed to parse:\n{}", formatted);
+        if let Ok(reparsed) = reparsed {
+            prop_assert_eq!(format_db(&reparsed), formatted);
+        }
+    }
+
+    #[test]
+    fn span_index_points_at_pairs(db in db()) {
+        let formatted = format_db(&db);
+        let Ok((cache, parsed)) = parse_with_cache(&formatted) else {
+            return Err(TestCaseError::fail(format!("formatted base failed to parse:\n{}", formatted)));
+        };
+        let index = cache.span_index();
+
+        prop_assert_eq!(index.entries.len(), parsed.entries.len());
+        for (entry, spans) in parsed.entries.iter().zip(index.entries.iter()) {
+            prop_assert_eq!(entry.conditions.len(), spans.conditions.len());
+            let pairs = entry
+                .conditions
+                .iter()
+                .map(|x| (&x.category, &x.value))
+                .chain([(&entry.category, &entry.value)]);
+            let spans = spans.conditions.iter().chain([&spans.conclusion]);
+            for ((category, value), span) in pairs.zip(spans) {
+                let pair = &formatted[span.bytes.clone()];
+                prop_assert!(pair.starts_with(category.as_str()), "{} at {:?}", pair, span);
+                prop_assert!(pair.ends_with(value.as_str()), "{} at {:?}", pair, span);
+            }
+        }
+    }
+
+    #[test]
+    fn streaming_parse_matches_full_parse(db in db()) {
+        let formatted = format_db(&db);
+        let (Ok((cache, full)), Ok((streamed_cache, streamed))) = (
+            parse_with_cache(&formatted),
+            parse_streaming(&formatted, |_| {}),
+        ) else {
+            return Err(TestCaseError::fail(format!("formatted base failed to parse:\n{}", formatted)));
+        };
+
+        prop_assert_eq!(format_db(&streamed), format_db(&full));
+        let blocks = |cache: &expert_kb::parser::ParseCache| {
+            cache
+                .span_index()
+                .entries
+                .into_iter()
+                .map(|x| x.block.bytes)
+                .collect::<Vec<_>>()
+        };
+        prop_assert_eq!(blocks(&streamed_cache), blocks(&cache));
+    }
+
+    #[test]
+    fn normalizing_names_is_idempotent(db in db()) {
+        let normalized = db.normalized();
+        prop_assert_eq!(format_db(&normalized.normalized()), format_db(&normalized));
+        for entry in normalized.entries.iter() {
+            let shouted = format!("  {} ", entry.value.to_uppercase());
+            prop_assert_eq!(normalized.spelling(&shouted), entry.value.as_str());
+        }
+    }
+
+    #[test]
+    fn sqlite_keeps_the_base(db in db()) {
+        let path = scratch_file("sqlite-keeps-the-base.sqlite");
+        let loaded = save_db(&db, &path).and_then(|()| load_db(&path));
+        let _ = std::fs::remove_file(&path);
+        let loaded = loaded.map_err(|err| TestCaseError::fail(err.to_string()))?;
+
+        prop_assert_eq!(format_db(&loaded), format_db(&db));
+        prop_assert_eq!(&loaded.categories, &db.categories);
+        prop_assert_eq!(&loaded.questions, &db.questions);
+        prop_assert_eq!(&loaded.tests, &db.tests);
+    }
+
+    #[test]
+    fn template_fills_known_placeholders(
+        text in "[^{}]*",
+        category in identifier(),
+        answer in "[^{}]*",
+        unknown in identifier(),
+    ) {
+        let lookup = |name: &str| (name == category).then_some(answer.as_str());
+        prop_assert_eq!(render(&text, lookup), text.clone());
+        prop_assume!(unknown != category);
+
+        let template = format!("{}{{{}}}{{{{{}}}}}{{{}}}", text, category, category, unknown);
+        let expected = format!("{}{}{{{}}}{{{}}}", text, answer, category, unknown);
+        prop_assert_eq!(render(&template, lookup), expected);
+    }
+
+    #[test]
+    fn parser_never_panics(contents in "\\PC*") {
+        let _ = parse_db_from_file(&contents);
+        let _ = parse_streaming(&contents, |_| {});
+    }
+}
+
+#[test]
+fn imported_probabilities_stay_between_0_and_1() {
+    let entry = |probability: &str| {
+        format!(
+            "entries:\n  - category: тип\n    value: дерево\n    conditions:\n      \
+             - category: ствол\n        value: один\n        probability: {}\n",
+            probability
+        )
+    };
+    let prior = |probability: &str| {
+        format!(
+            "{}priors:\n  тип:\n    дерево: {}\n",
+            entry("0.5"),
+            probability
+        )
+    };
+
+    assert!(Yaml.parse(&entry("0.5")).is_ok());
+    assert!(Yaml.parse(&prior("1")).is_ok());
+    for probability in ["-0.1", "1.5", ".nan"] {
+        assert!(Yaml.parse(&entry(probability)).is_err(), "{}", probability);
+        assert!(Yaml.parse(&prior(probability)).is_err(), "{}", probability);
+    }
+    let json = r#"{"entries": [{"category": "тип", "value": "дерево",
+        "conditions": [{"category": "ствол", "value": "один", "probability": -1}]}]}"#;
+    assert!(Json.parse(json).is_err());
+}
+
+#[test]
+fn imported_documents_survive_the_dsl() {
+    let json = r#"{
+        "entries": [
+            {"category": "тип", "value": "дерево", "author": "Иванов", "date": "2024-01-31",
+             "conditions": [{"category": "ствол", "value": "один", "weight": 2},
+                            {"category": "высота", "value": "высокое", "probability": 0.5}]},
+            {"category": "тип", "value": "куст", "ignored": ["высота"], "disabled": true,
+             "conditions": [{"category": "ствол", "value": "несколько"}]}
+        ],
+        "questions": {"ствол": "Сколько стволов (штук)?"},
+        "changes": {"тип": "Тип растения"},
+        "value_tips": {"тип": {"куст": "Подрезайте **весной**"}},
+        "languages": {"en": {"questions": {"ствол": "How many trunks?"}}},
+        "subtypes": {"куст": "растение"},
+        "aliases": {"кустарник": "куст"},
+        "target": "тип",
+        "priors": {"тип": {"дерево": 0.25}},
+        "tests": [{"name": "Дерево", "target": "тип", "answers": {"ствол": "один"},
+                   "expected": ["дерево"]}]
+    }"#;
+    let yaml = "entries:\n\
+                \x20 - {category: тип, value: дерево, author: Иванов, date: 2024-01-31,\n\
+                \x20    conditions: [{category: ствол, value: один, weight: 2},\n\
+                \x20                 {category: высота, value: высокое, probability: 0.5}]}\n\
+                \x20 - {category: тип, value: куст, ignored: [высота], disabled: true,\n\
+                \x20    conditions: [{category: ствол, value: несколько}]}\n\
+                questions: {ствол: Сколько стволов (штук)?}\n\
+                changes: {тип: Тип растения}\n\
+                value_tips: {тип: {куст: Подрезайте **весной**}}\n\
+                languages: {en: {questions: {ствол: How many trunks?}}}\n\
+                subtypes: {куст: растение}\n\
+                aliases: {кустарник: куст}\n\
+                target: тип\n\
+                priors: {тип: {дерево: 0.25}}\n\
+                tests: [{name: Дерево, target: тип, answers: {ствол: один}, expected: [дерево]}]\n";
+
+    let (Ok(from_json), Ok(from_yaml)) = (Json.parse(json), Yaml.parse(yaml)) else {
+        panic!("documents failed to import");
+    };
+    let formatted = format_db(&from_json);
+    assert_eq!(format_db(&from_yaml), formatted);
+    let Ok(reparsed) = parse_db_from_file(&formatted) else {
+        panic!("imported base failed to parse as DSL:\n{}", formatted);
+    };
+    assert_eq!(format_db(&reparsed), formatted);
+}
+
+#[test]
+fn imported_questions_follow_the_grammar() {
+    let document = |question: &str| {
+        format!(
+            "entries:\n  - {{category: тип, value: дерево, conditions: [{{category: ствол, value: один}}]}}\n\
+             questions:\n  ствол: \"{}\"\n",
+            question
+        )
+    };
+
+    assert!(Yaml.parse(&document("Сколько стволов?")).is_ok());
+    for question in [
+        "",
+        "Стволов: сколько?",
+        "Первая строка\\nвторая",
+        "Ствол, один?",
+    ] {
+        assert!(Yaml.parse(&document(question)).is_err(), "{}", question);
+    }
+}
+
+#[test]
+fn malformed_files_keep_the_blocks_that_parse() {
+    let contents = "1 если ствол - один то тип - дерево\n2 если ствол то тип\n\
+                    вопрос ствол Сколько стволов?\n3 если то\n";
+    let Err(ParserError::Recovered(errors, db)) = parse_db_from_file(contents) else {
+        panic!("malformed blocks were not recovered from");
+    };
+
+    assert_eq!(
+        errors
+            .iter()
+            .map(|(_, (line, _))| *line)
+            .collect::<Vec<_>>(),
+        [2, 4]
+    );
+    assert_eq!(db.entries.len(), 1);
+    assert!(db.questions.contains_key("ствол"));
+}
+
+#[test]
+fn enabling_entries_keeps_notes_mentioning_the_flag() {
+    let contents = "1 если ствол - один то тип - дерево\nотключено\n\
+                    примечание было отключено до проверки\n";
+    let Ok((cache, _)) = parse_with_cache(contents) else {
+        panic!("entry failed to parse");
+    };
+
+    assert_eq!(
+        set_entry_disabled(&cache, 0, false).as_deref(),
+        Some("1 если ствол - один то тип - дерево\nпримечание было отключено до проверки\n")
+    );
+    assert_eq!(set_entry_disabled(&cache, 0, true), None);
+}
+
+#[test]
+fn tips_never_take_the_entry_after_them() {
+    let db = parse_db_from_file(
+        "подсказка полив - обильно\n1 если стебель - зеленый то тип - трава\n\
+         подсказка тип = трава\nПоливать обильно\n2 если ствол - один то тип - дерево\n",
+    );
+    let Ok(db) = db else {
+        panic!("tips followed by entries failed to parse");
+    };
+
+    assert_eq!(db.entries.len(), 2);
+    assert_eq!(db.tips["полив"], "- обильно");
+    assert_eq!(db.value_tips["тип"]["трава"], "Поливать обильно");
+
+    let swallowing = "подсказка тип = трава\n1 если стебель - зеленый то тип - трава\n";
+    assert!(parse_db_from_file(swallowing).is_err());
+}
+
+#[test]
+fn conflicts_cover_the_text_of_their_entries() {
+    let contents = "1 если ствол - один то тип - дерево\n\n\
+                    2 если ствол - один то тип - куст\n";
+    let Ok((cache, db)) = parse_with_cache(contents) else {
+        panic!("entries failed to parse");
+    };
+
+    let spans = conflicts(&cache, &db)
+        .into_iter()
+        .map(|conflict| &contents[conflict.span])
+        .collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        [
+            "1 если ствол - один то тип - дерево",
+            "2 если ствол - один то тип - куст"
+        ]
+    );
+}
+
+#[test]
+fn entries_out_of_order_or_repeated_are_reported() {
+    let contents = "1 если ствол - один то тип - дерево\n\
+                    3 если ствол - несколько то тип - куст\n\
+                    3 если ствол - нет то тип - трава\n\
+                    4 если стебель - гибкий то тип - лиана\n";
+    let Ok((cache, db)) = parse_with_cache(contents) else {
+        panic!("entries failed to parse");
+    };
+
+    let findings = lint(&cache, &db, &[Check::Numbering]);
+    let findings = findings
+        .iter()
+        .map(|finding| (finding.warning.message.as_str(), finding.warning.position))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        findings,
+        [
+            ("expected entry number 2, found 3", (2, 1)),
+            ("entry number 3 is already used at line 2", (3, 1)),
+        ]
+    );
+}
+
+#[test]
+fn merged_spellings_point_at_the_names() {
+    let contents = "1 если ствол - один то тип - дерево\n\
+                    2 если Ствол - несколько то тип - куст\n\
+                    тест если Ствол - один то тип - Дерево\n\
+                    вопрос Ствол Сколько стволов?\n";
+    let Ok((cache, db)) = parse_with_cache(contents) else {
+        panic!("base failed to parse");
+    };
+
+    let warnings = merged_spellings(&cache, &db.normalized());
+    let warnings = warnings
+        .iter()
+        .map(|warning| (warning.message.as_str(), warning.position))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            ("`Ствол` is read as `ствол`, also at 3:11, 4:8", (2, 8)),
+            ("`Дерево` is read as `дерево`", (3, 33)),
+        ]
+    );
+}
+
+#[test]
+fn stray_braces_are_kept_as_written() {
+    let lookup = |name: &str| (name == "цвет").then_some("красный");
+
+    assert_eq!(render("}цвет} и {цвет}", lookup), "}цвет} и красный");
+    assert_eq!(render("{{цвет}} {цвет", lookup), "{цвет} {цвет");
+    assert_eq!(render("{вес {цвет}", lookup), "{вес красный");
+}
+
+#[test]
+fn text_formats_write_back_what_they_read() {
+    let text = "1 если ствол - один то тип - ёлка\nпримечание хвойное\n";
+
+    let format = TextFormat::default()
+        .with_encoding(FileEncoding::Windows1251)
+        .with_line_ending(LineEnding::CrLf);
+    let Ok(bytes) = format.encode(text) else {
+        panic!("text failed to encode in windows-1251");
+    };
+    assert_eq!(bytes.len(), text.chars().count() + 2);
+    let (decoded, detected) = decode(&bytes, FileEncoding::Windows1251);
+    assert_eq!(detected, format);
+    assert_eq!(decoded, text.replace('\n', "\r\n"));
+    assert!(format.encode("日本").is_err());
+
+    let format = TextFormat::default().with_encoding(FileEncoding::Utf16Le);
+    let Ok(bytes) = format.encode(&decoded) else {
+        panic!("text failed to encode in utf-16");
+    };
+    assert!(bytes.starts_with(&[0xFF, 0xFE]));
+    let (decoded, detected) = decode(&bytes, FileEncoding::Auto);
+    assert_eq!(detected, format);
+    assert_eq!(decoded, text);
+}
+
+#[test]
+fn appended_entries_follow_the_largest_number() {
+    let contents =
+        "7 если ствол - один то тип - дерево\n2 если ствол - несколько то тип - кустарник\n";
+    let Ok((cache, _)) = parse_with_cache(contents) else {
+        panic!("entries failed to parse");
+    };
+    let entry = Entry {
+        category: "тип".to_string(),
+        value: "лиана".to_string(),
+        conditions: vec![Condition::new("ствол", "гибкий")],
+        ..Entry::default()
+    };
+
+    assert_eq!(cache.next_entry_number(), 8);
+    let appended = append_entry(&cache, &entry);
+    assert!(appended[contents.len()..].starts_with("8 если"));
+}
+
+#[test]
+fn embedded_tests_run_with_the_strategy_they_name() {
+    let contents = "1 если ствол - один и крона - коническая то тип - хвойное\n\
+                    2 если ствол - несколько и крона - шаровидная то тип - лиственное\n\
+                    тест: по_весам если ствол - один и ствол - несколько то тип - хвойное\n\
+                    тест: по_весам если ствол - несколько и крона - коническая то тип - хвойное\n\
+                    тест если ствол - несколько и крона - коническая то тип - хвойное\n";
+    let Ok((cache, db)) = parse_with_cache(contents) else {
+        panic!("tests failed to parse");
+    };
+    assert_eq!(db.tests[0].strategy.as_deref(), Some("scored"));
+    assert_eq!(db.tests[2].strategy, None);
+
+    let warnings = validate(&cache, &db, &Exact);
+    let warnings = warnings
+        .iter()
+        .map(|warning| (warning.message.as_str(), warning.position))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            (
+                "test answers `ствол` more than once, only the last answer is checked",
+                (3, 36)
+            ),
+            ("test expected `хвойное`, found `лиственное`", (3, 1)),
+            ("test expected `хвойное`, found nothing", (5, 1)),
+        ]
+    );
+}
+
+#[test]
+fn merged_values_leave_no_alias_or_subtype_behind() {
+    let contents = "1 если ствол - один то тип - ель\n\
+                    2 если ствол - несколько то тип - пихта\n\
+                    синоним ёлка = ель\n\
+                    подтип ель - хвойное\n\
+                    подтип пихта - хвойное\n\
+                    подтип сосна - ель\n";
+    let Ok((cache, mut db)) = parse_with_cache(contents) else {
+        panic!("base failed to parse");
+    };
+    let Ok(changed) = db.merge_values("тип", "ель", "пихта") else {
+        panic!("values failed to merge");
+    };
+    assert_eq!(changed, 1);
+    assert_eq!(db.aliases["ёлка"], "пихта");
+    assert_eq!(
+        db.taxonomy.iter().collect::<Vec<_>>(),
+        [
+            (&"пихта".to_string(), &"хвойное".to_string()),
+            (&"сосна".to_string(), &"пихта".to_string()),
+        ]
+    );
+
+    let merged = merge_values(&cache, "тип", "ель", "пихта");
+    assert!(!merged.contains("ель"));
+    let Ok(reparsed) = parse_db_from_file(&merged) else {
+        panic!("merged source failed to parse");
+    };
+    assert_eq!(format_db(&reparsed), format_db(&db));
+}
+
+#[test]
+fn sqlite_keeps_the_lock() {
+    let mut db = KbBuilder::new()
+        .entry(&[("ствол", "один")], ("тип", "дерево"))
+        .build();
+    db.lock = Some(passphrase_hash("секрет"));
+
+    let path = scratch_file("sqlite-keeps-the-lock.sqlite");
+    let loaded = save_db(&db, &path).and_then(|()| load_db(&path));
+    let _ = std::fs::remove_file(&path);
+    let Ok(loaded) = loaded else {
+        panic!("base failed to round-trip");
+    };
+    assert_eq!(loaded.lock, db.lock);
+    assert!(loaded.unlocks("секрет"));
+    assert!(!loaded.unlocks("другой"));
+}
+
+#[test]
+fn refused_merges_leave_the_base_as_it_was() {
+    let doubtful = Entry {
+        value: "ель".to_string(),
+        category: "тип".to_string(),
+        conditions: vec![Condition {
+            probability: Some(1.5),
+            ..Condition::new("ствол".to_string(), "один".to_string())
+        }],
+        ..Entry::default()
+    };
+    let mut db = KbBuilder::new()
+        .with_entry(doubtful)
+        .entry(&[("ствол", "несколько")], ("тип", "пихта"))
+        .build();
+    let before = format_db(&db);
+
+    let merged = db.merge_values("тип", "ель", "пихта");
+    assert!(matches!(merged, Err(EditError::Schema(_))), "{:?}", merged);
+    assert_eq!(format_db(&db), before);
+    assert_eq!(db.categories["тип"], ["ель", "пихта"]);
+}
+
+#[test]
+fn removed_categories_leave_the_other_blocks_as_written() {
+    let contents = "10 если  ствол - один\n    то тип - дерево\n\n\
+                    20 если ствол - несколько и цвет - зеленый то тип - куст\n\n\
+                    30 если тип - куст то цвет - зеленый\n\n\
+                    тест если ствол - несколько и цвет - зеленый то тип - куст\n";
+    let Ok((cache, mut db)) = parse_with_cache(contents) else {
+        panic!("base failed to parse");
+    };
+    let Some(removed) = remove_category(&cache, "цвет") else {
+        panic!("category failed to be removed");
+    };
+    assert_eq!(
+        removed,
+        "10 если  ствол - один\n    то тип - дерево\n\n\
+         20 если ствол - несколько\nто тип - куст\n\n\
+         тест если ствол - несколько то тип - куст\n"
+    );
+
+    assert!(db.remove_category("цвет").is_ok());
+    let Ok(reparsed) = parse_db_from_file(&removed) else {
+        panic!("trimmed source failed to parse");
+    };
+    assert_eq!(format_db(&reparsed), format_db(&db));
+    assert_eq!(remove_category(&cache, "вкус"), None);
+}